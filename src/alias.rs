@@ -0,0 +1,48 @@
+//! `install-alias`: git aliases that run git-sherpa through git itself
+//! (`git ready`), so it fits into existing muscle memory.
+
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::git;
+
+/// (name, expansion, what it is for). Expansions go through `git sherpa`,
+/// which finds `git-sherpa` on PATH like any other git subcommand.
+pub const ALIASES: &[(&str, &str, &str)] = &[
+    ("ready", "sherpa check", "run every check before pushing"),
+    ("sherpa-fix", "sherpa fix", "print the fix plan"),
+];
+
+/// Sets the aliases in the repo config, or the user's with `global`. An
+/// alias already defined differently is left alone unless `force`.
+pub fn install(global: bool, force: bool) -> Result<()> {
+    let scope = if global { "global" } else { "local" };
+    println!("{}", format!("Git aliases ({} config):", scope).bold());
+    let mut kept = 0;
+    for (name, expansion, purpose) in ALIASES {
+        let key = format!("alias.{}", name);
+        let line = format!("git {:<11} -> git {}  ({})", name, expansion, purpose);
+        match git::scoped_config_get(global, &key)? {
+            Some(existing) if existing == *expansion => {
+                println!("  {} {}", "=".dimmed(), line.dimmed());
+            }
+            Some(existing) if !force => {
+                kept += 1;
+                println!(
+                    "  {} {} {}",
+                    "!".yellow(),
+                    format!("git {} is already '{}'; kept", name, existing).yellow(),
+                    "(--force replaces it)".dimmed()
+                );
+            }
+            _ => {
+                git::scoped_config_set(global, &key, expansion)?;
+                println!("  {} {}", "+".green(), line);
+            }
+        }
+    }
+    if kept == 0 {
+        println!("\n{}", "Try `git ready` before your next push.".green());
+    }
+    Ok(())
+}
@@ -0,0 +1,554 @@
+//! The `git-sherpa` command line: argument dispatch and exit codes.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{
+    self, AuthAction, BaselineAction, BranchAction, CacheAction, CheckFormat, Cli, Commands,
+    Format, HooksAction, NotesAction, PolicyAction, ReleaseAction,
+};
+use crate::config::{default_config_toml, load_config_or_default, Config};
+use crate::error::ExitStatus;
+use crate::{
+    alias, audit, auth, baseline, bootstrap, branch, branch_audit, cache, check, ci, compat,
+    conflicts, doctor, error, fix, git, hooks, lfs_audit, lint, message_template, notes, policy,
+    presets, release, repo_health, rollup, serve, stats, version, watch,
+};
+
+/// Parses the command line, runs the command and exits with its status.
+pub fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if version::is_version_json(&args) {
+        let capabilities = version::capabilities();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&capabilities).expect("serialize capabilities")
+        );
+        return;
+    }
+
+    let cli = cli::parse();
+    let json_errors = matches!(
+        &cli.command,
+        Commands::Check {
+            format: CheckFormat::Json | CheckFormat::Jsonl,
+            ..
+        } | Commands::LintMessage {
+            format: Format::Json,
+            ..
+        } | Commands::RepoHealth {
+            format: Format::Json,
+            ..
+        } | Commands::LfsAudit {
+            format: Format::Json,
+            ..
+        } | Commands::Audit {
+            format: Format::Json,
+            ..
+        } | Commands::Stats {
+            format: Format::Json,
+            ..
+        } | Commands::Release {
+            action: ReleaseAction::Preview {
+                format: Format::Json,
+                ..
+            },
+        } | Commands::Conflicts {
+            format: Format::Json,
+            ..
+        } | Commands::Branch {
+            action: BranchAction::Audit {
+                format: Format::Json,
+                ..
+            },
+        } | Commands::Policy {
+            action: PolicyAction::Simulate {
+                format: Format::Json,
+                ..
+            },
+        }
+    );
+    // The frozen v1 contract predates typed exit codes: every failure exits 1.
+    let frozen_exit_codes = matches!(
+        &cli.command,
+        Commands::Check { compat, stable, .. } if compat.is_some() || *stable
+    );
+
+    match run(cli) {
+        Ok(ExitStatus::Success) => {}
+        Ok(status) => std::process::exit(status.code()),
+        Err(err) => {
+            if json_errors {
+                println!("{}", error::to_json(&err));
+            } else {
+                eprintln!("Error: {:#}", err);
+            }
+            let code = if frozen_exit_codes {
+                compat::EXIT_VIOLATIONS_V1
+            } else {
+                error::exit_code(&err)
+            };
+            std::process::exit(code);
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<ExitStatus> {
+    git::set_backend(cli.git_backend)?;
+
+    match cli.command {
+        Commands::Init {
+            list_presets: true, ..
+        } => {
+            presets::list();
+            Ok(ExitStatus::Success)
+        }
+        Commands::Init {
+            config,
+            commit,
+            preset,
+            ..
+        } => done(init(&config, commit, preset.as_deref())),
+        Commands::Check {
+            config,
+            show_config: true,
+            ..
+        } => done(crate::config::show_config(&config)),
+        Commands::Check {
+            config,
+            all_configs: true,
+            ..
+        } => check::check_all_configs(&config),
+        Commands::Check {
+            config,
+            format,
+            remote: Some(remote),
+            ..
+        } => branch_audit::check_remote(&config, &remote, plain_format(format, "--remote")?),
+        Commands::Check {
+            config,
+            format,
+            commit_limit,
+            range,
+            since,
+            sample,
+            recursive: Some(root),
+            no_baseline,
+            ..
+        } => rollup::check_recursive(
+            &root,
+            &config,
+            rollup_format(format)?,
+            check::CommitSelection::new(commit_limit, range).sampled(since, sample),
+            !no_baseline,
+        ),
+        Commands::Check {
+            config,
+            format,
+            commit_limit,
+            range,
+            since,
+            sample,
+            watch: true,
+            interval,
+            no_baseline,
+            ..
+        } => done(watch::watch_check(
+            &config,
+            plain_format(format, "--watch")?,
+            check::CommitSelection::new(commit_limit, range).sampled(since, sample),
+            std::time::Duration::from_secs(interval),
+            !no_baseline,
+        )),
+        Commands::Check {
+            config,
+            format,
+            commit_limit,
+            range,
+            since,
+            sample,
+            compat,
+            stable,
+            notes,
+            pre_push,
+            fail_on,
+            quiet_on_success,
+            no_baseline,
+            staged_only,
+            timings,
+            ignore_rev_file,
+            no_cache,
+            ..
+        } => check::check(
+            &config,
+            check::CheckOptions {
+                format: report_format(format, compat.is_some() || stable)?,
+                commits: if staged_only {
+                    check::CommitSelection::Staged
+                } else {
+                    check::CommitSelection::new(commit_limit, range).sampled(since, sample)
+                },
+                compat: compat::resolve_version(compat, stable),
+                notes,
+                pre_push,
+                fail_on,
+                quiet_on_success,
+                baseline: !no_baseline,
+                timings,
+                ignore_rev_file,
+                cache: !no_cache,
+            },
+        ),
+        Commands::LintMessage {
+            config,
+            pr_title,
+            message_file,
+            format,
+            quiet_on_success,
+        } => lint::lint_message(
+            &config,
+            pr_title.as_deref(),
+            message_file.as_deref(),
+            format,
+            quiet_on_success,
+        ),
+        Commands::PrepareMessage {
+            config,
+            message_file,
+            source,
+            commit: _,
+        } => done(message_template::prepare_message(
+            &config,
+            &message_file,
+            source.as_deref(),
+        )),
+        Commands::SuggestMessage {
+            config,
+            message_file,
+        } => done(message_template::suggest_message(
+            &config,
+            message_file.as_deref(),
+        )),
+        Commands::Fix {
+            config,
+            commit_limit,
+            range,
+            apply,
+            interactive,
+            no_gitignore,
+            only,
+            except,
+        } => done(fix::fix(
+            &config,
+            check::CommitSelection::new(commit_limit, range),
+            if interactive {
+                fix::Mode::Interactive
+            } else if apply {
+                fix::Mode::Apply
+            } else {
+                fix::Mode::Suggest
+            },
+            !no_gitignore,
+            fix::RuleFilter { only, except },
+        )),
+        Commands::Ci {
+            config,
+            base,
+            commit_limit,
+        } => ci::ci(&config, base.as_deref(), commit_limit),
+        Commands::Serve { config, http } => {
+            done(serve::serve(&config, http.as_deref().unwrap_or_default()))
+        }
+        Commands::Conflicts {
+            config,
+            target,
+            format,
+        } => conflicts::conflicts(&config, target.as_deref(), format),
+        Commands::Bootstrap {
+            config,
+            preset_file,
+        } => done(bootstrap::bootstrap(&config, preset_file.as_deref())),
+        Commands::Doctor { config, offline } => doctor::doctor(&config, offline),
+        Commands::InstallAlias { global, force } => done(alias::install(global, force)),
+        Commands::RepoHealth {
+            format,
+            top,
+            lfs_threshold_kb,
+        } => done(repo_health::repo_health(format, top, lfs_threshold_kb)),
+        Commands::LfsAudit {
+            format,
+            threshold_kb,
+        } => done(lfs_audit::lfs_audit(format, threshold_kb)),
+        Commands::Audit {
+            config,
+            schedule,
+            output,
+            state,
+            base,
+            format,
+        } => audit::audit(
+            &config,
+            schedule,
+            output.as_deref(),
+            state.as_deref(),
+            base.as_deref(),
+            format,
+        ),
+        Commands::Stats {
+            config,
+            weeks,
+            format,
+        } => done(stats::stats(&config, weeks, format)),
+        Commands::Release {
+            action: ReleaseAction::Preview { from, format },
+        } => done(release::release_preview(from.as_deref(), format)),
+        Commands::Cache {
+            action: CacheAction::Clear,
+        } => done(cache::clear()),
+        Commands::Notes { action } => {
+            let cfg = load_config_or_default(Path::new(cli::DEFAULT_CONFIG_PATH));
+            match action {
+                NotesAction::Show { rev } => done(notes::show(&cfg.notes.notes_ref, &rev)),
+                NotesAction::Push { remote } => done(notes::push(&cfg.notes.notes_ref, &remote)),
+            }
+        }
+        Commands::Auth { action } => {
+            let cfg = load_config_or_default(Path::new(cli::DEFAULT_CONFIG_PATH));
+            match action {
+                AuthAction::Login {
+                    provider,
+                    host,
+                    with_token,
+                } => done(auth::login(&cfg, provider, host.as_deref(), with_token)),
+                AuthAction::Logout { provider, host } => {
+                    done(auth::logout(&cfg, provider, host.as_deref()))
+                }
+                AuthAction::Status => done(auth::status(&cfg)),
+            }
+        }
+        Commands::Policy { action } => match action {
+            PolicyAction::Simulate {
+                config,
+                branch,
+                messages,
+                files,
+                format,
+            } => policy::simulate(
+                &config,
+                policy::PolicyInputs {
+                    branch: branch.as_deref(),
+                    messages: &messages,
+                    files: &files,
+                },
+                format,
+            ),
+            PolicyAction::Sync { config } => done(policy::sync(&config)),
+        },
+        Commands::Baseline { action } => match action {
+            BaselineAction::Create { config, range } => done(baseline::create(&config, range)),
+        },
+        Commands::Branch { action } => match action {
+            BranchAction::New {
+                kind,
+                name,
+                ticket,
+                upstream,
+            } => {
+                let cfg = load_config_or_default(Path::new(cli::DEFAULT_CONFIG_PATH));
+                done(branch::new_branch(
+                    &cfg.branches,
+                    &kind,
+                    &name,
+                    ticket.as_deref(),
+                    upstream,
+                ))
+            }
+            BranchAction::Audit {
+                config,
+                base,
+                remote,
+                format,
+            } => done(branch_audit::audit(
+                &config,
+                base.as_deref(),
+                remote,
+                format,
+            )),
+        },
+        Commands::Hooks { action } => match action {
+            HooksAction::Install { force, chain } => {
+                let cfg = load_config_or_default(Path::new(cli::DEFAULT_CONFIG_PATH));
+                if chain {
+                    hooks::install_chained(force, &cfg.hooks)?;
+                } else {
+                    hooks::install_with_config(force, &cfg.hooks)?;
+                }
+                Ok(ExitStatus::Success)
+            }
+            HooksAction::Uninstall => done(hooks::uninstall()),
+            HooksAction::Update => {
+                let cfg = load_config_or_default(Path::new(cli::DEFAULT_CONFIG_PATH));
+                let updated = hooks::update(&cfg.hooks)?;
+                for path in &updated {
+                    println!("Updated {}", path.display());
+                }
+                if updated.is_empty() {
+                    println!("git-sherpa hooks are up to date");
+                }
+                Ok(ExitStatus::Success)
+            }
+            HooksAction::Simulate { hook, to } => {
+                if to.is_some() && !matches!(hook, cli::SimulatedHook::PrePush) {
+                    bail!("--to only applies to pre-push");
+                }
+                hooks::simulate(hook, to.as_deref())
+            }
+        },
+    }
+}
+
+/// The `check --format` for a single-repo report.
+fn report_format(format: CheckFormat, frozen: bool) -> Result<check::ReportFormat> {
+    Ok(match format {
+        CheckFormat::Text => check::ReportFormat::Text,
+        CheckFormat::Json => check::ReportFormat::Json,
+        CheckFormat::Github => check::ReportFormat::Github,
+        CheckFormat::Jsonl if frozen => {
+            bail!("--format jsonl is not supported with --compat or --stable")
+        }
+        CheckFormat::Jsonl => check::ReportFormat::Jsonl,
+        CheckFormat::Markdown => bail!("--format markdown is only supported by check --recursive"),
+    })
+}
+
+/// The `check --recursive --format` for the roll-up.
+fn rollup_format(format: CheckFormat) -> Result<rollup::RollupFormat> {
+    Ok(match format {
+        CheckFormat::Text => rollup::RollupFormat::Text,
+        CheckFormat::Json => rollup::RollupFormat::Json,
+        CheckFormat::Markdown => rollup::RollupFormat::Markdown,
+        CheckFormat::Github | CheckFormat::Jsonl => {
+            bail!(
+                "--format {} is not supported by check --recursive",
+                name(format)
+            )
+        }
+    })
+}
+
+/// The `check --format` for the modes that only print text or JSON.
+fn plain_format(format: CheckFormat, mode: &str) -> Result<Format> {
+    Ok(match format {
+        CheckFormat::Text => Format::Text,
+        CheckFormat::Json => Format::Json,
+        CheckFormat::Markdown | CheckFormat::Github | CheckFormat::Jsonl => {
+            bail!(
+                "--format {} is not supported by check {}",
+                name(format),
+                mode
+            )
+        }
+    })
+}
+
+fn name(format: CheckFormat) -> String {
+    clap::ValueEnum::to_possible_value(&format)
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// The status of a command that exits 0 unless it fails.
+fn done(result: Result<()>) -> Result<ExitStatus> {
+    result.map(|()| ExitStatus::Success)
+}
+
+/// Subject of the commit `init --commit` creates.
+const INIT_COMMIT_MESSAGE: &str = "chore: add git-sherpa configuration";
+
+fn init(config_path: &Path, commit: bool, preset: Option<&str>) -> Result<()> {
+    if config_path.exists() {
+        bail!("Config already exists at {}", config_path.display());
+    }
+    let preset = preset.map(presets::find).transpose()?;
+    let message = if commit {
+        // What the repo will use once the config exists: the template being
+        // written, or the defaults layered under any user config.
+        let config = match preset {
+            Some(preset) => preset.config()?,
+            None => load_config_or_default(config_path),
+        };
+        let message = init_commit_message(&config)?;
+        check_can_commit_init(&config, &message)?;
+        Some(message)
+    } else {
+        None
+    };
+
+    let toml = match preset {
+        Some(preset) => preset.toml.to_string(),
+        None => default_config_toml()?,
+    };
+    fs::write(config_path, toml).with_context(|| format!("write {}", config_path.display()))?;
+
+    let scripts_dir = PathBuf::from(".gitsherpa");
+    fs::create_dir_all(&scripts_dir)?;
+    fs::write(
+        scripts_dir.join("README.md"),
+        "# git-sherpa scripts\n\nAdd custom hooks or scripts for your repo here.\n",
+    )?;
+
+    match preset {
+        Some(preset) => println!(
+            "Initialized git-sherpa config at {} from preset '{}'",
+            config_path.display(),
+            preset.name
+        ),
+        None => println!("Initialized git-sherpa config at {}", config_path.display()),
+    }
+
+    if let Some(message) = message {
+        let config = config_path.to_string_lossy();
+        git::commit_paths(&[&config, ".gitsherpa"], &message)?;
+        println!("Committed as \"{}\"", INIT_COMMIT_MESSAGE);
+    }
+    Ok(())
+}
+
+/// The init commit's message, signed off by the committer when `config`
+/// requires it, as `git commit -s` would.
+fn init_commit_message(config: &Config) -> Result<String> {
+    if !config.commits.body.require_signoff {
+        return Ok(INIT_COMMIT_MESSAGE.to_string());
+    }
+    Ok(format!(
+        "{}\n\nSigned-off-by: {}",
+        INIT_COMMIT_MESSAGE,
+        git::committer_ident()?
+    ))
+}
+
+/// Refuses `init --commit` up front, before any file is written, when the
+/// commit could not be created cleanly or would itself fail `check`.
+fn check_can_commit_init(config: &Config, message: &str) -> Result<()> {
+    let branch = git::current_branch()?;
+    if branch == "HEAD" {
+        bail!("HEAD is detached; switch to a branch before `init --commit`");
+    }
+    let staged = git::staged_files()?;
+    if !staged.is_empty() {
+        bail!(
+            "Other changes are staged ({}); commit or unstage them before `init --commit`",
+            staged.join(", ")
+        );
+    }
+    let lint = lint::lint_commit_message(config, message)?;
+    if !lint.valid {
+        bail!(
+            "\"{}\" does not pass the commit convention ({}); run `init` and commit manually",
+            INIT_COMMIT_MESSAGE,
+            lint.problems.join("; ")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,166 @@
+//! `.gitattributes` policy: required attribute lines and text/binary handling
+//! of staged files.
+
+use anyhow::{Context, Result};
+use glob_match::glob_match;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::config::AttributesConfig;
+use crate::git;
+
+pub const GITATTRIBUTES: &str = ".gitattributes";
+
+#[derive(Debug, Default, Serialize)]
+pub struct AttributesReport {
+    pub missing_lines: Vec<String>,
+    pub violations: Vec<AttributeViolation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AttributeViolation {
+    pub path: String,
+    pub reason: String,
+    /// Commit that added the file; `None` for staged files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl AttributesReport {
+    pub fn issue_count(&self) -> usize {
+        self.missing_lines.len() + self.violations.len()
+    }
+}
+
+pub fn build_attributes_report(
+    config: &AttributesConfig,
+    staged: &[String],
+) -> Result<AttributesReport> {
+    if config.required.is_empty() && config.binary_patterns.is_empty() {
+        return Ok(AttributesReport::default());
+    }
+
+    let existing = fs::read_to_string(GITATTRIBUTES).unwrap_or_default();
+    let missing_lines = missing_lines(&existing, &config.required);
+
+    let binary_staged = git::staged_binary_files().unwrap_or_default();
+    let violations = binary_violations(config, staged, &binary_staged)?;
+
+    Ok(AttributesReport {
+        missing_lines,
+        violations,
+    })
+}
+
+/// Files among `paths` that are binary (detected, or matching
+/// `binary_patterns`) but neither marked binary nor tracked by LFS.
+pub fn binary_violations(
+    config: &AttributesConfig,
+    paths: &[String],
+    binary: &[String],
+) -> Result<Vec<AttributeViolation>> {
+    let candidates: Vec<String> = paths
+        .iter()
+        .filter(|f| binary.contains(f) || config.binary_patterns.iter().any(|p| glob_match(p, f)))
+        .cloned()
+        .collect();
+    let attrs = git::check_attr_cached(&["text", "filter"], &candidates)?;
+
+    Ok(candidates
+        .iter()
+        .filter_map(|path| {
+            let lookup = |name: &str| {
+                attrs
+                    .iter()
+                    .find(|(p, a, _)| p == path && a == name)
+                    .map(|(_, _, v)| v.as_str())
+                    .unwrap_or("unspecified")
+            };
+            let must_be_binary =
+                config.binary_patterns.iter().any(|p| glob_match(p, path)) || binary.contains(path);
+            contradiction(lookup("text"), lookup("filter"), must_be_binary).map(|reason| {
+                AttributeViolation {
+                    path: path.clone(),
+                    reason,
+                    commit: None,
+                }
+            })
+        })
+        .collect())
+}
+
+/// Required lines not present in `.gitattributes`, compared on normalized whitespace.
+fn missing_lines(existing: &str, required: &[String]) -> Vec<String> {
+    let normalize = |line: &str| line.split_whitespace().collect::<Vec<_>>().join(" ");
+    let present: Vec<String> = existing
+        .lines()
+        .map(normalize)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .collect();
+    required
+        .iter()
+        .filter(|line| !present.contains(&normalize(line)))
+        .cloned()
+        .collect()
+}
+
+/// A binary file must either be marked `-text` (e.g. via `binary`) or stored in LFS.
+fn contradiction(text: &str, filter: &str, must_be_binary: bool) -> Option<String> {
+    if !must_be_binary || filter == "lfs" || text == "unset" {
+        return None;
+    }
+    Some(match text {
+        "set" => "binary file is marked as text".to_string(),
+        "auto" => {
+            "binary file relies on text=auto; mark it binary or track it with LFS".to_string()
+        }
+        _ => "binary file has no binary or LFS attribute".to_string(),
+    })
+}
+
+/// Appends the missing required lines to `.gitattributes`.
+pub fn append_missing(lines: &[String]) -> Result<()> {
+    let path = Path::new(GITATTRIBUTES);
+    let mut contents = fs::read_to_string(path).unwrap_or_default();
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    for line in lines {
+        contents.push_str(line);
+        contents.push('\n');
+    }
+    fs::write(path, contents).with_context(|| format!("write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_missing_required_lines() {
+        let existing = "# attrs\n*   text=auto\n*.png binary\n";
+        let required = vec!["* text=auto".to_string(), "*.zip binary".to_string()];
+        assert_eq!(missing_lines(existing, &required), vec!["*.zip binary"]);
+    }
+
+    #[test]
+    fn commented_lines_do_not_count() {
+        let required = vec!["* text=auto".to_string()];
+        assert_eq!(missing_lines("# * text=auto\n", &required), required);
+    }
+
+    #[test]
+    fn binary_marked_text_is_flagged() {
+        assert!(contradiction("set", "unspecified", true).is_some());
+        assert!(contradiction("auto", "unspecified", true).is_some());
+        assert!(contradiction("unspecified", "unspecified", true).is_some());
+    }
+
+    #[test]
+    fn binary_or_lfs_is_accepted() {
+        assert!(contradiction("unset", "unspecified", true).is_none());
+        assert!(contradiction("set", "lfs", true).is_none());
+        assert!(contradiction("set", "unspecified", false).is_none());
+    }
+}
@@ -0,0 +1,255 @@
+//! `audit`: the checks too slow to run on every commit — secrets added
+//! anywhere in HEAD's history, stale branches and repository health —
+//! written to a JSON report. With `--schedule` it only runs once the last
+//! audit recorded in the state file is older than the interval, so a cron
+//! or CI job can call it every time and pay for it once a week.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::branch_audit::{self, AuditedBranch};
+use crate::cli::{Format, Schedule};
+use crate::config::{load_config, Config};
+use crate::error::ExitStatus;
+use crate::git;
+use crate::repo_health::{self, human_size, HealthReport};
+use crate::secrets::{self, ContentFinding};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+/// Blobs listed in the health section, as `repo-health` does by default.
+const HEALTH_TOP_BLOBS: usize = 10;
+const HEALTH_LFS_THRESHOLD_KB: u64 = 1024;
+
+#[derive(Debug, Serialize)]
+pub struct AuditReport {
+    /// Unix time the audit ran.
+    pub recorded_at: u64,
+    pub commits_scanned: usize,
+    /// Secrets added by any commit in HEAD's history, deleted since or not.
+    pub history_secrets: Vec<ContentFinding>,
+    pub stale_after_days: u64,
+    pub stale_branches: Vec<AuditedBranch>,
+    pub health: HealthReport,
+}
+
+/// What the state file remembers between runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AuditState {
+    last_audit: u64,
+}
+
+/// Printed with `--format json` when the audit is not due.
+#[derive(Serialize)]
+struct Skipped {
+    due: bool,
+    last_audit: u64,
+    next_audit: u64,
+}
+
+pub fn audit(
+    config_path: &Path,
+    schedule: Option<Schedule>,
+    output: Option<&Path>,
+    state: Option<&Path>,
+    base: Option<&str>,
+    format: Format,
+) -> Result<ExitStatus> {
+    let config = load_config(config_path)?;
+    let state_path = match state {
+        Some(path) => path.to_path_buf(),
+        None => cache_dir()?.join("audit-state.json"),
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    if let Some(schedule) = schedule {
+        let last = read_state(&state_path).last_audit;
+        if let Some(next) = next_audit(last, now, schedule.interval_secs()) {
+            match format {
+                Format::Text => println!(
+                    "{}",
+                    format!(
+                        "Audit not due: last run {} day(s) ago, next in {} day(s).",
+                        (now - last) / SECS_PER_DAY,
+                        (next - now).div_ceil(SECS_PER_DAY)
+                    )
+                    .dimmed()
+                ),
+                Format::Json => {
+                    let skipped = Skipped {
+                        due: false,
+                        last_audit: last,
+                        next_audit: next,
+                    };
+                    println!("{}", serde_json::to_string_pretty(&skipped)?)
+                }
+            }
+            return Ok(ExitStatus::Success);
+        }
+    }
+
+    let report = build(&config, base, now)?;
+    let output = match output {
+        Some(path) => path.to_path_buf(),
+        None => cache_dir()?.join("audit.json"),
+    };
+    write_json(&output, &report)?;
+    let failing = report
+        .history_secrets
+        .iter()
+        .any(|f| f.severity >= config.sensitive.fail_on);
+    // A failed audit stays due, so the next scheduled run repeats it.
+    if !failing {
+        write_json(&state_path, &AuditState { last_audit: now })?;
+    }
+
+    match format {
+        Format::Text => print_text(&report, &output),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    Ok(ExitStatus::from_violations(failing))
+}
+
+fn build(config: &Config, base: Option<&str>, now: u64) -> Result<AuditReport> {
+    let commits = if git::is_unborn()? {
+        Vec::new()
+    } else {
+        git::history_commits(None, None)?
+    };
+    let mut history_secrets = Vec::new();
+    for (hash, _) in &commits {
+        let diff = git::commit_diff(hash)?;
+        for mut finding in secrets::scan_diff(&config.sensitive, &config.files, &diff)? {
+            finding.commit = Some(hash.clone());
+            history_secrets.push(finding);
+        }
+    }
+
+    let branches = branch_audit::build(config, base, false)?;
+    Ok(AuditReport {
+        recorded_at: now,
+        commits_scanned: commits.len(),
+        history_secrets,
+        stale_after_days: branches.stale_after_days,
+        stale_branches: branches.branches.into_iter().filter(|b| b.stale).collect(),
+        health: repo_health::build_health_report(HEALTH_TOP_BLOBS, HEALTH_LFS_THRESHOLD_KB)?,
+    })
+}
+
+/// When the next audit is due, if it is not due yet at `now`. A `last` of
+/// 0 means no audit has run.
+fn next_audit(last: u64, now: u64, interval: u64) -> Option<u64> {
+    let next = last.checked_add(interval)?;
+    (last > 0 && last <= now && now < next).then_some(next)
+}
+
+fn cache_dir() -> Result<PathBuf> {
+    Ok(git::git_dir()?.join("sherpa-cache"))
+}
+
+/// A missing or unreadable state file makes the audit due.
+fn read_state(path: &Path) -> AuditState {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+    let mut json = serde_json::to_string_pretty(value)?;
+    json.push('\n');
+    fs::write(path, json).with_context(|| format!("write {}", path.display()))
+}
+
+fn print_text(report: &AuditReport, output: &Path) {
+    println!(
+        "{}",
+        format!(
+            "Audit of {} commit(s), report written to {}",
+            report.commits_scanned,
+            output.display()
+        )
+        .bold()
+    );
+
+    if report.history_secrets.is_empty() {
+        println!("  {} No secrets in history", "✓".green());
+    } else {
+        println!(
+            "  {} {} secret(s) in history:",
+            "✗".red(),
+            report.history_secrets.len()
+        );
+        for finding in &report.history_secrets {
+            println!(
+                "      {}:{} (in {}) {}",
+                finding.path,
+                finding.line,
+                git::short_hash(finding.commit.as_deref().unwrap_or_default()),
+                format!("{} [{}]", finding.rule, finding.severity).red()
+            );
+        }
+    }
+
+    if report.stale_branches.is_empty() {
+        println!(
+            "  {} No branches older than {} days",
+            "✓".green(),
+            report.stale_after_days
+        );
+    } else {
+        println!(
+            "  {} {} branch(es) older than {} days:",
+            "!".yellow(),
+            report.stale_branches.len(),
+            report.stale_after_days
+        );
+        for branch in &report.stale_branches {
+            println!("      {:<40} {}d", branch.name, branch.age_days);
+        }
+    }
+
+    let health = &report.health;
+    println!(
+        "  {} Repository: {}, {} loose object(s), {} pack(s)",
+        if health.suggestions.is_empty() {
+            "✓".green()
+        } else {
+            "!".yellow()
+        },
+        human_size(health.total_size_kb * 1024),
+        health.loose_objects,
+        health.packs
+    );
+    for suggestion in &health.suggestions {
+        println!("      {}", suggestion.cyan());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audit_is_due_once_the_interval_has_passed() {
+        let week = Schedule::Weekly.interval_secs();
+        assert_eq!(next_audit(0, 1_000, week), None);
+        assert_eq!(
+            next_audit(1_000, 1_000 + week - 1, week),
+            Some(1_000 + week)
+        );
+        assert_eq!(next_audit(1_000, 1_000 + week, week), None);
+        // A last run in the future (clock skew) does not postpone the audit.
+        assert_eq!(next_audit(5_000, 1_000, week), None);
+    }
+}
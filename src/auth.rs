@@ -0,0 +1,117 @@
+//! Provider tokens stored in the OS keychain (`auth login`), so developers do
+//! not need raw tokens in their environment.
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::io::BufRead;
+
+use crate::cli::AuthProvider;
+use crate::config::Config;
+use crate::providers::{Provider, TokenSource};
+
+/// Keychain service name; entries are keyed by API host.
+const KEYRING_SERVICE: &str = "git-sherpa";
+
+/// Token stored for `host`, or `None` if there is none or the keychain is
+/// unavailable (e.g. headless CI).
+pub fn stored_token(host: &str) -> Option<String> {
+    keyring::Entry::new(KEYRING_SERVICE, host)
+        .ok()?
+        .get_password()
+        .ok()
+        .filter(|t| !t.is_empty())
+}
+
+pub fn login(
+    config: &Config,
+    provider: AuthProvider,
+    host: Option<&str>,
+    with_token: bool,
+) -> Result<()> {
+    let provider = resolve(config, provider, host)?;
+    let host = provider.host();
+    let token = if with_token {
+        let mut line = String::new();
+        std::io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .context("read token from stdin")?;
+        line
+    } else {
+        rpassword::prompt_password(format!("Token for {}: ", host)).context("read token")?
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        bail!("No token given");
+    }
+
+    entry(&host)?
+        .set_password(token)
+        .with_context(|| format!("store token for {} in the keychain", host))?;
+    println!(
+        "{}",
+        format!("Stored token for {} in the keychain.", host).green()
+    );
+    Ok(())
+}
+
+pub fn logout(config: &Config, provider: AuthProvider, host: Option<&str>) -> Result<()> {
+    let host = resolve(config, provider, host)?.host();
+    match entry(&host)?.delete_credential() {
+        Ok(()) => println!("Removed token for {} from the keychain.", host),
+        Err(keyring::Error::NoEntry) => println!("No token stored for {}.", host),
+        Err(err) => {
+            return Err(err).with_context(|| format!("remove token for {} from the keychain", host))
+        }
+    }
+    Ok(())
+}
+
+/// Where each known provider's token would come from, without printing it.
+pub fn status(config: &Config) -> Result<()> {
+    let mut providers = vec![
+        Provider::GitHub,
+        Provider::GitLab {
+            host: "gitlab.com".to_string(),
+        },
+    ];
+    if let Some(base_url) = &config.references.jira_url {
+        providers.push(Provider::Jira {
+            base_url: base_url.clone(),
+        });
+    }
+    for provider in providers {
+        let source = match provider.token_source() {
+            Some(TokenSource::Env(var)) => format!("${}", var).green().to_string(),
+            Some(TokenSource::Keychain) => "keychain".green().to_string(),
+            Some(TokenSource::CredentialHelper) => "git credential helper".green().to_string(),
+            None => "not logged in".yellow().to_string(),
+        };
+        println!("{}: {}", provider.host(), source);
+    }
+    Ok(())
+}
+
+fn entry(host: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, host).context("open the OS keychain")
+}
+
+fn resolve(config: &Config, provider: AuthProvider, host: Option<&str>) -> Result<Provider> {
+    Ok(match provider {
+        AuthProvider::Github => Provider::GitHub,
+        AuthProvider::Gitlab => Provider::GitLab {
+            host: host.unwrap_or("gitlab.com").to_string(),
+        },
+        AuthProvider::Jira => {
+            let base_url = match host {
+                Some(host) => format!("https://{}", host),
+                None => config
+                    .references
+                    .jira_url
+                    .clone()
+                    .context("Pass --host or set [references] jira_url to log in to Jira")?,
+            };
+            Provider::Jira { base_url }
+        }
+    })
+}
@@ -0,0 +1,107 @@
+//! `[checks] allowed_author_domains` / `forbid_noreply_emails`: the author
+//! email of every inspected commit must follow the policy, e.g. so work
+//! commits are not attributed to a personal or GitHub noreply address.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::config::CheckConfig;
+use crate::git;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorProblem {
+    /// The domain is not in `allowed_author_domains`.
+    Domain,
+    /// A noreply address such as `123+me@users.noreply.github.com`.
+    Noreply,
+}
+
+impl AuthorProblem {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Domain => "domain not allowed",
+            Self::Noreply => "noreply address",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthorIssue {
+    pub hash: String,
+    pub email: String,
+    pub problem: AuthorProblem,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AuthorsReport {
+    /// `allowed_author_domains`, for the `git config user.email` fix.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_domains: Vec<String>,
+    /// Commits whose author email breaks the policy, in the order given.
+    pub issues: Vec<AuthorIssue>,
+}
+
+/// Commits among `hashes` whose author email breaks the policy.
+pub fn build_authors_report(config: &CheckConfig, hashes: &[String]) -> Result<AuthorsReport> {
+    let issues = git::author_emails(hashes)?
+        .into_iter()
+        .filter_map(|(hash, email)| {
+            let problem = problem(
+                &email,
+                &config.allowed_author_domains,
+                config.forbid_noreply_emails,
+            )?;
+            Some(AuthorIssue {
+                hash,
+                email,
+                problem,
+            })
+        })
+        .collect();
+    Ok(AuthorsReport {
+        allowed_domains: config.allowed_author_domains.clone(),
+        issues,
+    })
+}
+
+/// What is wrong with `email`, if anything. Domains match case-insensitively
+/// and include their subdomains: `company.com` allows `eu.company.com`.
+fn problem(email: &str, allowed_domains: &[String], forbid_noreply: bool) -> Option<AuthorProblem> {
+    let email = email.to_lowercase();
+    let domain = email.rsplit_once('@').map_or("", |(_, domain)| domain);
+    if forbid_noreply && (email.contains("noreply") || email.contains("no-reply")) {
+        return Some(AuthorProblem::Noreply);
+    }
+    let allowed = allowed_domains.is_empty()
+        || allowed_domains.iter().any(|allowed| {
+            let allowed = allowed.to_lowercase();
+            domain == allowed || domain.ends_with(&format!(".{}", allowed))
+        });
+    (!allowed).then_some(AuthorProblem::Domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emails_follow_domains_and_noreply_policy() {
+        let domains = vec!["Company.com".to_string()];
+        assert_eq!(problem("ann@company.com", &domains, true), None);
+        assert_eq!(problem("Ann@EU.company.com", &domains, true), None);
+        assert_eq!(
+            problem("ann@gmail.com", &domains, true),
+            Some(AuthorProblem::Domain)
+        );
+        assert_eq!(
+            problem("ann@notcompany.com", &domains, true),
+            Some(AuthorProblem::Domain)
+        );
+        assert_eq!(
+            problem("1+ann@users.noreply.github.com", &[], true),
+            Some(AuthorProblem::Noreply)
+        );
+        assert_eq!(problem("1+ann@users.noreply.github.com", &[], false), None);
+    }
+}
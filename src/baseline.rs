@@ -0,0 +1,226 @@
+//! `.gitsherpa-baseline.json`: violations accepted when adopting git-sherpa
+//! in a repository with a messy history. `baseline create` records the
+//! current ones; `check`, `fix` and `ci` then only fail on new violations.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::check::{build_report, CommitSelection, Report, Sampling};
+use crate::config::{load_config, Config};
+use crate::error::SherpaError;
+use crate::git;
+
+pub const BASELINE_FILE: &str = ".gitsherpa-baseline.json";
+
+/// Accepted violations, keyed so they survive unrelated changes: commits by
+/// full hash, files by path.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    /// Commits with an invalid message, or forbidden merge/fixup commits.
+    #[serde(default)]
+    pub commits: BTreeSet<String>,
+    #[serde(default)]
+    pub unsigned_commits: BTreeSet<String>,
+    /// Commits whose issue references do not resolve.
+    #[serde(default)]
+    pub dangling_references: BTreeSet<String>,
+    #[serde(default)]
+    pub sensitive_files: BTreeSet<String>,
+    #[serde(default)]
+    pub large_files: BTreeSet<String>,
+}
+
+/// The `baseline` block of the report.
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineReport {
+    pub path: String,
+    /// Violations left out of the report because the baseline accepts them.
+    pub accepted: usize,
+}
+
+impl Baseline {
+    /// Every violation in `report` that the baseline can hold.
+    pub fn from_report(report: &Report) -> Self {
+        Self {
+            commits: report
+                .commits
+                .iter()
+                .filter(|c| !c.valid)
+                .map(|c| c.hash.clone())
+                .collect(),
+            unsigned_commits: report.signatures.iter().map(|s| s.hash.clone()).collect(),
+            dangling_references: report
+                .references
+                .dangling
+                .iter()
+                .map(|r| r.commit.clone())
+                .collect(),
+            sensitive_files: report.sensitive.files.iter().cloned().collect(),
+            large_files: report.large_files.iter().map(|f| f.path.clone()).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commits.len()
+            + self.unsigned_commits.len()
+            + self.dangling_references.len()
+            + self.sensitive_files.len()
+            + self.large_files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Takes accepted violations out of `report` and its summary, returning
+    /// how many. Accepted commits stay listed, marked `baselined`. The
+    /// caller re-evaluates `report.rules`.
+    pub fn apply(&self, report: &mut Report) -> usize {
+        let mut accepted = 0;
+        for commit in &mut report.commits {
+            if !commit.valid && self.commits.contains(&commit.hash) {
+                commit.baselined = true;
+                accepted += 1;
+            }
+        }
+        accepted += retain(&mut report.signatures, |s| {
+            !self.unsigned_commits.contains(&s.hash)
+        });
+        accepted += retain(&mut report.references.dangling, |r| {
+            !self.dangling_references.contains(&r.commit)
+        });
+        accepted += retain(&mut report.sensitive.files, |f| {
+            !self.sensitive_files.contains(f)
+        });
+        retain(&mut report.sensitive.findings, |f| {
+            !self.sensitive_files.contains(&f.path)
+        });
+        accepted += retain(&mut report.large_files, |f| {
+            !self.large_files.contains(&f.path)
+        });
+
+        let summary = &mut report.summary;
+        summary.invalid_commits = report
+            .commits
+            .iter()
+            .filter(|c| !c.valid && !c.baselined)
+            .count();
+        summary.unsigned_commits = report.signatures.len();
+        summary.dangling_references = report.references.dangling.len();
+        summary.sensitive_files = report
+            .sensitive
+            .findings
+            .iter()
+            .filter(|f| f.severity >= report.sensitive.fail_on)
+            .count();
+        summary.large_files = report.large_files.len();
+        accepted
+    }
+}
+
+/// Keeps the items matching `keep`, returning how many were dropped.
+fn retain<T>(items: &mut Vec<T>, keep: impl FnMut(&T) -> bool) -> usize {
+    let before = items.len();
+    items.retain(keep);
+    before - items.len()
+}
+
+fn path() -> Result<PathBuf> {
+    Ok(git::toplevel()?.join(BASELINE_FILE))
+}
+
+fn load(path: &Path) -> Result<Option<Baseline>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+    };
+    let baseline = serde_json::from_str(&contents).map_err(|err| SherpaError::ConfigInvalid {
+        path: path.to_path_buf(),
+        span: None,
+        message: err.to_string(),
+    })?;
+    Ok(Some(baseline))
+}
+
+/// The repository's baseline file, if it has one.
+pub fn current() -> Result<Option<Baseline>> {
+    load(&path()?)
+}
+
+/// Applies the repository's baseline file, if it has one, to `report`.
+pub fn apply_to(config: &Config, report: &mut Report) -> Result<()> {
+    let Some(baseline) = current()? else {
+        return Ok(());
+    };
+    let accepted = baseline.apply(report);
+    report.evaluate_rules(&config.severity);
+    report.baseline = Some(BaselineReport {
+        path: BASELINE_FILE.to_string(),
+        accepted,
+    });
+    Ok(())
+}
+
+/// `baseline create`: records the violations in all of HEAD's history (or
+/// `range`) and the staged files, replacing any previous baseline.
+pub fn create(config_path: &Path, range: Option<String>) -> Result<()> {
+    let config = load_config(config_path)?;
+    let selection = CommitSelection::Sampled(Sampling {
+        range,
+        ..Sampling::default()
+    });
+    let report = build_report(&config, &selection)?;
+    let baseline = Baseline::from_report(&report);
+    let path = path()?;
+    let mut json = serde_json::to_string_pretty(&baseline)?;
+    json.push('\n');
+    fs::write(&path, json).with_context(|| format!("write {}", path.display()))?;
+    println!(
+        "{}",
+        format!(
+            "Recorded {} accepted violation(s) in {}",
+            baseline.len(),
+            BASELINE_FILE
+        )
+        .green()
+    );
+    println!(
+        "  {}",
+        "Commit it: check now only fails on violations that are not in it.".dimmed()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::CommitReport;
+
+    #[test]
+    fn accepted_violations_leave_the_summary() {
+        let mut report = Report::default();
+        for (hash, valid) in [("old", false), ("new", false), ("ok", true)] {
+            report.commits.push(CommitReport {
+                hash: hash.to_string(),
+                valid,
+                ..CommitReport::default()
+            });
+        }
+        report.sensitive.files = vec![".env".to_string(), "id_rsa".to_string()];
+        let baseline = Baseline {
+            commits: BTreeSet::from(["old".to_string()]),
+            sensitive_files: BTreeSet::from([".env".to_string()]),
+            ..Baseline::default()
+        };
+
+        assert_eq!(baseline.apply(&mut report), 2);
+        assert_eq!(report.summary.invalid_commits, 1);
+        assert!(report.commits[0].baselined);
+        assert_eq!(report.sensitive.files, vec!["id_rsa"]);
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{default_config_toml, load_config};
+use crate::git;
+use crate::hooks;
+
+const SCRIPTS_DIR: &str = ".gitsherpa";
+const COMMIT_TEMPLATE_PATH: &str = ".gitsherpa/commit-template.txt";
+
+const COMMIT_TEMPLATE: &str = "\
+# <type>(<scope>): <subject>
+#
+# Types: feat, fix, chore, docs, refactor, test, perf, ci, build
+# Keep the subject short and imperative; explain the why in the body.
+";
+
+/// Git settings recommended for every repository. Only applied when the key
+/// is not already set, so existing user choices are left untouched.
+const RECOMMENDED_GIT_CONFIG: &[(&str, &str)] = &[
+    ("fetch.prune", "true"),
+    ("pull.rebase", "true"),
+    ("rebase.autosquash", "true"),
+];
+
+/// An action performed by bootstrap that can be undone if a later step fails.
+enum Undo {
+    RemoveFile(PathBuf),
+    RemoveDir(PathBuf),
+    UnsetConfig(String),
+}
+
+#[derive(Default)]
+struct Transaction {
+    undo: Vec<Undo>,
+    summary: Vec<(String, bool)>,
+}
+
+impl Transaction {
+    fn done(&mut self, step: impl Into<String>) {
+        self.summary.push((step.into(), true));
+    }
+
+    fn skipped(&mut self, step: impl Into<String>) {
+        self.summary.push((step.into(), false));
+    }
+
+    fn rollback(self) {
+        for action in self.undo.into_iter().rev() {
+            let result = match &action {
+                Undo::RemoveFile(path) => fs::remove_file(path).map_err(anyhow::Error::from),
+                Undo::RemoveDir(path) => fs::remove_dir(path).map_err(anyhow::Error::from),
+                Undo::UnsetConfig(key) => git::config_unset(key),
+            };
+            if let Err(err) = result {
+                eprintln!("Warning: rollback step failed: {:#}", err);
+            }
+        }
+    }
+}
+
+pub fn bootstrap(config_path: &Path, preset: Option<&Path>) -> Result<()> {
+    // Fail early outside of a repository instead of half-writing files.
+    git::hooks_dir()?;
+
+    let mut tx = Transaction::default();
+    match run_steps(&mut tx, config_path, preset) {
+        Ok(()) => {
+            print_summary(&tx);
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("{}", "Bootstrap failed, rolling back...".red().bold());
+            tx.rollback();
+            Err(err)
+        }
+    }
+}
+
+fn run_steps(tx: &mut Transaction, config_path: &Path, preset: Option<&Path>) -> Result<()> {
+    write_config(tx, config_path, preset)?;
+    write_scripts_dir(tx)?;
+
+    let config = load_config(config_path)?;
+    let installed = hooks::install_with_config(false, &config.hooks)?;
+    if installed.is_empty() {
+        tx.skipped("git hooks already installed");
+    } else {
+        tx.done(format!("installed {} git hook(s)", installed.len()));
+        tx.undo.extend(installed.into_iter().map(Undo::RemoveFile));
+    }
+
+    set_commit_template(tx)?;
+    apply_recommended_config(tx)?;
+    Ok(())
+}
+
+fn write_config(tx: &mut Transaction, config_path: &Path, preset: Option<&Path>) -> Result<()> {
+    if config_path.exists() {
+        tx.skipped(format!("config {} already exists", config_path.display()));
+        return Ok(());
+    }
+
+    let contents = match preset {
+        Some(preset) => {
+            // Validate the preset before adopting it as the repo config.
+            load_config(preset)?;
+            fs::read_to_string(preset)
+                .with_context(|| format!("read preset {}", preset.display()))?
+        }
+        None => default_config_toml()?,
+    };
+    fs::write(config_path, contents).with_context(|| format!("write {}", config_path.display()))?;
+    tx.undo.push(Undo::RemoveFile(config_path.to_path_buf()));
+
+    match preset {
+        Some(preset) => tx.done(format!(
+            "wrote {} from preset {}",
+            config_path.display(),
+            preset.display()
+        )),
+        None => tx.done(format!("wrote default config {}", config_path.display())),
+    }
+    Ok(())
+}
+
+fn write_scripts_dir(tx: &mut Transaction) -> Result<()> {
+    let scripts_dir = PathBuf::from(SCRIPTS_DIR);
+    if !scripts_dir.exists() {
+        fs::create_dir_all(&scripts_dir)?;
+        tx.undo.push(Undo::RemoveDir(scripts_dir.clone()));
+    }
+
+    let template = PathBuf::from(COMMIT_TEMPLATE_PATH);
+    if template.exists() {
+        tx.skipped(format!(
+            "commit template {} already exists",
+            template.display()
+        ));
+    } else {
+        fs::write(&template, COMMIT_TEMPLATE)
+            .with_context(|| format!("write {}", template.display()))?;
+        tx.undo.push(Undo::RemoveFile(template.clone()));
+        tx.done(format!("wrote commit template {}", template.display()));
+    }
+    Ok(())
+}
+
+fn set_commit_template(tx: &mut Transaction) -> Result<()> {
+    match git::config_get("commit.template")? {
+        Some(existing) => tx.skipped(format!("commit.template already set to {}", existing)),
+        None => {
+            git::config_set("commit.template", COMMIT_TEMPLATE_PATH)?;
+            tx.undo
+                .push(Undo::UnsetConfig("commit.template".to_string()));
+            tx.done(format!("set commit.template = {}", COMMIT_TEMPLATE_PATH));
+        }
+    }
+    Ok(())
+}
+
+fn apply_recommended_config(tx: &mut Transaction) -> Result<()> {
+    for (key, value) in RECOMMENDED_GIT_CONFIG {
+        if let Some(existing) = git::config_get(key)? {
+            tx.skipped(format!("{} already set to {}", key, existing));
+            continue;
+        }
+        git::config_set(key, value)?;
+        tx.undo.push(Undo::UnsetConfig(key.to_string()));
+        tx.done(format!("set {} = {}", key, value));
+    }
+    Ok(())
+}
+
+fn print_summary(tx: &Transaction) {
+    println!("\n{}", "Bootstrap summary:".bold());
+    for (step, changed) in &tx.summary {
+        if *changed {
+            println!("  {} {}", "+".green(), step);
+        } else {
+            println!("  {} {}", "=".dimmed(), step.dimmed());
+        }
+    }
+    let changes = tx.summary.iter().filter(|(_, changed)| *changed).count();
+    if changes == 0 {
+        println!(
+            "\n{}",
+            "Already bootstrapped, nothing to do.".green().bold()
+        );
+    } else {
+        println!(
+            "\n{}",
+            format!("Applied {} change(s).", changes).green().bold()
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! `branch new`: creates a branch named from `[branches] template`, after
+//! checking the name against the branch naming rule, so it cannot fail `check`
+//! later. `fix` composes the name it suggests for an invalid branch the same
+//! way.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use regex::Regex;
+
+use crate::branch_naming::{closest, BranchNaming};
+use crate::config::BranchConfig;
+use crate::git;
+
+pub fn new_branch(
+    config: &BranchConfig,
+    kind: &str,
+    name: &str,
+    ticket: Option<&str>,
+    upstream: bool,
+) -> Result<()> {
+    let branch = compose(config, kind, name, ticket);
+    let problems = BranchNaming::from_config(config)?.problems(&branch);
+    if !problems.is_empty() {
+        let template = if ticket.is_some() {
+            "ticket_template"
+        } else {
+            "template"
+        };
+        bail!(
+            "'{}' {}; adjust the arguments or [branches] {}",
+            branch,
+            problems.join("; "),
+            template
+        );
+    }
+    git::create_branch(&branch)?;
+    println!("{}", format!("Created and switched to {}", branch).green());
+    if upstream {
+        git::push_set_upstream(&branch)?;
+    }
+    Ok(())
+}
+
+/// Fills in the template; the name is slugged, type and ticket are used as given.
+fn compose(config: &BranchConfig, kind: &str, name: &str, ticket: Option<&str>) -> String {
+    let template = match ticket {
+        Some(_) => &config.ticket_template,
+        None => &config.template,
+    };
+    template
+        .replace("{type}", kind)
+        .replace("{ticket}", ticket.unwrap_or_default())
+        .replace("{name}", &slug(name))
+}
+
+/// Types tried when neither the name nor the commits suggest one.
+const FALLBACK_TYPES: &[&str] = &["feat", "fix", "chore"];
+
+/// A name for the branch `current` that the naming rule accepts, composed
+/// from the templates: `current` slugged, with the type it starts with if
+/// that is allowed and else the type of most of `subjects` (its commit
+/// subjects, newest first), and with the ticket ID it or a subject mentions.
+/// `None` if no type makes it valid.
+pub fn suggest_name(
+    config: &BranchConfig,
+    current: &str,
+    subjects: &[&str],
+) -> Result<Option<String>> {
+    let naming = BranchNaming::from_config(config)?;
+    // (type, name to slug): the type the name starts with replaces it.
+    let mut candidates: Vec<(String, &str)> = Vec::new();
+    if let Some((prefix, rest)) = current.split_once(config.separator.as_str()) {
+        let kind = if config.types.is_empty() {
+            Some(prefix.to_lowercase())
+        } else {
+            closest(prefix, &config.types).map(str::to_string)
+        };
+        if let Some(kind) = kind {
+            candidates.push((kind, rest));
+        }
+    }
+    let kinds = commit_types(subjects)
+        .into_iter()
+        .chain(config.types.iter().cloned())
+        .chain(FALLBACK_TYPES.iter().map(|t| t.to_string()));
+    candidates.extend(kinds.map(|kind| (kind, current)));
+
+    let ticket = config
+        .ticket_pattern
+        .as_deref()
+        .and_then(|pattern| Regex::new(pattern).ok())
+        .and_then(|regex| {
+            std::iter::once(current)
+                .chain(subjects.iter().copied())
+                .find_map(|text| regex.find(text).map(|m| m.as_str().to_string()))
+        });
+    let without_ticket = |name: &str| match &ticket {
+        Some(ticket) => name.replace(ticket.as_str(), " "),
+        None => name.to_string(),
+    };
+    let tickets = [ticket.as_deref(), None];
+    Ok(candidates
+        .iter()
+        .map(|(kind, name)| (kind, without_ticket(name)))
+        .filter(|(_, name)| !slug(name).is_empty())
+        .flat_map(|(kind, name)| tickets.map(|ticket| compose(config, kind, &name, ticket)))
+        .find(|candidate| naming.problems(candidate).is_empty()))
+}
+
+/// The Conventional Commits types of `subjects`, most used first; ties go to
+/// the newest.
+fn commit_types(subjects: &[&str]) -> Vec<String> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for subject in subjects {
+        let Some((head, _)) = subject.split_once(':') else {
+            continue;
+        };
+        let kind = head
+            .split('(')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('!');
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+            continue;
+        }
+        let kind = kind.to_lowercase();
+        match counts.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((kind, 1)),
+        }
+    }
+    // Stable: equal counts keep the newest first.
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts.into_iter().map(|(kind, _)| kind).collect()
+}
+
+/// Lowercase, with each run of other characters turned into one `-`.
+fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    #[test]
+    fn composes_names_from_templates() {
+        let config = default_config().branches;
+        assert_eq!(
+            compose(&config, "feat", "Login flow!", None),
+            "feat/login-flow"
+        );
+        assert_eq!(
+            compose(&config, "fix", "login_flow", Some("ABC-123")),
+            "fix/ABC-123-login-flow"
+        );
+    }
+
+    #[test]
+    fn suggests_a_valid_name_for_an_invalid_branch() {
+        let config = default_config().branches;
+        let subjects = [
+            "docs: explain login",
+            "fix(auth): handle expiry",
+            "fix: retry",
+        ];
+        let suggest =
+            |current, subjects: &[&str]| suggest_name(&config, current, subjects).unwrap();
+        assert_eq!(
+            suggest("Login_Page", &subjects).as_deref(),
+            Some("fix/login-page")
+        );
+        assert_eq!(
+            suggest("Login_Page", &[]).as_deref(),
+            Some("feat/login-page")
+        );
+        // A type the name already has is kept, if the pattern allows it.
+        assert_eq!(
+            suggest("docs/Login Page", &subjects).as_deref(),
+            Some("docs/login-page")
+        );
+        assert_eq!(
+            suggest("wip/login", &subjects).as_deref(),
+            Some("fix/wip-login")
+        );
+        assert_eq!(suggest("__", &subjects), None);
+
+        let mut config = default_config().branches;
+        config.types = vec!["feature".to_string(), "bugfix".to_string()];
+        config.ticket_pattern = Some("[A-Z]+-[0-9]+".to_string());
+        let subjects = ["fix: handle expiry (AUTH-42)"];
+        assert_eq!(
+            suggest_name(&config, "feat/Login_Page", &subjects)
+                .unwrap()
+                .as_deref(),
+            Some("feature/AUTH-42-login-page")
+        );
+        assert_eq!(
+            suggest_name(&config, "AUTH-7_login", &[])
+                .unwrap()
+                .as_deref(),
+            Some("feature/AUTH-7-login")
+        );
+        assert_eq!(suggest_name(&config, "login", &[]).unwrap(), None);
+    }
+}
@@ -0,0 +1,324 @@
+//! `branches audit`: every local (and with `--remote`, remote-tracking)
+//! branch with its age, whether it is merged into the base branch and
+//! whether its name follows the branch naming rule, to drive cleanups.
+//! `check --remote` fetches a remote and fails on its branches that break
+//! the naming rule, so they are found without checking them out.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use glob_match::glob_match;
+use serde::Serialize;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::branch_naming::BranchNaming;
+use crate::cli::Format;
+use crate::config::{load_config, load_config_or_default, Config};
+use crate::error::ExitStatus;
+use crate::git;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Serialize)]
+pub struct BranchAuditReport {
+    /// Branch `merged` is measured against.
+    pub base: String,
+    pub stale_after_days: u64,
+    pub branches: Vec<AuditedBranch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditedBranch {
+    /// Short name, e.g. `feat/login` or `origin/feat/login`.
+    pub name: String,
+    pub remote: bool,
+    /// Days since the last commit's committer date.
+    pub age_days: u64,
+    pub stale: bool,
+    /// Fully contained in `base`, so safe to delete.
+    pub merged: bool,
+    /// Name matches the branch naming rule (remote prefix stripped).
+    pub compliant: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteBranchReport {
+    pub remote: String,
+    /// The regex names are validated against.
+    pub pattern: String,
+    /// Branches checked; the base branch and protected branches are exempt.
+    pub checked: usize,
+    pub offenders: Vec<RemoteOffender>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteOffender {
+    /// Without the remote prefix, e.g. `Login_Page`.
+    pub name: String,
+    /// Who committed last, to ask about renaming or deleting it.
+    pub committer: String,
+    /// Days since that commit.
+    pub age_days: u64,
+    pub problems: Vec<String>,
+}
+
+pub fn audit(config_path: &Path, base: Option<&str>, remote: bool, format: Format) -> Result<()> {
+    let config = load_config_or_default(config_path);
+    let report = build(&config, base, remote)?;
+    match format {
+        Format::Text => print_text(&report),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+/// `check --remote`: fetches `remote` and checks the name of each of its
+/// branches; exits 1 when any breaks the naming rule.
+pub fn check_remote(config_path: &Path, remote: &str, format: Format) -> Result<ExitStatus> {
+    let config = load_config(config_path)?;
+    git::fetch_prune(remote)?;
+    let report = remote_report(&config, remote)?;
+    match format {
+        Format::Text => print_remote_text(&report),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(ExitStatus::from_violations(!report.offenders.is_empty()))
+}
+
+fn remote_report(config: &Config, remote: &str) -> Result<RemoteBranchReport> {
+    let base = base_branch(config, None, remote)?;
+    let remotes = git::remotes()?;
+    let naming = BranchNaming::from_config(&config.branches)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let branches: Vec<_> = git::remote_branches(remote)?
+        .into_iter()
+        .filter(|(name, _, _)| !is_permanent(config, branch_part(&base, &remotes), name))
+        .collect();
+    let checked = branches.len();
+    let offenders = branches
+        .into_iter()
+        .filter_map(|(name, committer, committed)| {
+            let problems = naming.problems(&name);
+            (!problems.is_empty()).then(|| RemoteOffender {
+                age_days: now.saturating_sub(committed) / SECS_PER_DAY,
+                name,
+                committer,
+                problems,
+            })
+        })
+        .collect();
+    Ok(RemoteBranchReport {
+        remote: remote.to_string(),
+        pattern: naming.pattern().to_string(),
+        checked,
+        offenders,
+    })
+}
+
+/// `base`, else `[checks] base_branch`, else `remote`'s default branch.
+fn base_branch(config: &Config, base: Option<&str>, remote: &str) -> Result<String> {
+    Ok(
+        match base
+            .map(str::to_string)
+            .or(config.checks.base_branch.clone())
+        {
+            Some(base) => base,
+            None => match git::remote_head(remote)? {
+                Some(head) => head,
+                None => "main".to_string(),
+            },
+        },
+    )
+}
+
+/// Long-lived branches are neither stale nor held to the naming pattern.
+/// `protected_branches` entries are globs, as in the pre-push hook.
+fn is_permanent(config: &Config, base: &str, branch: &str) -> bool {
+    branch == base
+        || config
+            .hooks
+            .protected_branches
+            .iter()
+            .any(|pattern| glob_match(pattern, branch))
+}
+
+pub(crate) fn build(
+    config: &Config,
+    base: Option<&str>,
+    remote: bool,
+) -> Result<BranchAuditReport> {
+    let base = base_branch(config, base, "origin")?;
+    if git::resolve_rev(&base)?.is_none() {
+        bail!(
+            "Base branch '{}' not found: pass --base or set [checks] base_branch",
+            base
+        );
+    }
+    let naming = BranchNaming::from_config(&config.branches)?;
+    let merged = git::merged_branches(&base, remote)?;
+    let remotes = git::remotes()?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let stale_after_days = config.branches.stale_after_days;
+
+    let branches = git::branches(remote)?
+        .into_iter()
+        .filter_map(|(refname, committed)| {
+            let is_remote = refname.starts_with("refs/remotes/");
+            let name = short_name(&refname).to_string();
+            let branch = if is_remote {
+                branch_part(&name, &remotes)
+            } else {
+                &name
+            };
+            if is_permanent(config, branch_part(&base, &remotes), branch) {
+                return None;
+            }
+            let age_days = now.saturating_sub(committed) / SECS_PER_DAY;
+            Some(AuditedBranch {
+                remote: is_remote,
+                age_days,
+                stale: age_days >= stale_after_days,
+                merged: merged.contains(&refname),
+                compliant: naming.is_match(branch),
+                name,
+            })
+        })
+        .collect();
+
+    Ok(BranchAuditReport {
+        base,
+        stale_after_days,
+        branches,
+    })
+}
+
+fn short_name(refname: &str) -> &str {
+    refname
+        .strip_prefix("refs/heads/")
+        .or_else(|| refname.strip_prefix("refs/remotes/"))
+        .unwrap_or(refname)
+}
+
+/// `feat/login` from `origin/feat/login`; names without a remote prefix
+/// are returned as-is.
+fn branch_part<'a>(name: &'a str, remotes: &[String]) -> &'a str {
+    match name.split_once('/') {
+        Some((remote, branch)) if remotes.iter().any(|r| r == remote) => branch,
+        _ => name,
+    }
+}
+
+fn print_text(report: &BranchAuditReport) {
+    println!(
+        "{}",
+        format!(
+            "Branches (merged into {}, stale after {} days):",
+            report.base, report.stale_after_days
+        )
+        .bold()
+    );
+    for branch in &report.branches {
+        let age = format!("{}d", branch.age_days);
+        let merged = if branch.merged {
+            "merged".green()
+        } else {
+            "unmerged".normal()
+        };
+        let name = if branch.compliant {
+            "name OK".green()
+        } else {
+            "name INVALID".red()
+        };
+        println!(
+            "  {:<40} {:>6} {:<8} {}",
+            branch.name,
+            if branch.stale {
+                age.yellow()
+            } else {
+                age.normal()
+            },
+            merged,
+            name
+        );
+    }
+
+    let merged: Vec<&str> = report
+        .branches
+        .iter()
+        .filter(|b| b.merged && !b.remote)
+        .map(|b| b.name.as_str())
+        .collect();
+    let stale = report.branches.iter().filter(|b| b.stale).count();
+    let invalid = report.branches.iter().filter(|b| !b.compliant).count();
+    println!(
+        "\n{} branch(es): {} stale, {} merged, {} with invalid names",
+        report.branches.len(),
+        stale,
+        merged.len(),
+        invalid
+    );
+    if !merged.is_empty() {
+        println!("  {}", format!("git branch -d {}", merged.join(" ")).cyan());
+    }
+}
+
+fn print_remote_text(report: &RemoteBranchReport) {
+    println!(
+        "{}",
+        format!(
+            "Branches on {} checked against `{}`: {}",
+            report.remote, report.pattern, report.checked
+        )
+        .bold()
+    );
+    if report.offenders.is_empty() {
+        println!("{}", "All branch names follow the naming rule.".green());
+        return;
+    }
+    for offender in &report.offenders {
+        println!(
+            "  {:<40} {:>6} {:<24} {}",
+            format!("{}/{}", report.remote, offender.name).red(),
+            format!("{}d", offender.age_days),
+            offender.committer,
+            offender.problems.join("; ").dimmed()
+        );
+    }
+    println!("\n{} branch(es) with invalid names", report.offenders.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_names_drop_the_ref_namespace() {
+        assert_eq!(short_name("refs/heads/feat/login"), "feat/login");
+        assert_eq!(
+            short_name("refs/remotes/origin/feat/login"),
+            "origin/feat/login"
+        );
+    }
+
+    #[test]
+    fn protected_branch_globs_are_permanent() {
+        let mut config = crate::config::default_config();
+        config.hooks.protected_branches = vec!["main".to_string(), "release/*".to_string()];
+        assert!(is_permanent(&config, "develop", "develop"));
+        assert!(is_permanent(&config, "develop", "release/1.2"));
+        assert!(!is_permanent(&config, "develop", "feat/release"));
+    }
+
+    #[test]
+    fn branch_part_strips_known_remotes_only() {
+        let remotes = vec!["origin".to_string()];
+        assert_eq!(branch_part("origin/feat/login", &remotes), "feat/login");
+        assert_eq!(branch_part("feat/login", &remotes), "feat/login");
+    }
+}
@@ -0,0 +1,238 @@
+//! The branch naming rule: `branches.pattern` as written, or, when
+//! `branches.types` is set, a pattern derived from the structured schema
+//! `<type><separator>[<ticket>-]<slug>`. The schema also says what is wrong
+//! with a name instead of only that it does not match.
+
+use anyhow::Result;
+use regex::Regex;
+
+use crate::config::BranchConfig;
+use crate::error::SherpaError;
+
+pub struct BranchNaming {
+    pattern: String,
+    regex: Regex,
+    schema: Option<Schema>,
+}
+
+struct Schema {
+    types: Vec<String>,
+    separator: String,
+    ticket: Option<(String, Regex)>,
+    slug: (String, Regex),
+}
+
+impl BranchNaming {
+    pub fn from_config(config: &BranchConfig) -> Result<Self> {
+        if config.types.is_empty() {
+            let regex = compile(&config.pattern)?;
+            return Ok(Self {
+                pattern: config.pattern.clone(),
+                regex,
+                schema: None,
+            });
+        }
+
+        let types = config
+            .types
+            .iter()
+            .map(|t| regex::escape(t))
+            .collect::<Vec<_>>()
+            .join("|");
+        let ticket = match &config.ticket_pattern {
+            Some(ticket) => format!("(?:{})-", ticket),
+            None => String::new(),
+        };
+        let pattern = format!(
+            "^(?:{}){}{}(?:{})$",
+            types,
+            regex::escape(&config.separator),
+            ticket,
+            config.slug_pattern
+        );
+        let regex = compile(&pattern)?;
+        let anchored = |part: &str| compile(&format!("^(?:{})$", part));
+        let ticket = match &config.ticket_pattern {
+            Some(ticket) => Some((ticket.clone(), compile(&format!("^(?:{})-", ticket))?)),
+            None => None,
+        };
+        Ok(Self {
+            pattern,
+            regex,
+            schema: Some(Schema {
+                types: config.types.clone(),
+                separator: config.separator.clone(),
+                ticket,
+                slug: (config.slug_pattern.clone(), anchored(&config.slug_pattern)?),
+            }),
+        })
+    }
+
+    /// The regex names are validated against, derived or as configured.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+
+    /// Why `name` does not match; empty if it does. A plain pattern can only
+    /// be named, the schema points at the part that is wrong.
+    pub fn problems(&self, name: &str) -> Vec<String> {
+        if self.is_match(name) {
+            return Vec::new();
+        }
+        let problems = match &self.schema {
+            Some(schema) => schema.problems(name),
+            None => Vec::new(),
+        };
+        if problems.is_empty() {
+            // Also when the parts match on their own, e.g. a type containing the separator.
+            return vec![format!("does not match `{}`", self.pattern)];
+        }
+        problems
+    }
+}
+
+impl Schema {
+    fn problems(&self, name: &str) -> Vec<String> {
+        let Some((kind, rest)) = name.split_once(self.separator.as_str()) else {
+            return vec![format!(
+                "missing type prefix: expected <type>{}... (allowed: {})",
+                self.separator,
+                self.types.join(", ")
+            )];
+        };
+        let mut problems = Vec::new();
+        if !self.types.iter().any(|t| t == kind) {
+            problems.push(match closest(kind, &self.types) {
+                Some(suggestion) => format!(
+                    "type '{}' is not allowed; did you mean '{}'?",
+                    kind, suggestion
+                ),
+                None => format!(
+                    "type '{}' is not allowed (allowed: {})",
+                    kind,
+                    self.types.join(", ")
+                ),
+            });
+        }
+        let slug = match &self.ticket {
+            Some((pattern, ticket)) => match ticket.find(rest) {
+                Some(found) => &rest[found.end()..],
+                None => {
+                    problems.push(format!(
+                        "missing ticket ID: expected `{}-` after '{}{}'",
+                        pattern, kind, self.separator
+                    ));
+                    return problems;
+                }
+            },
+            None => rest,
+        };
+        let (pattern, regex) = &self.slug;
+        if !regex.is_match(slug) {
+            problems.push(format!(
+                "'{}' does not match slug_pattern `{}`",
+                slug, pattern
+            ));
+        }
+        problems
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Ok(Regex::new(pattern).map_err(|err| SherpaError::invalid_pattern(pattern, err))?)
+}
+
+/// The allowed type `kind` is most likely a typo or long form of: one it
+/// starts with (`feature` for `feat`), or one at most two edits away.
+pub fn closest<'a>(kind: &str, types: &'a [String]) -> Option<&'a str> {
+    let kind = kind.to_lowercase();
+    types
+        .iter()
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            let distance = if kind.starts_with(t.as_str()) || t.starts_with(&kind) {
+                0
+            } else {
+                edit_distance(&kind, t)
+            };
+            (distance, t.as_str())
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, t)| t)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    fn schema(ticket: Option<&str>) -> BranchNaming {
+        let mut config = default_config().branches;
+        config.types = vec!["feat".to_string(), "fix".to_string()];
+        config.ticket_pattern = ticket.map(str::to_string);
+        BranchNaming::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn schema_explains_what_is_wrong() {
+        let naming = schema(Some("[A-Z]+-[0-9]+"));
+        assert_eq!(
+            naming.pattern(),
+            "^(?:feat|fix)/(?:[A-Z]+-[0-9]+)-(?:[a-z0-9-]+)$"
+        );
+        assert!(naming.is_match("feat/ABC-12-login-flow"));
+        assert!(naming.problems("fix/ABC-1-typo").is_empty());
+        assert_eq!(
+            naming.problems("feat/login-flow"),
+            vec!["missing ticket ID: expected `[A-Z]+-[0-9]+-` after 'feat/'"]
+        );
+        assert_eq!(
+            naming.problems("feature/ABC-12-Login"),
+            vec![
+                "type 'feature' is not allowed; did you mean 'feat'?",
+                "'Login' does not match slug_pattern `[a-z0-9-]+`",
+            ]
+        );
+        assert_eq!(
+            naming.problems("login"),
+            vec!["missing type prefix: expected <type>/... (allowed: feat, fix)"]
+        );
+
+        let naming = schema(None);
+        assert!(naming.is_match("fix/typo"));
+        assert_eq!(
+            naming.problems("docs/typo"),
+            vec!["type 'docs' is not allowed (allowed: feat, fix)"]
+        );
+    }
+
+    #[test]
+    fn plain_pattern_is_used_as_written() {
+        let naming = BranchNaming::from_config(&default_config().branches).unwrap();
+        assert!(naming.is_match("feat/login"));
+        assert_eq!(
+            naming.problems("Login"),
+            vec!["does not match `^(feat|fix|chore|docs|refactor)/[a-z0-9-]+$`"]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! `.git/sherpa-cache`: results worth keeping between runs. `commits.json`
+//! holds the `commit-convention` verdict of each commit `check` validated,
+//! keyed by its hash and a fingerprint of the rules that judged it, so a hook
+//! run only validates the commits it has not seen under the current config.
+//! The synced policy and the audit state live there too, but are not caches:
+//! `cache clear` leaves them alone.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::check::ForbiddenCommit;
+use crate::config::Config;
+use crate::convention::{BodyRule, Convention};
+use crate::git;
+use crate::version::fnv1a;
+
+const COMMITS_FILE: &str = "commits.json";
+/// Provider API responses; see `providers`.
+pub const HTTP_FILE: &str = "http.json";
+
+/// Where every cache of the repository lives.
+pub fn dir() -> Result<PathBuf> {
+    Ok(git::git_dir()?.join("sherpa-cache"))
+}
+
+/// What validating a commit found; `valid` is `problems.is_empty()`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verdict {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub body_rules: Vec<BodyRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forbidden: Option<ForbiddenCommit>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CommitsFile {
+    /// Verdicts by `<fingerprint>:<commit hash>`.
+    entries: BTreeMap<String, Verdict>,
+}
+
+/// The verdicts of one check, read from and written back to `commits.json`.
+pub struct CommitCache {
+    path: Option<PathBuf>,
+    fingerprint: String,
+    file: CommitsFile,
+    changed: bool,
+}
+
+impl CommitCache {
+    /// The cached verdicts for `fingerprint`; an unreadable cache is empty.
+    pub fn load(fingerprint: u64) -> Self {
+        let path = dir().ok().map(|dir| dir.join(COMMITS_FILE));
+        let file = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            fingerprint: format!("{:016x}", fingerprint),
+            file,
+            changed: false,
+        }
+    }
+
+    fn key(&self, hash: &str) -> String {
+        format!("{}:{}", self.fingerprint, hash)
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&Verdict> {
+        self.file.entries.get(&self.key(hash))
+    }
+
+    pub fn insert(&mut self, hash: &str, verdict: Verdict) {
+        self.file.entries.insert(self.key(hash), verdict);
+        self.changed = true;
+    }
+
+    /// Writes the new verdicts back, dropping those of other configs, which
+    /// no later run under this one reads. The cache is an optimization;
+    /// failing to persist it is not an error.
+    pub fn save(mut self) {
+        if !self.changed {
+            return;
+        }
+        let prefix = format!("{}:", self.fingerprint);
+        self.file.entries.retain(|key, _| key.starts_with(&prefix));
+        let _ = self.write();
+    }
+
+    fn write(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+        }
+        let json = serde_json::to_string(&self.file)?;
+        fs::write(path, json).with_context(|| format!("write {}", path.display()))
+    }
+}
+
+/// Identifies what judges a commit: the conventions of `config` and its
+/// nested configs, where those apply, the merge and fixup rules, and the
+/// git-sherpa version. Equal fingerprints judge every commit alike.
+pub fn fingerprint(config: &Config, conventions: &[(&Config, Convention)]) -> u64 {
+    let mut text = env!("CARGO_PKG_VERSION").to_string();
+    for (scoped, convention) in conventions {
+        text.push_str(&format!(
+            "\n{:016x} {} {}",
+            convention.fingerprint(),
+            scoped.commits.forbid_merge_commits,
+            scoped.commits.forbid_fixup_commits
+        ));
+    }
+    for scope in &config.scopes {
+        text.push_str(&format!("\n{}", scope.dir));
+    }
+    fnv1a(text.as_bytes())
+}
+
+/// `cache clear`: removes the cached commit verdicts and API responses.
+pub fn clear() -> Result<()> {
+    let dir = dir()?;
+    let mut removed = 0;
+    for name in [COMMITS_FILE, HTTP_FILE] {
+        let path = dir.join(name);
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+            println!("Removed {}", path.display());
+            removed += 1;
+        }
+    }
+    if removed == 0 {
+        println!("Nothing cached in {}", dir.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    #[test]
+    fn fingerprints_change_with_what_judges_commits() {
+        let fingerprint_of = |config: &Config| {
+            let convention = Convention::from_config(&config.commits).unwrap();
+            fingerprint(config, &[(config, convention)])
+        };
+        let mut config = default_config();
+        let base = fingerprint_of(&config);
+        assert_eq!(fingerprint_of(&default_config()), base);
+
+        config.commits.ignore_hashes.push("a".repeat(40));
+        config.checks.require_upstream = false;
+        assert_eq!(fingerprint_of(&config), base);
+
+        config.commits.types = vec!["feat".to_string()];
+        assert_ne!(fingerprint_of(&config), base);
+        let mut config = default_config();
+        config.commits.forbid_fixup_commits = true;
+        assert_ne!(fingerprint_of(&config), base);
+    }
+}
@@ -1,132 +1,1079 @@
 use anyhow::{bail, Context, Result};
-use colored::Colorize;
+use colored::{ColoredString, Colorize};
 use regex::Regex;
-use serde::Serialize;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::cli::OutputFormat;
-use crate::config::{load_config, Config};
+use crate::attributes::AttributesReport;
+use crate::authors::AuthorsReport;
+use crate::baseline::{self, BaselineReport};
+use crate::branch_naming::BranchNaming;
+use crate::cache::{self, CommitCache, Verdict};
+use crate::ci;
+use crate::compat;
+use crate::config::{self, load_config, Config};
+use crate::convention::{BodyRule, Convention};
+use crate::environment::Environment;
+use crate::error::{ExitStatus, SherpaError};
 use crate::git;
-use crate::sensitive;
+use crate::hooks;
+use crate::jsonl;
+use crate::large_files::LargeFile;
+use crate::linear::Linearity;
+use crate::location::Location;
+use crate::notes;
+use crate::outgoing::{self, Outgoing};
+use crate::protected::ProtectedChange;
+use crate::pull_request::{self, PrState, PullRequestStatus};
+use crate::quiet_hours::QuietCommit;
+use crate::references::ReferencesReport;
+use crate::remote::{RemoteProblem, RemoteStatus};
+use crate::repo_mode::{self, RepoMode};
+use crate::rewrites::{self, UpstreamRewrite};
+use crate::rules::{self, Context as RuleContext, Registry, RuleRun};
+use crate::secrets::ContentFinding;
+use crate::sensitive::{SensitiveFinding, Severity};
+use crate::severity::{self, FailOn, RuleResult, RuleSeverity};
+use crate::signatures::SignatureIssue;
+use crate::staged_content::ContentIssue;
+use crate::version::fnv1a;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub struct Report {
+    /// Tool, config and machine that produced the report; filled in by the
+    /// CLI, left to the caller by [`build_report`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
     pub branch: BranchReport,
+    /// Revision range the commits were taken from, if one was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<String>,
+    /// How the commits were sampled, for `--since` / `--sample` audits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingReport>,
+    /// Commits scanned in pre-push mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outgoing: Option<Outgoing>,
     pub commits: Vec<CommitReport>,
+    /// Selected commits left out by `[commits] ignore_hashes` or
+    /// `--ignore-rev-file`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignored_commits: Vec<String>,
+    /// Inspected commits without a good signature, with
+    /// `[checks] require_signed_commits`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<SignatureIssue>,
+    /// Inspected commits whose author email breaks `[checks]
+    /// allowed_author_domains` or `forbid_noreply_emails`.
+    pub authors: AuthorsReport,
+    /// Advisory: inspected commits authored in `[checks] quiet_hours` or on
+    /// a weekend with `quiet_weekends`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub quiet_hours: Vec<QuietCommit>,
     pub repo: RepoReport,
     pub sensitive: SensitiveReport,
+    /// Files over `[checks] max_file_size_kb`.
+    pub large_files: Vec<LargeFile>,
+    /// Staged changes under `[checks] protected_paths`.
+    pub protected_paths: Vec<ProtectedChange>,
+    pub attributes: AttributesReport,
+    pub references: ReferencesReport,
+    /// Conflict markers and trailing whitespace in staged content.
+    pub staged_content: Vec<ContentIssue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pull_request: Option<PullRequestStatus>,
+    /// Violations accepted by `.gitsherpa-baseline.json`, when it exists.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<BaselineReport>,
+    /// Failing rules with their `[severity]`; rules set to `off` are left out.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RuleResult>,
     pub summary: Summary,
+    /// The rules that ran, for `check --timings`.
+    #[serde(skip)]
+    pub runs: Vec<RuleRun>,
+    /// The git queries the rules start from, for `check --timings`.
+    #[serde(skip)]
+    pub queries: QueryTimings,
+    /// Abbreviations of the selected commits, by full id, looked up in one
+    /// batch before the rules run.
+    #[serde(skip)]
+    pub short_hashes: HashMap<String, String>,
 }
 
-#[derive(Debug, Serialize)]
+/// How long the git queries run before the rules took.
+#[derive(Debug, Default)]
+pub struct QueryTimings {
+    /// Each query by name, e.g. `log` or `status`.
+    pub queries: Vec<(&'static str, Duration)>,
+    /// Wall time of the queries run at once; less than their sum.
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct BranchReport {
     pub name: String,
+    /// `branches.pattern`, or the one derived from `branches.types`.
     pub pattern: String,
     pub valid: bool,
+    /// Why the name does not match, e.g. a missing ticket ID.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+    /// With `[checks] max_branch_age_days`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age: Option<BranchAge>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchAge {
+    /// `base_branch`, else the default branch of `origin`.
+    pub base: String,
+    /// Days since the first commit not on `base` was authored.
+    pub days: u64,
+    pub max_days: u64,
+}
+
+impl BranchAge {
+    pub fn too_old(&self) -> bool {
+        self.days > self.max_days
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct CommitReport {
     pub hash: String,
+    /// `hash` abbreviated as text output shows it.
+    pub short_hash: String,
+    /// The commit, as other outputs point at it.
+    pub location: Location,
     pub message: String,
     pub valid: bool,
+    /// Why the commit is invalid, e.g. a disallowed type or a missing body.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+    /// `[commits.body]` rules the full message fails.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub body_rules: Vec<BodyRule>,
+    /// Set when `forbid_merge_commits` / `forbid_fixup_commits` flags the commit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forbidden: Option<ForbiddenCommit>,
+    /// Invalid, but accepted by the baseline file; not counted as a violation.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub baselined: bool,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForbiddenCommit {
+    Merge,
+    /// `fixup!`, `squash!` or `amend!` commits left for `--autosquash`.
+    Fixup,
+}
+
+/// Subject prefixes `git commit --fixup/--squash` write.
+pub(crate) const AUTOSQUASH_PREFIXES: [&str; 3] = ["fixup! ", "squash! ", "amend! "];
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Default, Serialize)]
 pub struct Summary {
     pub total_commits: usize,
     pub invalid_commits: usize,
+    pub unsigned_commits: usize,
+    pub disallowed_author_emails: usize,
+    pub quiet_hours_commits: usize,
     pub branch_valid: bool,
+    pub branch_too_old: bool,
     pub worktree_clean: bool,
+    /// The branch's remote is missing, unreachable or rejects the
+    /// credentials, with `require_remote`.
+    pub remote_broken: bool,
     pub upstream_set: bool,
     pub sensitive_files: usize,
+    /// Content findings at or above `fail_on`.
+    pub secrets: usize,
+    pub large_files: usize,
+    pub protected_paths: usize,
+    /// Commits on `[checks] base_branch` missing from this branch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub behind_base: Option<usize>,
+    /// Commits on this branch missing from `[checks] base_branch`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ahead_base: Option<usize>,
+    /// Ahead/behind counts exceed `max_ahead`/`max_behind`.
+    pub base_diverged: bool,
+    /// Merge commits on top of the base branch with `require_linear_history`.
+    pub nonlinear_history: bool,
+    /// Commits already on the upstream were rewritten locally, so pushing
+    /// needs `--force-with-lease`. A warning only: it does not fail the check.
+    pub diverged_from_upstream: bool,
+    pub attribute_issues: usize,
+    pub dangling_references: usize,
+    pub content_issues: usize,
+    /// Issues in failing rules with severity `error`.
+    pub errors: usize,
+    /// Issues in failing rules with severity `warning`.
+    pub warnings: usize,
 }
 
 #[derive(Debug, Serialize)]
+pub struct SamplingReport {
+    #[serde(flatten)]
+    pub params: Sampling,
+    /// Commits in the range and date window before sampling.
+    pub population: usize,
+    /// Commits inspected.
+    pub sampled: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct RepoReport {
+    /// The branch has no commits yet (a freshly initialized repository):
+    /// no commits are inspected and nothing is compared against a base.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub unborn: bool,
     pub worktree_clean: bool,
     pub upstream_set: bool,
+    /// `None` unless `require_remote` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote: Option<RemoteStatus>,
+    /// Partial clone / sparse checkout; content checks adapt to it.
+    pub mode: RepoMode,
+    /// `None` when the freshness check is disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch: Option<FetchFreshness>,
+    /// `None` when no base branch is configured or it does not exist.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<BaseDivergence>,
+    /// `None` unless `require_linear_history` is set and the base exists.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linearity: Option<Linearity>,
+    /// Pushed commits amended or rebased locally; `None` when there are
+    /// none or no upstream.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rewrites: Option<UpstreamRewrite>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
+pub struct BaseDivergence {
+    pub base: String,
+    pub ahead: usize,
+    pub behind: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_ahead: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_behind: Option<usize>,
+    /// Size of the diff a pull request against `base` would show.
+    pub changed_files: usize,
+    /// `None` in a partial clone, where counting lines would download every
+    /// changed blob.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_review_lines: Option<usize>,
+}
+
+impl BaseDivergence {
+    pub fn too_far_behind(&self) -> bool {
+        self.max_behind.is_some_and(|max| self.behind > max)
+    }
+
+    pub fn too_far_ahead(&self) -> bool {
+        self.max_ahead.is_some_and(|max| self.ahead > max)
+    }
+
+    /// Advisory only: large diffs are hard to review but not a violation.
+    pub fn too_large_to_review(&self) -> bool {
+        self.max_review_lines
+            .zip(self.changed_lines)
+            .is_some_and(|(max, lines)| lines > max)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct FetchFreshness {
+    pub remote: String,
+    /// `None` if the clone has never fetched.
+    pub last_fetch_age_secs: Option<u64>,
+    pub max_age_secs: u64,
+    pub fresh: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct SensitiveReport {
     pub files: Vec<String>,
+    pub findings: Vec<SensitiveFinding>,
+    /// Secrets found in lines added by staged changes.
+    pub content: Vec<ContentFinding>,
+    pub fail_on: Severity,
 }
 
-pub fn check(config_path: &Path, format: OutputFormat, commit_limit: usize) -> Result<()> {
-    let config = load_config(config_path)?;
-    let report = build_report(&config, commit_limit)?;
+impl Report {
+    /// Abbreviation of `hash`, one of the selected commits.
+    pub fn short_hash(&self, hash: &str) -> String {
+        match self.short_hashes.get(hash) {
+            Some(short) => short.clone(),
+            None => git::short_hash(hash),
+        }
+    }
 
-    match format {
-        OutputFormat::Text => print_text_report(&report),
-        OutputFormat::Json => print_json_report(&report)?,
+    pub fn commit_location(&self, hash: &str) -> Location {
+        Location::commit(hash, &self.short_hash(hash))
     }
 
-    let has_violations = !report.summary.branch_valid
-        || report.summary.invalid_commits > 0
-        || !report.summary.worktree_clean
-        || !report.summary.upstream_set
-        || report.summary.sensitive_files > 0;
+    /// A rule with severity `error` fails; see [`Report::fails`].
+    pub fn has_violations(&self) -> bool {
+        self.fails(FailOn::Error)
+    }
 
-    if has_violations {
-        std::process::exit(1);
+    /// Whether a failing rule reaches the `--fail-on` threshold.
+    pub fn fails(&self, fail_on: FailOn) -> bool {
+        self.rules.iter().any(|rule| fail_on.fails(rule.severity))
     }
 
-    Ok(())
+    /// Severity of `rule` if it fails; `None` if it passes or is `off`.
+    pub fn severity_of(&self, rule: &str) -> Option<RuleSeverity> {
+        self.rules
+            .iter()
+            .find(|r| r.rule == rule)
+            .map(|r| r.severity)
+    }
+
+    /// Re-derives `rules` and the per-severity counts from the summary,
+    /// after anything that changes it.
+    pub fn evaluate_rules(&mut self, severities: &BTreeMap<String, RuleSeverity>) {
+        self.rules = severity::evaluate(&self.summary, severities);
+        // Registered rules outside the summary count as they ran.
+        let plugins = self
+            .runs
+            .iter()
+            .filter(|run| !rules::is_builtin(run.rule) && !run.violations.is_empty());
+        self.rules.extend(plugins.map(|run| RuleResult {
+            rule: run.rule,
+            severity: run.severity,
+            issues: run.violations.len(),
+            violations: run.violations.clone(),
+        }));
+        let issues = |severity| {
+            self.rules
+                .iter()
+                .filter(|r| r.severity == severity)
+                .map(|r| r.issues)
+                .sum()
+        };
+        let (errors, warnings) = (issues(RuleSeverity::Error), issues(RuleSeverity::Warning));
+        self.summary.errors = errors;
+        self.summary.warnings = warnings;
+    }
 }
 
-pub fn build_report(config: &Config, commit_limit: usize) -> Result<Report> {
-    let branch_name = git::current_branch()?;
-    let branch_regex = Regex::new(&config.branches.pattern)
-        .with_context(|| format!("invalid branch regex {}", config.branches.pattern))?;
-    let branch_valid = branch_regex.is_match(&branch_name);
+/// Commits validated by a check.
+#[derive(Debug, Clone)]
+pub enum CommitSelection {
+    /// The last N commits reachable from HEAD.
+    Recent(usize),
+    /// An explicit revision range, e.g. `origin/main..HEAD` in CI.
+    Range(String),
+    /// History audits that trade completeness for runtime.
+    Sampled(Sampling),
+    /// No commits: only what is about to be committed, for the pre-commit
+    /// hook. Repository state (worktree, upstream, fetch age, base branch,
+    /// PR status) is left unchecked too.
+    Staged,
+}
 
-    let worktree_clean = !config.checks.require_clean_worktree || git::worktree_clean()?;
-    let upstream_set = !config.checks.require_upstream || git::has_upstream()?;
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Sampling {
+    /// Range to sample from; all of HEAD's history when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<String>,
+    /// Only commits after this date, as `git log --since` takes it
+    /// (`2024-01-01`, `6 months ago`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    /// Commits to keep, chosen by the lowest FNV-1a hash of their ids, so
+    /// reruns over the same history inspect the same commits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample: Option<usize>,
+}
 
-    let commit_regex = commit_regex_for(&config.commits.convention)?;
-    let commits = git::recent_commits(commit_limit)?;
-    let commit_reports: Vec<CommitReport> = commits
-        .into_iter()
-        .map(|(hash, message)| CommitReport {
-            valid: commit_regex.is_match(&message),
-            hash,
-            message,
+impl CommitSelection {
+    pub fn new(commit_limit: usize, range: Option<String>) -> Self {
+        match range {
+            Some(range) => CommitSelection::Range(range),
+            None => CommitSelection::Recent(commit_limit),
+        }
+    }
+
+    /// Turns the selection into a [`Sampling`] one when `since` or `sample`
+    /// is given; a range is kept, a commit limit is dropped.
+    pub fn sampled(self, since: Option<String>, sample: Option<usize>) -> Self {
+        if since.is_none() && sample.is_none() {
+            return self;
+        }
+        let range = match self {
+            CommitSelection::Range(range) => Some(range),
+            _ => None,
+        };
+        CommitSelection::Sampled(Sampling {
+            range,
+            since,
+            sample,
         })
+    }
+}
+
+/// The `size` commits with the lowest FNV-1a hash of their id, in their
+/// original order.
+fn sample_commits(commits: Vec<(String, String)>, size: usize) -> Vec<(String, String)> {
+    if commits.len() <= size {
+        return commits;
+    }
+    let mut ranked: Vec<(u64, usize)> = commits
+        .iter()
+        .enumerate()
+        .map(|(index, (hash, _))| (fnv1a(hash.as_bytes()), index))
         .collect();
+    ranked.sort_unstable();
+    let mut keep: Vec<usize> = ranked.into_iter().take(size).map(|(_, i)| i).collect();
+    keep.sort_unstable();
+    let mut keep = keep.into_iter().peekable();
+    commits
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| keep.next_if_eq(index).is_some())
+        .map(|(_, commit)| commit)
+        .collect()
+}
+
+/// The `check --format` values a single-repo report can be rendered in.
+#[derive(Clone, Copy, Debug)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Github,
+    Jsonl,
+}
+
+pub struct CheckOptions {
+    pub format: ReportFormat,
+    pub commits: CommitSelection,
+    /// Frozen output contract version, see `compat`.
+    pub compat: Option<u32>,
+    /// Record the result as a git note even if `[notes] enabled` is off.
+    pub notes: bool,
+    /// Remote being pushed to when running as the pre-push hook.
+    pub pre_push: Option<String>,
+    pub fail_on: FailOn,
+    /// Print nothing when the check passes and only a compact summary when
+    /// it fails, as the generated hooks do.
+    pub quiet_on_success: bool,
+    /// Leave out violations accepted by the baseline file.
+    pub baseline: bool,
+    /// Print how long each git query and rule took to stderr.
+    pub timings: bool,
+    /// More hashes to ignore, like `[commits] ignore_hashes`.
+    pub ignore_rev_file: Option<PathBuf>,
+    /// Reuse the commit verdicts cached by earlier checks.
+    pub cache: bool,
+}
+
+/// `git-sherpa check`: builds the report, prints it and tells whether the
+/// check failed, leaving the exit to the caller.
+pub fn check(config_path: &Path, opts: CheckOptions) -> Result<ExitStatus> {
+    let mut config = load_config(config_path)?;
+    if let Some(file) = &opts.ignore_rev_file {
+        config
+            .commits
+            .ignore_hashes
+            .extend(config::read_ignore_rev_file(file)?);
+    }
+    config.cache_commits = opts.cache;
+    hooks::refresh_on_check(&config.hooks);
+    let mut report = if matches!(opts.format, ReportFormat::Jsonl) {
+        // Invalid commits go out as they are found; the baseline is applied
+        // to the finished report too late to hold them back.
+        let accepted = match opts.baseline {
+            true => baseline::current()?.map(|b| b.commits).unwrap_or_default(),
+            false => Default::default(),
+        };
+        build_report_with(
+            &config,
+            &opts.commits,
+            &Registry::builtin(),
+            &mut |commit| {
+                if !commit.valid && !accepted.contains(&commit.hash) {
+                    jsonl::emit_commit(commit);
+                }
+            },
+        )?
+    } else {
+        build_report(&config, &opts.commits)?
+    };
+    report.environment = Some(Environment::capture(config_path, &config));
+    if opts.timings {
+        print_timings(&report.queries, &report.runs);
+    }
+    if opts.baseline {
+        baseline::apply_to(&config, &mut report)?;
+    }
+    if let Some(remote) = &opts.pre_push {
+        let mut refs = String::new();
+        std::io::stdin()
+            .read_to_string(&mut refs)
+            .context("read pushed refs from stdin")?;
+        let outgoing = outgoing::outgoing(remote, &refs)?;
+        outgoing::scan(&config, outgoing, &mut report)?;
+    }
+
+    let has_violations = report.fails(opts.fail_on);
+
+    if opts.notes || config.notes.enabled {
+        if let Err(err) = notes::record(&report, &config.notes.notes_ref, !has_violations) {
+            eprintln!("Warning: could not record git note: {:#}", err);
+        }
+    }
+
+    if let Some(version) = opts.compat {
+        let pinned = compat::translate(&report, version)?;
+        match opts.format {
+            ReportFormat::Text => print_text_report(&report),
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&pinned)?),
+            ReportFormat::Github => print!("{}", ci::github_annotations(&report)),
+            ReportFormat::Jsonl => {
+                bail!("--format jsonl is not supported with --compat or --stable")
+            }
+        }
+        return Ok(ExitStatus::from_violations(
+            pinned.exit_code() == compat::EXIT_VIOLATIONS_V1,
+        ));
+    }
+
+    match opts.format {
+        ReportFormat::Text if opts.quiet_on_success => {
+            print_compact_report(&report, has_violations)
+        }
+        ReportFormat::Text => print_text_report(&report),
+        ReportFormat::Json if opts.quiet_on_success && !has_violations => {}
+        ReportFormat::Json => print_json_report(&report)?,
+        ReportFormat::Github => print!("{}", ci::github_annotations(&report)),
+        ReportFormat::Jsonl => jsonl::emit_rest(&report, has_violations),
+    }
+
+    Ok(ExitStatus::from_violations(has_violations))
+}
 
-    let invalid_commits = commit_reports.iter().filter(|c| !c.valid).count();
-    let total_commits = commit_reports.len();
+/// `check --all-configs`: loads and validates the repo config at
+/// `config_path` and each nested config, listing every file, so a broken
+/// one is found before a commit under its directory trips over it.
+pub fn check_all_configs(config_path: &Path) -> Result<ExitStatus> {
+    let config = load_config(config_path)?;
+    validate_config(&config)?;
+    println!("{} {}", "OK".green(), config_path.display());
+    let mut first_error = None;
+    for (path, scope) in config::nested_configs(config_path)? {
+        match scope.and_then(|scope| validate_config(&scope.config)) {
+            Ok(()) => println!("{} {}", "OK".green(), path.display()),
+            Err(err) => {
+                println!("{} {}: {:#}", "INVALID".red(), path.display(), err);
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(ExitStatus::Success),
+    }
+}
 
-    let staged = git::staged_files().unwrap_or_default();
-    let sensitive_files = sensitive::check_sensitive_files(&staged, &config.sensitive.patterns);
+pub fn build_report(config: &Config, selection: &CommitSelection) -> Result<Report> {
+    build_report_with(config, selection, &Registry::builtin(), &mut |_| {})
+}
 
-    Ok(Report {
+/// [`build_report`] with the rules of `registry`, handing each commit to
+/// `on_commit` as soon as it is validated, for output that streams instead
+/// of waiting for the report.
+pub fn build_report_with(
+    config: &Config,
+    selection: &CommitSelection,
+    registry: &Registry,
+    on_commit: &mut dyn FnMut(&CommitReport),
+) -> Result<Report> {
+    let queries = Mutex::new(Vec::new());
+    let mut branch_name = timed(&queries, "branch", git::current_branch)?;
+    if branch_name == "HEAD" {
+        // Detached, as in most CI checkouts: the runner knows the branch.
+        if let Some(branch) = ci::detect().and_then(|env| env.branch) {
+            branch_name = branch;
+        }
+    }
+
+    let staged_only = matches!(selection, CommitSelection::Staged);
+    let unborn = timed(&queries, "unborn", git::is_unborn)?;
+    let upstream = !staged_only && timed(&queries, "upstream", git::has_upstream)?;
+
+    // The remaining queries are independent of each other: run them at
+    // once, as on a large repository each can take a while.
+    let started = Instant::now();
+    let (mode_base, fetch, selected, rewrites, pull_request, worktree_clean, changes) =
+        thread::scope(|s| {
+            let mode_base = s.spawn(|| {
+                timed(&queries, "base", || {
+                    let mode = repo_mode::detect()?;
+                    if staged_only || unborn {
+                        return Ok((mode, None));
+                    }
+                    let base = base_divergence(config, &mode)?;
+                    Ok((mode, base))
+                })
+            });
+            let fetch = s.spawn(|| {
+                timed(&queries, "fetch", || {
+                    if staged_only {
+                        return Ok(None);
+                    }
+                    fetch_freshness(config, &branch_name)
+                })
+            });
+            let selected = s.spawn(|| {
+                timed(&queries, "log", || {
+                    if unborn {
+                        return Ok((Vec::new(), None, None));
+                    }
+                    select_commits(selection)
+                })
+            });
+            let rewrites = s.spawn(|| {
+                timed(&queries, "rewrites", || {
+                    if unborn || !upstream {
+                        return Ok(None);
+                    }
+                    rewrites::detect()
+                })
+            });
+            let pull_request = s.spawn(|| {
+                timed(&queries, "pull-request", || {
+                    let wanted = !staged_only && config.pull_requests.status;
+                    if !wanted || !upstream {
+                        return Ok(None);
+                    }
+                    Ok(pull_request::pull_request_status(&branch_name))
+                })
+            });
+            let worktree_clean = s.spawn(|| {
+                timed(&queries, "status", || {
+                    if staged_only || !config.checks.require_clean_worktree {
+                        return Ok(true);
+                    }
+                    git::worktree_clean()
+                })
+            });
+            let changes = s.spawn(|| {
+                timed(&queries, "staged", || {
+                    Ok(git::staged_changes().unwrap_or_default())
+                })
+            });
+            (
+                join(mode_base),
+                join(fetch),
+                join(selected),
+                join(rewrites),
+                join(pull_request),
+                join(worktree_clean),
+                join(changes),
+            )
+        });
+    let queried = started.elapsed();
+    let (mode, base) = mode_base?;
+    let (commits, range, sampling) = selected?;
+    let ignored_commits = config.commits.ignored_commits()?;
+    let (commits, ignored): (Vec<_>, Vec<_>) = commits
+        .into_iter()
+        .partition(|(hash, _)| !ignored_commits.contains(hash));
+
+    // What a rule that does not run (`off`, or not applicable) leaves
+    // behind passes.
+    let report = Report {
         branch: BranchReport {
             name: branch_name,
-            pattern: config.branches.pattern.clone(),
-            valid: branch_valid,
+            valid: true,
+            ..BranchReport::default()
         },
-        commits: commit_reports,
+        range,
+        sampling,
+        ignored_commits: ignored.into_iter().map(|(hash, _)| hash).collect(),
         repo: RepoReport {
-            worktree_clean,
-            upstream_set,
+            unborn,
+            worktree_clean: true,
+            upstream_set: true,
+            remote: None,
+            mode: mode.clone(),
+            fetch: fetch?,
+            base,
+            linearity: None,
+            rewrites: rewrites?,
         },
         sensitive: SensitiveReport {
-            files: sensitive_files.clone(),
+            fail_on: config.sensitive.fail_on,
+            ..SensitiveReport::default()
         },
-        summary: Summary {
-            total_commits,
-            invalid_commits,
-            branch_valid,
-            worktree_clean,
-            upstream_set,
-            sensitive_files: sensitive_files.len(),
+        pull_request: pull_request?,
+        short_hashes: git::abbreviate_commits(&hashes(&commits))?,
+        ..Report::default()
+    };
+    let changes = changes?;
+    let mut ctx = RuleContext {
+        config,
+        staged_only,
+        unborn,
+        mode,
+        worktree_clean: worktree_clean?,
+        has_upstream: upstream,
+        staged: changes.iter().map(|c| c.path.clone()).collect(),
+        changes,
+        commits,
+        report,
+        on_commit,
+    };
+    let runs = registry.run(&mut ctx)?;
+
+    let mut report = ctx.report;
+    report.summary = summarize(&report);
+    report.runs = runs;
+    report.queries = QueryTimings {
+        queries: queries.into_inner().expect("query timings"),
+        elapsed: queried,
+    };
+    report.evaluate_rules(&config.severity);
+    Ok(report)
+}
+
+/// Runs `query`, adding how long it took to `timings` under `name`.
+fn timed<T>(
+    timings: &Mutex<Vec<(&'static str, Duration)>>,
+    name: &'static str,
+    query: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let started = Instant::now();
+    let result = query();
+    timings
+        .lock()
+        .expect("query timings")
+        .push((name, started.elapsed()));
+    result
+}
+
+/// The result of a query thread, passing its panic on.
+fn join<T>(handle: thread::ScopedJoinHandle<'_, T>) -> T {
+    handle
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+/// `max_fetch_age_hours`: how long ago the remote of `branch` was fetched.
+fn fetch_freshness(config: &Config, branch: &str) -> Result<Option<FetchFreshness>> {
+    let Some(hours) = config.checks.max_fetch_age_hours else {
+        return Ok(None);
+    };
+    let age = git::last_fetch_age_secs()?;
+    let max_age_secs = hours * 3600;
+    Ok(Some(FetchFreshness {
+        remote: git::tracking_remote(branch),
+        fresh: age.is_some_and(|a| a <= max_age_secs),
+        last_fetch_age_secs: age,
+        max_age_secs,
+    }))
+}
+
+/// `base_branch`: how far `HEAD` has drifted from it, and the size of the
+/// change. Line counts are skipped in partial clones, where they would
+/// fetch every blob.
+fn base_divergence(config: &Config, mode: &RepoMode) -> Result<Option<BaseDivergence>> {
+    let Some(base) = &config.checks.base_branch else {
+        return Ok(None);
+    };
+    if git::resolve_rev(base)?.is_none() {
+        eprintln!(
+            "Warning: base branch '{}' not found; skipping the divergence check",
+            base
+        );
+        return Ok(None);
+    }
+    let (behind, ahead) = git::ahead_behind(base)?;
+    let (changed_files, changed_lines) = if mode.is_partial() {
+        (git::diff_files(base)?, None)
+    } else {
+        let (files, lines) = git::diff_size(base)?;
+        (files, Some(lines))
+    };
+    Ok(Some(BaseDivergence {
+        base: base.clone(),
+        ahead,
+        behind,
+        max_ahead: config.checks.max_ahead,
+        max_behind: config.checks.max_behind,
+        changed_files,
+        changed_lines,
+        max_review_lines: config.checks.max_review_lines,
+    }))
+}
+
+/// `max_branch_age_days`: how long ago the branch diverged from its base.
+/// `None` without a limit, a base that resolves, or commits of its own.
+pub(crate) fn branch_age(config: &Config) -> Result<Option<BranchAge>> {
+    let Some(max_days) = config.checks.max_branch_age_days else {
+        return Ok(None);
+    };
+    let base = match &config.checks.base_branch {
+        Some(base) => base.clone(),
+        None => match git::remote_head("origin")? {
+            Some(head) => head,
+            None => "main".to_string(),
         },
+    };
+    if git::resolve_rev(&base)?.is_none() {
+        eprintln!(
+            "Warning: base branch '{}' not found; skipping the branch age check",
+            base
+        );
+        return Ok(None);
+    }
+    let Some(started) = git::first_unique_commit_time(&base)? else {
+        return Ok(None);
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    Ok(Some(BranchAge {
+        base,
+        days: now.saturating_sub(started) / SECS_PER_DAY,
+        max_days,
+    }))
+}
+
+/// (hash, subject) pairs of the selected commits, with the range and the
+/// sampling they came from.
+type Selected = (
+    Vec<(String, String)>,
+    Option<String>,
+    Option<SamplingReport>,
+);
+
+fn select_commits(selection: &CommitSelection) -> Result<Selected> {
+    Ok(match selection {
+        CommitSelection::Recent(limit) => (git::recent_commits(*limit)?, None, None),
+        CommitSelection::Range(range) => (git::range_commits(range)?, Some(range.clone()), None),
+        CommitSelection::Sampled(params) => {
+            let population =
+                git::history_commits(params.range.as_deref(), params.since.as_deref())?;
+            let total = population.len();
+            let commits = match params.sample {
+                Some(size) => sample_commits(population, size),
+                None => population,
+            };
+            let sampling = SamplingReport {
+                params: params.clone(),
+                population: total,
+                sampled: commits.len(),
+            };
+            (commits, params.range.clone(), Some(sampling))
+        }
+        CommitSelection::Staged => (Vec::new(), None, None),
     })
 }
 
-fn print_text_report(report: &Report) {
+/// The summary of the report's sections.
+fn summarize(report: &Report) -> Summary {
+    let fail_on = report.sensitive.fail_on;
+    let base = report.repo.base.as_ref();
+    Summary {
+        total_commits: report.commits.len(),
+        invalid_commits: report.commits.iter().filter(|c| !c.valid).count(),
+        unsigned_commits: report.signatures.len(),
+        disallowed_author_emails: report.authors.issues.len(),
+        quiet_hours_commits: report.quiet_hours.len(),
+        branch_valid: report.branch.valid,
+        branch_too_old: report.branch.age.as_ref().is_some_and(BranchAge::too_old),
+        worktree_clean: report.repo.worktree_clean,
+        remote_broken: report.repo.remote.as_ref().is_some_and(|r| !r.ok()),
+        upstream_set: report.repo.upstream_set,
+        sensitive_files: report
+            .sensitive
+            .findings
+            .iter()
+            .filter(|f| f.severity >= fail_on)
+            .count(),
+        secrets: report
+            .sensitive
+            .content
+            .iter()
+            .filter(|f| f.severity >= fail_on)
+            .count(),
+        large_files: report.large_files.len(),
+        protected_paths: report.protected_paths.len(),
+        behind_base: base.map(|b| b.behind),
+        ahead_base: base.map(|b| b.ahead),
+        base_diverged: base.is_some_and(|b| b.too_far_behind() || b.too_far_ahead()),
+        nonlinear_history: report
+            .repo
+            .linearity
+            .as_ref()
+            .is_some_and(|l| !l.is_linear()),
+        diverged_from_upstream: report.repo.rewrites.is_some(),
+        attribute_issues: report.attributes.issue_count(),
+        dangling_references: report.references.dangling.len(),
+        content_issues: report.staged_content.len(),
+        errors: 0,
+        warnings: 0,
+    }
+}
+
+/// Checks (hash, subject) pairs against the commit convention; full
+/// messages are only read when body rules are configured. A commit whose
+/// files all sit under one nested config follows that config's `[commits]`.
+pub fn validate_commits(
+    config: &Config,
+    commits: Vec<(String, String)>,
+) -> Result<Vec<CommitReport>> {
+    let short_hashes = git::abbreviate_commits(&hashes(&commits))?;
+    validate_commits_with(config, commits, &short_hashes, &mut |_| {})
+}
+
+fn hashes(commits: &[(String, String)]) -> Vec<String> {
+    commits.iter().map(|(hash, _)| hash.clone()).collect()
+}
+
+/// [`validate_commits`] with the commits' abbreviations already looked up,
+/// handing each report to `on_commit` as soon as it is built.
+pub(crate) fn validate_commits_with(
+    config: &Config,
+    commits: Vec<(String, String)>,
+    short_hashes: &HashMap<String, String>,
+    on_commit: &mut dyn FnMut(&CommitReport),
+) -> Result<Vec<CommitReport>> {
+    let conventions = config
+        .with_scopes()
+        .map(|scoped| Ok((scoped, Convention::from_config(&scoped.commits)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let mut cache = config
+        .cache_commits
+        .then(|| CommitCache::load(cache::fingerprint(config, &conventions)));
+    let hashes = hashes(&commits);
+    let uncached: Vec<String> = match &cache {
+        Some(cache) => hashes
+            .into_iter()
+            .filter(|hash| cache.get(hash).is_none())
+            .collect(),
+        None => hashes,
+    };
+    let files = match config.scopes.is_empty() {
+        true => HashMap::new(),
+        false => git::files_of_commits(&uncached)?,
+    };
+    let merges = if config.with_scopes().any(|c| c.commits.forbid_merge_commits) {
+        git::merge_commits_among(&uncached)?
+    } else {
+        Vec::new()
+    };
+    let reports = commits
+        .into_iter()
+        .map(|(hash, message)| {
+            let short_hash = match short_hashes.get(&hash) {
+                Some(short) => short.clone(),
+                None => git::short_hash(&hash),
+            };
+            let location = Location::commit(&hash, &short_hash);
+            if let Some(verdict) = cache.as_ref().and_then(|cache| cache.get(&hash)) {
+                let report = CommitReport {
+                    valid: verdict.problems.is_empty(),
+                    short_hash,
+                    location,
+                    problems: verdict.problems.clone(),
+                    body_rules: verdict.body_rules.clone(),
+                    forbidden: verdict.forbidden,
+                    hash,
+                    message,
+                    baselined: false,
+                };
+                on_commit(&report);
+                return Ok(report);
+            }
+            let governing = commit_config(config, &files, &hash);
+            let (config, convention) = conventions
+                .iter()
+                .find(|(scoped, _)| std::ptr::eq(*scoped, governing))
+                .expect("the governing config is one of the scopes");
+            let mut problems = convention.check_subject(&message);
+            let mut body_rules = Vec::new();
+            if convention.checks_body() {
+                for problem in convention.check_body(&git::commit_message(&hash)?) {
+                    if !body_rules.contains(&problem.rule) {
+                        body_rules.push(problem.rule);
+                    }
+                    problems.push(problem.message);
+                }
+            }
+            let forbidden = if config.commits.forbid_merge_commits && merges.contains(&hash) {
+                problems.push("merge commit (forbid_merge_commits)".to_string());
+                Some(ForbiddenCommit::Merge)
+            } else if config.commits.forbid_fixup_commits
+                && AUTOSQUASH_PREFIXES.iter().any(|p| message.starts_with(p))
+            {
+                problems.push("not squashed yet (forbid_fixup_commits)".to_string());
+                Some(ForbiddenCommit::Fixup)
+            } else {
+                None
+            };
+            let report = CommitReport {
+                valid: problems.is_empty(),
+                short_hash,
+                location,
+                hash,
+                message,
+                problems,
+                body_rules,
+                forbidden,
+                baselined: false,
+            };
+            if let Some(cache) = &mut cache {
+                let verdict = Verdict {
+                    problems: report.problems.clone(),
+                    body_rules: report.body_rules.clone(),
+                    forbidden: report.forbidden,
+                };
+                cache.insert(&report.hash, verdict);
+            }
+            on_commit(&report);
+            Ok(report)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(cache) = cache {
+        cache.save();
+    }
+    Ok(reports)
+}
+
+/// The config whose `[commits]` `hash` follows, given the files of each
+/// commit; see [`Config::for_paths`].
+fn commit_config<'c>(
+    config: &'c Config,
+    files: &HashMap<String, Vec<String>>,
+    hash: &str,
+) -> &'c Config {
+    match files.get(hash) {
+        Some(files) => config.for_paths(files.iter().map(String::as_str)),
+        None => config,
+    }
+}
+
+pub(crate) fn print_text_report(report: &Report) {
     let status = |ok: bool| -> String {
         if ok {
             "OK".green().to_string()
@@ -134,94 +1081,607 @@ fn print_text_report(report: &Report) {
             "INVALID".red().to_string()
         }
     };
+    // Failing rules in the color of their severity: red for errors, yellow
+    // for warnings, uncolored for rules that are `off`.
+    let paint = |rule: &str, text: &str| -> ColoredString {
+        match report.severity_of(rule) {
+            Some(RuleSeverity::Error) => text.red(),
+            Some(RuleSeverity::Warning) => text.yellow(),
+            Some(RuleSeverity::Off) | None => text.normal(),
+        }
+    };
+    let rule_status = |rule: &str, ok: bool| -> String {
+        if ok {
+            "OK".green().to_string()
+        } else {
+            paint(rule, "INVALID").to_string()
+        }
+    };
 
     println!("Branch: {}", report.branch.name);
     println!("Pattern: {}", report.branch.pattern);
-    println!("Branch: {}", status(report.branch.valid));
+    println!(
+        "Branch: {}",
+        rule_status("branch-pattern", report.branch.valid)
+    );
+    for problem in &report.branch.problems {
+        println!("    {}", problem);
+    }
+    if let Some(age) = &report.branch.age {
+        let line = format!("Age: {} day(s) since diverging from {}", age.days, age.base);
+        if age.too_old() {
+            println!(
+                "{} (max_branch_age_days={})",
+                paint("branch-age", &line),
+                age.max_days
+            );
+        } else {
+            println!("{}", line);
+        }
+    }
 
-    println!("\nCommits:");
+    match &report.range {
+        _ if report.repo.unborn => println!(
+            "\nCommits: {}",
+            "none yet (the branch is unborn until its first commit)".dimmed()
+        ),
+        Some(range) => println!("\nCommits ({}):", range),
+        None => println!("\nCommits:"),
+    }
+    if let Some(sampling) = &report.sampling {
+        let since = sampling
+            .params
+            .since
+            .as_ref()
+            .map(|date| format!(" since {}", date))
+            .unwrap_or_default();
+        println!(
+            "{}",
+            format!(
+                "(sampled {} of {} commits{})",
+                sampling.sampled, sampling.population, since
+            )
+            .dimmed()
+        );
+    }
     for commit in &report.commits {
         let tag = if commit.valid {
             "OK".green().to_string()
+        } else if commit.baselined {
+            "INVALID, baselined".dimmed().to_string()
         } else {
-            "INVALID".red().to_string()
+            paint("commit-convention", "INVALID").to_string()
         };
-        println!("- {} {} [{}]", &commit.hash[..8], commit.message, tag);
+        println!("- {} {} [{}]", commit.short_hash, commit.message, tag);
+        for problem in &commit.problems {
+            println!("    {}", problem);
+        }
+    }
+
+    if !report.signatures.is_empty() {
+        println!("\n{}", paint("signed-commits", "Commit signatures:").bold());
+        for issue in &report.signatures {
+            println!(
+                "- {} [{}]",
+                git::short_hash(&issue.hash),
+                paint("signed-commits", issue.status.label())
+            );
+        }
+    }
+
+    if !report.authors.issues.is_empty() {
+        println!("\n{}", paint("author-email", "Commit authors:").bold());
+        for issue in &report.authors.issues {
+            println!(
+                "- {} {} [{}]",
+                git::short_hash(&issue.hash),
+                issue.email,
+                paint("author-email", issue.problem.label())
+            );
+        }
+    }
+
+    if !report.quiet_hours.is_empty() {
+        println!(
+            "\n{}",
+            paint("quiet-hours", "Commits in quiet hours:").bold()
+        );
+        for commit in &report.quiet_hours {
+            println!(
+                "- {} {} [{}]",
+                git::short_hash(&commit.hash),
+                commit.authored,
+                paint("quiet-hours", commit.reason.label())
+            );
+        }
     }
 
     println!(
         "\nRepo: worktree_clean={}, upstream_set={}",
-        status(report.repo.worktree_clean),
-        status(report.repo.upstream_set)
+        rule_status("clean-worktree", report.repo.worktree_clean),
+        rule_status("upstream", report.repo.upstream_set)
     );
+    if let Some(remote) = &report.repo.remote {
+        match remote.problem {
+            None => println!("Remote: {} {}", remote.name, "reachable".green()),
+            Some(problem) => {
+                println!(
+                    "Remote: {} {}",
+                    remote.name,
+                    paint("remote", problem.label())
+                );
+                if let Some(detail) = &remote.detail {
+                    for line in detail.lines() {
+                        println!("    {}", line);
+                    }
+                }
+            }
+        }
+    }
+    if let Some(mode) = report.repo.mode.describe() {
+        println!("Mode: {}", mode);
+    }
+
+    if let Some(fetch) = report.repo.fetch.as_ref().filter(|f| !f.fresh) {
+        let when = match fetch.last_fetch_age_secs {
+            Some(secs) => format!("last fetched from '{}' {}h ago", fetch.remote, secs / 3600),
+            None => format!("'{}' has never been fetched", fetch.remote),
+        };
+        println!(
+            "{}",
+            format!(
+                "Warning: {} (max {}h); ahead/behind info may be stale",
+                when,
+                fetch.max_age_secs / 3600
+            )
+            .yellow()
+        );
+    }
 
-    if !report.sensitive.files.is_empty() {
-        println!("\n{}", "Sensitive files staged:".red().bold());
-        for f in &report.sensitive.files {
-            println!("  - {}", f.red());
+    if let Some(base) = &report.repo.base {
+        let line = format!(
+            "Base: {} ahead, {} behind {}",
+            base.ahead, base.behind, base.base
+        );
+        if base.too_far_behind() || base.too_far_ahead() {
+            let limits = [
+                base.max_ahead.map(|m| format!("max_ahead={}", m)),
+                base.max_behind.map(|m| format!("max_behind={}", m)),
+            ];
+            let limits: Vec<String> = limits.into_iter().flatten().collect();
+            println!(
+                "{} ({})",
+                paint("base-divergence", &line),
+                limits.join(", ")
+            );
+        } else {
+            println!("{}", line);
+        }
+        if base.too_large_to_review() {
+            println!(
+                "{}",
+                format!(
+                    "Review size: {} changed lines in {} file(s) (max_review_lines={}); consider splitting into stacked branches",
+                    base.changed_lines.unwrap_or_default(),
+                    base.changed_files,
+                    base.max_review_lines.unwrap_or_default()
+                )
+                .yellow()
+            );
         }
     }
 
-    let all_ok = report.summary.branch_valid
-        && report.summary.invalid_commits == 0
-        && report.summary.worktree_clean
-        && report.summary.upstream_set
-        && report.summary.sensitive_files == 0;
+    if let Some(rewrites) = &report.repo.rewrites {
+        println!(
+            "{}",
+            format!(
+                "Warning: {} commit(s) already on {} were rewritten locally; pushing needs --force-with-lease",
+                rewrites.rewritten.len(),
+                rewrites.upstream
+            )
+            .yellow()
+        );
+        if !rewrites.dropped.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "  {} other commit(s) on {} are not in HEAD and a force push would drop them",
+                    rewrites.dropped.len(),
+                    rewrites.upstream
+                )
+                .yellow()
+            );
+        }
+    }
 
-    let summary_label = if all_ok {
-        "Summary: ALL OK".green().bold().to_string()
+    if let Some(linearity) = &report.repo.linearity {
+        if linearity.is_linear() {
+            println!("History: linear on top of {}", linearity.base);
+        } else {
+            let mut problems = Vec::new();
+            if !linearity.merges.is_empty() {
+                problems.push(format!("{} merge commit(s)", linearity.merges.len()));
+            }
+            if linearity.criss_cross() {
+                problems.push(format!(
+                    "criss-cross merge ({} merge bases)",
+                    linearity.merge_bases
+                ));
+            }
+            let line = format!(
+                "History: not linear on top of {}: {}",
+                linearity.base,
+                problems.join(", ")
+            );
+            println!("{}", paint("linear-history", &line));
+            for merge in &linearity.merges {
+                println!("  - {}", report.short_hash(merge));
+            }
+        }
+    }
+
+    if let Some(pr) = &report.pull_request {
+        let state = match pr.state {
+            PrState::Open => "open".green().to_string(),
+            PrState::Draft => "draft".yellow().to_string(),
+            PrState::Merged => "merged".cyan().to_string(),
+            PrState::Closed => "closed".dimmed().to_string(),
+            PrState::None => "none".yellow().to_string(),
+        };
+        match (&pr.number, &pr.url) {
+            (Some(number), Some(url)) => println!("Pull request: {} (#{} {})", state, number, url),
+            _ => println!("Pull request: {}", state),
+        }
+    }
+
+    if !report.sensitive.findings.is_empty() {
+        println!(
+            "\n{}",
+            paint("sensitive-files", "Sensitive files staged:").bold()
+        );
+        for finding in &report.sensitive.findings {
+            let blocking = finding.severity >= report.sensitive.fail_on;
+            let tag = match &finding.name {
+                Some(name) => format!("{} [{}]", name, finding.severity),
+                None => format!("[{}]", finding.severity),
+            };
+            if blocking {
+                println!(
+                    "  - {} {}",
+                    paint("sensitive-files", &finding.path),
+                    paint("sensitive-files", &tag).bold()
+                );
+            } else {
+                println!(
+                    "  - {} {}",
+                    finding.path.yellow(),
+                    format!("{} (below fail_on, not blocking)", tag).yellow()
+                );
+            }
+            if let Some(description) = &finding.description {
+                println!("    {}", description.dimmed());
+            }
+        }
+    }
+
+    if let Some(outgoing) = &report.outgoing {
+        println!(
+            "\nOutgoing to '{}': {} commit(s) scanned",
+            outgoing.remote,
+            outgoing.commits.len()
+        );
+    }
+
+    if !report.sensitive.content.is_empty() {
+        let heading = if report.outgoing.is_some() {
+            "Secrets in staged and outgoing changes:"
+        } else {
+            "Secrets in staged changes:"
+        };
+        println!("\n{}", paint("sensitive-files", heading).bold());
+        for finding in &report.sensitive.content {
+            let location = match &finding.commit {
+                Some(commit) => format!(
+                    "{}:{} (in {})",
+                    finding.path,
+                    finding.line,
+                    git::short_hash(commit)
+                ),
+                None => format!("{}:{}", finding.path, finding.line),
+            };
+            let tag = format!("{} [{}]", finding.rule, finding.severity);
+            if finding.severity >= report.sensitive.fail_on {
+                println!(
+                    "  - {} {}",
+                    paint("sensitive-files", &location),
+                    paint("sensitive-files", &tag).bold()
+                );
+            } else {
+                println!(
+                    "  - {} {}",
+                    location.yellow(),
+                    format!("{} (below fail_on, not blocking)", tag).yellow()
+                );
+            }
+            if let Some(description) = &finding.description {
+                println!("    {}", description.dimmed());
+            }
+        }
+    }
+
+    if !report.large_files.is_empty() {
+        println!("\n{}", paint("large-files", "Large files:").bold());
+        for file in &report.large_files {
+            let origin = match &file.commit {
+                Some(commit) => format!("in {}", git::short_hash(commit)),
+                None => "staged".to_string(),
+            };
+            println!(
+                "  - {} {} KB ({})",
+                paint("large-files", &file.path),
+                file.size_kb(),
+                origin
+            );
+        }
+    }
+
+    if !report.protected_paths.is_empty() {
+        let heading = format!("Protected paths changed on {}:", report.branch.name);
+        println!("\n{}", paint("protected-paths", &heading).bold());
+        for change in &report.protected_paths {
+            println!(
+                "  - {} ({})",
+                paint("protected-paths", &change.path),
+                change.pattern
+            );
+        }
+    }
+
+    if !report.attributes.missing_lines.is_empty() {
+        println!(
+            "\n{}",
+            paint("attributes", "Missing .gitattributes lines:").bold()
+        );
+        for line in &report.attributes.missing_lines {
+            println!("  - {}", paint("attributes", line));
+        }
+    }
+    if !report.attributes.violations.is_empty() {
+        println!(
+            "\n{}",
+            paint("attributes", "Attribute policy violations:").bold()
+        );
+        for v in &report.attributes.violations {
+            let path = paint("attributes", &v.path);
+            match &v.commit {
+                Some(commit) => println!(
+                    "  - {}: {} (in {})",
+                    path,
+                    v.reason,
+                    git::short_hash(commit)
+                ),
+                None => println!("  - {}: {}", path, v.reason),
+            }
+        }
+    }
+
+    if !report.references.dangling.is_empty() {
+        println!(
+            "\n{}",
+            paint("references", "Dangling issue references:").bold()
+        );
+        for r in &report.references.dangling {
+            println!(
+                "  - {} in {}",
+                paint("references", &r.reference),
+                git::short_hash(&r.commit)
+            );
+        }
+    }
+    if report.references.unverified > 0 {
+        println!(
+            "\n{}",
+            format!(
+                "{} issue reference(s) could not be verified (offline or no credentials)",
+                report.references.unverified
+            )
+            .yellow()
+        );
+    }
+
+    if !report.staged_content.is_empty() {
+        println!(
+            "\n{}",
+            paint("staged-content", "Staged content issues:").bold()
+        );
+        for issue in &report.staged_content {
+            let location = format!("{}:{}", issue.path, issue.line);
+            println!("  - {} {}", paint("staged-content", &location), issue.kind);
+        }
+    }
+
+    if !report.ignored_commits.is_empty() {
+        println!(
+            "\n{}",
+            format!(
+                "{} commit(s) ignored by hash ([commits] ignore_hashes, --ignore-rev-file)",
+                report.ignored_commits.len()
+            )
+            .dimmed()
+        );
+    }
+
+    if let Some(baseline) = report.baseline.as_ref().filter(|b| b.accepted > 0) {
+        println!(
+            "\n{}",
+            format!(
+                "{} violation(s) accepted by {}",
+                baseline.accepted, baseline.path
+            )
+            .dimmed()
+        );
+    }
+
+    if report.rules.is_empty() {
+        println!("\n{}", "Summary: ALL OK".green().bold());
+        return;
+    }
+    let counts = format!(
+        "{} error(s), {} warning(s)",
+        report.summary.errors, report.summary.warnings
+    );
+    if report.has_violations() {
+        println!("\n{}", format!("Summary: {}", counts).red().bold());
     } else {
-        format!(
-            "Summary: branch_ok={}, invalid_commits={}, sensitive_files={}",
+        println!(
+            "\n{}",
+            format!("Summary: OK with {}", counts).yellow().bold()
+        );
+    }
+    for rule in &report.rules {
+        println!(
+            "  {:<8} {} ({})",
+            paint(rule.rule, &rule.severity.to_string()),
+            rule.rule,
+            rule.issues
+        );
+        for violation in &rule.violations {
+            match &violation.location {
+                Location::Repo => println!("    - {}", violation.message),
+                location => println!("    - {}: {}", location, violation.message),
+            }
+        }
+    }
+    if report.has_violations() {
+        println!(
+            "  branch_ok={}, branch_age_ok={}, base_ok={}, linear_ok={}, invalid_commits={}, unsigned_commits={}, disallowed_author_emails={}, quiet_hours_commits={}, sensitive_files={}, secrets={}, large_files={}, protected_paths={}, attribute_issues={}, dangling_references={}, content_issues={}",
             status(report.summary.branch_valid),
+            status(!report.summary.branch_too_old),
+            status(!report.summary.base_diverged),
+            status(!report.summary.nonlinear_history),
             report.summary.invalid_commits,
-            report.summary.sensitive_files
-        )
-    };
-    println!("\n{}", summary_label);
+            report.summary.unsigned_commits,
+            report.summary.disallowed_author_emails,
+            report.summary.quiet_hours_commits,
+            report.summary.sensitive_files,
+            report.summary.secrets,
+            report.summary.large_files,
+            report.summary.protected_paths,
+            report.summary.attribute_issues,
+            report.summary.dangling_references,
+            report.summary.content_issues
+        );
+    }
 }
 
-fn print_json_report(report: &Report) -> Result<()> {
-    let json = serde_json::to_string_pretty(report)?;
-    println!("{}", json);
-    Ok(())
+/// `--timings`: the git queries, then each rule that ran, slowest first, on
+/// stderr so the report on stdout stays parseable.
+fn print_timings(queries: &QueryTimings, runs: &[RuleRun]) {
+    let mut timings = queries.queries.clone();
+    timings.sort_by_key(|(_, elapsed)| Reverse(*elapsed));
+    eprintln!(
+        "{} {:.1}ms, concurrently",
+        "Git queries:".bold(),
+        queries.elapsed.as_secs_f64() * 1000.0
+    );
+    for (query, elapsed) in timings {
+        eprintln!("  {:<20} {:>8.1}ms", query, elapsed.as_secs_f64() * 1000.0);
+    }
+    let mut runs: Vec<&RuleRun> = runs.iter().collect();
+    runs.sort_by_key(|run| Reverse(run.elapsed));
+    eprintln!("{}", "Rule timings:".bold());
+    for run in runs {
+        eprintln!(
+            "  {:<20} {:>8.1}ms  {} violation(s)",
+            run.rule,
+            run.elapsed.as_secs_f64() * 1000.0,
+            run.violations.len()
+        );
+    }
 }
 
-pub(crate) fn commit_regex_for(convention: &str) -> Result<Regex> {
-    match convention {
-        "conventional" => Regex::new(
-            r"^(feat|fix|chore|docs|refactor|test|perf|ci|build)(\([a-z0-9-]+\))?: .+",
-        )
-        .context("invalid conventional commit regex"),
-        _ => bail!("Unsupported commit convention: {}", convention),
+/// `--quiet-on-success`: nothing for a clean report, one line for warnings,
+/// otherwise each failing rule with the command most likely to fix it.
+fn print_compact_report(report: &Report, failed: bool) {
+    if report.rules.is_empty() {
+        return;
+    }
+    if !failed {
+        let warnings: Vec<&str> = report.rules.iter().map(|r| r.rule).collect();
+        println!(
+            "{}",
+            format!("git-sherpa: warnings: {}", warnings.join(", ")).yellow()
+        );
+        return;
+    }
+    println!("{}", "git-sherpa: check failed".red().bold());
+    for rule in &report.rules {
+        println!(
+            "  {} {} ({}): {}",
+            "✗".red(),
+            rule.rule,
+            rule.issues,
+            fix_hint(rule.rule, report).cyan()
+        );
     }
+    println!(
+        "{}",
+        "Run `git-sherpa check` for the full report or `git-sherpa fix` for fixes.".dimmed()
+    );
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn valid_conventional_commits() {
-        let re = commit_regex_for("conventional").unwrap();
-        assert!(re.is_match("feat: add login"));
-        assert!(re.is_match("fix(auth): resolve token issue"));
-        assert!(re.is_match("chore: cleanup"));
-        assert!(re.is_match("docs: update readme"));
-        assert!(re.is_match("refactor(core): simplify logic"));
+/// Shortest useful fix for a failing rule; `git-sherpa fix` has the details.
+fn fix_hint(rule: &str, report: &Report) -> String {
+    let base = report
+        .repo
+        .base
+        .as_ref()
+        .map_or("<base>", |b| b.base.as_str());
+    match rule {
+        "branch-pattern" => format!("git branch -m <name matching {}>", report.branch.pattern),
+        "branch-age" => {
+            let age = report.branch.age.as_ref();
+            let base = age.map_or(base, |age| age.base.as_str());
+            format!("merge what is ready, then branch again from {}", base)
+        }
+        "commit-convention" | "signed-commits" | "author-email" | "references" => {
+            "git-sherpa fix".to_string()
+        }
+        "quiet-hours" => "advisory only; nothing to fix".to_string(),
+        "clean-worktree" => "git stash".to_string(),
+        "remote" => match &report.repo.remote {
+            Some(remote) if remote.problem == Some(RemoteProblem::Missing) => {
+                format!("git remote add {} <url>", remote.name)
+            }
+            Some(remote) => format!(
+                "git ls-remote {}  (check the URL, network and credentials)",
+                remote.name
+            ),
+            None => "git remote -v".to_string(),
+        },
+        "upstream" => format!("git push -u origin {}", report.branch.name),
+        "sensitive-files" | "large-files" | "protected-paths" => {
+            "git restore --staged <path>".to_string()
+        }
+        "base-divergence" | "linear-history" => format!("git rebase {}", base),
+        "attributes" => "add the missing .gitattributes entries".to_string(),
+        "staged-content" => "edit the lines, then git add -p <path>".to_string(),
+        _ => "git-sherpa fix".to_string(),
     }
+}
 
-    #[test]
-    fn invalid_conventional_commits() {
-        let re = commit_regex_for("conventional").unwrap();
-        assert!(!re.is_match("added login"));
-        assert!(!re.is_match("Fix bug"));
-        assert!(!re.is_match("random message"));
-        assert!(!re.is_match(""));
-    }
+pub(crate) fn print_json_report(report: &Report) -> Result<()> {
+    let json = serde_json::to_string_pretty(report)?;
+    println!("{}", json);
+    Ok(())
+}
 
-    #[test]
-    fn unknown_convention_returns_error() {
-        assert!(commit_regex_for("unknown").is_err());
+/// Compiles every pattern in `config` so mistakes surface before a report is built.
+pub(crate) fn validate_config(config: &Config) -> Result<()> {
+    BranchNaming::from_config(&config.branches)?;
+    Convention::from_config(&config.commits)?;
+    for pattern in &config.checks.protected_paths_allowed_branches {
+        Regex::new(pattern).map_err(|err| SherpaError::invalid_pattern(pattern, err))?;
     }
+    Ok(())
 }
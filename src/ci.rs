@@ -0,0 +1,535 @@
+//! `ci`: the check with defaults for CI runners. The provider (GitHub
+//! Actions, GitLab CI, Jenkins) is detected from its environment variables,
+//! which also give the pull request base and the branch name on the
+//! detached checkouts CI jobs run on. Issues are reported in the provider's
+//! native annotation format.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use crate::baseline;
+use crate::check::{build_report, print_text_report, CommitSelection, Report};
+use crate::config::load_config;
+use crate::error::ExitStatus;
+use crate::git;
+use crate::location::Location;
+use crate::severity::RuleSeverity;
+
+/// Where GitLab picks up the code quality report (declare it as a
+/// `codequality` artifact).
+pub const GITLAB_REPORT_PATH: &str = "gl-code-quality-report.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    GithubActions,
+    GitlabCi,
+    Jenkins,
+}
+
+impl Provider {
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::GithubActions => "github_actions",
+            Provider::GitlabCi => "gitlab_ci",
+            Provider::Jenkins => "jenkins",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CiEnv {
+    pub provider: Provider,
+    /// Revision the pull/merge request targets; `None` on branch builds.
+    pub base: Option<String>,
+    /// Branch being built; CI checkouts are usually a detached HEAD.
+    pub branch: Option<String>,
+}
+
+pub fn detect() -> Option<CiEnv> {
+    detect_from(|name| std::env::var(name).ok())
+}
+
+fn detect_from(lookup: impl Fn(&str) -> Option<String>) -> Option<CiEnv> {
+    let var = |name: &str| lookup(name).filter(|value| !value.is_empty());
+    let origin = |branch: String| format!("origin/{}", branch);
+    if var("GITHUB_ACTIONS").is_some() {
+        Some(CiEnv {
+            provider: Provider::GithubActions,
+            base: var("GITHUB_BASE_REF").map(origin),
+            branch: var("GITHUB_HEAD_REF").or_else(|| var("GITHUB_REF_NAME")),
+        })
+    } else if var("GITLAB_CI").is_some() {
+        Some(CiEnv {
+            provider: Provider::GitlabCi,
+            base: var("CI_MERGE_REQUEST_DIFF_BASE_SHA")
+                .or_else(|| var("CI_MERGE_REQUEST_TARGET_BRANCH_NAME").map(origin)),
+            branch: var("CI_MERGE_REQUEST_SOURCE_BRANCH_NAME").or_else(|| var("CI_COMMIT_BRANCH")),
+        })
+    } else if var("JENKINS_URL").is_some() {
+        Some(CiEnv {
+            provider: Provider::Jenkins,
+            base: var("CHANGE_TARGET").map(origin),
+            branch: var("CHANGE_BRANCH").or_else(|| var("BRANCH_NAME")),
+        })
+    } else {
+        None
+    }
+}
+
+pub fn ci(config_path: &Path, base: Option<&str>, commit_limit: usize) -> Result<ExitStatus> {
+    let mut config = load_config(config_path)?;
+    // CI checks out a detached HEAD into a fresh clone: there is no upstream
+    // and nothing uncommitted to protect.
+    config.checks.require_clean_worktree = false;
+    config.checks.require_upstream = false;
+
+    let env = detect();
+    let base = base
+        .map(str::to_string)
+        .or_else(|| env.as_ref().and_then(|env| env.base.clone()));
+    let selection = match &base {
+        Some(base) => {
+            if git::resolve_rev(base)?.is_none() {
+                bail!(
+                    "Base '{}' not found; fetch it first (shallow clones often lack it)",
+                    base
+                );
+            }
+            CommitSelection::Range(format!("{}..HEAD", base))
+        }
+        None => CommitSelection::Recent(commit_limit),
+    };
+    if config.checks.base_branch.is_none() {
+        config.checks.base_branch = base;
+    }
+
+    let mut report = build_report(&config, &selection)?;
+    baseline::apply_to(&config, &mut report)?;
+    print_text_report(&report);
+
+    let annotations = annotations(&report);
+    match env.map(|env| env.provider) {
+        Some(Provider::GithubActions) => print!("{}", render_github(&annotations)),
+        Some(Provider::Jenkins) => print!("{}", render_jenkins(&annotations)),
+        Some(Provider::GitlabCi) => {
+            let json = render_gitlab(&annotations, &config_path.display().to_string())?;
+            fs::write(GITLAB_REPORT_PATH, json)
+                .with_context(|| format!("write {}", GITLAB_REPORT_PATH))?;
+        }
+        None => {}
+    }
+
+    Ok(ExitStatus::from_violations(report.has_violations()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub title: &'static str,
+    pub message: String,
+    pub location: Location,
+    /// Fails the check; otherwise reported as a warning.
+    pub blocking: bool,
+}
+
+impl Annotation {
+    fn new(title: &'static str, message: String) -> Self {
+        Self {
+            title,
+            message,
+            location: Location::Repo,
+            blocking: true,
+        }
+    }
+
+    fn at(mut self, location: Location) -> Self {
+        self.location = location;
+        self
+    }
+}
+
+pub fn annotations(report: &Report) -> Vec<Annotation> {
+    let mut out = Vec::new();
+    if !report.branch.valid {
+        let message = if report.branch.problems.is_empty() {
+            format!("does not match {}", report.branch.pattern)
+        } else {
+            report.branch.problems.join("; ")
+        };
+        out.push(Annotation::new(
+            "Branch name",
+            format!("'{}' {}", report.branch.name, message),
+        ));
+    }
+    if let Some(age) = report.branch.age.as_ref().filter(|age| age.too_old()) {
+        out.push(Annotation::new(
+            "Branch age",
+            format!(
+                "'{}' diverged from {} {} day(s) ago (max_branch_age_days={})",
+                report.branch.name, age.base, age.days, age.max_days
+            ),
+        ));
+    }
+    if let Some(remote) = report.repo.remote.as_ref().filter(|r| !r.ok()) {
+        let mut message = format!(
+            "'{}' {}",
+            remote.name,
+            remote.problem.map_or("", |p| p.label())
+        );
+        if let Some(detail) = &remote.detail {
+            message.push_str(&format!("\n{}", detail));
+        }
+        out.push(Annotation::new("Remote", message));
+    }
+    for commit in report.commits.iter().filter(|c| !c.valid) {
+        let mut message = format!("{} {}", commit.short_hash, commit.message);
+        if !commit.problems.is_empty() {
+            message.push_str(&format!(" ({})", commit.problems.join("; ")));
+        }
+        out.push(Annotation::new("Commit convention", message).at(commit.location.clone()));
+    }
+    for finding in &report.sensitive.findings {
+        let mut message = format!("{} ({})", finding.describe(), finding.severity);
+        if let Some(description) = &finding.description {
+            message.push_str(&format!("\n{}", description));
+        }
+        let mut annotation =
+            Annotation::new("Sensitive file", message).at(Location::file(&finding.path));
+        annotation.blocking = finding.severity >= report.sensitive.fail_on;
+        out.push(annotation);
+    }
+    for finding in &report.sensitive.content {
+        let mut message = format!("{} ({})", finding.rule, finding.severity);
+        if let Some(description) = &finding.description {
+            message.push_str(&format!("\n{}", description));
+        }
+        let mut annotation =
+            Annotation::new("Secret", message).at(Location::file_line(&finding.path, finding.line));
+        annotation.blocking = finding.severity >= report.sensitive.fail_on;
+        out.push(annotation);
+    }
+    for file in &report.large_files {
+        out.push(
+            Annotation::new("Large file", format!("{} KiB", file.size_kb()))
+                .at(Location::file(&file.path)),
+        );
+    }
+    for line in &report.attributes.missing_lines {
+        out.push(
+            Annotation::new("Attributes", format!("missing `{}`", line))
+                .at(Location::file(".gitattributes")),
+        );
+    }
+    for violation in &report.attributes.violations {
+        out.push(
+            Annotation::new("Attributes", violation.reason.clone())
+                .at(Location::file(&violation.path)),
+        );
+    }
+    for issue in &report.staged_content {
+        out.push(
+            Annotation::new("Staged content", issue.kind.to_string())
+                .at(Location::file_line(&issue.path, issue.line)),
+        );
+    }
+    for dangling in &report.references.dangling {
+        out.push(
+            Annotation::new(
+                "Dangling reference",
+                format!(
+                    "{} in {}",
+                    dangling.reference,
+                    report.short_hash(&dangling.commit)
+                ),
+            )
+            .at(report.commit_location(&dangling.commit)),
+        );
+    }
+    if let Some(base) = report
+        .repo
+        .base
+        .as_ref()
+        .filter(|_| report.summary.base_diverged)
+    {
+        out.push(Annotation::new(
+            "Base branch",
+            format!("{} ahead, {} behind {}", base.ahead, base.behind, base.base),
+        ));
+    }
+    for change in &report.protected_paths {
+        out.push(
+            Annotation::new(
+                "Protected path",
+                format!("matches protected_paths '{}'", change.pattern),
+            )
+            .at(Location::file(&change.path)),
+        );
+    }
+    for issue in &report.signatures {
+        out.push(
+            Annotation::new(
+                "Unsigned commit",
+                format!(
+                    "{} ({})",
+                    report.short_hash(&issue.hash),
+                    issue.status.label()
+                ),
+            )
+            .at(report.commit_location(&issue.hash)),
+        );
+    }
+    for issue in &report.authors.issues {
+        out.push(
+            Annotation::new(
+                "Author email",
+                format!(
+                    "{} in {} ({})",
+                    issue.email,
+                    report.short_hash(&issue.hash),
+                    issue.problem.label()
+                ),
+            )
+            .at(report.commit_location(&issue.hash)),
+        );
+    }
+    for commit in &report.quiet_hours {
+        let mut annotation = Annotation::new(
+            "Quiet hours",
+            format!(
+                "{} authored {} ({})",
+                report.short_hash(&commit.hash),
+                commit.authored,
+                commit.reason.label()
+            ),
+        )
+        .at(report.commit_location(&commit.hash));
+        annotation.blocking = false;
+        out.push(annotation);
+    }
+    if let Some(linearity) = report.repo.linearity.as_ref().filter(|l| !l.is_linear()) {
+        out.push(Annotation::new(
+            "Linear history",
+            format!(
+                "{} merge commit(s) on top of {}",
+                linearity.merges.len(),
+                linearity.base
+            ),
+        ));
+    }
+    // Registered rules, whose violations the report keeps as they are.
+    for rule in &report.rules {
+        for violation in &rule.violations {
+            let mut annotation = Annotation::new(rule.rule, violation.message.clone())
+                .at(violation.location.clone());
+            annotation.blocking = rule.severity == RuleSeverity::Error;
+            out.push(annotation);
+        }
+    }
+    out
+}
+
+/// `check --format github`: the report's annotations as workflow commands.
+pub fn github_annotations(report: &Report) -> String {
+    render_github(&annotations(report))
+}
+
+/// GitHub Actions workflow commands (`::error file=...::message`).
+fn render_github(annotations: &[Annotation]) -> String {
+    let mut out = String::new();
+    for annotation in annotations {
+        let mut props = vec![format!("title={}", escape_property(annotation.title))];
+        if let Some(path) = annotation.location.path() {
+            props.push(format!("file={}", escape_property(path)));
+        }
+        if let Some(line) = annotation.location.line() {
+            props.push(format!("line={}", line));
+        }
+        out.push_str(&format!(
+            "::{} {}::{}\n",
+            if annotation.blocking {
+                "error"
+            } else {
+                "warning"
+            },
+            props.join(","),
+            escape_data(&annotation.message)
+        ));
+    }
+    out
+}
+
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// GCC-style lines, which Jenkins' Warnings Next Generation plugin parses.
+fn render_jenkins(annotations: &[Annotation]) -> String {
+    let mut out = String::new();
+    for annotation in annotations {
+        let location = match &annotation.location {
+            Location::FileLine { .. } => annotation.location.to_string(),
+            Location::File { path } => format!("{}:1", path),
+            Location::Repo | Location::Commit { .. } => "git-sherpa".to_string(),
+        };
+        out.push_str(&format!(
+            "{}: {}: {}: {}\n",
+            location,
+            if annotation.blocking {
+                "error"
+            } else {
+                "warning"
+            },
+            annotation.title,
+            annotation.message
+        ));
+    }
+    out
+}
+
+#[derive(Serialize)]
+struct CodeQualityIssue {
+    description: String,
+    check_name: &'static str,
+    fingerprint: String,
+    severity: &'static str,
+    location: CodeQualityLocation,
+}
+
+#[derive(Serialize)]
+struct CodeQualityLocation {
+    path: String,
+    lines: CodeQualityLines,
+}
+
+#[derive(Serialize)]
+struct CodeQualityLines {
+    begin: usize,
+}
+
+/// GitLab code quality report. Issues without a file are attached to the
+/// config, which is where the rule they break comes from.
+fn render_gitlab(annotations: &[Annotation], config_path: &str) -> Result<String> {
+    let issues: Vec<CodeQualityIssue> = annotations
+        .iter()
+        .map(|annotation| {
+            let path = annotation.location.path().unwrap_or(config_path);
+            let mut hasher = DefaultHasher::new();
+            (annotation.title, path, &annotation.message).hash(&mut hasher);
+            CodeQualityIssue {
+                description: format!("{}: {}", annotation.title, annotation.message),
+                check_name: annotation.title,
+                fingerprint: format!("{:016x}", hasher.finish()),
+                severity: if annotation.blocking {
+                    "major"
+                } else {
+                    "minor"
+                },
+                location: CodeQualityLocation {
+                    path: path.to_string(),
+                    lines: CodeQualityLines {
+                        begin: annotation.location.line().unwrap_or(1),
+                    },
+                },
+            }
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&issues)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> Option<CiEnv> {
+        detect_from(|name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn detects_providers_and_pull_request_base() {
+        assert_eq!(
+            env(&[
+                ("GITHUB_ACTIONS", "true"),
+                ("GITHUB_BASE_REF", "main"),
+                ("GITHUB_HEAD_REF", "feat/x"),
+                ("GITHUB_REF_NAME", "42/merge"),
+            ]),
+            Some(CiEnv {
+                provider: Provider::GithubActions,
+                base: Some("origin/main".into()),
+                branch: Some("feat/x".into()),
+            })
+        );
+        // Push builds have an empty GITHUB_BASE_REF.
+        let push = env(&[
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_BASE_REF", ""),
+            ("GITHUB_REF_NAME", "main"),
+        ])
+        .unwrap();
+        assert_eq!((push.base, push.branch), (None, Some("main".into())));
+
+        let gitlab = env(&[
+            ("GITLAB_CI", "true"),
+            ("CI_MERGE_REQUEST_TARGET_BRANCH_NAME", "develop"),
+            ("CI_MERGE_REQUEST_SOURCE_BRANCH_NAME", "fix/y"),
+        ])
+        .unwrap();
+        assert_eq!(gitlab.provider, Provider::GitlabCi);
+        assert_eq!(gitlab.base.as_deref(), Some("origin/develop"));
+
+        let jenkins = env(&[("JENKINS_URL", "https://ci"), ("BRANCH_NAME", "main")]).unwrap();
+        assert_eq!(jenkins.provider, Provider::Jenkins);
+        assert_eq!(jenkins.base, None);
+
+        assert_eq!(env(&[("CI", "true")]), None);
+    }
+
+    #[test]
+    fn github_annotations_are_escaped() {
+        let mut secret = Annotation::new("Secret", "aws-key\n(high)".into())
+            .at(Location::file_line("a,b:c.rs", 3));
+        secret.blocking = false;
+        let rendered = render_github(&[
+            secret,
+            Annotation::new("Branch name", "'x' does not match".into()),
+        ]);
+        assert_eq!(
+            rendered,
+            "::warning title=Secret,file=a%2Cb%3Ac.rs,line=3::aws-key%0A(high)\n\
+             ::error title=Branch name::'x' does not match\n"
+        );
+    }
+
+    #[test]
+    fn jenkins_and_gitlab_locations() {
+        let annotations = [
+            Annotation::new("Large file", "2048 KiB".into()).at(Location::file("data.bin")),
+            Annotation::new("Commit convention", "abc1234 wip".into())
+                .at(Location::commit("abc1234", "abc1234")),
+        ];
+        assert_eq!(
+            render_jenkins(&annotations),
+            "data.bin:1: error: Large file: 2048 KiB\n\
+             git-sherpa: error: Commit convention: abc1234 wip\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&render_gitlab(&annotations, ".gitsherpa.toml").unwrap()).unwrap();
+        assert_eq!(json[0]["location"]["path"], "data.bin");
+        assert_eq!(json[1]["location"]["path"], ".gitsherpa.toml");
+        assert_eq!(json[1]["severity"], "major");
+    }
+}
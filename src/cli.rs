@@ -1,30 +1,175 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use std::path::PathBuf;
 
+use crate::git::BackendKind;
+use crate::severity::FailOn;
+
 pub const DEFAULT_CONFIG_PATH: &str = ".gitsherpa.toml";
 
 #[derive(Parser)]
-#[command(name = "git-sherpa", version, about = "Git hygiene assistant")]
+#[command(
+    name = "git-sherpa",
+    version,
+    about = "Git hygiene assistant",
+    after_help = "Use `--version --json` for the version and capabilities as JSON."
+)]
 pub struct Cli {
+    /// How git is queried: `libgit2` answers the per-check queries in-process
+    /// (needs the `libgit2` feature), everything else still runs `git`
+    #[arg(long, global = true, value_enum, default_value = "subprocess")]
+    pub git_backend: BackendKind,
     #[command(subcommand)]
     pub command: Commands,
 }
 
+/// Parses the command line. Run by git as `git sherpa ...` (git exports
+/// `GIT_EXEC_PATH` to its subcommands), usage and errors say `git sherpa`.
+pub fn parse() -> Cli {
+    let mut command = Cli::command();
+    if std::env::var_os("GIT_EXEC_PATH").is_some() {
+        command = command.bin_name("git sherpa");
+    }
+    let matches = command.get_matches();
+    Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit())
+}
+
+/// Rule IDs (as in `[severity]`) accepted by `fix --only` / `--except`.
+fn rule_ids() -> clap::builder::PossibleValuesParser {
+    clap::builder::PossibleValuesParser::new(crate::rollup::RULES.iter().copied())
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Initialize repository configuration and scripts
     Init {
         #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
         config: PathBuf,
+        /// Commit the generated files as `chore: add git-sherpa configuration`
+        #[arg(long)]
+        commit: bool,
+        /// Write a named config template instead of the defaults
+        #[arg(long, value_name = "NAME")]
+        preset: Option<String>,
+        /// List the config templates available to --preset and exit
+        #[arg(long, conflicts_with_all = ["commit", "preset"])]
+        list_presets: bool,
     },
     /// Analyze repo branches and commits
     Check {
         #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
         config: PathBuf,
         #[arg(long, default_value = "text")]
-        format: OutputFormat,
+        format: CheckFormat,
         #[arg(long, default_value_t = 20)]
         commit_limit: usize,
+        /// Validate the commits in a revision range (e.g. origin/main..HEAD) instead of the last N
+        #[arg(long, value_name = "RANGE", conflicts_with = "commit_limit")]
+        range: Option<String>,
+        /// Render JSON output and exit codes through a frozen contract version
+        #[arg(long, value_name = "VERSION")]
+        compat: Option<u32>,
+        /// Use the latest frozen output contract (same as --compat with the newest version)
+        #[arg(long, conflicts_with = "compat")]
+        stable: bool,
+        /// Only inspect commits after this date (`2024-01-01`, `6 months ago`), in --range or all of HEAD's history
+        #[arg(long, value_name = "DATE", conflicts_with = "commit_limit")]
+        since: Option<String>,
+        /// Inspect a deterministic sample of N commits from --range / --since (all of HEAD's history without them)
+        #[arg(long, value_name = "N", conflicts_with = "commit_limit")]
+        sample: Option<usize>,
+        /// Record the result as a git note on HEAD (see [notes] in config)
+        #[arg(long)]
+        notes: bool,
+        /// Lowest rule severity (see [severity] in config) that makes the check exit 1
+        #[arg(long, value_enum, default_value = "error", conflicts_with_all = ["compat", "stable"])]
+        fail_on: FailOn,
+        /// Print nothing when the check passes and a compact summary with fix hints when it fails (used by the generated hooks)
+        #[arg(long, conflicts_with_all = ["compat", "stable", "watch", "recursive"])]
+        quiet_on_success: bool,
+        /// Re-run the check whenever the repo or config changes, reloading the config live
+        #[arg(long, conflicts_with_all = ["compat", "stable", "notes"])]
+        watch: bool,
+        /// Re-check at least this often in --watch, in seconds (the polling interval where files cannot be watched)
+        #[arg(long, default_value_t = 2, requires = "watch", value_name = "SECS")]
+        interval: u64,
+        /// Check every repository under DIR and print a roll-up summary
+        #[arg(
+            long,
+            value_name = "DIR",
+            num_args = 0..=1,
+            default_missing_value = ".",
+            conflicts_with_all = ["compat", "stable", "notes", "watch"]
+        )]
+        recursive: Option<PathBuf>,
+        /// Fetch REMOTE (default: origin) and check the name of every branch on it, listing offenders with their last committer and age
+        #[arg(
+            long,
+            value_name = "REMOTE",
+            num_args = 0..=1,
+            default_missing_value = "origin",
+            conflicts_with_all = ["compat", "stable", "notes", "watch", "recursive", "staged_only", "pre_push"]
+        )]
+        remote: Option<String>,
+        /// Run as the pre-push hook: also scan the commits being pushed to
+        /// REMOTE (refs are read from stdin as git passes them)
+        #[arg(long, value_name = "REMOTE", conflicts_with_all = ["watch", "recursive"])]
+        pre_push: Option<String>,
+        /// Print the effective config (repo config over ~/.config/git-sherpa/config.toml) and exit
+        #[arg(long, conflicts_with_all = ["watch", "recursive", "pre_push", "notes"])]
+        show_config: bool,
+        /// Load and validate the repo config and every nested one in subdirectories, then exit
+        #[arg(long, conflicts_with_all = ["watch", "recursive", "pre_push", "notes", "show_config"])]
+        all_configs: bool,
+        /// Also fail on violations accepted by .gitsherpa-baseline.json
+        #[arg(long)]
+        no_baseline: bool,
+        /// Only check what is being committed (staged files, protected paths, branch name), skipping commit history and upstream (used by the pre-commit hook)
+        #[arg(long, conflicts_with_all = ["commit_limit", "range", "since", "sample", "pre_push", "watch", "recursive"])]
+        staged_only: bool,
+        /// Print how long each git query and rule took, and what each rule found, to stderr
+        #[arg(long, conflicts_with_all = ["watch", "recursive"])]
+        timings: bool,
+        /// Leave out the commits listed in FILE (one hash per line, `#` comments), like `git blame --ignore-revs-file`
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["watch", "recursive"])]
+        ignore_rev_file: Option<PathBuf>,
+        /// Validate every commit again instead of reusing the verdicts cached in .git/sherpa-cache
+        #[arg(long, conflicts_with_all = ["watch", "recursive"])]
+        no_cache: bool,
+    },
+    /// Validate a single message against the commit convention
+    LintMessage {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// PR/MR title that will become the squash commit subject
+        #[arg(long, value_name = "TITLE", required_unless_present = "message_file")]
+        pr_title: Option<String>,
+        /// Commit message file, as passed to the commit-msg hook
+        #[arg(hide = true, conflicts_with = "pr_title")]
+        message_file: Option<PathBuf>,
+        #[arg(long, default_value = "text")]
+        format: Format,
+        /// Print nothing when the message is valid (used by the commit-msg hook)
+        #[arg(long)]
+        quiet_on_success: bool,
+    },
+    /// Fill {type}, {scope} and {ticket} in a commit message file from the branch and staged paths
+    PrepareMessage {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Commit message file, as passed to the prepare-commit-msg hook
+        message_file: PathBuf,
+        /// Where the message came from: message, template, merge, squash or commit
+        source: Option<String>,
+        /// Commit git passes along with the `commit` source; unused
+        #[arg(hide = true)]
+        commit: Option<String>,
+    },
+    /// Print a commented subject suggestion: allowed types, scope and ticket
+    SuggestMessage {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Add the suggestion above the comments of this message file instead
+        message_file: Option<PathBuf>,
     },
     /// Propose fixes for issues
     Fix {
@@ -32,9 +177,169 @@ pub enum Commands {
         config: PathBuf,
         #[arg(long, default_value_t = 20)]
         commit_limit: usize,
-        /// Automatically apply safe fixes (e.g. set upstream)
+        /// Only propose fixes for commits in a revision range (e.g. origin/main..HEAD)
+        #[arg(long, value_name = "RANGE", conflicts_with = "commit_limit")]
+        range: Option<String>,
+        /// Apply fixes: unstage sensitive files, set upstream, fetch, rename the branch, and reword unpushed invalid commits
         #[arg(long)]
         apply: bool,
+        /// Go through the fixes one by one, asking before running each (y/n/q, or y/n/e per reworded commit)
+        #[arg(long, conflicts_with = "apply")]
+        interactive: bool,
+        /// Unstage sensitive files without adding them to .gitignore
+        #[arg(long)]
+        no_gitignore: bool,
+        /// Only propose fixes for these rules (e.g. commit-convention)
+        #[arg(long, value_name = "RULE", value_delimiter = ',', value_parser = rule_ids())]
+        only: Vec<String>,
+        /// Propose fixes for every rule but these
+        #[arg(long, value_name = "RULE", value_delimiter = ',', value_parser = rule_ids(), conflicts_with = "only")]
+        except: Vec<String>,
+    },
+    /// Check a CI build: PR commit range, no worktree/upstream checks, native annotations
+    Ci {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Base of the commit range (default: the PR/MR target from the CI environment)
+        #[arg(long, value_name = "REV")]
+        base: Option<String>,
+        /// Commits to check when there is no base (branch builds)
+        #[arg(long, default_value_t = 20)]
+        commit_limit: usize,
+    },
+    /// Serve lint-message and range validation to bots over HTTP
+    Serve {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Address to listen on
+        #[arg(
+            long,
+            value_name = "ADDR",
+            num_args = 0..=1,
+            default_missing_value = "127.0.0.1:8720",
+            required = true
+        )]
+        http: Option<String>,
+    },
+    /// Predict whether merging or rebasing onto the target branch will conflict
+    Conflicts {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Branch to merge with (default: [checks] base_branch, then the remote's default branch)
+        #[arg(long, value_name = "REV")]
+        target: Option<String>,
+        #[arg(long, default_value = "text")]
+        format: Format,
+    },
+    /// Set up config, hooks, commit template and git settings in one step
+    Bootstrap {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Preset config file to adopt when the repo has no config yet
+        #[arg(long, value_name = "PATH")]
+        preset_file: Option<PathBuf>,
+    },
+    /// Diagnose the environment (git version, PATH, config, hooks, reflogs, remotes) with remediation hints
+    Doctor {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Skip the remote reachability checks
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Add git aliases: `git ready` (check) and `git sherpa-fix` (fix)
+    InstallAlias {
+        /// Write to the user's git config instead of this repository's
+        #[arg(long)]
+        global: bool,
+        /// Replace aliases of the same name that do something else
+        #[arg(long)]
+        force: bool,
+    },
+    /// Report repository size, largest blobs and object health
+    RepoHealth {
+        #[arg(long, default_value = "text")]
+        format: Format,
+        /// Number of largest blobs to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Blobs at least this large (KiB) are suggested for Git LFS
+        #[arg(long, default_value_t = 1024)]
+        lfs_threshold_kb: u64,
+    },
+    /// Find large blobs in history and plan their migration to Git LFS
+    LfsAudit {
+        #[arg(long, default_value = "text")]
+        format: Format,
+        /// Blobs at least this large (KiB) are reported
+        #[arg(long, default_value_t = 1024)]
+        threshold_kb: u64,
+    },
+    /// Scan history for secrets and report stale branches and repository health
+    Audit {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Only run if the last audit is older than this; otherwise exit 0
+        #[arg(long, value_name = "INTERVAL")]
+        schedule: Option<Schedule>,
+        /// Where to write the JSON report (default: .git/sherpa-cache/audit.json)
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// File recording when the last audit ran (default:
+        /// .git/sherpa-cache/audit-state.json); keep it in a cached directory in CI
+        #[arg(long, value_name = "PATH")]
+        state: Option<PathBuf>,
+        /// Branch to check merges against (default: [checks] base_branch, then origin/HEAD, then main)
+        #[arg(long, value_name = "REV")]
+        base: Option<String>,
+        #[arg(long, default_value = "text")]
+        format: Format,
+    },
+    /// Convention compliance per author, message length and merge ratio over recent weeks
+    Stats {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// How many weeks of history to analyze
+        #[arg(long, default_value_t = 12)]
+        weeks: u64,
+        #[arg(long, default_value = "text")]
+        format: Format,
+    },
+    /// Preview the next release from the conventional commits since the last tag
+    Release {
+        #[command(subcommand)]
+        action: ReleaseAction,
+    },
+    /// Manage the results cached in .git/sherpa-cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Show or share check results recorded as git notes
+    Notes {
+        #[command(subcommand)]
+        action: NotesAction,
+    },
+    /// Store provider tokens in the OS keychain
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+    /// Try the configured policy on hypothetical inputs, or sync the org policy
+    Policy {
+        #[command(subcommand)]
+        action: PolicyAction,
+    },
+    /// Record current violations so check only fails on new ones
+    Baseline {
+        #[command(subcommand)]
+        action: BaselineAction,
+    },
+    /// Create branches whose names follow the branch policy, or audit existing ones
+    #[command(visible_alias = "branches")]
+    Branch {
+        #[command(subcommand)]
+        action: BranchAction,
     },
     /// Manage git hooks
     Hooks {
@@ -43,20 +348,212 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum BranchAction {
+    /// Create and switch to a branch named from [branches] template
+    New {
+        /// Branch type, e.g. feat or fix
+        #[arg(long = "type", value_name = "TYPE")]
+        kind: String,
+        /// Short description; lowercased, with spaces and punctuation turned into dashes
+        #[arg(long)]
+        name: String,
+        /// Ticket key, filled into [branches] ticket_template
+        #[arg(long)]
+        ticket: Option<String>,
+        /// Push the new branch to origin and set it as upstream
+        #[arg(long)]
+        upstream: bool,
+    },
+    /// List branches with their age, merged status and name compliance
+    Audit {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Branch to check merges against (default: [checks] base_branch, then origin/HEAD, then main)
+        #[arg(long, value_name = "REV")]
+        base: Option<String>,
+        /// Include remote-tracking branches
+        #[arg(long)]
+        remote: bool,
+        #[arg(long, default_value = "text")]
+        format: Format,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ReleaseAction {
+    /// Print the semver bump and next version the commits since the last release tag call for
+    Preview {
+        /// Count commits from this tag or revision instead of the highest version tag reachable from HEAD
+        #[arg(long, value_name = "REV")]
+        from: Option<String>,
+        #[arg(long, default_value = "text")]
+        format: Format,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// Remove the cached commit verdicts and API responses
+    Clear,
+}
+
+#[derive(Subcommand)]
+pub enum PolicyAction {
+    /// Evaluate the policy against a branch, messages and files without reading the repository
+    #[command(arg_required_else_help = true)]
+    Simulate {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Branch name to check against the branch pattern and protected branches
+        #[arg(long)]
+        branch: Option<String>,
+        /// Commit message to lint (repeatable)
+        #[arg(long = "message", value_name = "MESSAGE")]
+        messages: Vec<String>,
+        /// Changed paths to check against sensitive and protected path patterns
+        #[arg(long, num_args = 1..)]
+        files: Vec<String>,
+        #[arg(long, default_value = "text")]
+        format: Format,
+    },
+    /// Fetch, verify and cache the org policy from [policy] source
+    Sync {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BaselineAction {
+    /// Write .gitsherpa-baseline.json with the violations in HEAD's history and the staged files
+    Create {
+        #[arg(long, default_value = DEFAULT_CONFIG_PATH)]
+        config: PathBuf,
+        /// Only record the commits in a revision range
+        #[arg(long, value_name = "RANGE")]
+        range: Option<String>,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum HooksAction {
-    /// Install pre-commit and pre-push hooks
+    /// Install pre-commit, prepare-commit-msg, commit-msg and pre-push hooks
     Install {
         /// Overwrite existing hooks
         #[arg(long)]
         force: bool,
+        /// Install into .gitsherpa/hooks via core.hooksPath, running the previously active hooks (e.g. husky's) first
+        #[arg(long)]
+        chain: bool,
     },
     /// Remove hooks installed by git-sherpa
     Uninstall,
+    /// Rewrite installed git-sherpa hooks from this version's templates
+    Update,
+    /// Run an installed hook as git would, without committing or pushing
+    Simulate {
+        hook: SimulatedHook,
+        /// Push target as <remote>/<branch> (pre-push only; default: upstream of the current branch)
+        #[arg(long, value_name = "REMOTE/BRANCH")]
+        to: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum SimulatedHook {
+    PreCommit,
+    PrePush,
+}
+
+impl SimulatedHook {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            SimulatedHook::PreCommit => "pre-commit",
+            SimulatedHook::PrePush => "pre-push",
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum AuthAction {
+    /// Prompt for a token and store it in the keychain
+    Login {
+        provider: AuthProvider,
+        /// API host (GitLab: defaults to gitlab.com; Jira: defaults to [references] jira_url)
+        #[arg(long)]
+        host: Option<String>,
+        /// Read the token from stdin instead of prompting
+        #[arg(long)]
+        with_token: bool,
+    },
+    /// Remove a stored token from the keychain
+    Logout {
+        provider: AuthProvider,
+        #[arg(long)]
+        host: Option<String>,
+    },
+    /// Show where each provider's token comes from
+    Status,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum Schedule {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Schedule {
+    pub fn interval_secs(self) -> u64 {
+        let days = match self {
+            Schedule::Daily => 1,
+            Schedule::Weekly => 7,
+            Schedule::Monthly => 30,
+        };
+        days * 24 * 60 * 60
+    }
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum AuthProvider {
+    Github,
+    Gitlab,
+    Jira,
+}
+
+#[derive(Subcommand)]
+pub enum NotesAction {
+    /// Print the check result recorded on a commit
+    Show {
+        #[arg(default_value = "HEAD")]
+        rev: String,
+    },
+    /// Push the notes ref to a remote
+    Push {
+        #[arg(default_value = "origin")]
+        remote: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
 }
 
-#[derive(Clone, Debug, clap::ValueEnum)]
-pub enum OutputFormat {
+/// The formats `check` accepts; which of them a given mode supports is
+/// settled before dispatch (see `app`).
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum CheckFormat {
     Text,
     Json,
+    /// Markdown, for PR comments and wiki pages (--recursive only)
+    Markdown,
+    /// GitHub Actions workflow commands (`::error file=...::message`) that
+    /// show violations as inline annotations
+    Github,
+    /// One JSON object per finding, streamed as the check runs, then a
+    /// summary line
+    Jsonl,
 }
@@ -0,0 +1,226 @@
+//! Frozen output contracts for scripting.
+//!
+//! `check --compat <N>` (or `--stable`, the latest frozen version) renders the
+//! report through a pinned schema so JSON structure, rule IDs and exit codes
+//! stay the same while the native report evolves. New fields are added to the
+//! native report only; each pinned schema translates from it.
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use crate::check::Report;
+
+/// Latest frozen contract, selected by `--stable`.
+pub const STABLE_VERSION: u32 = 1;
+
+/// Rule IDs guaranteed by contract version 1, in report order.
+pub const RULE_IDS_V1: &[&str] = &[
+    "branch-pattern",
+    "commit-convention",
+    "clean-worktree",
+    "upstream",
+    "sensitive-files",
+];
+
+/// Exit codes guaranteed by contract version 1. Errors also exit with 1.
+pub const EXIT_OK_V1: i32 = 0;
+pub const EXIT_VIOLATIONS_V1: i32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct ReportV1 {
+    pub schema_version: u32,
+    pub branch: BranchV1,
+    pub commits: Vec<CommitV1>,
+    pub repo: RepoV1,
+    pub sensitive: SensitiveV1,
+    pub summary: SummaryV1,
+    pub violations: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BranchV1 {
+    pub name: String,
+    pub pattern: String,
+    pub valid: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommitV1 {
+    pub hash: String,
+    pub message: String,
+    pub valid: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepoV1 {
+    pub worktree_clean: bool,
+    pub upstream_set: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SensitiveV1 {
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SummaryV1 {
+    pub total_commits: usize,
+    pub invalid_commits: usize,
+    pub branch_valid: bool,
+    pub worktree_clean: bool,
+    pub upstream_set: bool,
+    pub sensitive_files: usize,
+}
+
+impl ReportV1 {
+    pub fn exit_code(&self) -> i32 {
+        if self.violations.is_empty() {
+            EXIT_OK_V1
+        } else {
+            EXIT_VIOLATIONS_V1
+        }
+    }
+}
+
+pub fn resolve_version(compat: Option<u32>, stable: bool) -> Option<u32> {
+    compat.or(if stable { Some(STABLE_VERSION) } else { None })
+}
+
+pub fn translate(report: &Report, version: u32) -> Result<ReportV1> {
+    match version {
+        1 => Ok(to_v1(report)),
+        _ => bail!(
+            "Unsupported compat version {} (supported: 1..={})",
+            version,
+            STABLE_VERSION
+        ),
+    }
+}
+
+fn to_v1(report: &Report) -> ReportV1 {
+    let summary = &report.summary;
+    let failed = [
+        !summary.branch_valid,
+        summary.invalid_commits > 0,
+        !summary.worktree_clean,
+        !summary.upstream_set,
+        // v1 fails on any sensitive file, regardless of severity tiers.
+        !report.sensitive.files.is_empty(),
+    ];
+    let violations = RULE_IDS_V1
+        .iter()
+        .zip(failed)
+        .filter(|(_, failed)| *failed)
+        .map(|(id, _)| *id)
+        .collect();
+
+    ReportV1 {
+        schema_version: 1,
+        branch: BranchV1 {
+            name: report.branch.name.clone(),
+            pattern: report.branch.pattern.clone(),
+            valid: report.branch.valid,
+        },
+        commits: report
+            .commits
+            .iter()
+            .map(|c| CommitV1 {
+                hash: c.hash.clone(),
+                message: c.message.clone(),
+                valid: c.valid,
+            })
+            .collect(),
+        repo: RepoV1 {
+            worktree_clean: report.repo.worktree_clean,
+            upstream_set: report.repo.upstream_set,
+        },
+        sensitive: SensitiveV1 {
+            files: report.sensitive.files.clone(),
+        },
+        summary: SummaryV1 {
+            total_commits: summary.total_commits,
+            invalid_commits: summary.invalid_commits,
+            branch_valid: summary.branch_valid,
+            worktree_clean: summary.worktree_clean,
+            upstream_set: summary.upstream_set,
+            sensitive_files: report.sensitive.files.len(),
+        },
+        violations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::{BranchReport, CommitReport, RepoReport, SensitiveReport, Summary};
+    use crate::location::Location;
+
+    fn sample_report(branch_valid: bool, sensitive: Vec<String>) -> Report {
+        Report {
+            branch: BranchReport {
+                name: "feat/x".into(),
+                pattern: "^feat/.+$".into(),
+                valid: branch_valid,
+                problems: Vec::new(),
+                age: None,
+            },
+            commits: vec![CommitReport {
+                hash: "a".repeat(40),
+                short_hash: "a".repeat(7),
+                location: Location::Commit {
+                    sha: "a".repeat(40),
+                    short: "a".repeat(7),
+                },
+                message: "feat: x".into(),
+                valid: true,
+                problems: Vec::new(),
+                body_rules: Vec::new(),
+                forbidden: None,
+                baselined: false,
+            }],
+            repo: RepoReport {
+                worktree_clean: true,
+                upstream_set: true,
+                ..Default::default()
+            },
+            summary: Summary {
+                total_commits: 1,
+                branch_valid,
+                worktree_clean: true,
+                upstream_set: true,
+                ..Default::default()
+            },
+            sensitive: SensitiveReport {
+                files: sensitive,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn clean_report_has_no_violations() {
+        let v1 = translate(&sample_report(true, vec![]), 1).unwrap();
+        assert!(v1.violations.is_empty());
+        assert_eq!(v1.exit_code(), EXIT_OK_V1);
+    }
+
+    #[test]
+    fn violations_use_frozen_rule_ids() {
+        let v1 = translate(&sample_report(false, vec![".env".into()]), 1).unwrap();
+        assert_eq!(v1.violations, vec!["branch-pattern", "sensitive-files"]);
+        assert_eq!(v1.exit_code(), EXIT_VIOLATIONS_V1);
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        assert!(translate(&sample_report(true, vec![]), 99).is_err());
+    }
+
+    #[test]
+    fn stable_resolves_to_latest() {
+        assert_eq!(resolve_version(None, true), Some(STABLE_VERSION));
+        assert_eq!(resolve_version(Some(1), false), Some(1));
+        assert_eq!(resolve_version(None, false), None);
+    }
+}
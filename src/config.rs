@@ -1,7 +1,19 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use glob_match::glob_match;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+
+use crate::error::{span_at, SherpaError};
+use crate::git;
+use crate::quiet_hours::Window;
+use crate::rollup;
+use crate::sensitive::{SensitivePattern, Severity};
+use crate::severity::{self, RuleSeverity};
+
+pub use crate::convention::LengthMode;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
@@ -12,33 +24,374 @@ pub struct Config {
     pub sensitive: SensitiveConfig,
     #[serde(default)]
     pub hooks: HooksConfig,
+    #[serde(default)]
+    pub notes: NotesConfig,
+    #[serde(default)]
+    pub attributes: AttributesConfig,
+    #[serde(default)]
+    pub references: ReferencesConfig,
+    #[serde(default)]
+    pub pull_requests: PullRequestsConfig,
+    #[serde(default)]
+    pub files: FilesConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    /// Severity per rule, e.g. `linear-history = "warning"`; unlisted rules
+    /// are errors.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub severity: BTreeMap<String, RuleSeverity>,
+    /// Configs of subdirectories, deepest first; see [`Config::for_path`].
+    #[serde(skip)]
+    pub scopes: Vec<ConfigScope>,
+    /// Reuse the commit verdicts in `.git/sherpa-cache`; set by `check`
+    /// unless `--no-cache`.
+    #[serde(skip)]
+    pub cache_commits: bool,
+}
+
+impl Config {
+    /// The config governing `path`, relative to the repo root: the nested
+    /// config of the deepest directory above it that has one, else this one.
+    pub fn for_path(&self, path: &str) -> &Config {
+        self.scopes
+            .iter()
+            .find(|scope| {
+                path.strip_prefix(scope.dir.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .map_or(self, |scope| &scope.config)
+    }
+
+    /// The config governing all of `paths`, e.g. the files of one commit,
+    /// if a single one does; else this one.
+    pub fn for_paths<'p>(&self, paths: impl IntoIterator<Item = &'p str>) -> &Config {
+        let mut governing = paths.into_iter().map(|path| self.for_path(path));
+        match governing.next() {
+            Some(first) if governing.all(|config| std::ptr::eq(config, first)) => first,
+            _ => self,
+        }
+    }
+
+    /// This config and those of its scopes.
+    pub fn with_scopes(&self) -> impl Iterator<Item = &Config> {
+        std::iter::once(self).chain(self.scopes.iter().map(|scope| &scope.config))
+    }
+}
+
+/// A config file in a subdirectory of a monorepo, e.g.
+/// `packages/api/.gitsherpa.toml`. It is layered over the repo config and
+/// the nested configs above it, and governs the staged files and commit
+/// messages of changes under its directory.
+#[derive(Debug)]
+pub struct ConfigScope {
+    /// Relative to the repo root, without a trailing `/`.
+    pub dir: String,
+    pub path: PathBuf,
+    pub config: Config,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BranchConfig {
+    /// Regex branch names must match, unless `types` is set. Left out, it
+    /// is the built-in pattern rather than one that matches anything.
+    #[serde(default = "default_branch_pattern")]
     pub pattern: String,
+    /// Structured alternative to `pattern`: names are
+    /// `<type><separator>[<ticket>-]<slug>`, with a ticket only when
+    /// `ticket_pattern` is set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+    #[serde(default = "default_separator")]
+    pub separator: String,
+    #[serde(default = "default_slug_pattern")]
+    pub slug_pattern: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticket_pattern: Option<String>,
+    /// Name `branch new` composes from `{type}` and `{name}`.
+    #[serde(default = "default_branch_template")]
+    pub template: String,
+    /// Used instead of `template` when `branch new --ticket` is given.
+    #[serde(default = "default_ticket_template")]
+    pub ticket_template: String,
+    /// `branches audit` flags branches without commits for this many days.
+    #[serde(default = "default_stale_after_days")]
+    pub stale_after_days: u64,
+}
+
+fn default_branch_pattern() -> String {
+    "^(feat|fix|chore|docs|refactor)/[a-z0-9-]+$".to_string()
+}
+
+fn default_separator() -> String {
+    "/".to_string()
+}
+
+fn default_slug_pattern() -> String {
+    "[a-z0-9-]+".to_string()
+}
+
+fn default_stale_after_days() -> u64 {
+    30
+}
+
+fn default_branch_template() -> String {
+    "{type}/{name}".to_string()
+}
+
+fn default_ticket_template() -> String {
+    "{type}/{ticket}-{name}".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CommitConfig {
+    /// `conventional`, `gitmoji`, `jira`, or `custom` with `pattern`.
     pub convention: String,
+    /// Subject regex for `convention = "custom"`. Named groups `type` and
+    /// `scope` enable the `types` and `require_scope` rules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Allowed types (project keys for `jira`); empty keeps the convention's defaults.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+    #[serde(default)]
+    pub require_scope: bool,
+    /// Allowed scopes; `"auto"` (alone or in the list) derives them from the
+    /// workspace members or top-level directories. Empty allows any scope.
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub scopes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_subject_length: Option<usize>,
+    /// How the length limits count: `graphemes` (default), `chars`,
+    /// `bytes`, or `width` for terminal columns.
+    #[serde(default)]
+    pub length_mode: LengthMode,
+    #[serde(default)]
+    pub body: BodyConfig,
+    /// Regexes stripped from the start of a subject before validation,
+    /// e.g. `\[.*?\]\s*` for `[skip ci]` or ticket tags.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strip_prefixes: Vec<String>,
+    /// Maximum length of PR titles checked by `lint-message --pr-title`.
+    #[serde(default = "default_pr_title_max_length")]
+    pub pr_title_max_length: usize,
+    /// Flag merge commits in the inspected commits.
+    #[serde(default)]
+    pub forbid_merge_commits: bool,
+    /// Flag `fixup!` / `squash!` commits that were never autosquashed.
+    #[serde(default)]
+    pub forbid_fixup_commits: bool,
+    /// Message the `prepare-commit-msg` hook pre-fills, with `{type}`,
+    /// `{scope}` and `{ticket}` taken from the branch and staged paths.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Commits left out of every check, by full hash or an abbreviation of
+    /// at least 7 digits, e.g. imported history that predates the conventions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_hashes: Vec<String>,
+}
+
+impl CommitConfig {
+    /// The full hashes of the commits in `ignore_hashes`. An abbreviation
+    /// that is ambiguous in the repo names none.
+    pub fn ignored_commits(&self) -> Result<HashSet<String>> {
+        Ok(git::resolve_commits(&self.ignore_hashes)?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+}
+
+/// 7 to 64 hex digits: a full SHA-1 or SHA-256 hash or an abbreviation of
+/// one long enough not to match commits by accident.
+fn is_hash(s: &str) -> bool {
+    (7..=64).contains(&s.len()) && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The hashes in a file like `git blame --ignore-revs-file` reads: one per
+/// line, `#` starting a comment.
+pub fn read_ignore_rev_file(path: &Path) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    let mut hashes = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let hash = line.split('#').next().unwrap_or_default().trim();
+        if hash.is_empty() {
+            continue;
+        }
+        if !is_hash(hash) {
+            bail!(
+                "{}:{}: `{}` is not a commit hash",
+                path.display(),
+                i + 1,
+                hash
+            );
+        }
+        hashes.push(hash.to_string());
+    }
+    Ok(hashes)
+}
+
+fn default_pr_title_max_length() -> usize {
+    72
+}
+
+/// A string as a list of one, for `scopes = "auto"`.
+fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(one) => vec![one],
+        OneOrMany::Many(many) => many,
+    })
+}
+
+/// Rules for the commit message body; all off by default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BodyConfig {
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub blank_line_after_subject: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_line_length: Option<usize>,
+    /// Require a `Signed-off-by: Name <email>` trailer (DCO).
+    #[serde(default)]
+    pub require_signoff: bool,
+    /// Words not allowed anywhere in the message, matched case-insensitively.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forbidden_words: Vec<String>,
+    /// Require an issue reference footer such as `Refs: #12`.
+    #[serde(default)]
+    pub require_issue_reference: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CheckConfig {
     pub require_clean_worktree: bool,
     pub require_upstream: bool,
+    /// Fail when the branch's remote is missing, unreachable or rejects the
+    /// credentials.
+    #[serde(default)]
+    pub require_remote: bool,
+    /// Warn when the last `git fetch` is older than this many hours.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fetch_age_hours: Option<u64>,
+    /// Flag staged files larger than this many KiB.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_size_kb: Option<u64>,
+    /// Also flag oversized blobs added by the checked commits.
+    #[serde(default)]
+    pub large_files_in_commits: bool,
+    /// Branch to measure divergence against, e.g. `origin/main`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_branch: Option<String>,
+    /// Fail when the branch is more than this many commits behind `base_branch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_behind: Option<usize>,
+    /// Fail when the branch is more than this many commits ahead of `base_branch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_ahead: Option<usize>,
+    /// Fail when the first commit not on `base_branch` is older than this
+    /// many days.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_branch_age_days: Option<u64>,
+    /// Warn (without failing) when the diff against `base_branch` changes
+    /// more lines than this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_review_lines: Option<usize>,
+    /// Reject merge commits (and criss-cross merges) on top of `base_branch`.
+    #[serde(default)]
+    pub require_linear_history: bool,
+    /// Reject inspected commits without a good GPG/SSH signature.
+    #[serde(default)]
+    pub require_signed_commits: bool,
+    /// Reject inspected commits authored with an email outside these
+    /// domains (subdomains included), e.g. `company.com`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_author_domains: Vec<String>,
+    /// Reject inspected commits authored with a noreply address.
+    #[serde(default)]
+    pub forbid_noreply_emails: bool,
+    /// Warn about inspected commits authored in this window of the
+    /// author's local time, e.g. `22:00-07:00`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<String>,
+    /// Warn about inspected commits authored on a Saturday or Sunday.
+    #[serde(default)]
+    pub quiet_weekends: bool,
+    /// Globs of paths staged changes must not touch, e.g. `migrations/**`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_paths: Vec<String>,
+    /// Branch regexes allowed to change `protected_paths` anyway.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_paths_allowed_branches: Vec<String>,
+    /// Reject staged files with leftover `<<<<<<<` / `>>>>>>>` lines.
+    #[serde(default = "default_forbid_conflict_markers")]
+    pub forbid_conflict_markers: bool,
+    /// Reject staged lines ending in spaces or tabs.
+    #[serde(default)]
+    pub forbid_trailing_whitespace: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SensitiveConfig {
-    pub patterns: Vec<String>,
+    /// File globs, or named rules with a `glob` or a content `regex`.
+    #[serde(default = "crate::sensitive::default_patterns")]
+    pub patterns: Vec<SensitivePattern>,
+    /// Findings below this severity are reported but do not fail the check.
+    #[serde(default)]
+    pub fail_on: Severity,
+    /// Severity overrides, keyed by file pattern or content rule ID.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub severity: BTreeMap<String, Severity>,
+    /// Scan lines added in staged changes for secrets, not just file names.
+    #[serde(default = "default_scan_content")]
+    pub scan_content: bool,
+    /// Extra content rules: rule ID to regex, matched against added lines.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, String>,
+}
+
+/// Paths content checks leave alone.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FilesConfig {
+    /// Globs of vendored or generated code, e.g. `third_party/**`, that is
+    /// not held to local rules. File-name checks still apply to it.
+    #[serde(default)]
+    pub vendored: Vec<String>,
+}
+
+impl FilesConfig {
+    pub fn is_vendored(&self, path: &str) -> bool {
+        self.vendored
+            .iter()
+            .any(|pattern| glob_match(pattern, path))
+    }
+}
+
+fn default_scan_content() -> bool {
+    true
+}
+
+fn default_forbid_conflict_markers() -> bool {
+    true
 }
 
 impl Default for SensitiveConfig {
     fn default() -> Self {
         Self {
             patterns: crate::sensitive::default_patterns(),
+            fail_on: Severity::default(),
+            severity: BTreeMap::new(),
+            scan_content: default_scan_content(),
+            rules: BTreeMap::new(),
         }
     }
 }
@@ -46,38 +399,456 @@ impl Default for SensitiveConfig {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HooksConfig {
     pub protected_branches: Vec<String>,
+    /// Tag globs (e.g. `v*`) that the pre-push hook refuses to delete or move.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_tags: Vec<String>,
+    /// Block pushes whose new objects take more than this on disk.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_push_size_mb: Option<u64>,
+    /// Block pushes that send more than this many objects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_push_objects: Option<u64>,
+    /// Also protect the branches protected on the GitHub/GitLab `origin`.
+    #[serde(default)]
+    pub fetch_protected: bool,
+    /// Let `check` rewrite installed hooks that predate the running binary
+    /// instead of only warning about them.
+    #[serde(default)]
+    pub auto_update: bool,
+    /// Have the prepare-commit-msg hook add a commented suggestion (types,
+    /// scope, ticket) to the message of a plain `git commit`.
+    #[serde(default)]
+    pub suggest_message: bool,
 }
 
 impl Default for HooksConfig {
     fn default() -> Self {
         Self {
             protected_branches: vec!["main".to_string(), "master".to_string()],
+            protected_tags: Vec::new(),
+            max_push_size_mb: None,
+            max_push_objects: None,
+            fetch_protected: false,
+            auto_update: false,
+            suggest_message: false,
+        }
+    }
+}
+
+/// `.gitattributes` policy; empty lists disable the check.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AttributesConfig {
+    /// Lines that must be present in `.gitattributes`, e.g. `* text=auto`.
+    #[serde(default)]
+    pub required: Vec<String>,
+    /// Globs of files that must be marked binary or tracked with LFS.
+    #[serde(default)]
+    pub binary_patterns: Vec<String>,
+}
+
+/// Verification of `Fixes #123` / `Closes ABC-42` footers against the tracker.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReferencesConfig {
+    #[serde(default)]
+    pub verify: bool,
+    /// `owner/repo` for `#123` references; defaults to the GitHub `origin` remote.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_repo: Option<String>,
+    /// Base URL of the Jira instance for `KEY-123` references.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jira_url: Option<String>,
+}
+
+/// Lookup of the PR/MR linked to the current branch on GitHub or GitLab.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PullRequestsConfig {
+    #[serde(default)]
+    pub status: bool,
+}
+
+/// Org-wide policy that `policy sync` fetches and caches; the cached copy
+/// is layered under the user and repo config.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PolicyConfig {
+    /// `https://` URL or path of a TOML file, or a git repository (URL or
+    /// path) containing `path`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// File within a git `source`; `.gitsherpa.toml` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Branch or tag of a git `source`; its default branch when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// Expected SHA-256 of the policy file, in hex.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Require a good GPG/SSH signature on the commit a git `source` is
+    /// read from.
+    #[serde(default)]
+    pub verify_signature: bool,
+}
+
+/// Opt-in recording of check results as git notes on the tip commit.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotesConfig {
+    pub enabled: bool,
+    #[serde(rename = "ref")]
+    pub notes_ref: String,
+}
+
+impl Default for NotesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            notes_ref: "sherpa".to_string(),
         }
     }
 }
 
+/// `$XDG_CONFIG_HOME/git-sherpa/config.toml`, falling back to
+/// `~/.config/git-sherpa/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    let dir = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("git-sherpa").join("config.toml"))
+}
+
+/// Where `policy sync` caches the org policy.
+pub fn policy_cache_path() -> Result<PathBuf> {
+    Ok(git::git_dir()?.join("sherpa-cache").join("policy.toml"))
+}
+
+/// `[policy]` of the repo config at `path` alone: the rest of the config
+/// may only be complete once the policy is synced.
+pub fn load_policy_config(path: &Path) -> Result<PolicyConfig> {
+    let repo = read_layer(path)?;
+    match repo.get("policy") {
+        Some(policy) => PolicyConfig::deserialize(policy.clone()).map_err(|err| {
+            SherpaError::ConfigInvalid {
+                path: path.to_path_buf(),
+                span: None,
+                message: err.message().to_string(),
+            }
+            .into()
+        }),
+        None => Ok(PolicyConfig::default()),
+    }
+}
+
+/// The cached org policy, if the repo config at `path` sets
+/// `[policy] source`; warns when it has not been synced yet.
+fn synced_policy(path: &Path) -> Option<PathBuf> {
+    let contents = fs::read_to_string(path).ok()?;
+    let repo: toml::Table = toml::from_str(&contents).ok()?;
+    repo.get("policy")?.get("source")?;
+    let cache = policy_cache_path().ok()?;
+    if !cache.is_file() {
+        static WARNED: Once = Once::new();
+        WARNED.call_once(|| {
+            eprintln!(
+                "Warning: [policy] source is set but not synced; run `git-sherpa policy sync`"
+            )
+        });
+        return None;
+    }
+    Some(cache)
+}
+
+/// Config files under the repo config, lowest precedence first: the synced
+/// org policy and the user config, where present.
+fn lower_layers(path: &Path) -> Vec<PathBuf> {
+    synced_policy(path)
+        .into_iter()
+        .chain(user_config_path().filter(|p| p.is_file()))
+        .collect()
+}
+
+/// Config files that apply, lowest precedence first: the synced org policy,
+/// the user config and the repo config at `path`, where present.
+pub fn config_sources(path: &Path) -> Vec<PathBuf> {
+    lower_layers(path)
+        .into_iter()
+        .chain(Some(path.to_path_buf()).filter(|p| p.is_file()))
+        .collect()
+}
+
+/// Repo config at `path` layered over the org policy and user config;
+/// fields set in none of them take their built-in defaults. The repo config
+/// must exist. The tracked nested configs of the repo become its
+/// [`Config::scopes`].
 pub fn load_config(path: &Path) -> Result<Config> {
-    let contents = fs::read_to_string(path)
-        .with_context(|| format!("read config at {}", path.display()))?;
-    let config: Config = toml::from_str(&contents).context("parse config")?;
+    let mut config = into_config(layered(path)?, path)?;
+    config.scopes = load_scopes(path);
     Ok(config)
 }
 
+/// The layers of [`load_config`], merged over [`default_config`].
+fn layered(path: &Path) -> Result<toml::Table> {
+    let repo = read_layer(path)?;
+    let mut merged = toml::Table::try_from(default_config())?;
+    for layer in lower_layers(path) {
+        merge(&mut merged, read_layer(&layer)?);
+    }
+    merge(&mut merged, repo);
+    Ok(merged)
+}
+
+/// Configs in subdirectories of the repo, named like the repo config at
+/// `path`, deepest first, for [`Config::scopes`]. One that does not load is
+/// left out with a warning, so it only misses the changes under its
+/// directory; `check --all-configs` reports it. Outside a repo there are none.
+fn load_scopes(path: &Path) -> Vec<ConfigScope> {
+    let Ok(nested) = nested_configs(path) else {
+        return Vec::new();
+    };
+    let mut scopes: Vec<ConfigScope> = nested
+        .into_iter()
+        .filter_map(|(file, scope)| {
+            scope
+                .map_err(|err| {
+                    eprintln!("Warning: ignoring {}: {:#}", file.display(), err);
+                })
+                .ok()
+        })
+        .collect();
+    scopes.reverse();
+    scopes
+}
+
+/// Each tracked config in a subdirectory of the repo, outermost first, loaded over
+/// the repo config at `path` and the nested configs above it, or the error
+/// that kept it from loading.
+pub fn nested_configs(path: &Path) -> Result<Vec<(PathBuf, Result<ConfigScope>)>> {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Ok(Vec::new());
+    };
+    let root = git::toplevel()?;
+    let repo_config = fs::canonicalize(path).ok();
+    let mut files: Vec<(String, PathBuf)> = git::nested_files(name)?
+        .into_iter()
+        .map(|file| {
+            let dir = file.rsplit_once('/').map_or("", |(dir, _)| dir).to_string();
+            (dir, root.join(file))
+        })
+        .filter(|(_, file)| file.is_file() && fs::canonicalize(file).ok() != repo_config)
+        .collect();
+    files.sort_by_key(|(dir, _)| dir.len());
+
+    let load = |dir: &str, file: &Path| -> Result<Config> {
+        let mut merged = layered(path)?;
+        for (outer, layer) in &files {
+            if outer == dir || dir.starts_with(&format!("{}/", outer)) {
+                merge(&mut merged, read_layer(layer)?);
+            }
+        }
+        into_config(merged, file)
+    };
+    Ok(files
+        .iter()
+        .map(|(dir, file)| {
+            let scope = load(dir, file).map(|config| ConfigScope {
+                dir: dir.clone(),
+                path: file.clone(),
+                config,
+            });
+            (file.clone(), scope)
+        })
+        .collect())
+}
+
+fn read_layer(path: &Path) -> Result<toml::Table> {
+    let contents = fs::read_to_string(path).map_err(|err| {
+        if err.kind() == std::io::ErrorKind::NotFound {
+            anyhow::Error::from(SherpaError::ConfigNotFound {
+                path: path.to_path_buf(),
+            })
+        } else {
+            anyhow::Error::from(err).context(format!("read config at {}", path.display()))
+        }
+    })?;
+    parse_layer(path, &contents)
+}
+
+/// One config file, which may leave out fields other layers provide; errors
+/// name `path`.
+pub fn parse_layer(path: &Path, contents: &str) -> Result<toml::Table> {
+    // Validate the layer on its own first so type errors point into the
+    // file that has them; a partial layer only fails on missing fields.
+    if let Err(err) = toml::from_str::<Config>(contents) {
+        if err.span().is_some() && !err.message().starts_with("missing field") {
+            return Err(invalid(path, contents, err).into());
+        }
+    }
+    toml::from_str(contents).map_err(|err| invalid(path, contents, err).into())
+}
+
+fn invalid(path: &Path, contents: &str, err: toml::de::Error) -> SherpaError {
+    SherpaError::ConfigInvalid {
+        path: path.to_path_buf(),
+        span: err.span().map(|range| span_at(contents, range.start)),
+        message: err.message().to_string(),
+    }
+}
+
+fn into_config(table: toml::Table, path: &Path) -> Result<Config> {
+    let invalid = |message: String| SherpaError::ConfigInvalid {
+        path: path.to_path_buf(),
+        span: None,
+        message,
+    };
+    let config = Config::deserialize(toml::Value::Table(table))
+        .map_err(|err| invalid(err.message().to_string()))?;
+    if let Some(rule) = severity::unknown_rule(&config.severity) {
+        return Err(invalid(format!(
+            "unknown rule `{}` in [severity]; expected one of {}",
+            rule,
+            rollup::RULES.join(", ")
+        ))
+        .into());
+    }
+    if let Some(window) = &config.checks.quiet_hours {
+        if Window::parse(window).is_none() {
+            return Err(invalid(format!(
+                "`{}` in [checks] quiet_hours is not a time window like `22:00-07:00`",
+                window
+            ))
+            .into());
+        }
+    }
+    for rule in config
+        .sensitive
+        .patterns
+        .iter()
+        .filter_map(SensitivePattern::rule)
+    {
+        if rule.glob.is_some() == rule.regex.is_some() {
+            return Err(invalid(format!(
+                "`{}` in [sensitive] patterns needs either `glob` or `regex`",
+                rule.name
+            ))
+            .into());
+        }
+    }
+    if let Some(hash) = config.commits.ignore_hashes.iter().find(|h| !is_hash(h)) {
+        return Err(invalid(format!(
+            "`{}` in [commits] ignore_hashes is not a commit hash",
+            hash
+        ))
+        .into());
+    }
+    Ok(config)
+}
+
+/// Overlays `top` onto `base`: tables merge key by key, anything else
+/// (including arrays) is replaced.
+fn merge(base: &mut toml::Table, top: toml::Table) {
+    for (key, value) in top {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(top)) => merge(base, top),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
 pub fn default_config() -> Config {
     Config {
         branches: BranchConfig {
-            pattern: "^(feat|fix|chore|docs|refactor)/[a-z0-9-]+$".to_string(),
+            pattern: default_branch_pattern(),
+            types: Vec::new(),
+            separator: default_separator(),
+            slug_pattern: default_slug_pattern(),
+            ticket_pattern: None,
+            template: default_branch_template(),
+            ticket_template: default_ticket_template(),
+            stale_after_days: default_stale_after_days(),
         },
         commits: CommitConfig {
             convention: "conventional".to_string(),
+            pattern: None,
+            types: Vec::new(),
+            require_scope: false,
+            scopes: Vec::new(),
+            max_subject_length: None,
+            length_mode: LengthMode::default(),
+            body: BodyConfig::default(),
+            strip_prefixes: Vec::new(),
+            pr_title_max_length: default_pr_title_max_length(),
+            forbid_merge_commits: false,
+            forbid_fixup_commits: false,
+            template: None,
+            ignore_hashes: Vec::new(),
         },
         checks: CheckConfig {
             require_clean_worktree: true,
             require_upstream: true,
+            require_remote: false,
+            max_fetch_age_hours: None,
+            max_file_size_kb: None,
+            large_files_in_commits: false,
+            base_branch: None,
+            max_behind: None,
+            max_ahead: None,
+            max_branch_age_days: None,
+            max_review_lines: None,
+            require_linear_history: false,
+            require_signed_commits: false,
+            allowed_author_domains: Vec::new(),
+            forbid_noreply_emails: false,
+            quiet_hours: None,
+            quiet_weekends: false,
+            protected_paths: Vec::new(),
+            protected_paths_allowed_branches: Vec::new(),
+            forbid_conflict_markers: true,
+            forbid_trailing_whitespace: false,
         },
         sensitive: SensitiveConfig::default(),
         hooks: HooksConfig::default(),
+        notes: NotesConfig::default(),
+        attributes: AttributesConfig::default(),
+        references: ReferencesConfig::default(),
+        pull_requests: PullRequestsConfig::default(),
+        files: FilesConfig::default(),
+        policy: PolicyConfig::default(),
+        severity: BTreeMap::new(),
+        scopes: Vec::new(),
+        cache_commits: false,
+    }
+}
+
+/// Loads the repo config if present, falling back to built-in defaults.
+/// Like [`load_config`], but over [`default_config`] and without requiring
+/// a repo config; any error yields the defaults.
+pub fn load_config_or_default(path: &Path) -> Config {
+    let layered = || -> Result<Config> {
+        let mut merged = toml::Table::try_from(default_config())?;
+        for source in config_sources(path) {
+            merge(&mut merged, read_layer(&source)?);
+        }
+        into_config(merged, path)
+    };
+    layered().unwrap_or_else(|_| default_config())
+}
+
+/// Effective config for `check --show-config`, with its sources as comments.
+pub fn show_config(path: &Path) -> Result<()> {
+    let config = load_config(path)?;
+    for source in config_sources(path) {
+        println!("# from {}", source.display());
     }
+    print!(
+        "{}",
+        toml::to_string_pretty(&config).context("serialize config")?
+    );
+    Ok(())
+}
+
+pub fn default_config_toml() -> Result<String> {
+    toml::to_string_pretty(&default_config()).context("serialize config")
 }
 
 #[cfg(test)]
@@ -95,6 +866,50 @@ mod tests {
         assert!(cfg.hooks.protected_branches.contains(&"main".to_string()));
     }
 
+    fn table(toml_str: &str) -> toml::Table {
+        toml::from_str(toml_str).unwrap()
+    }
+
+    #[test]
+    fn repo_layer_overrides_user_layer() {
+        let mut merged = table(
+            r#"
+[branches]
+pattern = "^user/.+$"
+
+[commits]
+convention = "gitmoji"
+types = ["a", "b"]
+
+[checks]
+require_clean_worktree = false
+require_upstream = false
+max_behind = 10
+"#,
+        );
+        merge(
+            &mut merged,
+            table(
+                r#"
+[commits]
+convention = "conventional"
+types = ["c"]
+
+[checks]
+require_upstream = true
+"#,
+            ),
+        );
+        let cfg = into_config(merged, Path::new(".gitsherpa.toml")).unwrap();
+        assert_eq!(cfg.branches.pattern, "^user/.+$");
+        assert_eq!(cfg.commits.convention, "conventional");
+        // Arrays are replaced, not concatenated.
+        assert_eq!(cfg.commits.types, vec!["c"]);
+        assert!(!cfg.checks.require_clean_worktree);
+        assert!(cfg.checks.require_upstream);
+        assert_eq!(cfg.checks.max_behind, Some(10));
+    }
+
     #[test]
     fn valid_toml_parses() {
         let toml_str = r#"
@@ -118,6 +933,46 @@ require_upstream = false
         assert!(!cfg.hooks.protected_branches.is_empty());
     }
 
+    #[test]
+    fn notes_are_off_by_default_and_configurable() {
+        let base = r#"
+[branches]
+pattern = "^main$"
+
+[commits]
+convention = "conventional"
+
+[checks]
+require_clean_worktree = false
+require_upstream = false
+"#;
+        let cfg: Config = toml::from_str(base).unwrap();
+        assert!(!cfg.notes.enabled);
+        assert_eq!(cfg.notes.notes_ref, "sherpa");
+
+        let toml_str = format!("{}\n[notes]\nenabled = true\nref = \"ci\"\n", base);
+        let cfg: Config = toml::from_str(&toml_str).unwrap();
+        assert!(cfg.notes.enabled);
+        assert_eq!(cfg.notes.notes_ref, "ci");
+    }
+
+    #[test]
+    fn branch_types_without_pattern_keep_the_built_in_one() {
+        let toml_str = r#"
+[branches]
+types = ["feat", "fix"]
+
+[commits]
+convention = "conventional"
+
+[checks]
+require_clean_worktree = false
+require_upstream = false
+"#;
+        let cfg: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(cfg.branches.pattern, default_config().branches.pattern);
+    }
+
     #[test]
     fn custom_sensitive_patterns() {
         let toml_str = r#"
@@ -135,7 +990,153 @@ require_upstream = false
 patterns = ["*.secret"]
 "#;
         let cfg: Config = toml::from_str(toml_str).unwrap();
-        assert_eq!(cfg.sensitive.patterns, vec!["*.secret"]);
+        assert_eq!(
+            cfg.sensitive.patterns,
+            vec![SensitivePattern::Glob("*.secret".to_string())]
+        );
+        assert_eq!(cfg.sensitive.fail_on, Severity::High);
+    }
+
+    #[test]
+    fn sensitive_patterns_mix_globs_and_named_rules() {
+        let table = |patterns: &str| -> toml::Table {
+            format!(
+                "[branches]\npattern = \"^main$\"\n[commits]\nconvention = \"conventional\"\n\
+                 [checks]\nrequire_clean_worktree = false\nrequire_upstream = false\n\
+                 [sensitive]\npatterns = {}\n",
+                patterns
+            )
+            .parse()
+            .unwrap()
+        };
+        let cfg = into_config(
+            table(
+                r#"[".env", { name = "AWS credentials file", glob = "**/.aws/credentials", severity = "critical", allowlist = ["docs/**"] }]"#,
+            ),
+            Path::new(".gitsherpa.toml"),
+        )
+        .unwrap();
+        assert_eq!(
+            cfg.sensitive.patterns[0],
+            SensitivePattern::Glob(".env".to_string())
+        );
+        let rule = cfg.sensitive.patterns[1].rule().unwrap();
+        assert_eq!(rule.name, "AWS credentials file");
+        assert_eq!(rule.severity, Some(Severity::Critical));
+        assert!(rule.allows("docs/.aws/credentials"));
+        let round_trip: Config = toml::from_str(&toml::to_string(&cfg).unwrap()).unwrap();
+        assert_eq!(round_trip.sensitive.patterns, cfg.sensitive.patterns);
+
+        let err = into_config(
+            table(r#"[{ name = "key", glob = "*.key", regex = "KEY" }]"#),
+            Path::new(".gitsherpa.toml"),
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("needs either `glob` or `regex`"),
+            "{}",
+            err
+        );
+        let err = into_config(
+            table(r#"[{ name = "key", glob = "*.key", severity = "severe" }]"#),
+            Path::new(".gitsherpa.toml"),
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("severe"), "{:#}", err);
+    }
+
+    #[test]
+    fn sensitive_severity_overrides_parse() {
+        let toml_str = r#"
+[branches]
+pattern = "^main$"
+
+[commits]
+convention = "conventional"
+
+[checks]
+require_clean_worktree = false
+require_upstream = false
+
+[sensitive]
+fail_on = "critical"
+
+[sensitive.severity]
+".env" = "medium"
+"#;
+        let cfg: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(cfg.sensitive.fail_on, Severity::Critical);
+        assert_eq!(cfg.sensitive.severity[".env"], Severity::Medium);
+        assert!(!cfg.sensitive.patterns.is_empty());
+        assert!(cfg.sensitive.scan_content);
+    }
+
+    #[test]
+    fn ignore_hashes_must_be_hashes() {
+        let config = |hashes: &str| {
+            into_config(
+                table(&format!(
+                    "[branches]\npattern = \"x\"\n[commits]\nconvention = \"conventional\"\n\
+                     ignore_hashes = {}\n[checks]\nrequire_clean_worktree = false\n\
+                     require_upstream = false\n",
+                    hashes
+                )),
+                Path::new(".gitsherpa.toml"),
+            )
+        };
+        let sha1 = "0123456789abcdef0123456789abcdef01234567";
+        let sha256 = "0123456789abcdef".repeat(4);
+        let cfg = config(&format!(r#"["ABC1234", "{}", "{}"]"#, sha1, sha256)).unwrap();
+        assert_eq!(cfg.commits.ignore_hashes.len(), 3);
+        assert!(config(r#"["abc123"]"#).is_err());
+        assert!(config(&format!(r#"["{}0"]"#, sha256)).is_err());
+        assert!(config(r#"["HEAD~1"]"#).is_err());
+        assert!(config(r#"[""]"#).is_err());
+    }
+
+    #[test]
+    fn quiet_hours_must_be_a_time_window() {
+        let config = |window: &str| {
+            into_config(
+                table(&format!(
+                    "[branches]\npattern = \"x\"\n[commits]\nconvention = \"conventional\"\n\
+                     [checks]\nrequire_clean_worktree = false\nrequire_upstream = false\n\
+                     quiet_hours = \"{}\"\n",
+                    window
+                )),
+                Path::new(".gitsherpa.toml"),
+            )
+        };
+        assert!(config("22:00-07:00").is_ok());
+        let err = config("after 10pm").unwrap_err().to_string();
+        assert!(err.contains("quiet_hours"), "{}", err);
+    }
+
+    #[test]
+    fn paths_follow_the_deepest_nested_config() {
+        let scope = |dir: &str, convention: &str| {
+            let mut config = default_config();
+            config.commits.convention = convention.to_string();
+            ConfigScope {
+                dir: dir.to_string(),
+                path: PathBuf::from(dir).join(".gitsherpa.toml"),
+                config,
+            }
+        };
+        let mut root = default_config();
+        root.scopes = vec![scope("pkg/api/v2", "jira"), scope("pkg/api", "gitmoji")];
+        let convention = |path: &str| root.for_path(path).commits.convention.as_str();
+        assert_eq!(convention("pkg/api/v2/main.rs"), "jira");
+        assert_eq!(convention("pkg/api/lib.rs"), "gitmoji");
+        assert_eq!(convention("pkg/api-client/lib.rs"), "conventional");
+        assert_eq!(convention("README.md"), "conventional");
+
+        let commits = |paths: &[&str]| &root.for_paths(paths.iter().copied()).commits.convention;
+        assert_eq!(commits(&["pkg/api/a.rs", "pkg/api/b.rs"]), "gitmoji");
+        // Files under different configs fall back to the root config.
+        assert_eq!(commits(&["pkg/api/a.rs", "pkg/api/v2/b"]), "conventional");
+        assert_eq!(commits(&["pkg/api/a.rs", "README.md"]), "conventional");
+        assert_eq!(commits(&[]), "conventional");
     }
 
     #[test]
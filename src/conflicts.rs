@@ -0,0 +1,84 @@
+//! `conflicts`: predicts whether the branch will merge (or rebase) cleanly
+//! onto its target by merging the two in memory with `git merge-tree`.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::cli::Format;
+use crate::config::load_config_or_default;
+use crate::error::ExitStatus;
+use crate::git;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ConflictReport {
+    pub branch: String,
+    pub target: String,
+    /// Commits on the target the branch does not have yet.
+    pub behind: usize,
+    /// Paths that would conflict; empty when the merge is clean.
+    pub paths: Vec<String>,
+}
+
+pub fn conflicts(config_path: &Path, target: Option<&str>, format: Format) -> Result<ExitStatus> {
+    let config = load_config_or_default(config_path);
+    let target = match target.map(str::to_string).or(config.checks.base_branch) {
+        Some(target) => target,
+        None => match git::remote_head("origin")? {
+            Some(head) => head,
+            None => bail!("No target branch: pass --target or set [checks] base_branch"),
+        },
+    };
+    if git::resolve_rev(&target)?.is_none() {
+        bail!("Target '{}' not found (fetch it first?)", target);
+    }
+
+    let (behind, _) = git::ahead_behind(&target)?;
+    let report = ConflictReport {
+        branch: git::current_branch()?,
+        paths: git::merge_tree("HEAD", &target)?,
+        target,
+        behind,
+    };
+
+    match format {
+        Format::Text => print_text(&report),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    Ok(ExitStatus::from_violations(!report.paths.is_empty()))
+}
+
+fn print_text(report: &ConflictReport) {
+    if report.paths.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "{} merges cleanly with {} ({} commit(s) behind)",
+                report.branch, report.target, report.behind
+            )
+            .green()
+        );
+        return;
+    }
+
+    println!(
+        "{}",
+        format!(
+            "{} path(s) in {} would conflict with {}:",
+            report.paths.len(),
+            report.branch,
+            report.target
+        )
+        .red()
+        .bold()
+    );
+    for path in &report.paths {
+        println!("  - {}", path);
+    }
+    println!(
+        "\nRebase early to resolve them on your own terms:\n  {}",
+        format!("git rebase {}", report.target).cyan()
+    );
+}
@@ -0,0 +1,585 @@
+//! Commit message conventions: built-in (`conventional`, `gitmoji`, `jira`)
+//! or a `custom` regex, plus the type, scope, length and body rules from
+//! `[commits]`.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::{BodyConfig, CommitConfig};
+use crate::error::SherpaError;
+use crate::references;
+use crate::scopes;
+use crate::version::fnv1a;
+
+/// How `max_subject_length`, `body.max_line_length` and
+/// `pr_title_max_length` measure text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthMode {
+    /// UTF-8 bytes, as tools with byte limits count.
+    Bytes,
+    /// Unicode scalar values.
+    Chars,
+    /// User-perceived characters: `é` or a flag emoji count once however
+    /// they are encoded.
+    #[default]
+    Graphemes,
+    /// Terminal columns: East Asian wide characters count twice.
+    Width,
+}
+
+impl LengthMode {
+    pub fn measure(self, text: &str) -> usize {
+        match self {
+            LengthMode::Bytes => text.len(),
+            LengthMode::Chars => text.chars().count(),
+            LengthMode::Graphemes => text.graphemes(true).count(),
+            LengthMode::Width => text.width(),
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            LengthMode::Bytes => "bytes",
+            LengthMode::Chars | LengthMode::Graphemes => "characters",
+            LengthMode::Width => "columns",
+        }
+    }
+}
+
+/// Types accepted by the `conventional` convention unless `types` is set.
+pub const CONVENTIONAL_TYPES: &[&str] = &[
+    "feat", "fix", "chore", "docs", "refactor", "test", "perf", "ci", "build",
+];
+
+const CONVENTIONAL_PATTERN: &str = r"^(?P<type>[a-z]+)(?:\((?P<scope>[a-z0-9-]+)\))?: .+";
+/// An emoji or `:shortcode:`, an optional scope, then the description.
+const GITMOJI_PATTERN: &str = r"^(?P<type>:[a-z0-9_+-]+:|\p{Extended_Pictographic}\x{FE0F}?)(?:\((?P<scope>[a-z0-9-]+)\))? \S.*";
+/// A ticket key such as `ABC-123` first; `type` is the project key.
+const JIRA_PATTERN: &str = r"^(?P<type>[A-Z][A-Z0-9]+)-\d+:? \S.*";
+
+pub struct Convention {
+    name: String,
+    subject: Regex,
+    types: Vec<String>,
+    require_scope: bool,
+    /// Allowed scopes with `auto` resolved; empty allows any.
+    scopes: Vec<String>,
+    max_subject_length: Option<usize>,
+    length_mode: LengthMode,
+    body: BodyConfig,
+    /// `body.forbidden_words` as one whole-word, case-insensitive regex.
+    forbidden_words: Option<Regex>,
+    prefixes: Vec<Regex>,
+}
+
+/// A `[commits.body]` rule, named in the report of each commit failing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyRule {
+    BlankLineAfterSubject,
+    Required,
+    MaxLineLength,
+    Signoff,
+    ForbiddenWords,
+    IssueReference,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyProblem {
+    pub rule: BodyRule,
+    pub message: String,
+}
+
+impl BodyProblem {
+    fn new(rule: BodyRule, message: impl Into<String>) -> Self {
+        Self {
+            rule,
+            message: message.into(),
+        }
+    }
+}
+
+impl Convention {
+    pub fn from_config(commits: &CommitConfig) -> Result<Self> {
+        let name = commits.convention.as_str();
+        let (pattern, default_types) = match name {
+            "conventional" => (CONVENTIONAL_PATTERN.to_string(), CONVENTIONAL_TYPES),
+            "gitmoji" => (GITMOJI_PATTERN.to_string(), &[][..]),
+            "jira" => (JIRA_PATTERN.to_string(), &[][..]),
+            "custom" => match &commits.pattern {
+                Some(pattern) => (pattern.clone(), &[][..]),
+                None => return Err(invalid(name, "`pattern` is required")),
+            },
+            _ => {
+                return Err(SherpaError::ConventionUnknown {
+                    name: name.to_string(),
+                }
+                .into())
+            }
+        };
+        let subject =
+            Regex::new(&pattern).map_err(|err| SherpaError::invalid_pattern(&pattern, err))?;
+        let has_group = |group: &str| subject.capture_names().flatten().any(|n| n == group);
+        if !commits.types.is_empty() && !has_group("type") {
+            return Err(invalid(
+                name,
+                "`types` needs a `(?P<type>...)` group in the pattern",
+            ));
+        }
+        if commits.require_scope && !has_group("scope") {
+            return Err(invalid(
+                name,
+                "`require_scope` needs a `(?P<scope>...)` group in the pattern",
+            ));
+        }
+        if !commits.scopes.is_empty() && !has_group("scope") {
+            return Err(invalid(
+                name,
+                "`scopes` needs a `(?P<scope>...)` group in the pattern",
+            ));
+        }
+
+        let types = if commits.types.is_empty() {
+            default_types.iter().map(|t| t.to_string()).collect()
+        } else {
+            commits.types.clone()
+        };
+        Ok(Self {
+            name: name.to_string(),
+            subject,
+            types,
+            require_scope: commits.require_scope,
+            scopes: scopes::resolve(&commits.scopes)
+                .context("derive commits.scopes = \"auto\" from the repository layout")?,
+            max_subject_length: commits.max_subject_length,
+            length_mode: commits.length_mode,
+            body: commits.body.clone(),
+            forbidden_words: forbidden_words_regex(&commits.body.forbidden_words),
+            prefixes: prefix_regexes(&commits.strip_prefixes)?,
+        })
+    }
+
+    /// Identifies what the convention accepts, with `auto` scopes resolved:
+    /// conventions with equal fingerprints judge every message alike.
+    pub fn fingerprint(&self) -> u64 {
+        let prefixes: Vec<&str> = self.prefixes.iter().map(Regex::as_str).collect();
+        let text = format!(
+            "{}\n{}\n{:?}\n{}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}",
+            self.name,
+            self.subject.as_str(),
+            self.types,
+            self.require_scope,
+            self.scopes,
+            self.max_subject_length,
+            self.length_mode,
+            self.body,
+            prefixes
+        );
+        fnv1a(text.as_bytes())
+    }
+
+    /// Allowed types; empty when the convention has none.
+    pub fn types(&self) -> &[String] {
+        &self.types
+    }
+
+    /// Problems with a subject line; empty when it is valid. Configured
+    /// prefixes are stripped before matching.
+    pub fn check_subject(&self, subject: &str) -> Vec<String> {
+        let stripped = strip_prefixes(subject, &self.prefixes);
+        let Some(caps) = self.subject.captures(stripped) else {
+            return vec![format!("does not follow the '{}' convention", self.name)];
+        };
+
+        let mut problems = Vec::new();
+        if let Some(kind) = caps.name("type").map(|m| m.as_str()) {
+            if !self.types.is_empty() && !self.types.iter().any(|t| t == kind) {
+                problems.push(format!(
+                    "type '{}' is not allowed (allowed: {})",
+                    kind,
+                    self.types.join(", ")
+                ));
+            }
+        }
+        match caps.name("scope").map(|m| m.as_str()) {
+            None | Some("") if self.require_scope => problems.push("missing scope".to_string()),
+            Some("") => problems.push("empty scope '()'".to_string()),
+            Some(scope) if !self.scopes.is_empty() && !self.scopes.iter().any(|s| s == scope) => {
+                problems.push(format!(
+                    "scope '{}' is not allowed (allowed: {})",
+                    scope,
+                    self.scopes.join(", ")
+                ))
+            }
+            _ => {}
+        }
+        if let Some(max) = self.max_subject_length {
+            let len = self.length_mode.measure(subject);
+            if len > max {
+                problems.push(format!(
+                    "subject is {} {} long (max {})",
+                    len,
+                    self.length_mode.unit(),
+                    max
+                ));
+            }
+        }
+        problems
+    }
+
+    /// Whether any body rule is enabled, i.e. full messages must be read.
+    pub fn checks_body(&self) -> bool {
+        self.body.required
+            || self.body.blank_line_after_subject
+            || self.body.max_line_length.is_some()
+            || self.body.require_signoff
+            || self.forbidden_words.is_some()
+            || self.body.require_issue_reference
+    }
+
+    /// Problems with the body and footers of a full commit message.
+    pub fn check_body(&self, message: &str) -> Vec<BodyProblem> {
+        let lines: Vec<&str> = message.lines().collect();
+        let mut problems = Vec::new();
+        if self.body.blank_line_after_subject && lines.get(1).is_some_and(|l| !l.trim().is_empty())
+        {
+            problems.push(BodyProblem::new(
+                BodyRule::BlankLineAfterSubject,
+                "missing blank line after the subject",
+            ));
+        }
+        if self.body.required && lines.iter().skip(1).all(|l| l.trim().is_empty()) {
+            problems.push(BodyProblem::new(BodyRule::Required, "body is required"));
+        }
+        if let Some(max) = self.body.max_line_length {
+            for (index, line) in lines.iter().enumerate().skip(1) {
+                let len = self.length_mode.measure(line);
+                if len > max {
+                    problems.push(BodyProblem::new(
+                        BodyRule::MaxLineLength,
+                        format!(
+                            "line {} is {} {} long (max {})",
+                            index + 1,
+                            len,
+                            self.length_mode.unit(),
+                            max
+                        ),
+                    ));
+                }
+            }
+        }
+        if self.body.require_signoff && !lines.iter().skip(1).any(|l| is_signoff(l)) {
+            problems.push(BodyProblem::new(
+                BodyRule::Signoff,
+                "missing `Signed-off-by: Name <email>` trailer (git commit -s)",
+            ));
+        }
+        if let Some(forbidden) = &self.forbidden_words {
+            let mut found: Vec<String> = forbidden
+                .find_iter(message)
+                .map(|m| m.as_str().to_lowercase())
+                .collect();
+            found.dedup();
+            for word in found {
+                problems.push(BodyProblem::new(
+                    BodyRule::ForbiddenWords,
+                    format!("contains forbidden word '{}'", word),
+                ));
+            }
+        }
+        if self.body.require_issue_reference
+            && references::subject_and_footer_references(message).is_empty()
+        {
+            problems.push(BodyProblem::new(
+                BodyRule::IssueReference,
+                "missing issue reference footer (e.g. `Refs: #12`)",
+            ));
+        }
+        problems
+    }
+}
+
+fn is_signoff(line: &str) -> bool {
+    line.trim()
+        .strip_prefix("Signed-off-by:")
+        .and_then(|rest| rest.trim().split_once(" <"))
+        .is_some_and(|(name, email)| !name.trim().is_empty() && email.ends_with('>'))
+}
+
+fn forbidden_words_regex(words: &[String]) -> Option<Regex> {
+    if words.is_empty() {
+        return None;
+    }
+    let alternatives: Vec<String> = words.iter().map(|w| regex::escape(w)).collect();
+    Some(
+        Regex::new(&format!(r"(?i)\b(?:{})\b", alternatives.join("|")))
+            .expect("escaped words form a valid regex"),
+    )
+}
+
+fn invalid(name: &str, message: &str) -> anyhow::Error {
+    SherpaError::ConventionInvalid {
+        name: name.to_string(),
+        message: message.to_string(),
+    }
+    .into()
+}
+
+/// Compiles `[commits] strip_prefixes`, anchoring each pattern at the start.
+pub(crate) fn prefix_regexes(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|p| {
+            Regex::new(&format!("^(?:{})", p))
+                .map_err(|err| SherpaError::invalid_pattern(p, err).into())
+        })
+        .collect()
+}
+
+/// Removes configured prefixes (`[skip ci]`, ticket tags...) from a subject,
+/// repeatedly, so stacked prefixes are all stripped.
+pub(crate) fn strip_prefixes<'a>(subject: &'a str, prefixes: &[Regex]) -> &'a str {
+    let mut rest = subject;
+    loop {
+        let stripped = prefixes.iter().find_map(|re| {
+            re.find(rest)
+                .filter(|m| !m.as_str().is_empty())
+                .map(|m| &rest[m.end()..])
+        });
+        match stripped {
+            Some(next) => rest = next,
+            None => return rest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    fn convention(edit: impl FnOnce(&mut CommitConfig)) -> Convention {
+        let mut commits = default_config().commits;
+        edit(&mut commits);
+        Convention::from_config(&commits).unwrap()
+    }
+
+    fn valid(convention: &Convention, subject: &str) -> bool {
+        convention.check_subject(subject).is_empty()
+    }
+
+    #[test]
+    fn valid_conventional_commits() {
+        let c = convention(|_| {});
+        assert!(valid(&c, "feat: add login"));
+        assert!(valid(&c, "fix(auth): resolve token issue"));
+        assert!(valid(&c, "chore: cleanup"));
+        assert!(valid(&c, "docs: update readme"));
+        assert!(valid(&c, "refactor(core): simplify logic"));
+    }
+
+    #[test]
+    fn invalid_conventional_commits() {
+        let c = convention(|_| {});
+        assert!(!valid(&c, "added login"));
+        assert!(!valid(&c, "Fix bug"));
+        assert!(!valid(&c, "random message"));
+        assert!(!valid(&c, ""));
+        assert_eq!(
+            c.check_subject("feature: add login"),
+            vec!["type 'feature' is not allowed (allowed: feat, fix, chore, docs, refactor, test, perf, ci, build)"]
+        );
+    }
+
+    #[test]
+    fn strips_configured_prefixes() {
+        let prefixes = prefix_regexes(&[r"\[.*?\]\s*".to_string()]).unwrap();
+        assert_eq!(
+            strip_prefixes("[skip ci] [backport] fix: typo", &prefixes),
+            "fix: typo"
+        );
+        assert_eq!(strip_prefixes("feat: x [wip]", &prefixes), "feat: x [wip]");
+    }
+
+    #[test]
+    fn stripped_subject_validates() {
+        let c = convention(|c| c.strip_prefixes = vec![r"[A-Z]+-\d+\s+".to_string()]);
+        assert!(valid(&c, "ABC-12 feat: add login"));
+        assert!(!valid(&c, "ABC-12 add login"));
+    }
+
+    #[test]
+    fn invalid_prefix_regex_returns_error() {
+        assert!(prefix_regexes(&["[".to_string()]).is_err());
+    }
+
+    #[test]
+    fn unknown_convention_returns_error() {
+        let mut commits = default_config().commits;
+        commits.convention = "unknown".to_string();
+        assert!(Convention::from_config(&commits).is_err());
+    }
+
+    #[test]
+    fn scope_types_and_length_rules() {
+        let c = convention(|c| {
+            c.types = vec!["feat".to_string(), "fix".to_string()];
+            c.require_scope = true;
+            c.max_subject_length = Some(20);
+        });
+        assert!(valid(&c, "feat(ui): add button"));
+        assert_eq!(c.check_subject("fix: typo"), vec!["missing scope"]);
+        assert_eq!(c.check_subject("docs(x): y").len(), 1);
+        assert_eq!(
+            c.check_subject("feat(ui): add a much longer button"),
+            vec!["subject is 34 characters long (max 20)"]
+        );
+    }
+
+    #[test]
+    fn scopes_restrict_the_scope_when_given() {
+        let c = convention(|c| c.scopes = vec!["api".to_string(), "web".to_string()]);
+        assert!(valid(&c, "feat(api): add endpoint"));
+        assert!(valid(&c, "fix: typo"));
+        assert_eq!(
+            c.check_subject("feat(mobile): add screen"),
+            vec!["scope 'mobile' is not allowed (allowed: api, web)"]
+        );
+    }
+
+    #[test]
+    fn an_empty_scope_is_reported_as_such() {
+        let c = convention(|c| {
+            c.convention = "custom".to_string();
+            c.pattern = Some(r"^(?P<type>[a-z]+)(?:\((?P<scope>[^)]*)\))?: .+".to_string());
+            c.scopes = vec!["api".to_string()];
+        });
+        assert_eq!(
+            c.check_subject("feat(): add screen"),
+            vec!["empty scope '()'"]
+        );
+    }
+
+    #[test]
+    fn gitmoji_and_jira_builtins() {
+        let gitmoji = convention(|c| c.convention = "gitmoji".to_string());
+        assert!(valid(&gitmoji, ":sparkles: add login"));
+        assert!(valid(&gitmoji, "✨ add login"));
+        assert!(valid(&gitmoji, "🐛(auth) fix token refresh"));
+        assert!(!valid(&gitmoji, "add login"));
+
+        let jira = convention(|c| {
+            c.convention = "jira".to_string();
+            c.types = vec!["ABC".to_string()];
+        });
+        assert!(valid(&jira, "ABC-123 add login"));
+        assert!(valid(&jira, "ABC-123: add login"));
+        assert!(!valid(&jira, "add login"));
+        assert_eq!(jira.check_subject("OPS-7 restart").len(), 1);
+    }
+
+    #[test]
+    fn custom_convention_requires_pattern_and_groups() {
+        let mut commits = default_config().commits;
+        commits.convention = "custom".to_string();
+        assert!(Convention::from_config(&commits).is_err());
+
+        commits.pattern = Some(r"^\[\w+\] .+".to_string());
+        let c = Convention::from_config(&commits).unwrap();
+        assert!(valid(&c, "[core] add login"));
+        assert!(!valid(&c, "add login"));
+
+        commits.types = vec!["core".to_string()];
+        assert!(Convention::from_config(&commits).is_err());
+        commits.pattern = Some(r"^\[(?P<type>\w+)\] .+".to_string());
+        let c = Convention::from_config(&commits).unwrap();
+        assert!(valid(&c, "[core] add login"));
+        assert!(!valid(&c, "[ui] add login"));
+    }
+
+    #[test]
+    fn body_rules() {
+        let c = convention(|c| {
+            c.body.required = true;
+            c.body.blank_line_after_subject = true;
+            c.body.max_line_length = Some(10);
+        });
+        let messages = |message: &str| -> Vec<String> {
+            c.check_body(message)
+                .into_iter()
+                .map(|p| p.message)
+                .collect()
+        };
+        assert!(c.checks_body());
+        assert!(c.check_body("feat: x\n\nshort body\n").is_empty());
+        assert_eq!(messages("feat: x\n"), vec!["body is required"]);
+        assert_eq!(
+            messages("feat: x\nno blank line"),
+            vec![
+                "missing blank line after the subject",
+                "line 2 is 13 characters long (max 10)"
+            ]
+        );
+        assert!(!convention(|_| {}).checks_body());
+    }
+
+    #[test]
+    fn length_modes_count_what_readers_see() {
+        // Decomposed é (e + U+0301), a flag and two CJK ideographs.
+        let subject = "feat: cafe\u{301} \u{1F1EB}\u{1F1F7} 日本";
+        assert_eq!(LengthMode::Bytes.measure(subject), 28);
+        assert_eq!(LengthMode::Chars.measure(subject), 17);
+        assert_eq!(LengthMode::Graphemes.measure(subject), 15);
+        assert_eq!(LengthMode::Width.measure(subject), 18);
+
+        let c = convention(|c| c.max_subject_length = Some(15));
+        assert!(valid(&c, subject));
+        let c = convention(|c| {
+            c.max_subject_length = Some(15);
+            c.length_mode = LengthMode::Width;
+        });
+        assert_eq!(
+            c.check_subject(subject),
+            vec!["subject is 18 columns long (max 15)"]
+        );
+        assert_eq!(crate::git::short_hash("abc"), "abc");
+    }
+
+    #[test]
+    fn footer_rules() {
+        let c = convention(|c| {
+            c.body.require_signoff = true;
+            c.body.forbidden_words = vec!["WIP".to_string(), "todo".to_string()];
+            c.body.require_issue_reference = true;
+        });
+        let rules = |message: &str| -> Vec<BodyRule> {
+            c.check_body(message).into_iter().map(|p| p.rule).collect()
+        };
+        assert!(c.checks_body());
+        assert!(rules("feat: x\n\nRefs: #12\nSigned-off-by: Ada <ada@example.com>\n").is_empty());
+        assert_eq!(
+            rules("feat: wip login\n\nSigned-off-by: Ada\n"),
+            vec![
+                BodyRule::Signoff,
+                BodyRule::ForbiddenWords,
+                BodyRule::IssueReference
+            ]
+        );
+        // In the subject or the footer, not the body's prose.
+        let signed = "Signed-off-by: Ada <ada@example.com>";
+        assert!(rules(&format!("fix: crash (#12)\n\n{}", signed)).is_empty());
+        assert_eq!(
+            rules(&format!("fix: crash\n\nFixes #12 was wrong.\n\n{}", signed)),
+            vec![BodyRule::IssueReference]
+        );
+        // Whole words only.
+        assert!(
+            !rules("feat: x\n\nTodos list\nRefs: #1\nSigned-off-by: A <a@b>")
+                .contains(&BodyRule::ForbiddenWords)
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Environment diagnostics with actionable remediation.
+
+use anyhow::Result;
+use colored::Colorize;
+use std::env;
+use std::path::Path;
+
+use crate::check;
+use crate::config::load_config;
+use crate::error::{ExitStatus, SherpaError};
+use crate::git;
+use crate::hooks::{self, HookState};
+use crate::remote;
+use crate::secrets;
+
+/// Reflog retention below this is considered too short to recover from a bad
+/// rebase, reword or rename.
+const MIN_REFLOG_EXPIRE_SECS: u64 = 7 * 24 * 3600;
+
+/// Oldest git with `git switch` and `git restore`, which fixes suggest.
+const MIN_GIT_VERSION: (u32, u32) = (2, 23);
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+    pub remediation: Option<String>,
+}
+
+impl Diagnostic {
+    fn pass(name: &str, detail: String) -> Self {
+        Self {
+            name: name.to_string(),
+            ok: true,
+            detail,
+            remediation: None,
+        }
+    }
+
+    fn fail(name: &str, detail: String, remediation: String) -> Self {
+        Self {
+            name: name.to_string(),
+            ok: false,
+            detail,
+            remediation: Some(remediation),
+        }
+    }
+}
+
+/// Runs every diagnostic; `offline` skips the remotes.
+pub fn doctor(config_path: &Path, offline: bool) -> Result<ExitStatus> {
+    let mut diagnostics = vec![git_version_diagnostic(), path_diagnostic()];
+    diagnostics.extend(config_diagnostics(config_path));
+    if git::toplevel().is_ok() {
+        diagnostics.extend(hook_diagnostics());
+        diagnostics.extend(reflog_diagnostics());
+        if !offline {
+            diagnostics.extend(remote_diagnostics());
+        }
+    } else {
+        diagnostics.push(Diagnostic::fail(
+            "repository",
+            "not inside a git work tree; hooks and remotes not checked".to_string(),
+            "run git-sherpa doctor from the repository".to_string(),
+        ));
+        diagnostics.extend(reflog_diagnostics());
+    }
+    let failures = diagnostics.iter().filter(|d| !d.ok).count();
+
+    println!("{}", "git-sherpa doctor".bold());
+    for d in &diagnostics {
+        if d.ok {
+            println!("  {} {}: {}", "OK".green(), d.name, d.detail);
+        } else {
+            println!("  {} {}: {}", "FAIL".red(), d.name, d.detail);
+            if let Some(fix) = &d.remediation {
+                println!("      {}", fix.cyan());
+            }
+        }
+    }
+
+    if failures > 0 {
+        println!(
+            "\n{}",
+            format!("{} problem(s) found.", failures).red().bold()
+        );
+        return Ok(ExitStatus::Violations);
+    }
+    println!("\n{}", "Everything looks good.".green().bold());
+    Ok(ExitStatus::Success)
+}
+
+/// git is new enough for the commands git-sherpa runs and suggests.
+fn git_version_diagnostic() -> Diagnostic {
+    let remediation = format!(
+        "upgrade git to {}.{} or newer",
+        MIN_GIT_VERSION.0, MIN_GIT_VERSION.1
+    );
+    match git::version() {
+        Ok(version) => match parse_version(&version) {
+            Some(found) if found < MIN_GIT_VERSION => Diagnostic::fail(
+                "git version",
+                format!(
+                    "{} (need {}.{})",
+                    version, MIN_GIT_VERSION.0, MIN_GIT_VERSION.1
+                ),
+                remediation,
+            ),
+            _ => Diagnostic::pass("git version", version),
+        },
+        Err(err) => Diagnostic::fail("git version", format!("{:#}", err), remediation),
+    }
+}
+
+/// (major, minor) of `2.39.5`, `2.45.1.windows.1` or `2.39.5 (Apple Git-154)`.
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split(['.', ' ']);
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+/// The installed hooks run `git-sherpa` by name, so it must be on `PATH`.
+fn path_diagnostic() -> Diagnostic {
+    let name = format!("git-sherpa{}", env::consts::EXE_SUFFIX);
+    let found = env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .map(|dir| dir.join(&name))
+            .find(|path| path.is_file())
+    });
+    if let Some(path) = found {
+        return Diagnostic::pass("on PATH", path.display().to_string());
+    }
+    let remediation = match env::current_exe().ok().as_deref().and_then(Path::parent) {
+        Some(dir) => format!("export PATH=\"{}:$PATH\"", dir.display()),
+        None => "cargo install git-sherpa".to_string(),
+    };
+    Diagnostic::fail(
+        "on PATH",
+        "git-sherpa not found; installed hooks will fail".to_string(),
+        remediation,
+    )
+}
+
+/// The config parses and every pattern in it compiles.
+fn config_diagnostics(path: &Path) -> Vec<Diagnostic> {
+    let config = match load_config(path) {
+        Ok(config) => config,
+        Err(err) => {
+            let remediation = match err.downcast_ref::<SherpaError>() {
+                Some(SherpaError::ConfigNotFound { .. }) => "git-sherpa init".to_string(),
+                _ => format!("fix {}, then run git-sherpa doctor again", path.display()),
+            };
+            return vec![Diagnostic::fail(
+                "config",
+                format!("{:#}", err),
+                remediation,
+            )];
+        }
+    };
+    let patterns = check::validate_config(&config).and_then(|()| {
+        let sensitive = &config.sensitive;
+        secrets::rules(&sensitive.rules, &sensitive.patterns, &sensitive.severity)
+    });
+    vec![
+        Diagnostic::pass("config", format!("{} parses", path.display())),
+        match patterns {
+            Ok(_) => Diagnostic::pass("patterns", "all regexes compile".to_string()),
+            Err(err) => Diagnostic::fail(
+                "patterns",
+                format!("{:#}", err),
+                format!("fix the pattern in {}", path.display()),
+            ),
+        },
+    ]
+}
+
+/// Each hook git-sherpa installs is there, ours, executable and current.
+fn hook_diagnostics() -> Vec<Diagnostic> {
+    let states = match hooks::states() {
+        Ok(states) => states,
+        Err(err) => {
+            return vec![Diagnostic::fail(
+                "hooks",
+                format!("{:#}", err),
+                "git-sherpa hooks install".to_string(),
+            )]
+        }
+    };
+    states
+        .into_iter()
+        .map(|(hook, path, state)| {
+            let name = format!("hook {}", hook);
+            let shown = path.display();
+            match state {
+                HookState::Current => Diagnostic::pass(&name, format!("{} is current", shown)),
+                HookState::Missing => Diagnostic::fail(
+                    &name,
+                    format!("{} is not installed", shown),
+                    "git-sherpa hooks install".to_string(),
+                ),
+                HookState::Foreign => Diagnostic::fail(
+                    &name,
+                    format!("{} was not written by git-sherpa", shown),
+                    "git-sherpa hooks install --chain".to_string(),
+                ),
+                HookState::NotExecutable => Diagnostic::fail(
+                    &name,
+                    format!("{} is not executable, so git skips it", shown),
+                    format!("chmod +x {}", shown),
+                ),
+                HookState::Outdated(version) => Diagnostic::fail(
+                    &name,
+                    format!(
+                        "{} is template version {}, this binary writes {}",
+                        shown,
+                        version,
+                        hooks::HOOK_TEMPLATE_VERSION
+                    ),
+                    "git-sherpa hooks update".to_string(),
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Each remote answers, without prompting for credentials.
+fn remote_diagnostics() -> Vec<Diagnostic> {
+    let remotes = git::remotes().unwrap_or_default();
+    remotes
+        .iter()
+        .map(|remote| {
+            let name = format!("remote {}", remote);
+            match git::probe_remote(remote, remote::TIMEOUT) {
+                Ok(()) => Diagnostic::pass(&name, "reachable".to_string()),
+                Err(err) => Diagnostic::fail(
+                    &name,
+                    format!("{:#}", err),
+                    format!(
+                        "git ls-remote {}  (check the URL, network and credentials)",
+                        remote
+                    ),
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Checks that reflogs are written and kept long enough to undo destructive fixes.
+pub fn reflog_diagnostics() -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+
+    let log_all = git::config_get("core.logAllRefUpdates").ok().flatten();
+    let enabled = !matches!(log_all.as_deref(), Some("false"));
+    out.push(Diagnostic {
+        name: "reflog enabled".to_string(),
+        ok: enabled,
+        detail: match &log_all {
+            Some(v) => format!("core.logAllRefUpdates = {}", v),
+            None => "core.logAllRefUpdates unset (git default: enabled)".to_string(),
+        },
+        remediation: (!enabled).then(|| "git config core.logAllRefUpdates true".to_string()),
+    });
+
+    for (key, default) in [
+        ("gc.reflogExpire", "90.days.ago"),
+        ("gc.reflogExpireUnreachable", "30.days.ago"),
+    ] {
+        let value = git::config_get(key)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| default.to_string());
+        let ok = match parse_expire(&value) {
+            Some(Expiry::Never) => true,
+            Some(Expiry::After(secs)) => secs >= MIN_REFLOG_EXPIRE_SECS,
+            None => true,
+        };
+        out.push(Diagnostic {
+            name: key.to_string(),
+            ok,
+            detail: format!("{} = {}", key, value),
+            remediation: (!ok).then(|| format!("git config {} {}", key, default)),
+        });
+    }
+
+    out
+}
+
+/// Whether destructive fixes can be safely undone via the reflog.
+pub fn reflog_safe() -> bool {
+    reflog_diagnostics().iter().all(|d| d.ok)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Expiry {
+    Never,
+    After(u64),
+}
+
+/// Parses git expiry values such as `90.days.ago`, `2 weeks`, `now` or `never`.
+fn parse_expire(value: &str) -> Option<Expiry> {
+    let normalized = value.trim().to_lowercase().replace('.', " ");
+    match normalized.as_str() {
+        "never" | "false" => return Some(Expiry::Never),
+        "now" | "all" => return Some(Expiry::After(0)),
+        _ => {}
+    }
+    let mut words = normalized.split_whitespace();
+    let amount: u64 = words.next()?.parse().ok()?;
+    let unit = words.next()?.trim_end_matches('s');
+    let secs = match unit {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 3600,
+        "day" => 86_400,
+        "week" => 7 * 86_400,
+        "month" => 30 * 86_400,
+        "year" => 365 * 86_400,
+        _ => return None,
+    };
+    Some(Expiry::After(amount * secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_git_expiry_values() {
+        assert_eq!(
+            parse_expire("90.days.ago"),
+            Some(Expiry::After(90 * 86_400))
+        );
+        assert_eq!(parse_expire("2 weeks"), Some(Expiry::After(14 * 86_400)));
+        assert_eq!(parse_expire("never"), Some(Expiry::Never));
+        assert_eq!(parse_expire("now"), Some(Expiry::After(0)));
+        assert_eq!(parse_expire("garbage"), None);
+    }
+
+    #[test]
+    fn parses_git_versions() {
+        assert_eq!(parse_version("2.39.5"), Some((2, 39)));
+        assert_eq!(parse_version("2.45.1.windows.1"), Some((2, 45)));
+        assert_eq!(parse_version("2.39.5 (Apple Git-154)"), Some((2, 39)));
+        assert!(parse_version("2.22.0") < Some(MIN_GIT_VERSION));
+        assert_eq!(parse_version("unknown"), None);
+    }
+}
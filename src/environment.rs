@@ -0,0 +1,49 @@
+//! The `environment` block of the JSON report: what produced it, so an
+//! archived report can be traced back to the tool, config and machine.
+
+use serde::Serialize;
+use std::path::Path;
+
+use crate::ci;
+use crate::config::{config_sources, Config};
+use crate::git;
+use crate::version::fnv1a;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Environment {
+    pub sherpa_version: &'static str,
+    /// `None` if `git --version` could not be run.
+    pub git_version: Option<String>,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub repo_root: Option<String>,
+    pub config_path: String,
+    /// Config files merged into the effective config, lowest precedence first.
+    pub config_sources: Vec<String>,
+    /// Hash of the effective config; equal fingerprints mean equal rules.
+    pub config_fingerprint: String,
+    /// CI provider detected from the environment, e.g. `github_actions`.
+    pub ci: Option<&'static str>,
+}
+
+impl Environment {
+    /// Best effort: fields that cannot be determined are left empty rather
+    /// than failing the check.
+    pub fn capture(config_path: &Path, config: &Config) -> Self {
+        let serialized = toml::to_string(config).unwrap_or_default();
+        Self {
+            sherpa_version: env!("CARGO_PKG_VERSION"),
+            git_version: git::version().ok(),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            repo_root: git::toplevel().ok().map(|root| root.display().to_string()),
+            config_path: config_path.display().to_string(),
+            config_sources: config_sources(config_path)
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect(),
+            config_fingerprint: format!("{:016x}", fnv1a(serialized.as_bytes())),
+            ci: ci::detect().map(|env| env.provider.name()),
+        }
+    }
+}
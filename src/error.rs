@@ -0,0 +1,212 @@
+//! Typed failure categories, so scripts and library users can react to
+//! failures programmatically instead of parsing messages.
+
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Position of a config parse error, 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SherpaError {
+    #[error("not a git repository")]
+    NotARepo,
+    #[error("`{cmd}` failed{}{}", fmt_code(.code), fmt_stderr(.stderr))]
+    GitCommandFailed {
+        cmd: String,
+        /// Exit code, or `None` if git was killed by a signal.
+        code: Option<i32>,
+        stderr: String,
+    },
+    #[error("config not found at {}", .path.display())]
+    ConfigNotFound { path: PathBuf },
+    #[error("invalid config at {}{}: {message}", .path.display(), fmt_span(.span))]
+    ConfigInvalid {
+        path: PathBuf,
+        span: Option<Span>,
+        message: String,
+    },
+    #[error("unsupported commit convention: {name}")]
+    ConventionUnknown { name: String },
+    #[error("invalid commit convention `{name}`: {message}")]
+    ConventionInvalid { name: String, message: String },
+    #[error("invalid pattern `{pattern}`: {message}")]
+    InvalidPattern { pattern: String, message: String },
+    /// A query answered by the libgit2 backend failed.
+    #[error("libgit2 {operation} failed: {message}")]
+    Libgit2 { operation: String, message: String },
+}
+
+fn fmt_stderr(stderr: &str) -> String {
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", stderr)
+    }
+}
+
+fn fmt_code(code: &Option<i32>) -> String {
+    match code {
+        Some(code) => format!(" with exit code {}", code),
+        None => " (terminated by signal)".to_string(),
+    }
+}
+
+fn fmt_span(span: &Option<Span>) -> String {
+    span.map(|s| format!(":{}", s)).unwrap_or_default()
+}
+
+/// Exit code for failures that are not categorized.
+pub const EXIT_ERROR: i32 = 2;
+
+/// How a command that ran to the end exits. A failure that stops it is an
+/// error instead, which exits with [`exit_code`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub enum ExitStatus {
+    Success,
+    /// It found violations: exit 1.
+    Violations,
+}
+
+impl ExitStatus {
+    /// [`ExitStatus::Violations`] if `violations`, else success.
+    pub fn from_violations(violations: bool) -> Self {
+        if violations {
+            ExitStatus::Violations
+        } else {
+            ExitStatus::Success
+        }
+    }
+
+    pub fn code(self) -> i32 {
+        match self {
+            ExitStatus::Success => 0,
+            ExitStatus::Violations => 1,
+        }
+    }
+}
+
+impl SherpaError {
+    /// Exit code per category: 3 = not a repo, 4 = git failure, 5 = configuration.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SherpaError::NotARepo => 3,
+            SherpaError::GitCommandFailed { .. } | SherpaError::Libgit2 { .. } => 4,
+            SherpaError::ConfigNotFound { .. }
+            | SherpaError::ConfigInvalid { .. }
+            | SherpaError::ConventionUnknown { .. }
+            | SherpaError::ConventionInvalid { .. }
+            | SherpaError::InvalidPattern { .. } => 5,
+        }
+    }
+
+    pub fn invalid_pattern(pattern: &str, err: regex::Error) -> Self {
+        SherpaError::InvalidPattern {
+            pattern: pattern.to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Finds the typed error anywhere in an anyhow context chain.
+pub fn find(err: &anyhow::Error) -> Option<&SherpaError> {
+    err.chain().find_map(|e| e.downcast_ref::<SherpaError>())
+}
+
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    find(err).map(SherpaError::exit_code).unwrap_or(EXIT_ERROR)
+}
+
+/// Structured `{"error": {...}}` object for `--format json` output.
+pub fn to_json(err: &anyhow::Error) -> serde_json::Value {
+    let mut object = match find(err).and_then(|e| serde_json::to_value(e).ok()) {
+        Some(serde_json::Value::Object(map)) => map,
+        _ => {
+            let mut map = serde_json::Map::new();
+            map.insert("kind".to_string(), "error".into());
+            map
+        }
+    };
+    object.insert("message".to_string(), format!("{:#}", err).into());
+    serde_json::json!({ "error": object })
+}
+
+/// 1-based line/column of a byte offset into `text`.
+pub fn span_at(text: &str, offset: usize) -> Span {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map(|l| l.chars().count())
+        .unwrap_or(0)
+        + 1;
+    Span { line, column }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn span_from_offset() {
+        let text = "a = 1\nbb = [\n";
+        assert_eq!(span_at(text, 0), Span { line: 1, column: 1 });
+        assert_eq!(span_at(text, 8), Span { line: 2, column: 3 });
+    }
+
+    #[test]
+    fn exit_codes_by_category() {
+        assert_eq!(exit_code(&SherpaError::NotARepo.into()), 3);
+        let git = SherpaError::GitCommandFailed {
+            cmd: "git log".into(),
+            code: Some(128),
+            stderr: "fatal".into(),
+        };
+        assert_eq!(exit_code(&git.into()), 4);
+        assert_eq!(exit_code(&anyhow::anyhow!("other")), EXIT_ERROR);
+        assert_eq!(ExitStatus::from_violations(false).code(), 0);
+        assert_eq!(ExitStatus::from_violations(true).code(), 1);
+    }
+
+    #[test]
+    fn typed_error_found_through_context() {
+        let err = anyhow::Error::from(SherpaError::ConventionUnknown { name: "x".into() })
+            .context("build report");
+        assert!(matches!(
+            find(&err),
+            Some(SherpaError::ConventionUnknown { .. })
+        ));
+    }
+
+    #[test]
+    fn json_error_object() {
+        let err: anyhow::Error = SherpaError::GitCommandFailed {
+            cmd: "git log".into(),
+            code: Some(128),
+            stderr: "fatal: bad revision".into(),
+        }
+        .into();
+        let json = to_json(&err);
+        assert_eq!(json["error"]["code"], 128);
+        assert_eq!(json["error"]["kind"], "git_command_failed");
+        assert_eq!(json["error"]["cmd"], "git log");
+        assert!(json["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("bad revision"));
+    }
+}
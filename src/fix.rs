@@ -1,91 +1,1248 @@
 use anyhow::Result;
 use colored::Colorize;
+use glob_match::glob_match;
+use std::collections::HashMap;
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::path::Path;
 
-use crate::check::build_report;
-use crate::config::load_config;
+use crate::attributes;
+use crate::baseline;
+use crate::branch;
+use crate::branch_naming::BranchNaming;
+use crate::check::{build_report, CommitReport, CommitSelection, ForbiddenCommit, Report};
+use crate::config::{load_config, Config};
+use crate::doctor;
 use crate::git;
+use crate::pull_request::PrState;
+use crate::remote::RemoteProblem;
+use crate::reword;
+use crate::sensitive;
 
-pub fn fix(config_path: &Path, commit_limit: usize, apply: bool) -> Result<()> {
+/// The branch's name in advice before a rename, when no name was suggested.
+const NEW_NAME: &str = "<new-name>";
+
+/// When a fix runs relative to the others. Steps are printed and applied in
+/// this order, so each one can assume the ones before it are done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    /// Take out what must not be committed, before any advice to commit.
+    Unstage,
+    /// Edits to tracked files, committed along with the rest of the worktree.
+    EditFiles,
+    /// Rebasing and rewording need a clean worktree.
+    CleanWorktree,
+    /// Rebases onto the base branch need its latest state.
+    Fetch,
+    /// Publishing below uses the new name.
+    RenameBranch,
+    Squash,
+    Rebase,
+    Reword,
+    /// Last rewrite, since any rewrite after it drops the signatures.
+    Sign,
+    /// After all rewrites, so the push needs no `--force`.
+    Publish,
+    PullRequest,
+    /// Advice without a command, such as splitting the branch.
+    Advice,
+}
+
+/// How `fix` goes through the steps it plans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Print the commands to run.
+    Suggest,
+    /// `--apply`: run every step that has an [`Action`].
+    Apply,
+    /// `--interactive`: print each step and ask before running its action.
+    Interactive,
+}
+
+/// What `--apply` runs for a step instead of printing its commands.
+enum Action<'a> {
+    Fetch(String),
+    SetUpstream(String),
+    /// To the suggested name, which a terminal is asked to confirm or edit
+    /// first; without one, only `--interactive` offers it, asking for a name.
+    RenameBranch {
+        from: String,
+        to: Option<String>,
+    },
+    AppendAttributes(Vec<String>),
+    /// Unstage sensitive files and append the missing `.gitignore` entries.
+    UnstageSensitive {
+        paths: Vec<String>,
+        ignore: Vec<String>,
+    },
+    Reword(Vec<&'a CommitReport>),
+}
+
+struct Step<'a> {
+    phase: Phase,
+    /// Rule the step repairs, for `--only` / `--except`; `None` for
+    /// housekeeping such as fetching or opening a pull request.
+    rule: Option<&'static str>,
+    title: String,
+    /// Titled in red: a leaked file or secret rather than a convention.
+    urgent: bool,
+    lines: Vec<Line>,
+    action: Option<Action<'a>>,
+}
+
+enum Line {
+    Command(String),
+    Note(String),
+    Warning(String),
+    Item(String),
+}
+
+impl<'a> Step<'a> {
+    fn new(phase: Phase, title: impl Into<String>) -> Self {
+        Self {
+            phase,
+            rule: None,
+            title: title.into(),
+            urgent: false,
+            lines: Vec::new(),
+            action: None,
+        }
+    }
+
+    fn rule(mut self, rule: &'static str) -> Self {
+        self.rule = Some(rule);
+        self
+    }
+
+    fn urgent(mut self) -> Self {
+        self.urgent = true;
+        self
+    }
+
+    fn command(mut self, command: impl Into<String>) -> Self {
+        self.lines.push(Line::Command(command.into()));
+        self
+    }
+
+    fn note(mut self, note: impl Into<String>) -> Self {
+        self.lines.push(Line::Note(note.into()));
+        self
+    }
+
+    fn warning(mut self, warning: impl Into<String>) -> Self {
+        self.lines.push(Line::Warning(warning.into()));
+        self
+    }
+
+    fn item(mut self, item: impl Into<String>) -> Self {
+        self.lines.push(Line::Item(item.into()));
+        self
+    }
+
+    fn action(mut self, action: Action<'a>) -> Self {
+        self.action = Some(action);
+        self
+    }
+}
+
+impl Action<'_> {
+    /// Shown while the action runs.
+    fn progress(&self) -> String {
+        match self {
+            Action::Fetch(remote) => format!("Fetching from '{}'...", remote),
+            Action::SetUpstream(_) => "Setting upstream...".to_string(),
+            Action::RenameBranch { .. } => "Renaming the branch...".to_string(),
+            Action::AppendAttributes(_) => "Appending missing .gitattributes lines...".to_string(),
+            Action::UnstageSensitive { .. } => "Unstaging sensitive files...".to_string(),
+            Action::Reword(_) => "Rewording invalid commits...".to_string(),
+        }
+    }
+}
+
+/// Which rules' fixes `fix` proposes: `--only` these, or all `--except`
+/// these. Both empty means every rule.
+#[derive(Debug, Default)]
+pub struct RuleFilter {
+    pub only: Vec<String>,
+    pub except: Vec<String>,
+}
+
+impl RuleFilter {
+    fn is_set(&self) -> bool {
+        !self.only.is_empty() || !self.except.is_empty()
+    }
+
+    /// Steps that repair no rule only run when no rule is singled out.
+    fn allows(&self, rule: Option<&str>) -> bool {
+        match rule {
+            Some(rule) if !self.only.is_empty() => self.only.iter().any(|r| r == rule),
+            Some(rule) => !self.except.iter().any(|r| r == rule),
+            None => self.only.is_empty(),
+        }
+    }
+}
+
+/// With `gitignore`, staged sensitive files also get a `.gitignore` entry.
+pub fn fix(
+    config_path: &Path,
+    commits: CommitSelection,
+    mode: Mode,
+    gitignore: bool,
+    filter: RuleFilter,
+) -> Result<()> {
     let config = load_config(config_path)?;
-    let report = build_report(&config, commit_limit)?;
+    let mut report = build_report(&config, &commits)?;
+    baseline::apply_to(&config, &mut report)?;
+
+    let steps = plan(&config, &report, mode, gitignore, &filter)?;
 
     println!("{}", "Suggested fixes:".yellow().bold());
 
-    let mut has_fixes = false;
+    // Without commits there is no history to lose.
+    let destructive = steps
+        .iter()
+        .any(|s| s.phase >= Phase::RenameBranch && s.phase <= Phase::Sign);
+    if destructive && !report.repo.unborn {
+        print_undo_point();
+    }
+
+    if steps.is_empty() {
+        let done = if filter.is_set() {
+            "No fixes needed for the selected rules."
+        } else {
+            "No fixes needed. You're good to go!"
+        };
+        println!("\n{}", done.green().bold());
+        return Ok(());
+    }
+    run(&config, steps, mode)
+}
+
+/// The fixes `filter` allows for `report`, in [`Phase`] order. Unless
+/// suggesting, steps that would run out of order (pushing before a pending
+/// rewrite, or under a name about to change) are left as advice.
+fn plan<'a>(
+    config: &Config,
+    report: &'a Report,
+    mode: Mode,
+    gitignore: bool,
+    filter: &RuleFilter,
+) -> Result<Vec<Step<'a>>> {
+    let apply = mode != Mode::Suggest;
+    let mut steps = Vec::new();
+    // The name a rename step suggests, for the push after it.
+    let mut new_name = None;
+
+    if !report.sensitive.findings.is_empty() {
+        let paths: Vec<String> = report
+            .sensitive
+            .findings
+            .iter()
+            .map(|f| f.path.clone())
+            .collect();
+        let ignore = if gitignore {
+            let wanted: Vec<String> = report
+                .sensitive
+                .findings
+                .iter()
+                .flat_map(sensitive::gitignore_entries)
+                .collect();
+            sensitive::missing_gitignore_entries(&sensitive::read_gitignore(), &wanted)
+        } else {
+            Vec::new()
+        };
+        let mut step = Step::new(Phase::Unstage, "Sensitive files staged:")
+            .rule("sensitive-files")
+            .urgent();
+        for path in &paths {
+            step = step.command(if report.repo.unborn {
+                format!("git rm --cached -- {}", path)
+            } else {
+                format!("git reset HEAD -- {}", path)
+            });
+        }
+        for entry in &ignore {
+            step = step.command(format!("echo '{}' >> {}", entry, sensitive::GITIGNORE));
+        }
+        steps.push(step.action(Action::UnstageSensitive { paths, ignore }));
+    }
+
+    for finding in &report.sensitive.content {
+        steps.push(
+            Step::new(
+                Phase::Unstage,
+                format!(
+                    "Secret ({}) staged at {}:{}:",
+                    finding.rule, finding.path, finding.line
+                ),
+            )
+            .rule("sensitive-files")
+            .urgent()
+            .note("Remove it from the file, rotate the credential, then re-stage:")
+            .command(format!("git add -p {}", finding.path)),
+        );
+    }
+
+    if !report.protected_paths.is_empty() {
+        let mut step = Step::new(Phase::Unstage, "Staged changes to protected paths:")
+            .rule("protected-paths")
+            .note("Unstage them, or make the change on an allowed branch:");
+        for change in &report.protected_paths {
+            step = step.command(format!("git reset HEAD -- {}", change.path));
+        }
+        steps.push(step);
+    }
+
+    for file in &report.large_files {
+        let step = match &file.commit {
+            Some(commit) => Step::new(
+                Phase::Rebase,
+                format!(
+                    "Large file {} ({} KB) added in {}:",
+                    file.path,
+                    file.size_kb(),
+                    git::short_hash(commit)
+                ),
+            )
+            .command(format!("git lfs track '{}'", file.path))
+            .command(format!(
+                "git rebase -i {}^  (edit the commit to drop or LFS-track it)",
+                commit
+            )),
+            None => Step::new(
+                Phase::Unstage,
+                format!("Large file {} ({} KB) staged:", file.path, file.size_kb()),
+            )
+            .command(format!("git lfs track '{}'", file.path))
+            .command(format!("git reset HEAD {}", file.path)),
+        };
+        steps.push(step.rule("large-files"));
+    }
+
+    if !report.attributes.missing_lines.is_empty() {
+        let mut step = Step::new(Phase::EditFiles, "Missing .gitattributes lines:")
+            .rule("attributes")
+            .action(Action::AppendAttributes(
+                report.attributes.missing_lines.clone(),
+            ));
+        for line in &report.attributes.missing_lines {
+            step = step.command(format!("echo '{}' >> {}", line, attributes::GITATTRIBUTES));
+        }
+        steps.push(step);
+    }
+
+    for v in &report.attributes.violations {
+        steps.push(
+            Step::new(
+                Phase::EditFiles,
+                format!("Attribute policy violation for {}:", v.path),
+            )
+            .rule("attributes")
+            .note(v.reason.clone())
+            .command(format!(
+                "echo '{} binary' >> {}",
+                v.path,
+                attributes::GITATTRIBUTES
+            ))
+            .command(format!("git lfs track '{}'", v.path)),
+        );
+    }
+
+    if !report.staged_content.is_empty() {
+        let mut step = Step::new(Phase::EditFiles, "Staged content issues:").rule("staged-content");
+        let mut paths: Vec<&str> = Vec::new();
+        for issue in &report.staged_content {
+            step = step.item(format!("{}:{} {}", issue.path, issue.line, issue.kind));
+            if !paths.contains(&issue.path.as_str()) {
+                paths.push(&issue.path);
+            }
+        }
+        step = step.note("Edit the lines in the working tree, then stage the fix:");
+        for path in paths {
+            step = step.command(format!("git add -p {}", path));
+        }
+        steps.push(step);
+    }
+
+    if report.repo.unborn && !report.repo.worktree_clean {
+        // `git stash` needs a commit to stash against.
+        steps.push(
+            Step::new(
+                Phase::CleanWorktree,
+                "Working tree is dirty (no commits yet):",
+            )
+            .rule("clean-worktree")
+            .command("git add . && git commit"),
+        );
+    } else if !report.repo.worktree_clean {
+        steps.push(
+            Step::new(Phase::CleanWorktree, "Working tree is dirty:")
+                .rule("clean-worktree")
+                .command("git stash  or  git add . && git commit"),
+        );
+    }
+
+    // Fetching from or pushing to a remote that does not work only fails.
+    let broken_remote = report.repo.remote.as_ref().filter(|r| !r.ok());
+    if let Some(remote) = broken_remote {
+        let problem = remote.problem.map_or("", |p| p.label());
+        let mut step = Step::new(
+            Phase::Fetch,
+            format!("Remote '{}' {}:", remote.name, problem),
+        )
+        .rule("remote");
+        if let Some(detail) = &remote.detail {
+            step = step.item(detail.lines().next().unwrap_or_default());
+        }
+        step = match (remote.problem, &remote.url) {
+            (Some(RemoteProblem::Missing), _) => {
+                step.command(format!("git remote add {} <url>", remote.name))
+            }
+            (Some(RemoteProblem::Authentication), Some(url)) => step
+                .command(format!("git ls-remote {}", remote.name))
+                .note(format!("(check your credentials or SSH key for {})", url)),
+            (_, url) => step
+                .command(format!("git ls-remote {}", remote.name))
+                .note(format!(
+                    "(check the URL{}, the network and your credentials)",
+                    url.as_ref()
+                        .map_or(String::new(), |url| format!(" {}", url))
+                )),
+        };
+        steps.push(step);
+    }
+
+    if let Some(fetch) = report.repo.fetch.as_ref().filter(|f| !f.fresh) {
+        let mut step = Step::new(Phase::Fetch, "Remote data is stale:")
+            .command(format!("git fetch {}", fetch.remote));
+        if broken_remote.is_some_and(|r| r.name == fetch.remote) {
+            step = step.note("(after fixing the remote above)");
+        } else {
+            step = step.action(Action::Fetch(fetch.remote.clone()));
+        }
+        steps.push(step);
+    }
 
     if !report.branch.valid {
-        has_fixes = true;
-        println!(
-            "\n{}",
-            "Branch name does not match pattern:".yellow().bold()
+        let mut step = Step::new(Phase::RenameBranch, "Branch name does not match pattern:")
+            .rule("branch-pattern");
+        for problem in &report.branch.problems {
+            step = step.item(problem);
+        }
+        let subjects: Vec<&str> = report.commits.iter().map(|c| c.message.as_str()).collect();
+        let suggestion = branch::suggest_name(&config.branches, &report.branch.name, &subjects)?;
+        let target = suggestion
+            .clone()
+            .unwrap_or_else(|| format!("<new-name-matching:{}>", report.branch.pattern));
+        new_name = suggestion.clone();
+        step = step.command(format!("git branch -m {} {}", report.branch.name, target));
+        if mode == Mode::Interactive || (apply && suggestion.is_some()) {
+            step = step.action(Action::RenameBranch {
+                from: report.branch.name.clone(),
+                to: suggestion,
+            });
+        }
+        steps.push(step);
+    }
+
+    let forbidden = |kind| {
+        report
+            .commits
+            .iter()
+            .filter(|c| c.forbidden == Some(kind) && !c.baselined)
+            .count()
+    };
+    let fixups = forbidden(ForbiddenCommit::Fixup);
+    if fixups > 0 {
+        steps.push(
+            Step::new(
+                Phase::Squash,
+                format!("{} fixup!/squash! commit(s) not squashed yet:", fixups),
+            )
+            .rule("commit-convention")
+            .command(format!(
+                "git rebase -i --autosquash {}",
+                rebase_base(report)?
+            )),
         );
-        println!(
-            "  {}",
-            format!(
-                "git branch -m {} <new-name-matching:{}>",
-                report.branch.name, report.branch.pattern
+    }
+    let merges = forbidden(ForbiddenCommit::Merge);
+    if merges > 0 {
+        steps.push(
+            Step::new(Phase::Rebase, format!("{} merge commit(s):", merges))
+                .rule("commit-convention")
+                .command(format!("git rebase {}", rebase_base(report)?))
+                .note("(replays the merged-in commits on top of the base branch, without merges)"),
+        );
+    }
+
+    if let Some(base) = &report.repo.base {
+        if base.too_far_behind() {
+            steps.push(
+                Step::new(
+                    Phase::Rebase,
+                    format!("Branch is {} commits behind {}:", base.behind, base.base),
+                )
+                .rule("base-divergence")
+                .command(format!("git rebase {}", base.base)),
+            );
+        }
+        if base.too_far_ahead() {
+            steps.push(
+                Step::new(
+                    Phase::Advice,
+                    format!("Branch is {} commits ahead of {}:", base.ahead, base.base),
+                )
+                .rule("base-divergence")
+                .note("Split it into smaller branches and merge the first part."),
+            );
+        }
+        if base.too_large_to_review() {
+            steps.push(
+                Step::new(
+                    Phase::Advice,
+                    format!(
+                        "Diff against {} changes {} lines:",
+                        base.base,
+                        base.changed_lines.unwrap_or_default()
+                    ),
+                )
+                .rule("base-divergence")
+                .note("Split it into stacked branches, each reviewable on its own."),
+            );
+        }
+    }
+
+    if let Some(age) = report.branch.age.as_ref().filter(|age| age.too_old()) {
+        steps.push(
+            Step::new(
+                Phase::Advice,
+                format!("Branch diverged from {} {} days ago:", age.base, age.days),
             )
-            .cyan()
+            .rule("branch-age")
+            .note("Merge what is ready and continue on a fresh branch from the base."),
         );
     }
 
-    if !report.repo.worktree_clean {
-        has_fixes = true;
-        println!("\n{}", "Working tree is dirty:".yellow().bold());
-        println!(
-            "  {}",
-            "git stash  or  git add . && git commit".cyan()
+    if let Some(linearity) = report.repo.linearity.as_ref().filter(|l| !l.is_linear()) {
+        let mut step = Step::new(
+            Phase::Rebase,
+            format!("History on top of {} is not linear:", linearity.base),
+        )
+        .rule("linear-history")
+        .command(format!("git rebase {}", linearity.base));
+        if !linearity.overlapping_paths.is_empty() {
+            step = step.warning("Conflicts likely; these paths also changed upstream:");
+            for path in &linearity.overlapping_paths {
+                step = step.item(path.clone());
+            }
+        }
+        steps.push(step);
+    }
+
+    // Squashing and rebasing above remove these, so they need no reword.
+    let invalid: Vec<&CommitReport> = report
+        .commits
+        .iter()
+        .filter(|c| !c.valid && !c.baselined && c.forbidden.is_none())
+        .collect();
+    let targets = fixup_targets(report, &invalid)?;
+    let mut to_reword = Vec::new();
+    for commit in invalid {
+        if let Some(target) = targets.get(commit.hash.as_str()) {
+            steps.push(
+                Step::new(
+                    Phase::Squash,
+                    format!(
+                        "Invalid commit {} looks like a follow-up to {} (\"{}\"):",
+                        commit.short_hash,
+                        target.short_hash,
+                        target.message.lines().next().unwrap_or_default()
+                    ),
+                )
+                .rule("commit-convention")
+                .command(format!("git rebase -i {}", rebase_base(report)?))
+                .note(format!(
+                    "(move {} right below {} in the todo list and mark it 'fixup')",
+                    commit.short_hash, target.short_hash
+                )),
+            );
+            continue;
+        }
+        if apply {
+            to_reword.push(commit);
+            continue;
+        }
+        steps.push(
+            Step::new(
+                Phase::Reword,
+                format!("Invalid commit {}:", commit.short_hash),
+            )
+            .rule("commit-convention")
+            .command(format!("git rebase -i --reword {}^", commit.hash))
+            .note("(use --apply to reword unpushed commits)"),
+        );
+    }
+    if !to_reword.is_empty() {
+        steps.push(
+            Step::new(Phase::Reword, "Invalid commits to reword:")
+                .rule("commit-convention")
+                .action(Action::Reword(to_reword)),
+        );
+    }
+
+    for r in &report.references.dangling {
+        steps.push(
+            Step::new(
+                Phase::Reword,
+                format!(
+                    "Commit {} references missing issue {}:",
+                    git::short_hash(&r.commit),
+                    r.reference
+                ),
+            )
+            .rule("references")
+            .command(format!("git rebase -i --reword {}^", r.commit)),
+        );
+    }
+
+    if !report.authors.issues.is_empty() {
+        let issues = &report.authors.issues;
+        // Commits are listed newest first.
+        let head = report.commits.first().map(|c| c.hash.as_str());
+        let oldest = &issues[issues.len() - 1].hash;
+        let email = match report.authors.allowed_domains.first() {
+            Some(domain) => format!("<you>@{}", domain),
+            None => "<your work email>".to_string(),
+        };
+        let amend = "git commit --amend --no-edit --reset-author";
+        // The rebase replays the commits in between too; only those with a
+        // disallowed email are amended.
+        let mut emails: Vec<String> = Vec::new();
+        for issue in issues {
+            let quoted = format!("\"{}\"", issue.email);
+            if !emails.contains(&quoted) {
+                emails.push(quoted);
+            }
+        }
+        let amend_disallowed = format!(
+            "case \"$(git log -1 --format=%ae)\" in {}) {} ;; esac",
+            emails.join("|"),
+            amend
+        );
+        let rewrite = if issues.len() == 1 && head == Some(oldest.as_str()) {
+            amend.to_string()
+        } else if git::resolve_rev(&format!("{}^", oldest))?.is_none() {
+            format!("git rebase --root --exec '{}'", amend_disallowed)
+        } else {
+            format!("git rebase {}^ --exec '{}'", oldest, amend_disallowed)
+        };
+        let mut step = Step::new(
+            Phase::Reword,
+            format!("{} commit(s) with a disallowed author email:", issues.len()),
+        )
+        .rule("author-email");
+        for issue in issues {
+            step = step.item(format!(
+                "{} {} ({})",
+                git::short_hash(&issue.hash),
+                issue.email,
+                issue.problem.label()
+            ));
+        }
+        steps.push(
+            step.command(format!("git config user.email {}", email))
+                .command(rewrite),
+        );
+    }
+
+    // What follows depends on the steps before it, so only on the kept ones.
+    steps.retain(|step| filter.allows(step.rule));
+
+    if !report.signatures.is_empty() {
+        // Commits are listed newest first.
+        let head = report.commits.first().map(|c| c.hash.as_str());
+        let oldest = &report.signatures[report.signatures.len() - 1].hash;
+        let rewritten = steps.iter().any(|s| s.phase > Phase::RenameBranch);
+        let command = if rewritten {
+            // The hashes change with the rewrites above; sign the whole branch.
+            format!(
+                "git rebase --force-rebase --gpg-sign {}",
+                rebase_base(report)?
+            )
+        } else if report.signatures.len() == 1 && head == Some(oldest.as_str()) {
+            "git commit --amend --no-edit -S".to_string()
+        } else if git::resolve_rev(&format!("{}^", oldest))?.is_none() {
+            "git rebase --force-rebase --gpg-sign --root".to_string()
+        } else {
+            format!("git rebase --force-rebase --gpg-sign {}^", oldest)
+        };
+        steps.push(
+            Step::new(
+                Phase::Sign,
+                format!(
+                    "{} commit(s) without a good signature:",
+                    report.signatures.len()
+                ),
+            )
+            .rule("signed-commits")
+            .command(command)
+            .note("(set user.signingkey first; signatures that cannot be verified need the signer's public key)"),
         );
     }
 
     if !report.repo.upstream_set {
-        has_fixes = true;
-        if apply {
-            println!("\n{}", "Setting upstream...".yellow().bold());
-            git::push_set_upstream(&report.branch.name)?;
-            println!("  {}", "Upstream set successfully.".green());
+        let renaming = steps.iter().any(|s| s.phase == Phase::RenameBranch);
+        let branch = if renaming {
+            new_name.as_deref().unwrap_or(NEW_NAME)
+        } else {
+            report.branch.name.as_str()
+        };
+        // Pushing now would publish the old name (or with --apply, one it may
+        // have inferred unseen), or history about to be rewritten and then
+        // need a force-push.
+        let pending = steps.iter().any(|s| {
+            (s.phase == Phase::RenameBranch && mode != Mode::Interactive)
+                || (s.phase > Phase::RenameBranch && s.phase < Phase::Publish && s.action.is_none())
+        });
+        let mut step = Step::new(Phase::Publish, "No upstream tracking branch:")
+            .rule("upstream")
+            .command(push_command(branch));
+        if report.repo.unborn {
+            step = step.note("(after the first commit; there is nothing to push yet)");
+        } else if broken_remote.is_some() {
+            step =
+                step.note("(after fixing the remote above; --apply leaves it to you until then)");
+        } else if pending {
+            step = step.note("(after the steps above; --apply leaves it to you until then)");
+        } else {
+            step = step.action(Action::SetUpstream(report.branch.name.clone()));
+        }
+        steps.push(step);
+    }
+
+    if let Some(rewrites) = &report.repo.rewrites {
+        let mut step = Step::new(
+            Phase::Publish,
+            format!(
+                "{} commit(s) already on {} were rewritten locally:",
+                rewrites.rewritten.len(),
+                rewrites.upstream
+            ),
+        )
+        .command("git push --force-with-lease")
+        .note("(unlike --force, refuses the push if the upstream moved since your last fetch)");
+        if !rewrites.dropped.is_empty() {
+            step = step.warning(format!(
+                "These commits on {} are not in HEAD and the push would drop them:",
+                rewrites.upstream
+            ));
+            for hash in &rewrites.dropped {
+                step = step.item(git::short_hash(hash));
+            }
+            step = step.note(format!(
+                "Cherry-pick the ones to keep first (see git log HEAD..{})",
+                rewrites.upstream
+            ));
+        }
+        steps.push(step);
+    }
+
+    if let Some(pr) = report
+        .pull_request
+        .as_ref()
+        .filter(|pr| pr.state == PrState::None)
+    {
+        let command = if pr.forge == "gitlab" {
+            "glab mr create --fill"
         } else {
-            println!("\n{}", "No upstream tracking branch:".yellow().bold());
+            "gh pr create --fill"
+        };
+        steps.push(
+            Step::new(Phase::PullRequest, "No pull request for this branch yet:").command(command),
+        );
+    }
+
+    steps.retain(|step| filter.allows(step.rule));
+    // Stable: steps of one phase keep the order they were added in.
+    steps.sort_by_key(|s| s.phase);
+    Ok(steps)
+}
+
+/// Prints the numbered plan, running each step's action in place with
+/// [`Mode::Apply`], or asking first with [`Mode::Interactive`]. A command
+/// repeated from an earlier step is referred back to.
+fn run(config: &Config, steps: Vec<Step>, mode: Mode) -> Result<()> {
+    let mut printed: HashMap<String, usize> = HashMap::new();
+    // With --interactive: the name the branch got, or `None` once renaming
+    // it was declined, so it is not published under the invalid one.
+    let mut renamed: Option<Option<String>> = None;
+    // The name the push step was planned with, before the rename.
+    let mut planned = NEW_NAME.to_string();
+    for (n, step) in steps.into_iter().enumerate() {
+        let n = n + 1;
+        // A new branch name needs confirming; without a terminal to ask
+        // on, the rename is left as a suggestion.
+        let confirmable =
+            !matches!(step.action, Some(Action::RenameBranch { .. })) || io::stdin().is_terminal();
+        if let (Mode::Apply, Some(action), true) = (mode, &step.action, confirmable) {
+            let progress = format!("{}. {}", n, action.progress());
+            if step.urgent {
+                println!("\n{}", progress.red().bold());
+            } else {
+                println!("\n{}", progress.yellow().bold());
+            }
+            match action {
+                Action::RenameBranch { from, to } => {
+                    planned = to.clone().unwrap_or_else(|| NEW_NAME.to_string());
+                    renamed = Some(rename(config, from, to.as_deref())?);
+                }
+                _ => execute(config, action, mode, None)?,
+            }
+            continue;
+        }
+
+        let title = format!("{}. {}", n, step.title);
+        if step.urgent {
+            println!("\n{}", title.red().bold());
+        } else {
+            println!("\n{}", title.yellow().bold());
+        }
+        for line in &step.lines {
+            match line {
+                Line::Command(command) => match printed.get(command) {
+                    // Planned before the rename, under the name it suggested.
+                    _ if *command == push_command(&planned) => match &renamed {
+                        Some(Some(name)) => println!("  {}", push_command(name).cyan()),
+                        _ => println!("  {}", command.cyan()),
+                    },
+                    Some(earlier) => println!(
+                        "  {}",
+                        format!("(`{}`, done in step {})", command, earlier).dimmed()
+                    ),
+                    None => {
+                        println!("  {}", command.cyan());
+                        printed.insert(command.clone(), n);
+                    }
+                },
+                Line::Note(note) => println!("  {}", note.dimmed()),
+                Line::Warning(warning) => println!("  {}", warning.yellow()),
+                Line::Item(item) => println!("    - {}", item),
+            }
+        }
+        let Some(action) = &step.action else {
+            continue;
+        };
+        if mode == Mode::Apply {
+            // Only a rename without a terminal to confirm it gets here.
             println!(
                 "  {}",
-                format!("git push -u origin {}", report.branch.name).cyan()
+                "(left to you: there is no terminal to confirm the name)".dimmed()
             );
+            continue;
+        }
+        if mode == Mode::Suggest {
             println!(
                 "  {}",
                 "(use --apply to execute this automatically)".dimmed()
             );
+            continue;
+        }
+        match action {
+            Action::RenameBranch { from, to } => {
+                planned = to.clone().unwrap_or_else(|| NEW_NAME.to_string());
+                renamed = Some(rename(config, from, to.as_deref())?);
+            }
+            // Asks about each commit itself.
+            Action::Reword(_) => execute(config, action, mode, None)?,
+            Action::SetUpstream(_) if renamed == Some(None) => {
+                println!("  {}", "(left to you: the branch was not renamed)".dimmed())
+            }
+            _ => match ask("  Apply? [y/n/q] ", "ynq")? {
+                Some('y') => {
+                    println!("  {}", action.progress().dimmed());
+                    execute(config, action, mode, renamed.clone().flatten().as_deref())?;
+                }
+                Some('n') => println!("  {}", "Skipped.".dimmed()),
+                _ => {
+                    println!(
+                        "\n{}",
+                        "Stopped; the remaining steps were not run.".yellow()
+                    );
+                    return Ok(());
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+fn push_command(branch: &str) -> String {
+    format!("git push -u origin {}", branch)
+}
+
+/// Runs `action`, the branch renamed to `branch` if given.
+fn execute(config: &Config, action: &Action, mode: Mode, branch: Option<&str>) -> Result<()> {
+    match action {
+        Action::Fetch(remote) => {
+            git::fetch(remote)?;
+            println!("  {}", "Fetched successfully.".green());
+        }
+        Action::SetUpstream(name) => {
+            let branch = branch.unwrap_or(name);
+            if push_would_diverge(config, branch)? {
+                return Ok(());
+            }
+            git::push_set_upstream(branch)?;
+            println!("  {}", "Upstream set successfully.".green());
+        }
+        // May need asking; see `run`.
+        Action::RenameBranch { .. } => {}
+        Action::AppendAttributes(lines) => {
+            attributes::append_missing(lines)?;
+            println!("  {}", "Updated .gitattributes.".green());
+        }
+        Action::UnstageSensitive { paths, ignore } => {
+            git::unstage(paths)?;
+            println!("  {}", format!("Unstaged {}.", paths.join(", ")).green());
+            if !ignore.is_empty() {
+                sensitive::append_gitignore(ignore)?;
+                println!(
+                    "  {}",
+                    format!("Added {} to {}.", ignore.join(", "), sensitive::GITIGNORE).green()
+                );
+            }
+        }
+        Action::Reword(commits) => reword::reword(config, commits, mode == Mode::Interactive)?,
+    }
+    Ok(())
+}
+
+/// Renames the branch `from` to `to` once confirmed or edited, or to a name
+/// asked for when there is no suggestion; the name it got.
+fn rename(config: &Config, from: &str, to: Option<&str>) -> Result<Option<String>> {
+    let naming = BranchNaming::from_config(&config.branches)?;
+    let name = match to {
+        Some(to) => {
+            println!("  {} -> {}", from, to.cyan());
+            match ask("  Rename? [y/n/e] ", "yne")? {
+                Some('y') => Some(to.to_string()),
+                Some('e') => prompt_branch_name(&naming, from)?,
+                _ => None,
+            }
+        }
+        None => prompt_branch_name(&naming, from)?,
+    };
+    match &name {
+        Some(name) => {
+            git::rename_branch(from, name)?;
+            println!("  {}", format!("Renamed to {}.", name).green());
+        }
+        None => println!("  {}", "Skipped.".dimmed()),
+    }
+    Ok(name)
+}
+
+/// Asks `question` until the answer starts with one of `choices`; `None`
+/// at the end of input.
+pub fn ask(question: &str, choices: &str) -> Result<Option<char>> {
+    let stdin = io::stdin();
+    loop {
+        print!("{}", question);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(None);
+        }
+        let answer = line.trim().chars().next().map(|c| c.to_ascii_lowercase());
+        if let Some(choice) = answer.filter(|c| choices.contains(*c)) {
+            return Ok(Some(choice));
+        }
+    }
+}
+
+/// Asks for a name `naming` accepts until one is given; an empty answer
+/// (or the end of input) keeps `current`.
+fn prompt_branch_name(naming: &BranchNaming, current: &str) -> Result<Option<String>> {
+    let stdin = io::stdin();
+    loop {
+        print!("  New name for {} (empty to skip): ", current);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(None);
+        }
+        let name = line.trim();
+        if name.is_empty() {
+            return Ok(None);
         }
+        let problems = naming.problems(name);
+        if problems.is_empty() {
+            return Ok(Some(name.to_string()));
+        }
+        for problem in problems {
+            println!("    {}", format!("'{}' {}", name, problem).red());
+        }
+    }
+}
+
+/// Before `git push -u`: whether `origin` already has `branch` with commits
+/// HEAD lacks. The push would be rejected, or need a force-push that drops
+/// them, so it is left to the user with what to run instead; force-pushing
+/// is only offered for branches outside `[hooks] protected_branches`.
+fn push_would_diverge(config: &Config, branch: &str) -> Result<bool> {
+    let Some(tip) = git::remote_branch_tip("origin", branch)? else {
+        return Ok(false);
+    };
+    git::fetch_branch("origin", branch)?;
+    if git::is_ancestor(&tip, "HEAD")? {
+        return Ok(false);
+    }
+    let remote = format!("origin/{}", branch);
+    let (behind, ahead) = git::ahead_behind(&remote)?;
+    println!(
+        "  {}",
+        format!(
+            "{} already exists with {} commit(s) that are not in your branch ({} of yours are not on it); not pushing.",
+            remote, behind, ahead
+        )
+        .red()
+    );
+    println!("  To keep them, integrate them first, then push:");
+    println!(
+        "  {}",
+        format!("git pull --rebase origin {}", branch).cyan()
+    );
+    println!("  {}", format!("git push -u origin {}", branch).cyan());
+    let protected = config
+        .hooks
+        .protected_branches
+        .iter()
+        .any(|pattern| glob_match(pattern, branch));
+    if protected {
+        println!(
+            "  {}",
+            format!(
+                "(overwriting it is not an option: '{}' is in [hooks] protected_branches)",
+                branch
+            )
+            .dimmed()
+        );
+    } else {
+        println!("  To replace them with your history instead:");
+        println!(
+            "  {}",
+            format!("git push --force-with-lease -u origin {}", branch).cyan()
+        );
+        println!(
+            "  {}",
+            "(refuses the push if the branch moves again before it lands)".dimmed()
+        );
+    }
+    Ok(true)
+}
+
+/// Branch to rebase onto: `[checks] base_branch`, else `origin`'s default
+/// branch.
+fn rebase_base(report: &Report) -> Result<String> {
+    if let Some(base) = &report.repo.base {
+        return Ok(base.base.clone());
     }
+    Ok(git::remote_head("origin")?.unwrap_or_else(|| "main".to_string()))
+}
 
-    for commit in report.commits.iter().filter(|c| !c.valid) {
-        has_fixes = true;
+/// Warns when the reflog cannot be relied on, then prints the entry to return
+/// to if a rename, rebase or reword goes wrong.
+fn print_undo_point() {
+    if !doctor::reflog_safe() {
         println!(
             "\n{}",
-            format!("Invalid commit {}:", &commit.hash[..8])
-                .yellow()
+            "Warning: reflogs are disabled or expire quickly; rewriting history may not be recoverable."
+                .red()
                 .bold()
         );
+        println!("  {}", "Run `git-sherpa doctor` for details.".dimmed());
+    }
+    if let Ok(Some((hash, selector, subject))) = git::head_reflog_entry() {
+        println!(
+            "\n{}",
+            format!("Undo point: {} ({}: {})", hash, selector, subject).dimmed()
+        );
         println!(
             "  {}",
-            format!("git rebase -i --reword {}^", commit.hash).cyan()
+            format!("to undo a rewrite: git reset --hard {}", hash).dimmed()
         );
     }
+}
+
+/// Subjects that typically describe review follow-ups rather than a change of
+/// their own; these are better folded into the commit they amend.
+const FOLLOWUP_HINTS: &[&str] = &[
+    "address comment",
+    "address comments",
+    "address review",
+    "review comments",
+    "review feedback",
+    "pr feedback",
+    "fix typo",
+    "typo",
+    "oops",
+    "wip",
+    "fixup",
+    "nit",
+];
+
+fn is_followup_message(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    FOLLOWUP_HINTS.iter().any(|hint| {
+        let hint_words: Vec<&str> = hint.split(' ').collect();
+        words
+            .windows(hint_words.len())
+            .any(|window| window == hint_words.as_slice())
+    })
+}
 
-    if !report.sensitive.files.is_empty() {
-        has_fixes = true;
-        println!("\n{}", "Sensitive files staged:".red().bold());
-        for f in &report.sensitive.files {
-            println!("  {}", format!("git reset HEAD {}", f).cyan());
+/// Maps each follow-up among `invalid` to the closest earlier checked commit
+/// that touched any of its files. Only the checked commits are candidates, so
+/// the advice never rewrites the base branch.
+fn fixup_targets<'a>(
+    report: &'a Report,
+    invalid: &[&CommitReport],
+) -> Result<HashMap<&'a str, &'a CommitReport>> {
+    if !invalid.iter().any(|c| is_followup_message(&c.message)) {
+        return Ok(HashMap::new());
+    }
+    let hashes: Vec<String> = report.commits.iter().map(|c| c.hash.clone()).collect();
+    let files = git::files_of_commits(&hashes)?;
+    let touched = |commit: &CommitReport| files.get(&commit.hash).map_or(&[][..], Vec::as_slice);
+    let mut targets = HashMap::new();
+    for commit in invalid.iter().filter(|c| is_followup_message(&c.message)) {
+        let Some(i) = report.commits.iter().position(|c| c.hash == commit.hash) else {
+            continue;
+        };
+        // Newest first, so the commits after a follow-up are the earlier ones.
+        let own = touched(&report.commits[i]);
+        let target = report.commits[i + 1..]
+            .iter()
+            .find(|earlier| touched(earlier).iter().any(|path| own.contains(path)));
+        if let Some(target) = target {
+            targets.insert(report.commits[i].hash.as_str(), target);
         }
     }
+    Ok(targets)
+}
 
-    if !has_fixes {
-        println!(
-            "\n{}",
-            "No fixes needed. You're good to go!".green().bold()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+    use crate::rollup;
+    use crate::sensitive::{SensitiveFinding, Severity};
+
+    #[test]
+    fn detects_followup_messages() {
+        assert!(is_followup_message("address comments"));
+        assert!(is_followup_message("Address review feedback"));
+        assert!(is_followup_message("fix typo"));
+        assert!(is_followup_message("WIP"));
+        assert!(is_followup_message("nit: rename var"));
+    }
+
+    #[test]
+    fn plan_orders_dependent_fixes() {
+        let mut report = Report::default();
+        report.branch.name = "Login_Page".to_string();
+        report.sensitive.findings = vec![SensitiveFinding {
+            path: ".env".to_string(),
+            pattern: ".env".to_string(),
+            name: None,
+            description: None,
+            severity: Severity::High,
+            renamed_from: None,
+        }];
+        let config = default_config();
+        let steps = plan(&config, &report, Mode::Apply, false, &RuleFilter::default()).unwrap();
+        assert!(steps
+            .iter()
+            .filter_map(|s| s.rule)
+            .all(|rule| rollup::RULES.contains(&rule)));
+        let titles: Vec<&str> = steps.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Sensitive files staged:",
+                "Working tree is dirty:",
+                "Branch name does not match pattern:",
+                "No upstream tracking branch:",
+            ]
         );
+        // Not pushed under the old name, even with --apply.
+        let publish = steps.last().unwrap();
+        assert!(publish.action.is_none());
+        assert!(matches!(
+            &publish.lines[0],
+            Line::Command(c) if c == "git push -u origin feat/login-page"
+        ));
+
+        // --interactive asks for the name, then can push under it.
+        let steps = plan(
+            &config,
+            &report,
+            Mode::Interactive,
+            false,
+            &RuleFilter::default(),
+        )
+        .unwrap();
+        let rename = &steps[2];
+        assert!(matches!(
+            &rename.action,
+            Some(Action::RenameBranch { from, to: Some(to) })
+                if from == "Login_Page" && to == "feat/login-page"
+        ));
+        assert!(steps.last().unwrap().action.is_some());
+
+        // With the rename filtered out, the push waits for nothing.
+        let only = RuleFilter {
+            only: vec!["upstream".to_string()],
+            ..RuleFilter::default()
+        };
+        let steps = plan(&config, &report, Mode::Apply, false, &only).unwrap();
+        assert_eq!(steps.len(), 1);
+        assert!(matches!(
+            &steps[0].lines[..],
+            [Line::Command(c)] if c == "git push -u origin Login_Page"
+        ));
+        assert!(steps[0].action.is_some());
     }
 
-    Ok(())
+    #[test]
+    fn filter_keeps_the_selected_rules() {
+        let only = RuleFilter {
+            only: vec!["upstream".to_string()],
+            ..RuleFilter::default()
+        };
+        assert!(only.allows(Some("upstream")));
+        assert!(!only.allows(Some("branch-pattern")));
+        assert!(!only.allows(None));
+
+        let except = RuleFilter {
+            except: vec!["upstream".to_string()],
+            ..RuleFilter::default()
+        };
+        assert!(!except.allows(Some("upstream")));
+        assert!(except.allows(Some("branch-pattern")));
+        assert!(except.allows(None));
+    }
+
+    #[test]
+    fn ignores_regular_messages() {
+        assert!(!is_followup_message("add login page"));
+        assert!(!is_followup_message("update dependencies"));
+        assert!(!is_followup_message("wipe cache on logout"));
+    }
 }
@@ -1,96 +1,1731 @@
 use anyhow::{bail, Context, Result};
-use std::path::PathBuf;
-use std::process::Command;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
-pub fn current_branch() -> Result<String> {
-    let output = Command::new("git")
-        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+use crate::error::SherpaError;
+
+/// Answers the read-only queries every check makes. Everything else runs
+/// the `git` binary whichever backend is selected.
+pub trait GitBackend: Send + Sync {
+    /// Short name of the checked-out branch, `HEAD` when detached.
+    fn current_branch(&self) -> Result<String>;
+    /// (hash, subject) of the last `limit` commits from HEAD, newest first.
+    fn recent_commits(&self, limit: usize) -> Result<Vec<(String, String)>>;
+    /// (hash, subject) of the commits in a range such as `origin/main..HEAD`.
+    fn range_commits(&self, range: &str) -> Result<Vec<(String, String)>>;
+    /// No staged, unstaged or untracked changes.
+    fn worktree_clean(&self) -> Result<bool>;
+    fn has_upstream(&self) -> Result<bool>;
+    fn resolve_rev(&self, rev: &str) -> Result<Option<String>>;
+    /// `git diff --cached --name-status -M -C`: renames and copies keep
+    /// their source path.
+    fn staged_changes(&self) -> Result<Vec<StagedChange>>;
+    fn config_get(&self, key: &str) -> Result<Option<String>>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BackendKind {
+    /// Run the `git` binary for every query.
+    #[default]
+    Subprocess,
+    /// Answer the hot-path queries in-process with libgit2 (needs the
+    /// `libgit2` feature).
+    Libgit2,
+}
+
+static BACKEND: OnceLock<Box<dyn GitBackend>> = OnceLock::new();
+
+/// Selects the backend for the rest of the process; the first call wins.
+/// Without a call, the subprocess backend is used.
+pub fn set_backend(kind: BackendKind) -> Result<()> {
+    let backend: Box<dyn GitBackend> = match kind {
+        BackendKind::Subprocess => Box::new(Subprocess),
+        #[cfg(feature = "libgit2")]
+        BackendKind::Libgit2 => Box::new(crate::libgit2::Libgit2),
+        #[cfg(not(feature = "libgit2"))]
+        BackendKind::Libgit2 => {
+            anyhow::bail!("--git-backend libgit2 needs git-sherpa built with the `libgit2` feature")
+        }
+    };
+    let _ = BACKEND.set(backend);
+    Ok(())
+}
+
+fn backend() -> &'static dyn GitBackend {
+    BACKEND.get_or_init(|| Box::new(Subprocess)).as_ref()
+}
+
+/// The `git` binary, one process per query.
+pub struct Subprocess;
+
+impl GitBackend for Subprocess {
+    fn current_branch(&self) -> Result<String> {
+        // `symbolic-ref` also names an unborn branch, which has no commit
+        // for `rev-parse` to resolve; it fails quietly when detached.
+        let output = git_unchecked(&["symbolic-ref", "--short", "-q", "HEAD"])?;
+        match output.status.code() {
+            Some(0) => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+            Some(1) => Ok("HEAD".to_string()),
+            _ => Err(failed(&["symbolic-ref", "--short", "-q", "HEAD"], &output)),
+        }
+    }
+
+    fn recent_commits(&self, limit: usize) -> Result<Vec<(String, String)>> {
+        let stdout = git_stdout(&["log", &format!("-n{}", limit), "--pretty=format:%H:::%s"])?;
+        Ok(stdout.lines().filter_map(parse_hash_subject).collect())
+    }
+
+    fn range_commits(&self, range: &str) -> Result<Vec<(String, String)>> {
+        let stdout = git_stdout(&["log", "--pretty=format:%H:::%s", "--end-of-options", range])?;
+        Ok(stdout.lines().filter_map(parse_hash_subject).collect())
+    }
+
+    fn worktree_clean(&self) -> Result<bool> {
+        Ok(git_stdout(&["status", "--porcelain"])?.trim().is_empty())
+    }
+
+    fn has_upstream(&self) -> Result<bool> {
+        let output = git_unchecked(&["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"])?;
+        Ok(output.status.success())
+    }
+
+    fn resolve_rev(&self, rev: &str) -> Result<Option<String>> {
+        let output = git_unchecked(&["rev-parse", "--verify", "--quiet", "--end-of-options", rev])?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    }
+
+    fn staged_changes(&self) -> Result<Vec<StagedChange>> {
+        // Copies of files the commit leaves unchanged count too, e.g. a
+        // committed key duplicated under an innocent name.
+        let stdout = git_stdout(&[
+            "diff",
+            "--cached",
+            "--name-status",
+            "-M",
+            "-C",
+            "--find-copies-harder",
+            "-z",
+        ])?;
+        Ok(parse_name_status(&stdout))
+    }
+
+    fn config_get(&self, key: &str) -> Result<Option<String>> {
+        let output = git_unchecked(&["config", "--get", key])?;
+        // Exit code 1 means the key is unset; anything else is a real failure.
+        match output.status.code() {
+            Some(0) => Ok(Some(
+                String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            )),
+            Some(1) => Ok(None),
+            _ => Err(failed(&["config", "--get", key], &output)),
+        }
+    }
+}
+
+/// `git` followed by the arguments, quoted where needed, for error messages.
+fn command_line(args: &[&str]) -> String {
+    let mut line = String::from("git");
+    for arg in args {
+        line.push(' ');
+        if arg.is_empty() || arg.contains(char::is_whitespace) {
+            line.push_str(&format!("'{}'", arg));
+        } else {
+            line.push_str(arg);
+        }
+    }
+    line
+}
+
+/// Typed error carrying the command line, exit code and trimmed stderr.
+fn failed(args: &[&str], output: &Output) -> anyhow::Error {
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if stderr.contains("not a git repository") {
+        return SherpaError::NotARepo.into();
+    }
+    SherpaError::GitCommandFailed {
+        cmd: command_line(args),
+        code: output.status.code(),
+        stderr,
+    }
+    .into()
+}
+
+/// Runs git where a non-zero exit is a meaningful answer rather than an error.
+fn git_unchecked(args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .args(args)
         .output()
-        .context("git rev-parse")?;
+        .with_context(|| format!("failed to run `{}` (is git installed?)", command_line(args)))
+}
+
+/// Runs git, failing with full command context on a non-zero exit.
+fn git(args: &[&str]) -> Result<Output> {
+    let output = git_unchecked(args)?;
     if !output.status.success() {
-        bail!("Not a git repository or failed to get branch name");
+        return Err(failed(args, &output));
     }
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    Ok(output)
 }
 
-pub fn recent_commits(limit: usize) -> Result<Vec<(String, String)>> {
+fn git_stdout(args: &[&str]) -> Result<String> {
+    Ok(String::from_utf8_lossy(&git(args)?.stdout).into_owned())
+}
+
+/// Runs a user-visible operation (push, fetch): stdout passes through, stderr is
+/// captured for the error and replayed on success so progress is not lost.
+fn git_forwarding(args: &[&str]) -> Result<()> {
     let output = Command::new("git")
-        .args([
-            "log",
-            &format!("-n{}", limit),
-            "--pretty=format:%H:::%s",
-        ])
+        .args(args)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::piped())
         .output()
-        .context("git log")?;
+        .with_context(|| format!("failed to run `{}` (is git installed?)", command_line(args)))?;
+    if !output.status.success() {
+        return Err(failed(args, &output));
+    }
+    std::io::stderr().write_all(&output.stderr).ok();
+    Ok(())
+}
+
+fn parse_hash_subject(line: &str) -> Option<(String, String)> {
+    let mut parts = line.splitn(2, ":::");
+    let hash = parts.next()?.to_string();
+    let message = parts.next()?.to_string();
+    Some((hash, message))
+}
+
+/// Abbreviations already looked up, by full id.
+static SHORT_HASHES: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+/// Abbreviation of a commit or blob id for display, as long as the
+/// repository needs to keep it unambiguous (`git rev-parse --short`). An
+/// id git does not know is cut to 8 characters; shorter input is returned
+/// whole.
+pub fn short_hash(hash: &str) -> String {
+    let cache = SHORT_HASHES.get_or_init(Default::default);
+    if let Some(short) = cache.lock().expect("hash cache").get(hash) {
+        return short.clone();
+    }
+    let is_id = !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit());
+    let short = is_id
+        .then(|| {
+            let object = format!("{}^{{object}}", hash);
+            git_unchecked(&["rev-parse", "--verify", "--quiet", "--short", &object]).ok()
+        })
+        .flatten()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| hash.get(..8).unwrap_or(hash).to_string());
+    cache
+        .lock()
+        .expect("hash cache")
+        .insert(hash.to_string(), short.clone());
+    short
+}
+
+/// The abbreviations of `hashes`, all commits, by full id, looked up with
+/// one `git log`. [`short_hash`] answers for them too afterwards, without
+/// a process each.
+pub fn abbreviate_commits(hashes: &[String]) -> Result<HashMap<String, String>> {
+    if hashes.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut args = vec!["log", "--no-walk=unsorted", "--pretty=format:%H %h"];
+    args.extend(hashes.iter().map(String::as_str));
+    let stdout = git_stdout(&args)?;
+    let mut cache = SHORT_HASHES
+        .get_or_init(Default::default)
+        .lock()
+        .expect("hash cache");
+    let shorts: HashMap<String, String> = stdout
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(hash, short)| (hash.to_string(), short.to_string()))
+        .collect();
+    cache.extend(shorts.clone());
+    Ok(shorts)
+}
+
+/// `2.39.5` from `git version 2.39.5`.
+pub fn version() -> Result<String> {
+    let stdout = git_stdout(&["--version"])?;
+    let version = stdout.trim();
+    Ok(version
+        .strip_prefix("git version ")
+        .unwrap_or(version)
+        .to_string())
+}
+
+pub fn current_branch() -> Result<String> {
+    backend().current_branch()
+}
+
+pub fn recent_commits(limit: usize) -> Result<Vec<(String, String)>> {
+    backend().recent_commits(limit)
+}
+
+/// Whether HEAD names a branch without commits yet, as in a freshly
+/// initialized repository; history queries against HEAD fail there.
+pub fn is_unborn() -> Result<bool> {
+    Ok(resolve_rev("HEAD")?.is_none())
+}
+
+/// Commits in a revision range such as `origin/main..HEAD`, newest first.
+pub fn range_commits(range: &str) -> Result<Vec<(String, String)>> {
+    backend().range_commits(range)
+}
+
+/// (hash, subject) of the commits in `range` (all of HEAD's history when
+/// `None`) committed after `since`, newest first. Always runs `git`: audits
+/// over long histories are what `git log` is fastest at.
+pub fn history_commits(range: Option<&str>, since: Option<&str>) -> Result<Vec<(String, String)>> {
+    let since = since.map(|date| format!("--since={}", date));
+    let mut args = vec!["log", "--pretty=format:%H:::%s"];
+    args.extend(since.as_deref());
+    args.extend(["--end-of-options", range.unwrap_or("HEAD")]);
+    let stdout = git_stdout(&args)?;
+    Ok(stdout.lines().filter_map(parse_hash_subject).collect())
+}
+
+/// (hash, full message) of the commits in `range` other than merges, newest
+/// first.
+pub fn commit_messages(range: &str) -> Result<Vec<(String, String)>> {
+    let stdout = git_stdout(&[
+        "log",
+        "--no-color",
+        "--no-merges",
+        "--format=%H%x00%B%x1e",
+        "--end-of-options",
+        range,
+    ])?;
+    Ok(stdout
+        .split('\x1e')
+        .filter_map(|record| {
+            let (hash, message) = record.trim_start_matches('\n').split_once('\0')?;
+            Some((hash.to_string(), message.trim_end().to_string()))
+        })
+        .collect())
+}
+
+/// Tags reachable from HEAD, highest version first.
+pub fn merged_tags() -> Result<Vec<String>> {
+    let stdout = git_stdout(&["tag", "--merged", "HEAD", "--sort=-v:refname"])?;
+    Ok(stdout.lines().map(|l| l.to_string()).collect())
+}
+
+/// A commit as listed by [`commit_log`].
+pub struct LogEntry {
+    pub hash: String,
+    /// Author name, with `.mailmap` applied.
+    pub author: String,
+    /// Author date as unix seconds.
+    pub timestamp: u64,
+    pub merge: bool,
+    pub message: String,
+}
+
+/// The commits in HEAD's history committed after `since`, newest first,
+/// with author and full message.
+pub fn commit_log(since: &str) -> Result<Vec<LogEntry>> {
+    let since = format!("--since={}", since);
+    let stdout = git_stdout(&[
+        "log",
+        "--no-color",
+        "--format=%H%x00%aN%x00%at%x00%P%x00%B%x1e",
+        &since,
+        "HEAD",
+    ])?;
+    Ok(stdout
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(5, '\0');
+            let hash = fields.next().filter(|h| !h.is_empty())?.to_string();
+            let author = fields.next()?.to_string();
+            let timestamp = fields.next()?.parse().ok()?;
+            let merge = fields.next()?.split_whitespace().count() > 1;
+            let message = fields.next()?.trim_end().to_string();
+            Some(LogEntry {
+                hash,
+                author,
+                timestamp,
+                merge,
+                message,
+            })
+        })
+        .collect())
+}
+
+pub fn worktree_clean() -> Result<bool> {
+    backend().worktree_clean()
+}
+
+pub fn has_upstream() -> Result<bool> {
+    backend().has_upstream()
+}
+
+pub fn git_dir() -> Result<PathBuf> {
+    Ok(PathBuf::from(
+        git_stdout(&["rev-parse", "--git-dir"])?.trim(),
+    ))
+}
+
+/// Shallow clone of `rev` (the default branch when `None`) of `source`
+/// into `dest`.
+pub fn clone_shallow(source: &str, rev: Option<&str>, dest: &Path) -> Result<()> {
+    let dest = dest.to_string_lossy();
+    let mut args = vec!["clone", "--quiet", "--depth", "1"];
+    if let Some(rev) = rev {
+        args.extend(["--branch", rev]);
+    }
+    args.extend(["--", source, &dest]);
+    git(&args)?;
+    Ok(())
+}
+
+/// `%G?` signature code of HEAD in the repository at `dir`.
+pub fn head_signature_code(dir: &Path) -> Result<String> {
+    let dir = dir.to_string_lossy();
+    Ok(git_stdout(&["-C", &dir, "log", "-1", "--format=%G?"])?
+        .trim()
+        .to_string())
+}
 
+pub fn toplevel() -> Result<PathBuf> {
+    Ok(PathBuf::from(
+        git_stdout(&["rev-parse", "--show-toplevel"])?.trim(),
+    ))
+}
+
+/// Full hash `rev` points at, or `None` if it does not resolve.
+pub fn resolve_rev(rev: &str) -> Result<Option<String>> {
+    backend().resolve_rev(rev)
+}
+
+pub fn remotes() -> Result<Vec<String>> {
+    let stdout = git_stdout(&["remote"])?;
+    Ok(stdout.lines().map(str::to_string).collect())
+}
+
+/// Where git runs hooks from: `core.hooksPath` if set, else `.git/hooks`.
+pub fn hooks_dir() -> Result<PathBuf> {
+    Ok(PathBuf::from(
+        git_stdout(&["rev-parse", "--git-path", "hooks"])?.trim(),
+    ))
+}
+
+/// Staged changes without context lines, for scanning added content: what
+/// the next commit adds, whatever the working tree holds.
+pub fn staged_diff() -> Result<String> {
+    git_stdout(&[
+        "diff",
+        "--cached",
+        "-U0",
+        "--no-color",
+        "--no-ext-diff",
+        "--no-textconv",
+        SRC_PREFIX,
+        DST_PREFIX,
+    ])
+}
+
+/// Patches that get parsed name their files `a/<path>` and `b/<path>`,
+/// whatever `diff.noprefix` or `diff.mnemonicPrefix` say.
+const SRC_PREFIX: &str = "--src-prefix=a/";
+const DST_PREFIX: &str = "--dst-prefix=b/";
+
+/// Contents of the blobs `hashes` as text, in order, with one `git
+/// cat-file`; `None` for binary or missing ones.
+pub fn blob_texts(hashes: &[String]) -> Result<Vec<Option<String>>> {
+    Ok(read_blobs(hashes)?
+        .into_iter()
+        .map(|blob| blob.and_then(text))
+        .collect())
+}
+
+fn text(blob: Vec<u8>) -> Option<String> {
+    (!blob.iter().take(8000).any(|&b| b == 0)).then(|| String::from_utf8_lossy(&blob).into_owned())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Copied,
+}
+
+/// A staged change to one path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedChange {
+    pub kind: ChangeKind,
+    /// The path in the index; the removed path for a deletion.
+    pub path: String,
+    /// Where a rename or copy came from.
+    pub from: Option<String>,
+}
+
+impl StagedChange {
+    /// The paths whose content the change adds or removes: a rename also
+    /// removes its source, a copy leaves it as it is.
+    pub fn touched(&self) -> impl Iterator<Item = &str> {
+        let source = self
+            .from
+            .as_deref()
+            .filter(|_| self.kind == ChangeKind::Renamed);
+        std::iter::once(self.path.as_str()).chain(source)
+    }
+}
+
+/// Parses `--name-status -z` output: a status field, then one path, or the
+/// source and destination of a rename or copy.
+fn parse_name_status(stdout: &str) -> Vec<StagedChange> {
+    let mut fields = stdout.split('\0').filter(|f| !f.is_empty());
+    let mut changes = Vec::new();
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        let kind = match status.as_bytes()[0] {
+            b'A' => ChangeKind::Added,
+            b'D' => ChangeKind::Deleted,
+            b'R' => ChangeKind::Renamed,
+            b'C' => ChangeKind::Copied,
+            _ => ChangeKind::Modified,
+        };
+        let change = match kind {
+            ChangeKind::Renamed | ChangeKind::Copied => match fields.next() {
+                Some(to) => StagedChange {
+                    kind,
+                    path: to.to_string(),
+                    from: Some(path.to_string()),
+                },
+                None => break,
+            },
+            _ => StagedChange {
+                kind,
+                path: path.to_string(),
+                from: None,
+            },
+        };
+        changes.push(change);
+    }
+    changes
+}
+
+pub fn staged_changes() -> Result<Vec<StagedChange>> {
+    backend().staged_changes()
+}
+
+/// Paths in the index that differ from `HEAD`, including deleted ones; a
+/// renamed or copied file is listed under its new path.
+pub fn staged_files() -> Result<Vec<String>> {
+    Ok(staged_changes()?.into_iter().map(|c| c.path).collect())
+}
+
+/// Paths in the index, relative to the top of the worktree.
+pub fn tracked_files() -> Result<Vec<String>> {
+    let stdout = git_stdout(&["ls-files", "--full-name", "--", ":/"])?;
+    Ok(stdout.lines().map(|l| l.to_string()).collect())
+}
+
+/// Tracked files named `name` in subdirectories of the worktree, relative
+/// to its top.
+pub fn nested_files(name: &str) -> Result<Vec<String>> {
+    let pathspec = format!(":(top,glob)**/{}", name);
+    let stdout = git_stdout(&["ls-files", "--full-name", "--cached", "--", &pathspec])?;
+    Ok(stdout
+        .lines()
+        .filter(|l| l.contains('/'))
+        .map(|l| l.to_string())
+        .collect())
+}
+
+/// Creates `name` at `HEAD` and checks it out.
+pub fn create_branch(name: &str) -> Result<()> {
+    git(&["checkout", "-q", "-b", name]).map(|_| ())
+}
+
+/// `Name <email>` of the committer, as `git commit -s` signs off with.
+pub fn committer_ident() -> Result<String> {
+    let stdout = git_stdout(&["var", "GIT_COMMITTER_IDENT"])?;
+    // `Name <email> <timestamp> <zone>`
+    let ident = stdout.trim();
+    Ok(ident.rsplitn(3, ' ').nth(2).unwrap_or(ident).to_string())
+}
+
+/// Stages `paths` and commits them, and only them, with `message`.
+pub fn commit_paths(paths: &[&str], message: &str) -> Result<()> {
+    let mut add = vec!["add", "--"];
+    add.extend(paths);
+    git(&add)?;
+    let mut commit = vec!["commit", "-q", "-m", message, "--"];
+    commit.extend(paths);
+    git(&commit).map(|_| ())
+}
+
+/// Takes `paths` out of the index, keeping the worktree copies. Before the
+/// first commit there is no `HEAD` to reset to, so they are removed instead.
+pub fn unstage(paths: &[String]) -> Result<()> {
+    let mut args = if is_unborn()? {
+        vec!["rm", "-q", "--cached", "--"]
+    } else {
+        vec!["reset", "-q", "HEAD", "--"]
+    };
+    args.extend(paths.iter().map(String::as_str));
+    git(&args).map(|_| ())
+}
+
+/// `git branch -m`: renames `from`, with its config and reflog, to `to`.
+pub fn rename_branch(from: &str, to: &str) -> Result<()> {
+    git(&["branch", "-m", from, to]).map(|_| ())
+}
+
+pub fn push_set_upstream(branch: &str) -> Result<()> {
+    git_forwarding(&["push", "-u", "origin", branch])
+}
+
+/// Tip of `branch` on `remote`, asking the remote itself; `None` if it has
+/// no such branch.
+pub fn remote_branch_tip(remote: &str, branch: &str) -> Result<Option<String>> {
+    let refname = format!("refs/heads/{}", branch);
+    let stdout = git_stdout(&["ls-remote", "--heads", remote, &refname])?;
+    Ok(stdout.lines().find_map(|line| {
+        let (hash, name) = line.split_once('\t')?;
+        (name == refname).then(|| hash.to_string())
+    }))
+}
+
+/// Fetches `branch` from `remote` into `<remote>/<branch>`, whatever the
+/// configured refspecs.
+pub fn fetch_branch(remote: &str, branch: &str) -> Result<()> {
+    let refspec = format!("+refs/heads/{0}:refs/remotes/{1}/{0}", branch, remote);
+    git_forwarding(&["fetch", remote, &refspec])
+}
+
+pub fn config_get(key: &str) -> Result<Option<String>> {
+    backend().config_get(key)
+}
+
+/// The prefix git strips from commit message lines, `core.commentChar`:
+/// `#` when unset or `auto`, which git only resolves while editing.
+pub fn comment_char() -> Result<String> {
+    Ok(config_get("core.commentChar")?
+        .filter(|value| value != "auto" && !value.is_empty())
+        .unwrap_or_else(|| "#".to_string()))
+}
+
+/// Remote a partial clone lazily fetches missing objects from:
+/// `extensions.partialClone`, or the first `remote.<name>.promisor`.
+pub fn promisor_remote() -> Result<Option<String>> {
+    if let Some(remote) = config_get("extensions.partialClone")? {
+        return Ok(Some(remote));
+    }
+    let args = [
+        "config",
+        "--bool",
+        "--get-regexp",
+        r"^remote\..*\.promisor$",
+    ];
+    let output = git_unchecked(&args)?;
+    match output.status.code() {
+        Some(0) => Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.strip_suffix(".promisor true"))
+            .find_map(|key| key.strip_prefix("remote."))
+            .map(str::to_string)),
+        Some(1) => Ok(None),
+        _ => Err(failed(&args, &output)),
+    }
+}
+
+pub fn config_set(key: &str, value: &str) -> Result<()> {
+    git(&["config", key, value]).map(|_| ())
+}
+
+/// `git config` restricted to the repo (`--local`) or user (`--global`)
+/// file, for settings whose scope the user picks.
+pub fn scoped_config_get(global: bool, key: &str) -> Result<Option<String>> {
+    let scope = if global { "--global" } else { "--local" };
+    let args = ["config", scope, "--get", key];
+    let output = git_unchecked(&args)?;
+    match output.status.code() {
+        Some(0) => Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        )),
+        Some(1) => Ok(None),
+        _ => Err(failed(&args, &output)),
+    }
+}
+
+pub fn scoped_config_set(global: bool, key: &str, value: &str) -> Result<()> {
+    let scope = if global { "--global" } else { "--local" };
+    git(&["config", scope, key, value]).map(|_| ())
+}
+
+pub fn config_unset(key: &str) -> Result<()> {
+    git(&["config", "--unset", key]).map(|_| ())
+}
+
+/// Files each of `hashes` changed, with one `git log`.
+pub fn files_of_commits(hashes: &[String]) -> Result<HashMap<String, Vec<String>>> {
+    if hashes.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut args = vec![
+        "log",
+        "--no-walk=unsorted",
+        "--cc",
+        "--name-only",
+        "--pretty=format:%x00%H",
+    ];
+    args.extend(hashes.iter().map(String::as_str));
+    let stdout = git_stdout(&args)?;
+    Ok(stdout
+        .split('\0')
+        .filter_map(|entry| {
+            let mut lines = entry.lines().filter(|l| !l.is_empty());
+            let hash = lines.next()?.to_string();
+            Some((hash, lines.map(str::to_string).collect()))
+        })
+        .collect())
+}
+
+pub fn notes_add(notes_ref: &str, rev: &str, message: &str) -> Result<()> {
+    git(&["notes", "--ref", notes_ref, "add", "-f", "-m", message, rev]).map(|_| ())
+}
+
+pub fn notes_show(notes_ref: &str, rev: &str) -> Result<Option<String>> {
+    let output = git_unchecked(&["notes", "--ref", notes_ref, "show", rev])?;
     if !output.status.success() {
-        bail!("Failed to read git log");
+        return Ok(None);
     }
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let commits = stdout
+pub fn push_ref(remote: &str, refspec: &str) -> Result<()> {
+    git_forwarding(&["push", remote, refspec])
+}
+
+/// Raw `git count-objects -v` output as key/value pairs.
+pub fn count_objects() -> Result<Vec<(String, String)>> {
+    let stdout = git_stdout(&["count-objects", "-v"])?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            Some((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect())
+}
+
+/// Blobs reachable from any ref, as (hash, size in bytes, path), largest first.
+/// Blobs a partial clone filtered out are skipped rather than downloaded.
+pub fn largest_blobs(limit: usize) -> Result<Vec<(String, u64, String)>> {
+    let objects = git(&[
+        "rev-list",
+        "--objects",
+        "--missing=allow-promisor",
+        "--branches",
+        "--tags",
+        "--remotes",
+    ])?;
+    let stdout = batch_check(
+        "%(objecttype) %(objectname) %(objectsize) %(rest)",
+        objects.stdout,
+    )?;
+    let mut blobs: Vec<(String, u64, String)> = stdout
         .lines()
         .filter_map(|line| {
-            let mut parts = line.splitn(2, ":::");
+            let mut parts = line.splitn(4, ' ');
+            if parts.next()? != "blob" {
+                return None;
+            }
             let hash = parts.next()?.to_string();
-            let message = parts.next()?.to_string();
-            Some((hash, message))
+            let size = parts.next()?.parse().ok()?;
+            let path = parts.next().unwrap_or_default().to_string();
+            Some((hash, size, path))
         })
         .collect();
+    blobs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    blobs.dedup_by(|a, b| a.0 == b.0);
+    blobs.truncate(limit);
+    Ok(blobs)
+}
 
-    Ok(commits)
+/// Ids of the blobs in the tree of `rev`.
+pub fn tree_blobs(rev: &str) -> Result<Vec<String>> {
+    let stdout = git_stdout(&["ls-tree", "-r", "--full-tree", rev])?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let (meta, _path) = line.split_once('\t')?;
+            let mut parts = meta.split(' ');
+            let (_mode, kind, hash) = (parts.next()?, parts.next()?, parts.next()?);
+            (kind == "blob").then(|| hash.to_string())
+        })
+        .collect())
 }
 
-pub fn worktree_clean() -> Result<bool> {
-    let output = Command::new("git")
-        .args(["status", "--porcelain"])
-        .output()
-        .context("git status")?;
+/// Runs `git cat-file --batch-check` over `input` (one object per line, an
+/// optional rest after a space) and returns its output.
+fn batch_check(format: &str, input: Vec<u8>) -> Result<String> {
+    let format_arg = format!("--batch-check={}", format);
+    let stdout = cat_file_batch(&format_arg, input)?;
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+/// Contents of each of `objects` (hashes or `:path` index entries), in
+/// order, read with one `git cat-file --batch`; `None` for one that is
+/// missing.
+pub fn read_blobs(objects: &[String]) -> Result<Vec<Option<Vec<u8>>>> {
+    if objects.is_empty() {
+        return Ok(Vec::new());
+    }
+    let input: String = objects
+        .iter()
+        .map(|object| format!("{}\n", object))
+        .collect();
+    let stdout = cat_file_batch("--batch", input.into_bytes())?;
+    // Each object is `<oid> <type> <size>\n<content>\n`, or a
+    // `<object> missing` line.
+    let mut blobs = Vec::with_capacity(objects.len());
+    let mut rest = stdout.as_slice();
+    while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+        let header = String::from_utf8_lossy(&rest[..end]).into_owned();
+        rest = &rest[end + 1..];
+        // The last field is `missing` or `ambiguous` for an unreadable one.
+        let size = header
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse::<usize>().ok());
+        match size {
+            Some(size) if size < rest.len() => {
+                blobs.push(Some(rest[..size].to_vec()));
+                rest = &rest[size + 1..];
+            }
+            _ => blobs.push(None),
+        }
+    }
+    Ok(blobs)
+}
+
+/// The full hash of the commit each of `revs` names, or `None` for one
+/// that is missing, ambiguous or not a commit.
+pub fn resolve_commits(revs: &[String]) -> Result<Vec<Option<String>>> {
+    if revs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let input: String = revs
+        .iter()
+        .map(|rev| format!("{}^{{commit}}\n", rev))
+        .collect();
+    let stdout = cat_file_batch("--batch-check=%(objectname)", input.into_bytes())?;
+    // A resolved one is its hash alone; others are `<rev> missing` or
+    // `<rev> ambiguous`.
+    Ok(String::from_utf8_lossy(&stdout)
+        .lines()
+        .map(|line| (!line.contains(' ')).then(|| line.to_string()))
+        .collect())
+}
+
+/// Runs `git cat-file <batch_arg>` over `input` and returns its output.
+fn cat_file_batch(batch_arg: &str, input: Vec<u8>) -> Result<Vec<u8>> {
+    let batch_args = ["cat-file", batch_arg];
+    let mut child = Command::new("git")
+        .args(batch_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run `{}`", command_line(&batch_args)))?;
+    // Feed stdin from a thread so a large object list cannot fill the pipe
+    // while cat-file is blocked writing its own output.
+    let mut stdin = child.stdin.take().context("open cat-file stdin")?;
+    let feeder = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output().context("git cat-file")?;
+    feeder
+        .join()
+        .map_err(|_| anyhow::anyhow!("cat-file feeder thread panicked"))?
+        .context("write to cat-file")?;
     if !output.status.success() {
-        bail!("Failed to read git status");
+        return Err(failed(&batch_args, &output));
     }
-    Ok(String::from_utf8_lossy(&output.stdout).trim().is_empty())
+    Ok(output.stdout)
 }
 
-pub fn has_upstream() -> Result<bool> {
-    let output = Command::new("git")
-        .args(["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"])
-        .output()
-        .context("git upstream")?;
-    Ok(output.status.success())
+/// (path, size in bytes) of each blob in `blobs`, given as (hash, path).
+pub fn blob_sizes(blobs: &[(String, String)]) -> Result<Vec<(String, u64)>> {
+    if blobs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let input: String = blobs
+        .iter()
+        .map(|(hash, path)| format!("{} {}\n", hash, path))
+        .collect();
+    let stdout = batch_check("%(objectsize) %(rest)", input.into_bytes())?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let (size, path) = line.split_once(' ')?;
+            Some((path.to_string(), size.parse().ok()?))
+        })
+        .collect())
 }
 
-pub fn hooks_dir() -> Result<PathBuf> {
-    let output = Command::new("git")
-        .args(["rev-parse", "--git-dir"])
-        .output()
-        .context("git rev-parse --git-dir")?;
+/// (hash, path) of blobs added or modified, from `diff --raw -z` output.
+fn parse_raw_blobs(raw: &[u8]) -> Vec<(String, String)> {
+    let raw = String::from_utf8_lossy(raw);
+    let mut fields = raw.split('\0');
+    let mut blobs = Vec::new();
+    while let (Some(meta), Some(path)) = (fields.next(), fields.next()) {
+        // :<old mode> <new mode> <old hash> <new hash> <status>
+        let Some(new_hash) = meta.split(' ').nth(3) else {
+            break;
+        };
+        // Gitlinks (submodules) are commits, not blobs.
+        if !meta
+            .split(' ')
+            .nth(1)
+            .is_some_and(|mode| mode.starts_with("160"))
+        {
+            blobs.push((new_hash.to_string(), path.to_string()));
+        }
+    }
+    blobs
+}
+
+/// Blobs the next commit adds or modifies, as (hash, path).
+pub fn staged_blobs() -> Result<Vec<(String, String)>> {
+    let output = git(&[
+        "diff",
+        "--cached",
+        "--raw",
+        "-z",
+        "--no-abbrev",
+        "--no-renames",
+        "--diff-filter=AM",
+    ])?;
+    Ok(parse_raw_blobs(&output.stdout))
+}
+
+/// Blobs a commit adds or modifies relative to its first parent, as (hash, path).
+pub fn commit_blobs(rev: &str) -> Result<Vec<(String, String)>> {
+    let output = git(&[
+        "diff-tree",
+        "-r",
+        "-z",
+        "--root",
+        "--no-commit-id",
+        "--no-abbrev",
+        "--diff-filter=AM",
+        rev,
+    ])?;
+    Ok(parse_raw_blobs(&output.stdout))
+}
+
+/// Objects a commit introduces that are not present locally, i.e. that a
+/// partial clone filtered out. Listing them does not fetch them.
+pub fn missing_objects(rev: &str) -> Result<Vec<String>> {
+    let parents = format!("{}^@", rev);
+    let stdout = git_stdout(&[
+        "rev-list",
+        "--objects",
+        "--missing=print",
+        "--no-object-names",
+        rev,
+        "--not",
+        &parents,
+    ])?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix('?'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// (hash, `%G?` signature code) of each commit in `hashes`, in order.
+pub fn signature_codes(hashes: &[String]) -> Result<Vec<(String, String)>> {
+    if hashes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut args = vec!["log", "--no-walk=unsorted", "--pretty=format:%H %G?"];
+    args.extend(hashes.iter().map(String::as_str));
+    let stdout = git_stdout(&args)?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let (hash, code) = line.split_once(' ')?;
+            Some((hash.to_string(), code.to_string()))
+        })
+        .collect())
+}
+
+/// (hash, author email) of each commit in `hashes`, in order.
+pub fn author_emails(hashes: &[String]) -> Result<Vec<(String, String)>> {
+    if hashes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut args = vec!["log", "--no-walk=unsorted", "--pretty=format:%H %ae"];
+    args.extend(hashes.iter().map(String::as_str));
+    let stdout = git_stdout(&args)?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let (hash, email) = line.split_once(' ')?;
+            Some((hash.to_string(), email.to_string()))
+        })
+        .collect())
+}
+
+/// (hash, author time) of each commit in `hashes`, in order, in the
+/// author's time zone as `<ISO weekday> <HH:MM> <offset>`.
+pub fn author_local_times(hashes: &[String]) -> Result<Vec<(String, String)>> {
+    if hashes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut args = vec![
+        "log",
+        "--no-walk=unsorted",
+        "--date=format:%u %H:%M %z",
+        "--pretty=format:%H %ad",
+    ];
+    args.extend(hashes.iter().map(String::as_str));
+    let stdout = git_stdout(&args)?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let (hash, time) = line.split_once(' ')?;
+            Some((hash.to_string(), time.to_string()))
+        })
+        .collect())
+}
+
+/// Merge commits (more than one parent) among `hashes`.
+pub fn merge_commits_among(hashes: &[String]) -> Result<Vec<String>> {
+    if hashes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut args = vec!["log", "--no-walk=unsorted", "--pretty=format:%H %P"];
+    args.extend(hashes.iter().map(String::as_str));
+    let stdout = git_stdout(&args)?;
+    Ok(stdout
+        .lines()
+        .filter(|line| line.split(' ').count() > 2)
+        .filter_map(|line| line.split(' ').next().map(str::to_string))
+        .collect())
+}
+
+/// Attribute values for `paths` as seen by the index, as (path, attr, value).
+pub fn check_attr_cached(
+    attrs: &[&str],
+    paths: &[String],
+) -> Result<Vec<(String, String, String)>> {
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut args = vec!["check-attr", "--cached"];
+    args.extend(attrs);
+    args.push("--");
+    args.extend(paths.iter().map(String::as_str));
+    let stdout = git_stdout(&args)?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.rsplitn(3, ": ");
+            let value = parts.next()?.to_string();
+            let attr = parts.next()?.to_string();
+            let path = parts.next()?.to_string();
+            Some((path, attr, value))
+        })
+        .collect())
+}
+
+/// Staged files git considers binary (numstat reports `-` for their line counts).
+pub fn staged_binary_files() -> Result<Vec<String>> {
+    let stdout = git_stdout(&["diff", "--cached", "--numstat", "--no-textconv"])?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let added = parts.next()?;
+            let deleted = parts.next()?;
+            let path = parts.next()?;
+            (added == "-" && deleted == "-").then(|| path.to_string())
+        })
+        .collect())
+}
+
+/// Files a commit adds that git considers binary.
+pub fn commit_added_binary_files(rev: &str) -> Result<Vec<String>> {
+    let stdout = git_stdout(&[
+        "diff-tree",
+        "-r",
+        "--root",
+        "--no-commit-id",
+        "--numstat",
+        "--no-textconv",
+        "--diff-filter=A",
+        rev,
+    ])?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("-\t-\t").map(str::to_string))
+        .collect())
+}
+
+/// Zero-context patch of a commit against its first parent, for scanning
+/// added lines.
+pub fn commit_diff(rev: &str) -> Result<String> {
+    git_stdout(&[
+        "diff-tree",
+        "-p",
+        "-U0",
+        "--root",
+        "--no-commit-id",
+        "--no-color",
+        "--no-ext-diff",
+        "--no-textconv",
+        SRC_PREFIX,
+        DST_PREFIX,
+        rev,
+    ])
+}
+
+/// Commits reachable from `tips` but not from `exclude` or any
+/// remote-tracking ref of `remote`, i.e. what a push would send.
+pub fn outgoing_commits(tips: &[String], exclude: &[String], remote: &str) -> Result<Vec<String>> {
+    if tips.is_empty() {
+        return Ok(Vec::new());
+    }
+    let remotes = format!("--remotes={}", remote);
+    let mut args = vec!["rev-list"];
+    args.extend(tips.iter().map(String::as_str));
+    args.push("--not");
+    args.extend(exclude.iter().map(String::as_str));
+    args.push(&remotes);
+    let stdout = git_stdout(&args)?;
+    Ok(stdout.lines().map(str::to_string).collect())
+}
+
+/// Blobs reachable from `tips` but not from `exclude` or any remote-tracking
+/// ref of `remote`, as (hash, path): every file version a push would send,
+/// whichever commit introduced it. Blobs a partial clone filtered out are
+/// skipped rather than downloaded.
+pub fn outgoing_blobs(
+    tips: &[String],
+    exclude: &[String],
+    remote: &str,
+) -> Result<Vec<(String, String)>> {
+    if tips.is_empty() {
+        return Ok(Vec::new());
+    }
+    let remotes = format!("--remotes={}", remote);
+    let mut args = vec!["rev-list", "--objects", "--missing=allow-promisor"];
+    args.extend(tips.iter().map(String::as_str));
+    args.push("--not");
+    args.extend(exclude.iter().map(String::as_str));
+    args.push(&remotes);
+    let objects = git(&args)?;
+    let stdout = batch_check("%(objecttype) %(objectname) %(rest)", objects.stdout)?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            if parts.next()? != "blob" {
+                return None;
+            }
+            let hash = parts.next()?.to_string();
+            Some((hash, parts.next().unwrap_or_default().to_string()))
+        })
+        .collect())
+}
+
+/// The oldest commit among those [`outgoing_blobs`] walks whose diff
+/// against a parent, merges included, adds `blob`.
+pub fn introducing_commit(
+    blob: &str,
+    tips: &[String],
+    exclude: &[String],
+    remote: &str,
+) -> Result<Option<String>> {
+    let find = format!("--find-object={}", blob);
+    let remotes = format!("--remotes={}", remote);
+    let mut args = vec!["log", "-m", "--format=%H", &find];
+    args.extend(tips.iter().map(String::as_str));
+    args.push("--not");
+    args.extend(exclude.iter().map(String::as_str));
+    args.push(&remotes);
+    let stdout = git_stdout(&args)?;
+    Ok(stdout.lines().last().map(str::to_string))
+}
+
+/// (behind, ahead): commits only on `base`, and only on `HEAD`.
+pub fn ahead_behind(base: &str) -> Result<(usize, usize)> {
+    let range = format!("{}...HEAD", base);
+    let stdout = git_stdout(&["rev-list", "--left-right", "--count", &range])?;
+    let mut counts = stdout.split_whitespace().map(|n| n.parse().unwrap_or(0));
+    Ok((counts.next().unwrap_or(0), counts.next().unwrap_or(0)))
+}
+
+/// Unix time the oldest commit on `HEAD` but not on `base` was authored;
+/// `None` when there is no such commit.
+pub fn first_unique_commit_time(base: &str) -> Result<Option<u64>> {
+    let range = format!("{}..HEAD", base);
+    let stdout = git_stdout(&["log", "--format=%at", &range])?;
+    Ok(stdout.lines().filter_map(|line| line.parse().ok()).min())
+}
+
+/// Commits on one side of `@{upstream}...HEAD`, newest first: the
+/// upstream's with `upstream_side`, else the local ones. Each is (hash,
+/// subject, whether the other side has a commit with the same patch).
+pub fn upstream_side_commits(upstream_side: bool) -> Result<Vec<(String, String, bool)>> {
+    let side = if upstream_side {
+        "--left-only"
+    } else {
+        "--right-only"
+    };
+    let stdout = git_stdout(&[
+        "log",
+        side,
+        "--cherry-mark",
+        "--format=%m%x09%H%x09%s",
+        "@{upstream}...HEAD",
+    ])?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let mark = fields.next()?;
+            let hash = fields.next()?.to_string();
+            let subject = fields.next().unwrap_or_default().to_string();
+            Some((hash, subject, mark == "="))
+        })
+        .collect())
+}
+
+/// Short name of the upstream, e.g. `origin/feat/login`.
+pub fn upstream_name() -> Result<String> {
+    Ok(git_stdout(&["rev-parse", "--abbrev-ref", "@{upstream}"])?
+        .trim()
+        .to_string())
+}
+
+/// Merge commits in `range`, newest first.
+pub fn merge_commits(range: &str) -> Result<Vec<String>> {
+    let stdout = git_stdout(&["rev-list", "--merges", range])?;
+    Ok(stdout.lines().map(str::to_string).collect())
+}
+
+/// Best common ancestors of `a` and `b`; empty for unrelated histories.
+pub fn merge_bases(a: &str, b: &str) -> Result<Vec<String>> {
+    // Exit status 1 just means there is no common ancestor.
+    let output = git_unchecked(&["merge-base", "--all", a, b])?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Paths that differ between two revisions.
+pub fn changed_paths(from: &str, to: &str) -> Result<Vec<String>> {
+    let stdout = git_stdout(&["diff", "--name-only", "--no-renames", from, to])?;
+    Ok(stdout.lines().map(str::to_string).collect())
+}
+
+/// (files, lines) changed between the merge base of `base` and `HEAD`, and
+/// `HEAD`; i.e. what a pull request against `base` would show.
+pub fn diff_size(base: &str) -> Result<(usize, usize)> {
+    let range = format!("{}...HEAD", base);
+    Ok(parse_numstat(&git_stdout(&[
+        "diff",
+        "--numstat",
+        "--no-renames",
+        "--no-textconv",
+        &range,
+    ])?))
+}
+
+/// Files changed in the same range as [`diff_size`], comparing trees only so
+/// no blob is read.
+pub fn diff_files(base: &str) -> Result<usize> {
+    let range = format!("{}...HEAD", base);
+    let stdout = git_stdout(&["diff", "--name-only", "--no-renames", &range])?;
+    Ok(stdout.lines().count())
+}
+
+/// (files, added + deleted lines) from `--numstat` output; binary files
+/// (`-` counts) add to the file count only.
+fn parse_numstat(stdout: &str) -> (usize, usize) {
+    stdout
+        .lines()
+        .filter(|line| !line.is_empty())
+        .fold((0, 0), |(files, lines), line| {
+            let mut counts = line.split('\t').take(2).map(|n| n.parse().unwrap_or(0));
+            let changed: usize = counts.by_ref().sum();
+            (files + 1, lines + changed)
+        })
+}
+
+/// Seconds since the last fetch, from the mtime of `FETCH_HEAD`, or `None`
+/// if this clone has never fetched.
+pub fn last_fetch_age_secs() -> Result<Option<u64>> {
+    let fetch_head = git_dir()?.join("FETCH_HEAD");
+    let Ok(meta) = std::fs::metadata(&fetch_head) else {
+        return Ok(None);
+    };
+    let modified = meta
+        .modified()
+        .with_context(|| format!("mtime of {}", fetch_head.display()))?;
+    Ok(Some(
+        modified.elapsed().map(|d| d.as_secs()).unwrap_or_default(),
+    ))
+}
+
+/// (full ref name, committer date as unix seconds) of local branches, plus
+/// remote-tracking branches with `remotes`. Symbolic refs such as
+/// `origin/HEAD` are skipped.
+pub fn branches(remotes: bool) -> Result<Vec<(String, u64)>> {
+    let mut args = vec![
+        "for-each-ref",
+        "--format=%(refname)%00%(committerdate:unix)%00%(symref)",
+        "refs/heads",
+    ];
+    if remotes {
+        args.push("refs/remotes");
+    }
+    let stdout = git_stdout(&args)?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let refname = fields.next()?;
+            let date = fields.next()?.parse().ok()?;
+            let symref = fields.next().unwrap_or_default();
+            symref.is_empty().then(|| (refname.to_string(), date))
+        })
+        .collect())
+}
+
+/// Remote-tracking branches of `remote` as (name without the remote prefix,
+/// last committer, committer date as a unix timestamp); `HEAD` is left out.
+pub fn remote_branches(remote: &str) -> Result<Vec<(String, String, u64)>> {
+    let prefix = format!("refs/remotes/{}/", remote);
+    let stdout = git_stdout(&[
+        "for-each-ref",
+        "--format=%(refname)%00%(committername)%00%(committerdate:unix)%00%(symref)",
+        &prefix,
+    ])?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let name = fields.next()?.strip_prefix(&prefix)?;
+            let committer = fields.next()?;
+            let date = fields.next()?.parse().ok()?;
+            let symref = fields.next().unwrap_or_default();
+            symref
+                .is_empty()
+                .then(|| (name.to_string(), committer.to_string(), date))
+        })
+        .collect())
+}
+
+/// Full ref names of the branches (as in [`branches`]) merged into `base`.
+pub fn merged_branches(base: &str, remotes: bool) -> Result<Vec<String>> {
+    let merged = format!("--merged={}", base);
+    let mut args = vec!["for-each-ref", &merged, "--format=%(refname)", "refs/heads"];
+    if remotes {
+        args.push("refs/remotes");
+    }
+    Ok(git_stdout(&args)?.lines().map(str::to_string).collect())
+}
+
+/// Remote tracked by `branch`, defaulting to `origin`.
+pub fn tracking_remote(branch: &str) -> String {
+    config_get(&format!("branch.{}.remote", branch))
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "origin".to_string())
+}
+
+/// Default branch of `remote` as `<remote>/<branch>`, from its `HEAD` ref.
+pub fn remote_head(remote: &str) -> Result<Option<String>> {
+    let head = format!("refs/remotes/{}/HEAD", remote);
+    let output = git_unchecked(&["symbolic-ref", "--quiet", "--short", &head])?;
     if !output.status.success() {
-        bail!("Not a git repository");
+        return Ok(None);
     }
-    let git_dir = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    Ok(PathBuf::from(git_dir).join("hooks"))
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
 }
 
-pub fn staged_files() -> Result<Vec<String>> {
+/// Paths that would conflict when merging `ours` and `theirs`, computed in
+/// memory without touching the worktree or index. Empty for a clean merge.
+pub fn merge_tree(ours: &str, theirs: &str) -> Result<Vec<String>> {
+    let args = [
+        "merge-tree",
+        "--write-tree",
+        "--name-only",
+        "--no-messages",
+        ours,
+        theirs,
+    ];
+    let output = git_unchecked(&args)?;
+    // Exit status 1 means the merge has conflicts; anything else is a failure.
+    match output.status.code() {
+        Some(0) => Ok(Vec::new()),
+        Some(1) => Ok(parse_merge_tree(&String::from_utf8_lossy(&output.stdout))),
+        _ => Err(failed(&args, &output)),
+    }
+}
+
+/// Conflicted paths from `merge-tree --name-only --no-messages` output: the
+/// resulting tree on the first line, then one line per conflicted path.
+fn parse_merge_tree(stdout: &str) -> Vec<String> {
+    let mut paths: Vec<String> = stdout
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    paths.dedup();
+    paths
+}
+
+pub fn fetch(remote: &str) -> Result<()> {
+    git_forwarding(&["fetch", remote])
+}
+
+/// Like [`fetch`], also dropping the remote-tracking branches of branches
+/// deleted on `remote`.
+pub fn fetch_prune(remote: &str) -> Result<()> {
+    git_forwarding(&["fetch", "--prune", remote])
+}
+
+/// Latest HEAD reflog entry as (abbreviated hash, selector, subject).
+pub fn head_reflog_entry() -> Result<Option<(String, String, String)>> {
+    let output = git_unchecked(&["reflog", "-1", "--format=%h:::%gd:::%gs"])?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().next().and_then(|line| {
+        let mut parts = line.splitn(3, ":::");
+        Some((
+            parts.next()?.to_string(),
+            parts.next()?.to_string(),
+            parts.next()?.to_string(),
+        ))
+    }))
+}
+
+/// Full commit message (subject, body and trailers).
+pub fn commit_message(rev: &str) -> Result<String> {
+    git_stdout(&["log", "-1", "--pretty=format:%B", rev])
+}
+
+/// Status letter (`A`, `M`, `D`...) and path of each file a commit changed.
+pub fn commit_changes(rev: &str) -> Result<Vec<(String, String)>> {
+    let stdout = git_stdout(&["show", "--name-status", "--pretty=format:", rev])?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(status, path)| (status.to_string(), path.to_string()))
+        .collect())
+}
+
+/// Whether any remote-tracking ref already contains `rev`.
+pub fn is_pushed(rev: &str) -> Result<bool> {
+    let stdout = git_stdout(&[
+        "for-each-ref",
+        "--format=%(refname)",
+        "--contains",
+        rev,
+        "refs/remotes",
+    ])?;
+    Ok(!stdout.trim().is_empty())
+}
+
+pub fn is_ancestor(ancestor: &str, rev: &str) -> Result<bool> {
+    let args = ["merge-base", "--is-ancestor", ancestor, rev];
+    let output = git_unchecked(&args)?;
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => Err(failed(&args, &output)),
+    }
+}
+
+/// Commits after `base` up to `HEAD` (all of history when `None`), oldest
+/// first, each with its number of parents.
+pub fn commits_since(base: Option<&str>) -> Result<Vec<(String, usize)>> {
+    let range = base.map(|b| format!("{}..HEAD", b));
+    let mut args = vec!["rev-list", "--reverse", "--parents"];
+    args.push(range.as_deref().unwrap_or("HEAD"));
+    let stdout = git_stdout(&args)?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let mut ids = line.split_whitespace();
+            let hash = ids.next()?.to_string();
+            Some((hash, ids.count()))
+        })
+        .collect())
+}
+
+/// Runs `git rebase -i` onto `base` (from the root when `None`) with the todo
+/// list replaced by `todo`. A failed rebase is aborted so the branch is left
+/// as it was.
+pub fn rebase_with_todo(base: Option<&str>, todo: &Path) -> Result<()> {
+    let todo = todo.to_string_lossy().replace('\'', "'\\''");
+    let args = ["rebase", "-i", base.unwrap_or("--root")];
     let output = Command::new("git")
-        .args(["diff", "--cached", "--name-only"])
+        .args(args)
+        .env("GIT_SEQUENCE_EDITOR", format!("cp '{}'", todo))
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::piped())
         .output()
-        .context("git diff --cached")?;
+        .with_context(|| {
+            format!(
+                "failed to run `{}` (is git installed?)",
+                command_line(&args)
+            )
+        })?;
+    if !output.status.success() {
+        let _ = git_unchecked(&["rebase", "--abort"]);
+        return Err(failed(&args, &output));
+    }
+    Ok(())
+}
+
+/// Password stored for `https://<host>` by the configured credential helpers,
+/// without ever prompting.
+pub fn credential_fill(host: &str) -> Result<Option<String>> {
+    let args = ["-c", "core.askPass=", "credential", "fill"];
+    let mut child = Command::new("git")
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env_remove("GIT_ASKPASS")
+        .env_remove("SSH_ASKPASS")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run `{}`", command_line(&args)))?;
+    let mut stdin = child.stdin.take().context("open credential stdin")?;
+    stdin.write_all(format!("protocol=https\nhost={}\n\n", host).as_bytes())?;
+    drop(stdin);
+    let output = child.wait_with_output().context("git credential fill")?;
+    // Fails when no helper knows the host and prompting is disabled.
     if !output.status.success() {
-        bail!("Failed to list staged files");
+        return Ok(None);
     }
     let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(stdout.lines().map(|l| l.to_string()).collect())
+    Ok(stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .filter(|p| !p.is_empty())
+        .map(str::to_string))
 }
 
-pub fn push_set_upstream(branch: &str) -> Result<()> {
-    let status = Command::new("git")
-        .args(["push", "-u", "origin", branch])
-        .status()
-        .context("git push -u origin")?;
-    if !status.success() {
-        bail!("Failed to push and set upstream for branch '{}'", branch);
+/// Fails unless `remote` answers `git ls-remote` within `timeout`. Never
+/// prompts for credentials, so a remote that needs them fails instead.
+pub fn probe_remote(remote: &str, timeout: Duration) -> Result<()> {
+    let args = [
+        "-c",
+        "core.askPass=",
+        "ls-remote",
+        "--quiet",
+        remote,
+        "HEAD",
+    ];
+    let mut command = Command::new("git");
+    if let Some(ssh) = batch_ssh_command() {
+        command.env("GIT_SSH_COMMAND", ssh);
+    }
+    let mut child = command
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env_remove("GIT_ASKPASS")
+        .env_remove("SSH_ASKPASS")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run `{}`", command_line(&args)))?;
+    let started = Instant::now();
+    while child.try_wait()?.is_none() {
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!(
+                "`{}` timed out after {}s",
+                command_line(&args),
+                timeout.as_secs()
+            );
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(failed(&args, &output));
     }
     Ok(())
 }
+
+/// The ssh command git would use (`GIT_SSH_COMMAND`, then
+/// `core.sshCommand`, then `ssh`) with `-o BatchMode=yes`, so ssh fails
+/// instead of asking for a passphrase or to trust a host key on the
+/// terminal. `None` when `GIT_SSH` names a program that may not be OpenSSH.
+fn batch_ssh_command() -> Option<String> {
+    let ssh = std::env::var("GIT_SSH_COMMAND")
+        .ok()
+        .filter(|ssh| !ssh.is_empty())
+        .or_else(|| config_get("core.sshCommand").ok().flatten());
+    if ssh.is_none() && std::env::var_os("GIT_SSH").is_some() {
+        return None;
+    }
+    Some(format!(
+        "{} -o BatchMode=yes",
+        ssh.as_deref().unwrap_or("ssh")
+    ))
+}
+
+pub fn remote_url(remote: &str) -> Result<Option<String>> {
+    config_get(&format!("remote.{}.url", remote))
+}
+
+/// Host and `owner/repo` path of a remote URL, for SSH and HTTP(S) forms.
+pub fn parse_remote_url(url: &str) -> Option<(String, String)> {
+    let url = url.trim();
+    let rest = if let Some((_, rest)) = url.split_once("://") {
+        rest.split_once('@').map(|(_, r)| r).unwrap_or(rest)
+    } else {
+        // scp-like syntax: git@host:owner/repo.git
+        let (_, rest) = url.split_once('@')?;
+        return rest.split_once(':').and_then(|(host, path)| {
+            let path = path.trim_end_matches('/').trim_end_matches(".git");
+            (!path.is_empty()).then(|| (host.to_string(), path.to_string()))
+        });
+    };
+    let (host, path) = rest.split_once('/')?;
+    let host = host.split(':').next()?.to_string();
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    if path.is_empty() {
+        return None;
+    }
+    Some((host, path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_raw_diff_blobs() {
+        let raw = format!(
+            ":000000 100644 {z} {a} A\0big.bin\0:100644 100644 {a} {b} M\0src/x.rs\0\
+             :000000 160000 {z} {c} A\0vendor/sub\0",
+            z = "0".repeat(40),
+            a = "a".repeat(40),
+            b = "b".repeat(40),
+            c = "c".repeat(40),
+        );
+        assert_eq!(
+            parse_raw_blobs(raw.as_bytes()),
+            vec![
+                ("a".repeat(40), "big.bin".to_string()),
+                ("b".repeat(40), "src/x.rs".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_name_status_with_renames() {
+        let stdout = "R100\0.env\0app.conf\0M\0src/a.rs\0C75\0a.pem\0b.pem\0D\0old.rs\0";
+        let changes = parse_name_status(stdout);
+        let summary: Vec<(ChangeKind, &str, Option<&str>)> = changes
+            .iter()
+            .map(|c| (c.kind, c.path.as_str(), c.from.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ChangeKind::Renamed, "app.conf", Some(".env")),
+                (ChangeKind::Modified, "src/a.rs", None),
+                (ChangeKind::Copied, "b.pem", Some("a.pem")),
+                (ChangeKind::Deleted, "old.rs", None),
+            ]
+        );
+        let touched: Vec<&str> = changes.iter().flat_map(StagedChange::touched).collect();
+        assert_eq!(
+            touched,
+            vec!["app.conf", ".env", "src/a.rs", "b.pem", "old.rs"]
+        );
+    }
+
+    #[test]
+    fn sums_numstat_lines_and_files() {
+        let stdout = "10\t2\tsrc/a.rs\n-\t-\tlogo.png\n0\t5\tREADME.md\n";
+        assert_eq!(parse_numstat(stdout), (3, 17));
+        assert_eq!(parse_numstat(""), (0, 0));
+    }
+
+    #[test]
+    fn parses_merge_tree_conflicts() {
+        let stdout = format!("{}\nsrc/a.rs\nsrc/a.rs\nREADME.md\n", "d".repeat(40));
+        assert_eq!(parse_merge_tree(&stdout), vec!["src/a.rs", "README.md"]);
+        assert!(parse_merge_tree(&"d".repeat(40)).is_empty());
+    }
+
+    #[test]
+    fn command_line_quotes_arguments_with_spaces() {
+        assert_eq!(
+            command_line(&["log", "--pretty=format:%H %s", "-n1"]),
+            "git log '--pretty=format:%H %s' -n1"
+        );
+    }
+
+    #[test]
+    fn parses_scp_like_remote() {
+        assert_eq!(
+            parse_remote_url("git@github.com:acme/widgets.git"),
+            Some(("github.com".into(), "acme/widgets".into()))
+        );
+    }
+
+    #[test]
+    fn parses_https_remote() {
+        assert_eq!(
+            parse_remote_url("https://gitlab.com/group/sub/proj.git"),
+            Some(("gitlab.com".into(), "group/sub/proj".into()))
+        );
+        assert_eq!(
+            parse_remote_url("https://token@github.com/acme/widgets"),
+            Some(("github.com".into(), "acme/widgets".into()))
+        );
+    }
+
+    #[test]
+    fn parses_ssh_url_with_port() {
+        assert_eq!(
+            parse_remote_url("ssh://git@git.example.com:2222/team/app.git"),
+            Some(("git.example.com".into(), "team/app".into()))
+        );
+    }
+
+    #[test]
+    fn rejects_local_paths() {
+        assert_eq!(parse_remote_url("/srv/repos/app.git"), None);
+    }
+}
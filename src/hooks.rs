@@ -1,18 +1,94 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
 use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
+use crate::cli::SimulatedHook;
+use crate::config::HooksConfig;
+use crate::error::ExitStatus;
 use crate::git;
+use crate::providers::{Client, Fetch, Provider};
+
+/// Branch protection rarely changes; refresh it at most hourly.
+const PROTECTED_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
 
 const HOOK_MARKER: &str = "# git-sherpa";
 
+/// Bump whenever a generated hook changes, so `check` can tell that the
+/// installed copies predate this binary. Hooks written before versions were
+/// stamped count as version 0.
+pub(crate) const HOOK_TEMPLATE_VERSION: u32 = 6;
+
+const HOOK_NAMES: [&str; 4] = ["pre-commit", "prepare-commit-msg", "commit-msg", "pre-push"];
+
+/// `hooks install --chain` writes the hooks here and points `core.hooksPath`
+/// at it, relative to the worktree root.
+const CHAINED_HOOKS_DIR: &str = ".gitsherpa/hooks";
+
+/// Where `hooks install --chain` remembers the `core.hooksPath` it replaced,
+/// for `hooks uninstall` to restore.
+const PREVIOUS_HOOKS_PATH_KEY: &str = "git-sherpa.previousHooksPath";
+
+/// Names the hook a chained wrapper runs first, so `hooks update` keeps it.
+const CHAIN_MARKER: &str = "# git-sherpa chains: ";
+
+/// First line after the shebang of every generated hook.
+fn marker() -> String {
+    format!("{} template-version={}", HOOK_MARKER, HOOK_TEMPLATE_VERSION)
+}
+
+/// Template version of an installed hook; `None` if git-sherpa did not write it.
+fn installed_version(content: &str) -> Option<u32> {
+    let line = content.lines().find(|l| l.starts_with(HOOK_MARKER))?;
+    Some(
+        line.split_once("template-version=")
+            .and_then(|(_, version)| version.trim().parse().ok())
+            .unwrap_or(0),
+    )
+}
+
+/// Object id git passes to hooks for refs that do not exist yet.
+const ZERO_OID: &str = "0000000000000000000000000000000000000000";
+
 pub(crate) fn hook_content() -> String {
-    format!("#!/bin/sh\n{}\nexec git-sherpa check\n", HOOK_MARKER)
+    format!(
+        "#!/bin/sh\n{}\nexec git-sherpa check --staged-only --quiet-on-success\n",
+        marker()
+    )
+}
+
+/// Fills the placeholders of the commit template before the editor opens.
+/// Runs even with `--no-verify`, so messages given with `-m`, merges and
+/// amends return before starting git-sherpa at all, and a failure, e.g. on
+/// a branch without a config, never blocks the commit.
+pub(crate) fn prepare_commit_msg_hook_content() -> String {
+    format!(
+        r#"#!/bin/sh
+{}
+case "$2" in
+    ""|template) git-sherpa prepare-message "$@" || true ;;
+esac
+exit 0
+"#,
+        marker()
+    )
+}
+
+/// Lints the message being written before the commit is created.
+pub(crate) fn commit_msg_hook_content() -> String {
+    format!(
+        "#!/bin/sh\n{}\nexec git-sherpa lint-message --quiet-on-success \"$1\"\n",
+        marker()
+    )
 }
 
-pub(crate) fn pre_push_hook_content(protected_branches: &[String]) -> String {
+pub(crate) fn pre_push_hook_content(protected_branches: &[String], config: &HooksConfig) -> String {
     let branches_list = protected_branches.join("|");
     format!(
         r#"#!/bin/sh
@@ -37,53 +113,439 @@ case "$current_branch" in
         ;;
 esac
 
-exec git-sherpa check
+# The pushed refs arrive once on stdin; the guards below share them.
+refs=$(cat)
+{tags}{size}
+printf '%s\n' "$refs" | git-sherpa check --pre-push "$1" --quiet-on-success
 "#,
-        marker = HOOK_MARKER,
+        marker = marker(),
         branches = branches_list,
+        tags = protected_tags_check(&config.protected_tags),
+        size = push_size_check(config.max_push_size_mb, config.max_push_objects),
+    )
+}
+
+/// Rejects deleting or moving an
+/// existing remote tag that matches `protected_tags`. Creating tags is allowed.
+fn protected_tags_check(protected_tags: &[String]) -> String {
+    if protected_tags.is_empty() {
+        return String::new();
+    }
+    format!(
+        r#"
+# Block deleting or moving protected tags
+while read -r local_ref local_sha remote_ref remote_sha; do
+    case "$remote_ref" in
+        refs/tags/*) tag=${{remote_ref#refs/tags/}} ;;
+        *) continue ;;
+    esac
+    case "$tag" in
+        {tags}) ;;
+        *) continue ;;
+    esac
+    case "$remote_sha" in
+        *[!0]*) ;;
+        *) continue ;;
+    esac
+    case "$local_sha" in
+        *[!0]*) ;;
+        *)
+            echo "git-sherpa: deleting protected tag '$tag' is blocked."
+            exit 1
+            ;;
+    esac
+    if [ "$local_sha" != "$remote_sha" ]; then
+        echo "git-sherpa: moving protected tag '$tag' is blocked."
+        exit 1
+    fi
+done <<EOF
+$refs
+EOF
+"#,
+        tags = protected_tags.join("|"),
     )
 }
 
-pub fn install_with_config(force: bool, protected_branches: &[String]) -> Result<()> {
+/// Rejects pushes whose new objects (those not already on a remote-tracking
+/// ref of the target remote) exceed the on-disk size or object count limits.
+fn push_size_check(max_size_mb: Option<u64>, max_objects: Option<u64>) -> String {
+    if max_size_mb.is_none() && max_objects.is_none() {
+        return String::new();
+    }
+    let mut script = String::from(
+        r#"
+# Block oversized pushes
+shas=$(printf '%s\n' "$refs" | while read -r local_ref local_sha remote_ref remote_sha; do
+    case "$local_sha" in
+        *[!0]*) echo "$local_sha" ;;
+    esac
+done)
+if [ -n "$shas" ]; then
+"#,
+    );
+    if let Some(mb) = max_size_mb {
+        script.push_str(&format!(
+            r#"    size=$(git rev-list --objects --disk-usage $shas --not --remotes="$1")
+    if [ "${{size:-0}}" -gt {limit} ]; then
+        echo "git-sherpa: push is $((size / 1048576)) MB, over the {mb} MB limit."
+        exit 1
+    fi
+"#,
+            limit = mb * 1024 * 1024,
+            mb = mb,
+        ));
+    }
+    if let Some(max) = max_objects {
+        script.push_str(&format!(
+            r#"    objects=$(git rev-list --objects $shas --not --remotes="$1" | wc -l)
+    if [ "$objects" -gt {max} ]; then
+        echo "git-sherpa: push has $objects objects, over the {max} object limit."
+        exit 1
+    fi
+"#,
+            max = max,
+        ));
+    }
+    script.push_str("fi\n");
+    script
+}
+
+/// Configured protected branches, plus those protected on the forge when
+/// `fetch_protected` is set. Forge patterns such as `release/*` are kept as-is
+/// since the pre-push hook matches with shell globs.
+pub fn protected_branches(config: &HooksConfig) -> Vec<String> {
+    let mut branches = config.protected_branches.clone();
+    if config.fetch_protected {
+        for branch in forge_protected_branches() {
+            if !branches.contains(&branch) {
+                branches.push(branch);
+            }
+        }
+    }
+    branches
+}
+
+fn forge_protected_branches() -> Vec<String> {
+    let Some((host, slug)) = git::remote_url("origin")
+        .ok()
+        .flatten()
+        .and_then(|url| git::parse_remote_url(&url))
+    else {
+        return Vec::new();
+    };
+    let Some(provider) = Provider::forge_for_host(&host) else {
+        return Vec::new();
+    };
+    let path = match provider {
+        Provider::GitLab { .. } => {
+            format!("/projects/{}/protected_branches", slug.replace('/', "%2F"))
+        }
+        _ => format!("/repos/{}/branches?protected=true", slug),
+    };
+    match Client::new(provider).get(&path, PROTECTED_CACHE_TTL) {
+        Fetch::Found(body) => branch_names(&body),
+        Fetch::NotFound | Fetch::Unavailable => {
+            eprintln!("Warning: could not fetch protected branches from {}", host);
+            Vec::new()
+        }
+    }
+}
+
+/// Names from a GitHub branches or GitLab protected-branches listing.
+fn branch_names(body: &serde_json::Value) -> Vec<String> {
+    body.as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item["name"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `path` quoted for `sh`.
+fn shell_quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', r"'\''"))
+}
+
+/// Previously active hook a chained wrapper runs first.
+fn chained_hook(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(CHAIN_MARKER))
+}
+
+/// `content` with the hook at `previous` run first, right after the marker
+/// line. A failing previous hook stops the wrapper with its exit code.
+/// pre-push reads the pushed refs once and hands them to both.
+fn chain(name: &str, content: &str, previous: Option<&str>) -> String {
+    let Some(previous) = previous else {
+        return content.to_string();
+    };
+    let quoted = shell_quote(previous);
+    let snippet = if name == "pre-push" {
+        format!(
+            r#"{marker}{previous}
+refs=$(cat)
+if [ -x {quoted} ]; then
+    printf '%s\n' "$refs" | {quoted} "$@" || exit $?
+fi
+exec <<SHERPA_REFS
+$refs
+SHERPA_REFS
+"#,
+            marker = CHAIN_MARKER,
+        )
+    } else {
+        format!(
+            r#"{marker}{previous}
+if [ -x {quoted} ]; then
+    {quoted} "$@" || exit $?
+fi
+"#,
+            marker = CHAIN_MARKER,
+        )
+    };
+    // Shebang and marker line first, as `installed_version` expects.
+    let mut lines = content.splitn(3, '\n');
+    let (shebang, marker, rest) = (
+        lines.next().unwrap_or_default(),
+        lines.next().unwrap_or_default(),
+        lines.next().unwrap_or_default(),
+    );
+    format!("{}\n{}\n{}{}", shebang, marker, snippet, rest)
+}
+
+/// Current contents of each hook, in [`HOOK_NAMES`] order.
+fn templates(config: &HooksConfig) -> [(&'static str, String); 4] {
+    [
+        ("pre-commit", hook_content()),
+        ("prepare-commit-msg", prepare_commit_msg_hook_content()),
+        ("commit-msg", commit_msg_hook_content()),
+        (
+            "pre-push",
+            pre_push_hook_content(&protected_branches(config), config),
+        ),
+    ]
+}
+
+/// Writes an executable hook. The new file is renamed into place, so a
+/// hook that is running right now (e.g. the one that ran `check`) keeps
+/// reading its old contents.
+fn write_hook(path: &Path, content: &str) -> Result<()> {
+    let tmp = path.with_extension("sherpa-tmp");
+    fs::write(&tmp, content).with_context(|| format!("write hook {}", tmp.display()))?;
+    #[cfg(unix)]
+    {
+        let perms = fs::Permissions::from_mode(0o755);
+        fs::set_permissions(&tmp, perms).with_context(|| format!("chmod {}", tmp.display()))?;
+    }
+    fs::rename(&tmp, path).with_context(|| format!("write hook {}", path.display()))
+}
+
+pub fn install_with_config(force: bool, config: &HooksConfig) -> Result<Vec<PathBuf>> {
     let hooks_dir = git::hooks_dir()?;
     fs::create_dir_all(&hooks_dir)?;
 
-    let pre_commit_content = hook_content();
-    let pre_push_content = pre_push_hook_content(protected_branches);
+    let mut installed = Vec::new();
+    for (name, content) in templates(config) {
+        let path = hooks_dir.join(name);
+        if path.exists() && !force {
+            eprintln!(
+                "Warning: {} already exists, skipping (use --force to overwrite)",
+                path.display()
+            );
+            continue;
+        }
+        write_hook(&path, &content)?;
+        println!("Installed {}", path.display());
+        installed.push(path);
+    }
+
+    Ok(installed)
+}
 
-    let hooks: [(&str, &str); 2] = [
-        ("pre-commit", &pre_commit_content),
-        ("pre-push", &pre_push_content),
-    ];
+/// Installs into [`CHAINED_HOOKS_DIR`] and points `core.hooksPath` at it.
+/// Each hook first runs the one that was active before (from `.git/hooks`
+/// or the previous `core.hooksPath`, e.g. husky's), so installing next to
+/// other hook managers neither clobbers nor skips their hooks.
+pub fn install_chained(force: bool, config: &HooksConfig) -> Result<Vec<PathBuf>> {
+    let toplevel = fs::canonicalize(git::toplevel()?)?;
+    let hooks_dir = toplevel.join(CHAINED_HOOKS_DIR);
+    let previous_dir = fs::canonicalize(git::hooks_dir()?).ok();
+    let reinstall = previous_dir.as_deref() == Some(hooks_dir.as_path());
+    fs::create_dir_all(&hooks_dir)?;
 
-    for (name, content) in &hooks {
+    let mut installed = Vec::new();
+    for (name, content) in templates(config) {
         let path = hooks_dir.join(name);
-        if path.exists() && !force {
+        let current = fs::read_to_string(&path).ok();
+        let ours = current
+            .as_deref()
+            .is_some_and(|c| installed_version(c).is_some());
+        if current.is_some() && !ours && !force {
             eprintln!(
                 "Warning: {} already exists, skipping (use --force to overwrite)",
                 path.display()
             );
             continue;
         }
-        fs::write(&path, content)
-            .with_context(|| format!("write hook {}", path.display()))?;
-        #[cfg(unix)]
-        {
-            let perms = fs::Permissions::from_mode(0o755);
-            fs::set_permissions(&path, perms)
-                .with_context(|| format!("chmod {}", path.display()))?;
+        let previous = if reinstall {
+            current
+                .as_deref()
+                .and_then(chained_hook)
+                .map(str::to_string)
+        } else {
+            previous_dir
+                .as_ref()
+                .map(|dir| dir.join(name))
+                .filter(|hook| {
+                    // Earlier git-sherpa hooks are replaced, not chained.
+                    fs::read_to_string(hook).is_ok_and(|c| installed_version(&c).is_none())
+                })
+                .map(|hook| match hook.strip_prefix(&toplevel) {
+                    Ok(relative) => relative.display().to_string(),
+                    Err(_) => hook.display().to_string(),
+                })
+        };
+        write_hook(&path, &chain(name, &content, previous.as_deref()))?;
+        match &previous {
+            Some(previous) => println!("Installed {} (runs {} first)", path.display(), previous),
+            None => println!("Installed {}", path.display()),
         }
-        println!("Installed {}", path.display());
+        installed.push(path);
     }
 
-    Ok(())
+    if !reinstall {
+        if let Some(previous) = git::config_get("core.hooksPath")? {
+            git::config_set(PREVIOUS_HOOKS_PATH_KEY, &previous)?;
+        }
+        git::config_set("core.hooksPath", CHAINED_HOOKS_DIR)?;
+        println!("Set core.hooksPath to {}", CHAINED_HOOKS_DIR);
+    }
+    Ok(installed)
+}
+
+/// Installed git-sherpa hooks whose template version is older than this
+/// binary's, as (name, installed version).
+pub fn outdated() -> Result<Vec<(&'static str, u32)>> {
+    let hooks_dir = git::hooks_dir()?;
+    Ok(HOOK_NAMES
+        .iter()
+        .filter_map(|name| {
+            let content = fs::read_to_string(hooks_dir.join(name)).ok()?;
+            let version = installed_version(&content)?;
+            (version < HOOK_TEMPLATE_VERSION).then_some((*name, version))
+        })
+        .collect())
+}
+
+/// How one of the hooks git-sherpa installs looks, for `doctor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookState {
+    Missing,
+    /// Written by something else, e.g. husky.
+    Foreign,
+    /// Ours, without the executable bit: git skips it.
+    NotExecutable,
+    /// Ours, from an older template version.
+    Outdated(u32),
+    Current,
+}
+
+/// Each hook git-sherpa installs, in [`HOOK_NAMES`] order, with its path.
+pub fn states() -> Result<Vec<(&'static str, PathBuf, HookState)>> {
+    let hooks_dir = git::hooks_dir()?;
+    Ok(HOOK_NAMES
+        .iter()
+        .map(|name| {
+            let path = hooks_dir.join(name);
+            let state = match fs::read_to_string(&path).ok() {
+                None => HookState::Missing,
+                Some(content) => match installed_version(&content) {
+                    None => HookState::Foreign,
+                    Some(_) if !is_executable(&path) => HookState::NotExecutable,
+                    Some(version) if version < HOOK_TEMPLATE_VERSION => {
+                        HookState::Outdated(version)
+                    }
+                    Some(_) => HookState::Current,
+                },
+            };
+            (*name, path, state)
+        })
+        .collect())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+/// Rewrites every installed git-sherpa hook from the current templates and
+/// config. Hooks git-sherpa did not write are left alone.
+pub fn update(config: &HooksConfig) -> Result<Vec<PathBuf>> {
+    let hooks_dir = git::hooks_dir()?;
+    let mut updated = Vec::new();
+    for (name, content) in templates(config) {
+        let path = hooks_dir.join(name);
+        let Ok(current) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if installed_version(&current).is_none() {
+            continue;
+        }
+        let content = chain(name, &content, chained_hook(&current));
+        if current == content {
+            continue;
+        }
+        write_hook(&path, &content)?;
+        updated.push(path);
+    }
+    Ok(updated)
+}
+
+/// Run by `check`: refreshes outdated hooks with `[hooks] auto_update`,
+/// otherwise warns. Never fails the check.
+pub fn refresh_on_check(config: &HooksConfig) {
+    let stale = match outdated() {
+        Ok(stale) if !stale.is_empty() => stale,
+        _ => return,
+    };
+    if config.auto_update {
+        // stdout carries the report, which may be JSON.
+        match update(config) {
+            Ok(_) => eprintln!(
+                "Refreshed git-sherpa hooks to template version {}",
+                HOOK_TEMPLATE_VERSION
+            ),
+            Err(err) => eprintln!("Warning: could not refresh hooks: {:#}", err),
+        }
+        return;
+    }
+    let names: Vec<String> = stale
+        .iter()
+        .map(|(name, version)| format!("{} (v{})", name, version))
+        .collect();
+    eprintln!(
+        "{}",
+        format!(
+            "Warning: installed git-sherpa hooks are older than this binary's templates (v{}): {}; run `git-sherpa hooks update`",
+            HOOK_TEMPLATE_VERSION,
+            names.join(", ")
+        )
+        .yellow()
+    );
 }
 
 pub fn uninstall() -> Result<()> {
     let hooks_dir = git::hooks_dir()?;
-    let hook_names = ["pre-commit", "pre-push"];
 
-    for name in &hook_names {
+    for name in &HOOK_NAMES {
         let path = hooks_dir.join(name);
         if !path.exists() {
             continue;
@@ -96,14 +558,139 @@ pub fn uninstall() -> Result<()> {
             );
             continue;
         }
-        fs::remove_file(&path)
-            .with_context(|| format!("remove hook {}", path.display()))?;
+        fs::remove_file(&path).with_context(|| format!("remove hook {}", path.display()))?;
         println!("Removed {}", path.display());
     }
 
+    // Undo `install --chain`: hand the hooks back to whoever had them.
+    if git::config_get("core.hooksPath")?.as_deref() == Some(CHAINED_HOOKS_DIR) {
+        match git::config_get(PREVIOUS_HOOKS_PATH_KEY)? {
+            Some(previous) => {
+                git::config_set("core.hooksPath", &previous)?;
+                git::config_unset(PREVIOUS_HOOKS_PATH_KEY)?;
+                println!("Restored core.hooksPath to {}", previous);
+            }
+            None => {
+                git::config_unset("core.hooksPath")?;
+                println!("Unset core.hooksPath");
+            }
+        }
+    }
+
     Ok(())
 }
 
+/// Arguments and stdin git would pass to a hook.
+#[derive(Debug, PartialEq, Eq)]
+pub struct HookInvocation {
+    pub args: Vec<String>,
+    pub stdin: String,
+}
+
+/// What git would pass to pre-push for `git push <remote> HEAD:<branch>`,
+/// where `to` is `<remote>/<branch>` (default: the current branch's remote
+/// and name).
+pub fn pre_push_invocation(to: Option<&str>) -> Result<HookInvocation> {
+    let branch = git::current_branch()?;
+    let (remote, remote_branch) = match to {
+        Some(target) => {
+            let remotes = git::remotes()?;
+            split_target(target, &remotes).with_context(|| {
+                format!(
+                    "'{}' does not start with a known remote ({})",
+                    target,
+                    remotes.join(", ")
+                )
+            })?
+        }
+        None => (git::tracking_remote(&branch), branch.clone()),
+    };
+    let url = git::remote_url(&remote)?.unwrap_or_else(|| remote.clone());
+    let local_sha = git::resolve_rev("HEAD")?.context("HEAD does not point at a commit")?;
+    let remote_sha = git::resolve_rev(&format!("refs/remotes/{}/{}", remote, remote_branch))?
+        .unwrap_or_else(|| ZERO_OID.to_string());
+    Ok(HookInvocation {
+        args: vec![remote, url],
+        stdin: format!(
+            "refs/heads/{} {} refs/heads/{} {}\n",
+            branch, local_sha, remote_branch, remote_sha
+        ),
+    })
+}
+
+/// Splits `origin/release/1.x` into the longest matching remote and branch.
+fn split_target(target: &str, remotes: &[String]) -> Option<(String, String)> {
+    remotes
+        .iter()
+        .filter(|remote| {
+            target
+                .strip_prefix(remote.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+                .is_some_and(|branch| !branch.is_empty())
+        })
+        .max_by_key(|remote| remote.len())
+        .map(|remote| (remote.clone(), target[remote.len() + 1..].to_string()))
+}
+
+/// Runs the installed hook with the arguments and stdin git would give it,
+/// non-interactively, and reports whether the operation would be blocked.
+pub fn simulate(hook: SimulatedHook, to: Option<&str>) -> Result<ExitStatus> {
+    let name = hook.file_name();
+    let path = git::hooks_dir()?.join(name);
+    if !path.exists() {
+        bail!(
+            "No {} hook installed at {} (run `git-sherpa hooks install`)",
+            name,
+            path.display()
+        );
+    }
+    let invocation = match hook {
+        SimulatedHook::PreCommit => HookInvocation {
+            args: Vec::new(),
+            stdin: String::new(),
+        },
+        SimulatedHook::PrePush => pre_push_invocation(to)?,
+    };
+
+    println!(
+        "{}",
+        format!("Simulating {} {}", name, invocation.args.join(" ")).dimmed()
+    );
+    for line in invocation.stdin.lines() {
+        println!("{}", format!("  stdin: {}", line).dimmed());
+    }
+
+    let mut child = Command::new(&path)
+        .args(&invocation.args)
+        .current_dir(git::toplevel()?)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("run hook {}", path.display()))?;
+    let mut stdin = child.stdin.take().context("open hook stdin")?;
+    // A hook may exit without reading its input; a broken pipe is fine then.
+    let _ = stdin.write_all(invocation.stdin.as_bytes());
+    drop(stdin);
+    let status = child.wait().context("wait for hook")?;
+
+    if status.success() {
+        println!("\n{}", format!("{}: would be allowed", name).green().bold());
+        Ok(ExitStatus::Success)
+    } else {
+        let code = status
+            .code()
+            .map(|c| format!("exit code {}", c))
+            .unwrap_or_else(|| "killed by signal".to_string());
+        println!(
+            "\n{}",
+            format!("{}: would be BLOCKED ({})", name, code)
+                .red()
+                .bold()
+        );
+        Ok(ExitStatus::Violations)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,17 +710,99 @@ mod tests {
         assert!(hook_content().contains("exec git-sherpa check"));
     }
 
+    #[test]
+    fn reads_stamped_template_version() {
+        assert_eq!(
+            installed_version(&hook_content()),
+            Some(HOOK_TEMPLATE_VERSION)
+        );
+        let unstamped = "#!/bin/sh\n# git-sherpa\nexec git-sherpa check\n";
+        assert_eq!(installed_version(unstamped), Some(0));
+        assert_eq!(installed_version("#!/bin/sh\nexit 0\n"), None);
+    }
+
+    #[test]
+    fn splits_push_target_on_longest_remote() {
+        let remotes = vec!["origin".to_string(), "origin/mirror".to_string()];
+        assert_eq!(
+            split_target("origin/release/1.x", &remotes),
+            Some(("origin".to_string(), "release/1.x".to_string()))
+        );
+        assert_eq!(
+            split_target("origin/mirror/main", &remotes),
+            Some(("origin/mirror".to_string(), "main".to_string()))
+        );
+        assert_eq!(split_target("upstream/main", &remotes), None);
+        assert_eq!(split_target("origin/", &remotes), None);
+    }
+
+    #[test]
+    fn branch_names_from_listing() {
+        let body =
+            serde_json::json!([{ "name": "main", "protected": true }, { "name": "release/*" }]);
+        assert_eq!(branch_names(&body), vec!["main", "release/*"]);
+    }
+
+    #[test]
+    fn protected_branches_without_fetch_are_configured_ones() {
+        let config = HooksConfig::default();
+        assert_eq!(protected_branches(&config), vec!["main", "master"]);
+    }
+
     #[test]
     fn pre_push_blocks_protected_branches() {
-        let content = pre_push_hook_content(&["main".into(), "master".into()]);
+        let content =
+            pre_push_hook_content(&["main".into(), "master".into()], &HooksConfig::default());
         assert!(content.contains("main|master"));
         assert!(content.contains("force push is blocked"));
         assert!(content.contains("direct push to"));
+        assert!(content.contains("git-sherpa check --pre-push \"$1\""));
+        assert!(!content.contains("protected tag"));
+    }
+
+    #[test]
+    fn pre_push_guards_protected_tags() {
+        let config = HooksConfig {
+            protected_tags: vec!["v*".into(), "release-*".into()],
+            ..HooksConfig::default()
+        };
+        let content = pre_push_hook_content(&["main".into()], &config);
+        assert!(content.contains("refs=$(cat)"));
+        assert!(content.contains("v*|release-*)"));
+        assert!(content.contains("deleting protected tag"));
+        assert!(content.contains("moving protected tag"));
+        assert!(!content.contains("oversized"));
+    }
+
+    #[test]
+    fn pre_push_guards_push_size() {
+        let config = HooksConfig {
+            max_push_size_mb: Some(50),
+            max_push_objects: Some(1000),
+            ..HooksConfig::default()
+        };
+        let content = pre_push_hook_content(&["main".into()], &config);
+        assert!(content.contains("refs=$(cat)"));
+        assert!(content.contains("--disk-usage"));
+        assert!(content.contains("-gt 52428800"));
+        assert!(content.contains("-gt 1000"));
+    }
+
+    #[test]
+    fn chained_hooks_run_the_previous_hook_after_the_marker() {
+        let content = chain("pre-commit", &hook_content(), Some(".husky/_/pre-commit"));
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(installed_version(&content), Some(HOOK_TEMPLATE_VERSION));
+        assert_eq!(lines[2], "# git-sherpa chains: .husky/_/pre-commit");
+        assert_eq!(chained_hook(&content), Some(".husky/_/pre-commit"));
+        assert!(content.contains("'.husky/_/pre-commit' \"$@\" || exit $?"));
+        assert!(content.ends_with("exec git-sherpa check --staged-only --quiet-on-success\n"));
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
     }
 
     #[test]
     fn pre_push_has_marker() {
-        let content = pre_push_hook_content(&["main".into()]);
+        let content = pre_push_hook_content(&["main".into()], &HooksConfig::default());
         assert!(content.contains(HOOK_MARKER));
     }
 }
@@ -0,0 +1,151 @@
+//! `check --format jsonl`: one JSON object per line, tagged with its `kind`.
+//! Invalid commits are printed as they are validated, the other findings
+//! once the report is built, and a `summary` line always comes last, so
+//! consumers of large audits can start before the check ends. Findings
+//! carry their [`Location`]; the summary has none.
+
+use serde::Serialize;
+use std::io::Write;
+
+use crate::attributes::AttributeViolation;
+use crate::authors::AuthorIssue;
+use crate::check::{BranchAge, BranchReport, CommitReport, Report, Summary};
+use crate::large_files::LargeFile;
+use crate::location::Location;
+use crate::protected::ProtectedChange;
+use crate::quiet_hours::QuietCommit;
+use crate::references::DanglingReference;
+use crate::remote::RemoteStatus;
+use crate::secrets::ContentFinding;
+use crate::sensitive::SensitiveFinding;
+use crate::severity::RuleResult;
+use crate::signatures::SignatureIssue;
+use crate::staged_content::ContentIssue;
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum Finding<'a> {
+    Branch(&'a BranchReport),
+    BranchAge(&'a BranchAge),
+    Remote(&'a RemoteStatus),
+    Commit(&'a CommitReport),
+    UnsignedCommit(&'a SignatureIssue),
+    AuthorEmail(&'a AuthorIssue),
+    QuietHours(&'a QuietCommit),
+    SensitiveFile(&'a SensitiveFinding),
+    Secret(&'a ContentFinding),
+    LargeFile(&'a LargeFile),
+    ProtectedPath(&'a ProtectedChange),
+    MissingAttribute {
+        line: &'a str,
+    },
+    Attribute(&'a AttributeViolation),
+    DanglingReference(&'a DanglingReference),
+    StagedContent(&'a ContentIssue),
+    Summary {
+        #[serde(flatten)]
+        summary: &'a Summary,
+        /// Failing rules with their severity.
+        rules: &'a [RuleResult],
+        violations: bool,
+    },
+}
+
+impl Finding<'_> {
+    /// Where the finding is; commits are abbreviated as `report` has them.
+    fn location(&self, report: &Report) -> Option<Location> {
+        Some(match self {
+            Finding::Branch(_) | Finding::BranchAge(_) | Finding::Remote(_) => Location::Repo,
+            // Serialized with the commit already.
+            Finding::Commit(_) => return None,
+            Finding::UnsignedCommit(issue) => report.commit_location(&issue.hash),
+            Finding::AuthorEmail(issue) => report.commit_location(&issue.hash),
+            Finding::QuietHours(commit) => report.commit_location(&commit.hash),
+            Finding::SensitiveFile(finding) => Location::file(&finding.path),
+            Finding::Secret(finding) => Location::file_line(&finding.path, finding.line),
+            Finding::LargeFile(file) => Location::file(&file.path),
+            Finding::ProtectedPath(change) => Location::file(&change.path),
+            Finding::MissingAttribute { .. } => Location::file(".gitattributes"),
+            Finding::Attribute(violation) => Location::file(&violation.path),
+            Finding::DanglingReference(reference) => report.commit_location(&reference.commit),
+            Finding::StagedContent(issue) => Location::file_line(&issue.path, issue.line),
+            Finding::Summary { .. } => return None,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    #[serde(flatten)]
+    finding: &'a Finding<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+}
+
+/// Prints `finding` as one line, flushed so a reader gets it right away.
+fn emit(finding: &Finding, location: Option<Location>) {
+    let line = Line { finding, location };
+    let line = serde_json::to_string(&line).expect("findings serialize");
+    let mut stdout = std::io::stdout().lock();
+    // A reader that went away (`| head`) ends the stream, not the check.
+    let _ = writeln!(stdout, "{}", line).and_then(|_| stdout.flush());
+}
+
+/// Prints an invalid commit as soon as it is validated; it carries its own
+/// location.
+pub(crate) fn emit_commit(commit: &CommitReport) {
+    emit(&Finding::Commit(commit), None);
+}
+
+/// Everything in `report` that is not streamed while it is built: the
+/// branch, the file and history findings, then the summary line.
+pub(crate) fn emit_rest(report: &Report, violations: bool) {
+    let found = |finding: Finding| emit(&finding, finding.location(report));
+    if !report.branch.valid {
+        found(Finding::Branch(&report.branch));
+    }
+    if let Some(age) = report.branch.age.as_ref().filter(|age| age.too_old()) {
+        found(Finding::BranchAge(age));
+    }
+    if let Some(remote) = report.repo.remote.as_ref().filter(|r| !r.ok()) {
+        found(Finding::Remote(remote));
+    }
+    for issue in &report.signatures {
+        found(Finding::UnsignedCommit(issue));
+    }
+    for issue in &report.authors.issues {
+        found(Finding::AuthorEmail(issue));
+    }
+    for commit in &report.quiet_hours {
+        found(Finding::QuietHours(commit));
+    }
+    for finding in &report.sensitive.findings {
+        found(Finding::SensitiveFile(finding));
+    }
+    for finding in &report.sensitive.content {
+        found(Finding::Secret(finding));
+    }
+    for file in &report.large_files {
+        found(Finding::LargeFile(file));
+    }
+    for change in &report.protected_paths {
+        found(Finding::ProtectedPath(change));
+    }
+    for line in &report.attributes.missing_lines {
+        found(Finding::MissingAttribute { line });
+    }
+    for violation in &report.attributes.violations {
+        found(Finding::Attribute(violation));
+    }
+    for reference in &report.references.dangling {
+        found(Finding::DanglingReference(reference));
+    }
+    for issue in &report.staged_content {
+        found(Finding::StagedContent(issue));
+    }
+    found(Finding::Summary {
+        summary: &report.summary,
+        rules: &report.rules,
+        violations,
+    });
+}
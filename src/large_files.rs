@@ -0,0 +1,123 @@
+//! Files over `[checks] max_file_size_kb`, staged or (optionally) added by
+//! the checked commits.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::git;
+use crate::repo_mode;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LargeFile {
+    pub path: String,
+    pub size_bytes: u64,
+    /// Commit that added the blob; `None` for staged files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl LargeFile {
+    pub fn size_kb(&self) -> u64 {
+        self.size_bytes.div_ceil(1024)
+    }
+}
+
+/// Staged blobs over the limit of the config governing their path, then
+/// blobs from `commits` when `large_files_in_commits` is set. Empty when no
+/// limit is configured.
+pub fn find_large_files(config: &Config, commits: &[String]) -> Result<Vec<LargeFile>> {
+    let limit = |path: &str| config.for_path(path).checks.max_file_size_kb;
+    if config
+        .with_scopes()
+        .all(|c| c.checks.max_file_size_kb.is_none())
+    {
+        return Ok(Vec::new());
+    }
+    let mut staged = git::staged_blobs()?;
+    staged.retain(|(_, path)| limit(path).is_some());
+    let mut large: Vec<LargeFile> = git::blob_sizes(&staged)?
+        .into_iter()
+        .filter(|(path, size)| limit(path).is_some_and(|max_kb| exceeds(*size, max_kb)))
+        .map(|(path, size_bytes)| LargeFile {
+            path,
+            size_bytes,
+            commit: None,
+        })
+        .collect();
+    if let (Some(max_kb), true) = (
+        config.checks.max_file_size_kb,
+        config.checks.large_files_in_commits,
+    ) {
+        large.extend(in_commits(max_kb, commits)?);
+    }
+    Ok(large)
+}
+
+/// Blobs over `max_kb` added or modified by `commits`. In a partial clone,
+/// blobs that were filtered out are skipped: asking for their size would
+/// download each one.
+pub fn in_commits(max_kb: u64, commits: &[String]) -> Result<Vec<LargeFile>> {
+    let partial = repo_mode::detect()?.is_partial();
+    let mut large = Vec::new();
+    let mut skipped = 0;
+    for commit in commits {
+        let mut blobs = git::commit_blobs(commit)?;
+        if partial {
+            let missing = git::missing_objects(commit)?;
+            let before = blobs.len();
+            blobs.retain(|(hash, _)| !missing.contains(hash));
+            skipped += before - blobs.len();
+        }
+        large.extend(over_limit(&blobs, max_kb, Some(commit))?);
+    }
+    if skipped > 0 {
+        eprintln!(
+            "Warning: skipped the size check for {} blob(s) not present in this partial clone",
+            skipped
+        );
+    }
+    Ok(large)
+}
+
+fn over_limit(
+    blobs: &[(String, String)],
+    max_kb: u64,
+    commit: Option<&String>,
+) -> Result<Vec<LargeFile>> {
+    Ok(git::blob_sizes(blobs)?
+        .into_iter()
+        .filter(|(_, size)| exceeds(*size, max_kb))
+        .map(|(path, size_bytes)| LargeFile {
+            path,
+            size_bytes,
+            commit: commit.cloned(),
+        })
+        .collect())
+}
+
+fn exceeds(size_bytes: u64, max_kb: u64) -> bool {
+    size_bytes > max_kb.saturating_mul(1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_is_inclusive() {
+        assert!(!exceeds(1024, 1));
+        assert!(exceeds(1025, 1));
+        assert!(!exceeds(0, 0));
+    }
+
+    #[test]
+    fn size_rounds_up_to_kb() {
+        let file = LargeFile {
+            path: "a".into(),
+            size_bytes: 1025,
+            commit: None,
+        };
+        assert_eq!(file.size_kb(), 2);
+    }
+}
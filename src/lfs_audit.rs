@@ -0,0 +1,218 @@
+//! `lfs-audit`: the blobs in history over a size threshold, grouped by the
+//! `--include` pattern that would move them to Git LFS, with the migration
+//! commands and what they save. Blobs no longer in `HEAD` are flagged: they
+//! only bloat clones, and only a history rewrite gets rid of them.
+
+use anyhow::Result;
+use colored::Colorize;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::cli::Format;
+use crate::git;
+use crate::repo_health::human_size;
+
+#[derive(Debug, Serialize)]
+pub struct LfsAudit {
+    pub threshold_kb: u64,
+    /// Largest total first.
+    pub groups: Vec<BlobGroup>,
+    /// Commands that move every group to LFS; empty when none qualifies.
+    pub plan: Vec<String>,
+    /// Bytes the plan takes out of regular git objects (uncompressed): every
+    /// blob matching a group's pattern, not only those over the threshold.
+    pub savings_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlobGroup {
+    /// `--include` pattern: `*.<ext>`, or the path of a file without one.
+    pub pattern: String,
+    /// Blobs over the threshold, largest first.
+    pub blobs: Vec<LargeBlob>,
+    /// Size of every blob in history matching `pattern`.
+    pub total_bytes: u64,
+    /// How many of `blobs` are no longer in `HEAD`.
+    pub dangling: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LargeBlob {
+    pub hash: String,
+    pub size: u64,
+    pub path: String,
+    pub in_head: bool,
+}
+
+/// The `git lfs migrate --include` pattern covering `path`.
+pub(crate) fn lfs_pattern(path: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((_, ext)) if !ext.contains('/') => format!("*.{}", ext),
+        _ => path.to_string(),
+    }
+}
+
+pub fn lfs_audit(format: Format, threshold_kb: u64) -> Result<()> {
+    let audit = build_audit(threshold_kb)?;
+    match format {
+        Format::Text => print_text(&audit),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&audit)?),
+    }
+    Ok(())
+}
+
+pub fn build_audit(threshold_kb: u64) -> Result<LfsAudit> {
+    let blobs = git::largest_blobs(usize::MAX)?;
+    let head: HashSet<String> = match git::resolve_rev("HEAD")? {
+        Some(_) => git::tree_blobs("HEAD")?.into_iter().collect(),
+        None => HashSet::new(),
+    };
+    Ok(audit(blobs, &head, threshold_kb))
+}
+
+/// Groups `blobs` (hash, size, path; largest first) into an audit.
+fn audit(blobs: Vec<(String, u64, String)>, head: &HashSet<String>, threshold_kb: u64) -> LfsAudit {
+    let mut groups: BTreeMap<String, BlobGroup> = BTreeMap::new();
+    for (hash, size, path) in blobs.iter().filter(|b| b.1 >= threshold_kb * 1024) {
+        let pattern = lfs_pattern(path);
+        let group = groups.entry(pattern.clone()).or_insert_with(|| BlobGroup {
+            pattern,
+            blobs: Vec::new(),
+            total_bytes: 0,
+            dangling: 0,
+        });
+        let in_head = head.contains(hash);
+        group.dangling += usize::from(!in_head);
+        group.blobs.push(LargeBlob {
+            hash: hash.clone(),
+            size: *size,
+            path: path.clone(),
+            in_head,
+        });
+    }
+    for (_, size, path) in &blobs {
+        if let Some(group) = groups.get_mut(&lfs_pattern(path)) {
+            group.total_bytes += size;
+        }
+    }
+
+    let mut groups: Vec<BlobGroup> = groups.into_values().collect();
+    groups.sort_by_key(|g| Reverse(g.total_bytes));
+    let plan = if groups.is_empty() {
+        Vec::new()
+    } else {
+        let patterns: Vec<&str> = groups.iter().map(|g| g.pattern.as_str()).collect();
+        vec![
+            "git lfs install".to_string(),
+            format!(
+                "git lfs migrate import --everything --include=\"{}\"",
+                patterns.join(",")
+            ),
+        ]
+    };
+    LfsAudit {
+        threshold_kb,
+        savings_bytes: groups.iter().map(|g| g.total_bytes).sum(),
+        groups,
+        plan,
+    }
+}
+
+fn print_text(audit: &LfsAudit) {
+    let threshold = human_size(audit.threshold_kb * 1024);
+    if audit.groups.is_empty() {
+        println!(
+            "{}",
+            format!("No blobs of {} or more in history.", threshold).green()
+        );
+        return;
+    }
+    println!(
+        "{}",
+        format!("Blobs of {} or more in history:", threshold).bold()
+    );
+    for group in &audit.groups {
+        let dangling = if group.dangling > 0 {
+            format!(", {} no longer in HEAD", group.dangling)
+        } else {
+            String::new()
+        };
+        println!(
+            "\n  {} ({} blob(s){}, {} in all matching blobs)",
+            group.pattern.cyan(),
+            group.blobs.len(),
+            dangling,
+            human_size(group.total_bytes)
+        );
+        for blob in &group.blobs {
+            let tag = if blob.in_head {
+                String::new()
+            } else {
+                format!("  {}", "[history only]".yellow())
+            };
+            println!(
+                "    {:>10}  {}  {}{}",
+                human_size(blob.size),
+                git::short_hash(&blob.hash),
+                blob.path,
+                tag
+            );
+        }
+    }
+
+    println!(
+        "\n{}",
+        format!(
+            "Migration plan (moves {} out of regular git objects):",
+            human_size(audit.savings_bytes)
+        )
+        .yellow()
+        .bold()
+    );
+    for command in &audit.plan {
+        println!("  {}", command.cyan());
+    }
+    println!(
+        "  {}",
+        "This rewrites every branch and tag: push with --force, and have everyone re-clone."
+            .dimmed()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blob(hash: char, size: u64, path: &str) -> (String, u64, String) {
+        (hash.to_string().repeat(40), size, path.to_string())
+    }
+
+    #[test]
+    fn groups_large_blobs_by_pattern() {
+        let blobs = vec![
+            blob('a', 5 << 20, "assets/intro.mp4"),
+            blob('b', 3 << 20, "design/logo.psd"),
+            blob('c', 2 << 20, "assets/old.mp4"),
+            blob('d', 1 << 10, "assets/tiny.mp4"),
+            blob('e', 4 << 10, "src/main.rs"),
+        ];
+        let head = HashSet::from(["a".repeat(40), "b".repeat(40)]);
+        let report = audit(blobs, &head, 1024);
+
+        let patterns: Vec<&str> = report.groups.iter().map(|g| g.pattern.as_str()).collect();
+        assert_eq!(patterns, vec!["*.mp4", "*.psd"]);
+        let mp4 = &report.groups[0];
+        assert_eq!(mp4.blobs.len(), 2);
+        assert_eq!(mp4.dangling, 1);
+        assert_eq!(mp4.total_bytes, (7 << 20) + (1 << 10));
+        assert_eq!(report.savings_bytes, (10 << 20) + (1 << 10));
+        assert_eq!(
+            report.plan[1],
+            "git lfs migrate import --everything --include=\"*.mp4,*.psd\""
+        );
+
+        let small = audit(vec![blob('a', 10, "a.bin")], &HashSet::new(), 1);
+        assert!(small.groups.is_empty() && small.plan.is_empty());
+    }
+}
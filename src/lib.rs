@@ -0,0 +1,91 @@
+//! git-sherpa as a library, for bots and editors that want to run the checks
+//! without shelling out to the CLI.
+//!
+//! The stable surface is [`Config`] / [`load_config`], [`build_report`] and
+//! the [`Report`] it returns, and the [`git`] helpers. Git runs in the current
+//! working directory, so change into the repository before building a report.
+//! To add rules of your own, [`rules::Registry::register`] them and pass the
+//! registry to [`check::build_report_with`].
+//!
+//! ```no_run
+//! use git_sherpa::{build_report, load_config, CommitSelection};
+//! use std::path::Path;
+//!
+//! let config = load_config(Path::new(".gitsherpa.toml"))?;
+//! let report = build_report(&config, &CommitSelection::Range("origin/main..HEAD".into()))?;
+//! if report.has_violations() {
+//!     println!("{}", serde_json::to_string_pretty(&report)?);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod attributes;
+pub mod authors;
+pub mod baseline;
+pub mod check;
+pub mod config;
+pub mod environment;
+pub mod error;
+pub mod git;
+pub mod large_files;
+pub mod linear;
+pub mod location;
+pub mod outgoing;
+pub mod protected;
+pub mod pull_request;
+pub mod quiet_hours;
+pub mod references;
+pub mod remote;
+pub mod repo_mode;
+pub mod rewrites;
+pub mod rules;
+pub mod secrets;
+pub mod sensitive;
+pub mod severity;
+pub mod signatures;
+pub mod staged_content;
+
+mod alias;
+#[doc(hidden)]
+pub mod app;
+mod audit;
+mod auth;
+mod bootstrap;
+mod branch;
+mod branch_audit;
+mod branch_naming;
+mod cache;
+mod ci;
+mod cli;
+mod compat;
+mod conflicts;
+mod convention;
+mod doctor;
+mod fix;
+mod hooks;
+mod jsonl;
+mod lfs_audit;
+#[cfg(feature = "libgit2")]
+mod libgit2;
+mod lint;
+mod message_template;
+mod notes;
+mod policy;
+mod presets;
+mod providers;
+mod release;
+mod repo_health;
+mod reword;
+mod rollup;
+mod scopes;
+mod serve;
+mod stats;
+mod version;
+mod watch;
+
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+pub use check::{build_report, CommitSelection, Report};
+pub use config::{load_config, Config};
+pub use error::SherpaError;
@@ -0,0 +1,201 @@
+//! `--git-backend libgit2`: the [`GitBackend`] queries answered in-process
+//! instead of one `git` process each. The repository is opened per query,
+//! since `check --recursive` changes directory between repositories.
+
+use anyhow::Result;
+use git2::{
+    Branch, Delta, DiffFile, DiffFindOptions, DiffOptions, ErrorCode, Repository, RevparseMode,
+    Revwalk, Sort, StatusOptions,
+};
+
+use crate::error::SherpaError;
+use crate::git::{ChangeKind, GitBackend, StagedChange};
+
+pub struct Libgit2;
+
+/// The repository containing the working directory, honoring `GIT_DIR`.
+fn open() -> Result<Repository> {
+    Repository::open_from_env().map_err(|err| match err.code() {
+        ErrorCode::NotFound => SherpaError::NotARepo.into(),
+        _ => failed("open", err),
+    })
+}
+
+fn failed(operation: &str, err: git2::Error) -> anyhow::Error {
+    SherpaError::Libgit2 {
+        operation: operation.to_string(),
+        message: err.message().to_string(),
+    }
+    .into()
+}
+
+/// Walks newest first like `git log`: by commit date, but never a parent
+/// before its child, which matters when commits share a timestamp.
+fn walk(repo: &Repository) -> Result<Revwalk<'_>> {
+    let mut walk = repo.revwalk().map_err(|err| failed("revwalk", err))?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+        .map_err(|err| failed("revwalk", err))?;
+    Ok(walk)
+}
+
+/// (hash, subject) of up to `limit` commits from `walk`.
+fn hash_subjects(
+    repo: &Repository,
+    walk: Revwalk<'_>,
+    limit: usize,
+) -> Result<Vec<(String, String)>> {
+    walk.take(limit)
+        .map(|oid| {
+            let oid = oid.map_err(|err| failed("revwalk", err))?;
+            let commit = repo.find_commit(oid).map_err(|err| failed("log", err))?;
+            Ok((
+                oid.to_string(),
+                commit.summary().unwrap_or_default().to_string(),
+            ))
+        })
+        .collect()
+}
+
+impl GitBackend for Libgit2 {
+    fn current_branch(&self) -> Result<String> {
+        let repo = open()?;
+        let head = match repo.head() {
+            Ok(head) => head,
+            // Unborn: HEAD still names the branch the first commit creates.
+            Err(err) if err.code() == ErrorCode::UnbornBranch => {
+                let head = repo
+                    .find_reference("HEAD")
+                    .map_err(|err| failed("HEAD", err))?;
+                let target = head.symbolic_target().unwrap_or("HEAD");
+                return Ok(target
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(target)
+                    .to_string());
+            }
+            Err(err) => return Err(failed("HEAD", err)),
+        };
+        Ok(match head.shorthand() {
+            Some(name) if head.is_branch() => name.to_string(),
+            _ => "HEAD".to_string(),
+        })
+    }
+
+    fn recent_commits(&self, limit: usize) -> Result<Vec<(String, String)>> {
+        let repo = open()?;
+        let mut walk = walk(&repo)?;
+        walk.push_head().map_err(|err| failed("log", err))?;
+        hash_subjects(&repo, walk, limit)
+    }
+
+    fn range_commits(&self, range: &str) -> Result<Vec<(String, String)>> {
+        let repo = open()?;
+        let spec = repo
+            .revparse(range)
+            .map_err(|err| failed("rev-parse", err))?;
+        let mut walk = walk(&repo)?;
+        let push = |walk: &mut Revwalk, object: Option<&git2::Object>| match object {
+            Some(object) => walk.push(object.id()).map_err(|err| failed("log", err)),
+            None => Ok(()),
+        };
+        if spec.mode().contains(RevparseMode::MERGE_BASE) {
+            // a...b: commits on either side since they diverged.
+            let (from, to) = (spec.from().map(|o| o.id()), spec.to().map(|o| o.id()));
+            push(&mut walk, spec.from())?;
+            push(&mut walk, spec.to())?;
+            if let (Some(from), Some(to)) = (from, to) {
+                let base = repo
+                    .merge_base(from, to)
+                    .map_err(|err| failed("merge-base", err))?;
+                walk.hide(base).map_err(|err| failed("log", err))?;
+            }
+        } else if spec.mode().contains(RevparseMode::RANGE) {
+            push(&mut walk, spec.to())?;
+            if let Some(from) = spec.from() {
+                walk.hide(from.id()).map_err(|err| failed("log", err))?;
+            }
+        } else {
+            push(&mut walk, spec.from())?;
+        }
+        hash_subjects(&repo, walk, usize::MAX)
+    }
+
+    fn worktree_clean(&self) -> Result<bool> {
+        let repo = open()?;
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).include_ignored(false);
+        let statuses = repo
+            .statuses(Some(&mut opts))
+            .map_err(|err| failed("status", err))?;
+        Ok(statuses.is_empty())
+    }
+
+    fn has_upstream(&self) -> Result<bool> {
+        let repo = open()?;
+        let Ok(head) = repo.head() else {
+            return Ok(false);
+        };
+        if !head.is_branch() {
+            return Ok(false);
+        }
+        let has_upstream = Branch::wrap(head).upstream().is_ok();
+        Ok(has_upstream)
+    }
+
+    fn resolve_rev(&self, rev: &str) -> Result<Option<String>> {
+        let repo = open()?;
+        Ok(repo.revparse_single(rev).ok().map(|o| o.id().to_string()))
+    }
+
+    fn staged_changes(&self) -> Result<Vec<StagedChange>> {
+        let repo = open()?;
+        // An unborn branch has no HEAD tree: everything in the index is new.
+        let head = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree().map_err(|err| failed("diff", err))?),
+            Err(_) => None,
+        };
+        let mut diff = repo
+            .diff_tree_to_index(head.as_ref(), None, Some(&mut DiffOptions::new()))
+            .map_err(|err| failed("diff", err))?;
+        let mut similar = DiffFindOptions::new();
+        similar
+            .renames(true)
+            .copies(true)
+            .copies_from_unmodified(true);
+        diff.find_similar(Some(&mut similar))
+            .map_err(|err| failed("diff", err))?;
+        let path = |file: DiffFile| file.path().map(|p| p.display().to_string());
+        Ok(diff
+            .deltas()
+            .filter_map(|delta| {
+                let (kind, from) = match delta.status() {
+                    Delta::Added => (ChangeKind::Added, None),
+                    Delta::Deleted => {
+                        return Some(StagedChange {
+                            kind: ChangeKind::Deleted,
+                            path: path(delta.old_file())?,
+                            from: None,
+                        })
+                    }
+                    Delta::Renamed => (ChangeKind::Renamed, path(delta.old_file())),
+                    Delta::Copied => (ChangeKind::Copied, path(delta.old_file())),
+                    _ => (ChangeKind::Modified, None),
+                };
+                Some(StagedChange {
+                    kind,
+                    path: path(delta.new_file())?,
+                    from,
+                })
+            })
+            .collect())
+    }
+
+    fn config_get(&self, key: &str) -> Result<Option<String>> {
+        let repo = open()?;
+        let config = repo.config().map_err(|err| failed("config", err))?;
+        match config.get_string(key) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.code() == ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(failed("config", err)),
+        }
+    }
+}
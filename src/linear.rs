@@ -0,0 +1,94 @@
+//! `[checks] require_linear_history`: the commits on top of `base_branch`
+//! must form a straight line that a plain rebase can replay.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::git;
+
+#[derive(Debug, Default, Serialize)]
+pub struct Linearity {
+    pub base: String,
+    /// Merge commits between `base` and `HEAD`.
+    pub merges: Vec<String>,
+    /// More than one merge base means a criss-cross merge.
+    pub merge_bases: usize,
+    /// Paths changed both on the branch and upstream since they forked;
+    /// a rebase will likely conflict on these. Only filled in when the
+    /// history is not linear.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overlapping_paths: Vec<String>,
+}
+
+impl Linearity {
+    pub fn is_linear(&self) -> bool {
+        self.merges.is_empty() && self.merge_bases <= 1
+    }
+
+    pub fn criss_cross(&self) -> bool {
+        self.merge_bases > 1
+    }
+}
+
+pub fn linearity(base: &str) -> Result<Linearity> {
+    let mut linearity = Linearity {
+        base: base.to_string(),
+        merges: git::merge_commits(&format!("{}..HEAD", base))?,
+        merge_bases: 0,
+        overlapping_paths: Vec::new(),
+    };
+    let merge_bases = git::merge_bases(base, "HEAD")?;
+    linearity.merge_bases = merge_bases.len();
+    if !linearity.is_linear() {
+        if let Some(fork) = merge_bases.first() {
+            linearity.overlapping_paths = overlapping(
+                &git::changed_paths(fork, "HEAD")?,
+                &git::changed_paths(fork, base)?,
+            );
+        }
+    }
+    Ok(linearity)
+}
+
+/// Paths in both lists, sorted.
+fn overlapping(ours: &[String], theirs: &[String]) -> Vec<String> {
+    let mut paths: Vec<String> = ours
+        .iter()
+        .filter(|p| theirs.contains(p))
+        .cloned()
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn overlap_is_sorted_intersection() {
+        let ours = strings(&["src/b.rs", "src/a.rs", "README.md"]);
+        let theirs = strings(&["src/a.rs", "Cargo.toml", "src/b.rs"]);
+        assert_eq!(
+            overlapping(&ours, &theirs),
+            strings(&["src/a.rs", "src/b.rs"])
+        );
+        assert!(overlapping(&ours, &[]).is_empty());
+    }
+
+    #[test]
+    fn criss_cross_is_not_linear() {
+        let linearity = Linearity {
+            merge_bases: 2,
+            ..Default::default()
+        };
+        assert!(linearity.criss_cross());
+        assert!(!linearity.is_linear());
+        assert!(Linearity::default().is_linear());
+    }
+}
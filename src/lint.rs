@@ -0,0 +1,212 @@
+//! Linting of a single message (PR title or commit message) against the
+//! configured convention, independent of repository history.
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::check::AUTOSQUASH_PREFIXES;
+use crate::cli::Format;
+use crate::config::{load_config, Config};
+use crate::convention::Convention;
+use crate::error::ExitStatus;
+use crate::git;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintMode {
+    /// A PR/MR title that becomes the squash commit subject on merge.
+    PrTitle,
+    /// The message file passed to the `commit-msg` hook.
+    CommitMsg,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LintResult {
+    pub mode: LintMode,
+    pub subject: String,
+    pub valid: bool,
+    pub problems: Vec<String>,
+}
+
+pub fn lint_message(
+    config_path: &Path,
+    pr_title: Option<&str>,
+    message_file: Option<&Path>,
+    format: Format,
+    quiet_on_success: bool,
+) -> Result<ExitStatus> {
+    let config = load_config(config_path)?;
+    let result = match (pr_title, message_file) {
+        (Some(title), _) => lint_subject(&config, title, LintMode::PrTitle)?,
+        (None, Some(path)) => {
+            let raw = fs::read_to_string(path)
+                .with_context(|| format!("read message file {}", path.display()))?;
+            let message = strip_comments(&raw, &git::comment_char()?);
+            if git::resolve_rev("MERGE_HEAD")?.is_some() {
+                // The merge commit being concluded; `check` judges merges
+                // by `forbid_merge_commits`, not by their message.
+                let subject = message.lines().next().unwrap_or_default();
+                exempt(subject)
+            } else {
+                // The commit being made follows the nested config its files sit under.
+                let staged = git::staged_files()?;
+                let governing = config.for_paths(staged.iter().map(String::as_str));
+                lint_commit_message(governing, &message)?
+            }
+        }
+        (None, None) => bail!("nothing to lint: pass --pr-title or a message file"),
+    };
+
+    match format {
+        _ if quiet_on_success && result.valid => {}
+        Format::Text => {
+            if result.valid {
+                println!("{} {}", "OK".green(), result.subject);
+            } else {
+                println!("{} {}", "INVALID".red(), result.subject);
+                for problem in &result.problems {
+                    println!("  - {}", problem);
+                }
+                if result.mode == LintMode::CommitMsg {
+                    println!("Commit aborted (bypass with `git commit --no-verify`).");
+                }
+            }
+        }
+        Format::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+    }
+
+    Ok(ExitStatus::from_violations(!result.valid))
+}
+
+pub fn lint_subject(config: &Config, subject: &str, mode: LintMode) -> Result<LintResult> {
+    let subject = subject.trim().to_string();
+    let mut problems = Convention::from_config(&config.commits)?.check_subject(&subject);
+
+    if mode == LintMode::PrTitle {
+        let max = config.commits.pr_title_max_length;
+        let mode = config.commits.length_mode;
+        let len = mode.measure(&subject);
+        if len > max {
+            problems.push(format!("is {} {} long (max {})", len, mode.unit(), max));
+        }
+        if subject.ends_with('.') {
+            problems.push("ends with a period".to_string());
+        }
+    }
+
+    Ok(LintResult {
+        mode,
+        valid: problems.is_empty(),
+        subject,
+        problems,
+    })
+}
+
+/// Lints a full commit message: the subject plus the `[commits.body]` rules.
+/// An empty message passes so git can abort the commit with its own error;
+/// so do `fixup!`/`squash!`/`amend!` commits, which autosquash folds into
+/// a commit that was linted already.
+pub fn lint_commit_message(config: &Config, message: &str) -> Result<LintResult> {
+    let subject = message.lines().next().unwrap_or_default();
+    if subject.trim().is_empty() || AUTOSQUASH_PREFIXES.iter().any(|p| subject.starts_with(p)) {
+        return Ok(exempt(subject));
+    }
+    let mut result = lint_subject(config, subject, LintMode::CommitMsg)?;
+    result.problems.extend(
+        Convention::from_config(&config.commits)?
+            .check_body(message)
+            .into_iter()
+            .map(|problem| problem.message),
+    );
+    result.valid = result.problems.is_empty();
+    Ok(result)
+}
+
+/// A commit message that is not held to the convention.
+fn exempt(subject: &str) -> LintResult {
+    LintResult {
+        mode: LintMode::CommitMsg,
+        subject: subject.trim().to_string(),
+        valid: true,
+        problems: Vec::new(),
+    }
+}
+
+const SCISSORS: &str = "------------------------ >8 ------------------------";
+
+/// The message as git will record it: comment lines and everything below
+/// the scissors line (`git commit -v`) removed, leading blank lines dropped.
+fn strip_comments(raw: &str, comment: &str) -> String {
+    let scissors = format!("{} {}", comment, SCISSORS);
+    raw.lines()
+        .take_while(|line| *line != scissors)
+        .filter(|line| !line.starts_with(comment))
+        .skip_while(|line| line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    #[test]
+    fn valid_pr_title() {
+        let cfg = default_config();
+        let result = lint_subject(&cfg, "feat(auth): add SSO login", LintMode::PrTitle).unwrap();
+        assert!(result.valid, "{:?}", result.problems);
+    }
+
+    #[test]
+    fn pr_title_must_follow_convention() {
+        let cfg = default_config();
+        let result = lint_subject(&cfg, "Add SSO login", LintMode::PrTitle).unwrap();
+        assert!(!result.valid);
+        assert_eq!(result.problems.len(), 1);
+    }
+
+    #[test]
+    fn pr_title_length_and_period() {
+        let mut cfg = default_config();
+        cfg.commits.pr_title_max_length = 20;
+        let result = lint_subject(
+            &cfg,
+            "feat: a rather long pull request title.",
+            LintMode::PrTitle,
+        )
+        .unwrap();
+        assert_eq!(result.problems.len(), 2);
+    }
+
+    #[test]
+    fn commit_message_comments_are_ignored() {
+        let raw = format!(
+            "# Please enter the message\n\nfeat: add login\n\nBody.\n# On branch x\n# {}\ndiff\n",
+            SCISSORS
+        );
+        assert_eq!(strip_comments(&raw, "#"), "feat: add login\n\nBody.");
+        assert_eq!(strip_comments(";x\nfix: y", ";"), "fix: y");
+    }
+
+    #[test]
+    fn commit_message_checks_subject_and_body() {
+        let mut cfg = default_config();
+        let result = lint_commit_message(&cfg, "feat: add login\n\nBody.").unwrap();
+        assert!(result.valid, "{:?}", result.problems);
+        assert!(!lint_commit_message(&cfg, "added login").unwrap().valid);
+        assert!(lint_commit_message(&cfg, "").unwrap().valid);
+        assert!(
+            lint_commit_message(&cfg, "fixup! added login")
+                .unwrap()
+                .valid
+        );
+
+        cfg.commits.body.required = true;
+        let result = lint_commit_message(&cfg, "feat: add login").unwrap();
+        assert_eq!(result.problems, vec!["body is required"]);
+    }
+}
@@ -0,0 +1,106 @@
+//! Where a violation is: a commit, a file, a line in a file, or the
+//! repository as a whole (branch name, upstream, history shape). Rules
+//! attach one to each violation and every output format renders it the same
+//! way, so annotations point at the line whenever a rule knows it.
+
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Location {
+    #[default]
+    Repo,
+    /// `short` is `sha` abbreviated as text output shows it.
+    Commit {
+        sha: String,
+        short: String,
+    },
+    File {
+        path: String,
+    },
+    FileLine {
+        path: String,
+        line: usize,
+    },
+}
+
+impl Location {
+    /// `short` comes from a batch lookup such as
+    /// [`crate::git::abbreviate_commits`].
+    pub fn commit(sha: &str, short: &str) -> Self {
+        Location::Commit {
+            sha: sha.to_string(),
+            short: short.to_string(),
+        }
+    }
+
+    pub fn file(path: &str) -> Self {
+        Location::File {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn file_line(path: &str, line: usize) -> Self {
+        Location::FileLine {
+            path: path.to_string(),
+            line,
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Location::File { path } | Location::FileLine { path, .. } => Some(path),
+            Location::Repo | Location::Commit { .. } => None,
+        }
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Location::FileLine { line, .. } => Some(*line),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Repo => write!(f, "repository"),
+            Location::Commit { short, .. } => write!(f, "commit {}", short),
+            Location::File { path } => write!(f, "{}", path),
+            Location::FileLine { path, line } => write!(f, "{}:{}", path, line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations_render_and_serialize_alike() {
+        let line = Location::file_line("src/lib.rs", 3);
+        assert_eq!(line.to_string(), "src/lib.rs:3");
+        assert_eq!((line.path(), line.line()), (Some("src/lib.rs"), Some(3)));
+        assert_eq!(
+            serde_json::to_value(&line).unwrap(),
+            serde_json::json!({"kind": "file_line", "path": "src/lib.rs", "line": 3})
+        );
+
+        let commit = Location::Commit {
+            sha: "a".repeat(40),
+            short: "aaaaaaa".to_string(),
+        };
+        assert_eq!(commit.to_string(), "commit aaaaaaa");
+        assert_eq!(commit.path(), None);
+        assert_eq!(
+            serde_json::to_value(&commit).unwrap()["short"],
+            serde_json::json!("aaaaaaa")
+        );
+        assert_eq!(
+            serde_json::to_value(Location::Repo).unwrap(),
+            serde_json::json!({"kind": "repo"})
+        );
+    }
+}
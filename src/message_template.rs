@@ -0,0 +1,304 @@
+//! `prepare-message`, run by the `prepare-commit-msg` hook: fills `{type}`,
+//! `{scope}` and `{ticket}` in the commit template from the branch name and
+//! the staged paths, so the pre-filled subject already follows the
+//! convention and only the description is left to write.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+use crate::config::{load_config, Config};
+use crate::convention::Convention;
+use crate::error::SherpaError;
+use crate::git;
+use crate::scopes::GENERIC_DIRS;
+
+const PLACEHOLDERS: [&str; 3] = ["{type}", "{scope}", "{ticket}"];
+
+/// Ticket IDs looked for in the branch name without `branches.ticket_pattern`.
+const DEFAULT_TICKET_PATTERN: &str = "[A-Z][A-Z0-9]+-[0-9]+";
+
+/// Branch prefixes that spell a Conventional Commits type differently.
+const TYPE_ALIASES: &[(&str, &str)] = &[
+    ("feature", "feat"),
+    ("bugfix", "fix"),
+    ("hotfix", "fix"),
+    ("doc", "docs"),
+    ("documentation", "docs"),
+    ("refactoring", "refactor"),
+    ("tests", "test"),
+];
+
+pub fn prepare_message(
+    config_path: &Path,
+    message_file: &Path,
+    source: Option<&str>,
+) -> Result<()> {
+    let config = load_config(config_path)?;
+    let raw = read_message(message_file)?;
+    let suggest = config.hooks.suggest_message;
+    let message = match source.unwrap_or_default() {
+        // `commit.template` or `-t`: filled in place.
+        "template" => raw.clone(),
+        // Plain `git commit`: the configured template goes above git's comments.
+        "" => match &config.commits.template {
+            Some(template) => format!("{}\n{}", template.trim_end_matches('\n'), raw),
+            None if suggest => raw.clone(),
+            None => return Ok(()),
+        },
+        // -m/-F, merges, squashes and amends bring their own message.
+        _ => return Ok(()),
+    };
+
+    let placeholders = Placeholders::current(&config)?;
+    let mut filled = placeholders.fill(&message);
+    if suggest && source.unwrap_or_default().is_empty() {
+        filled = insert_before_comments(&filled, &suggestion(&config, &placeholders)?);
+    }
+    if filled != raw {
+        write_message(message_file, &filled)?;
+    }
+    Ok(())
+}
+
+/// `suggest-message`: the commented suggestion the prepare-commit-msg hook
+/// adds with `[hooks] suggest_message`, printed or added to `message_file`.
+pub fn suggest_message(config_path: &Path, message_file: Option<&Path>) -> Result<()> {
+    let config = load_config(config_path)?;
+    let suggestion = suggestion(&config, &Placeholders::current(&config)?)?;
+    match message_file {
+        Some(path) => write_message(
+            path,
+            &insert_before_comments(&read_message(path)?, &suggestion),
+        ),
+        None => {
+            print!("{}", suggestion);
+            Ok(())
+        }
+    }
+}
+
+fn read_message(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("read message file {}", path.display()))
+}
+
+fn write_message(path: &Path, message: &str) -> Result<()> {
+    fs::write(path, message).with_context(|| format!("write message file {}", path.display()))
+}
+
+/// Comment lines describing the subject the branch and staged paths call
+/// for, each prefixed with git's comment character so they never end up in
+/// the commit.
+fn suggestion(config: &Config, placeholders: &Placeholders) -> Result<String> {
+    let comment = git::comment_char()?;
+    let types = Convention::from_config(&config.commits)?.types().to_vec();
+    let mut lines = Vec::new();
+    if let Some(subject) = placeholders.subject(&config.commits.convention) {
+        lines.push(format!("Suggested subject: {}", subject));
+    }
+    if !types.is_empty() {
+        lines.push(format!("Types: {}", types.join(", ")));
+    }
+    if let Some(scope) = &placeholders.scope {
+        lines.push(format!("Scope (from the staged paths): {}", scope));
+    }
+    if let Some(ticket) = &placeholders.ticket {
+        lines.push(format!("Ticket (from the branch): {}", ticket));
+    }
+    let mut block = String::new();
+    for line in lines {
+        block.push_str(&format!("{} {}\n", comment, line));
+    }
+    Ok(block)
+}
+
+/// `message` with `block` added above git's own comments, after the
+/// message text (a filled template) if there is any.
+fn insert_before_comments(message: &str, block: &str) -> String {
+    if block.is_empty() {
+        return message.to_string();
+    }
+    let comment = block.chars().next().unwrap_or('#');
+    let mut at = 0;
+    for line in message.split_inclusive('\n') {
+        if line.starts_with(comment) {
+            break;
+        }
+        at += line.len();
+    }
+    let (text, comments) = message.split_at(at);
+    let separator = if text.trim().is_empty() || text.ends_with("\n\n") {
+        ""
+    } else if text.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+    format!("{}{}{}{}", text, separator, block, comments)
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Placeholders {
+    kind: Option<String>,
+    scope: Option<String>,
+    ticket: Option<String>,
+}
+
+impl Placeholders {
+    /// Resolved from the current branch and the staged paths.
+    fn current(config: &Config) -> Result<Self> {
+        Self::resolve(config, &git::current_branch()?, &git::staged_files()?)
+    }
+
+    fn resolve(config: &Config, branch: &str, staged: &[String]) -> Result<Self> {
+        let convention = Convention::from_config(&config.commits)?;
+        let pattern = config
+            .branches
+            .ticket_pattern
+            .as_deref()
+            .unwrap_or(DEFAULT_TICKET_PATTERN);
+        let ticket =
+            Regex::new(pattern).map_err(|err| SherpaError::invalid_pattern(pattern, err))?;
+        Ok(Self {
+            kind: branch
+                .split_once(config.branches.separator.as_str())
+                .and_then(|(kind, _)| commit_type(kind, convention.types())),
+            scope: scope(staged),
+            ticket: ticket.find(branch).map(|m| m.as_str().to_string()),
+        })
+    }
+
+    /// A subject in `convention` with what is known filled in, for the
+    /// conventions whose shape is fixed.
+    fn subject(&self, convention: &str) -> Option<String> {
+        let ticket = match &self.ticket {
+            Some(ticket) => format!("{} ", ticket),
+            None => String::new(),
+        };
+        match convention {
+            "conventional" => Some(format!(
+                "{}{}: {}<description>",
+                self.kind.as_deref().unwrap_or("<type>"),
+                match &self.scope {
+                    Some(scope) => format!("({})", scope),
+                    None => String::new(),
+                },
+                ticket
+            )),
+            "jira" => Some(format!(
+                "{} <description>",
+                self.ticket.as_deref().unwrap_or("<TICKET-123>")
+            )),
+            _ => None,
+        }
+    }
+
+    /// `message` with the placeholders filled. Unresolved ones are left
+    /// empty, dropping the `()` or `[]` around them.
+    fn fill(&self, message: &str) -> String {
+        let mut filled: Vec<String> = Vec::new();
+        for line in message.split('\n') {
+            if !PLACEHOLDERS.iter().any(|p| line.contains(p)) {
+                filled.push(line.to_string());
+                continue;
+            }
+            let line = line
+                .replace("{type}", self.kind.as_deref().unwrap_or_default())
+                .replace("{scope}", self.scope.as_deref().unwrap_or_default())
+                .replace("{ticket}", self.ticket.as_deref().unwrap_or_default())
+                .replace("()", "")
+                .replace("[]", "");
+            filled.push(line.trim_start().to_string());
+        }
+        filled.join("\n")
+    }
+}
+
+/// The commit type a branch prefix stands for, if the convention allows it.
+/// Conventions without types (gitmoji, jira) take the prefix as-is.
+fn commit_type(prefix: &str, types: &[String]) -> Option<String> {
+    if types.is_empty() || types.iter().any(|t| t == prefix) {
+        return Some(prefix.to_string());
+    }
+    TYPE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == prefix)
+        .map(|(_, kind)| kind.to_string())
+        .filter(|kind| types.contains(kind))
+}
+
+/// The first non-generic directory shared by every staged path, e.g. `auth`
+/// for `src/auth/login.rs` and `src/auth/token.rs`.
+fn scope(staged: &[String]) -> Option<String> {
+    let mut scopes = staged.iter().map(|path| {
+        let mut dirs: Vec<&str> = path.split('/').collect();
+        dirs.pop();
+        dirs.into_iter().find(|dir| !GENERIC_DIRS.contains(dir))
+    });
+    let first = scopes.next()??;
+    scopes
+        .all(|scope| scope == Some(first))
+        .then(|| first.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn resolves_placeholders_from_branch_and_paths() {
+        let config = default_config();
+        let staged = paths(&["src/auth/login.rs", "src/auth/token.rs"]);
+        let placeholders = Placeholders::resolve(&config, "feature/ABC-12-login", &staged).unwrap();
+        assert_eq!(
+            placeholders,
+            Placeholders {
+                kind: Some("feat".to_string()),
+                scope: Some("auth".to_string()),
+                ticket: Some("ABC-12".to_string()),
+            }
+        );
+        assert_eq!(
+            placeholders.fill("{type}({scope}): {ticket} \n\n# comment ()"),
+            "feat(auth): ABC-12 \n\n# comment ()"
+        );
+
+        let staged = paths(&["src/auth/login.rs", "README.md"]);
+        let placeholders = Placeholders::resolve(&config, "wip", &staged).unwrap();
+        assert_eq!(placeholders, Placeholders::default());
+        assert_eq!(placeholders.fill("[{ticket}] {type}({scope}): "), ": ");
+    }
+
+    #[test]
+    fn suggestion_goes_above_git_comments() {
+        let placeholders = Placeholders {
+            kind: Some("fix".to_string()),
+            scope: None,
+            ticket: Some("ABC-1".to_string()),
+        };
+        assert_eq!(
+            placeholders.subject("conventional").unwrap(),
+            "fix: ABC-1 <description>"
+        );
+        assert_eq!(placeholders.subject("jira").unwrap(), "ABC-1 <description>");
+        assert_eq!(placeholders.subject("gitmoji"), None);
+
+        let block = "# Types: feat, fix\n";
+        let git = "\n# Please enter the commit message.\n";
+        assert_eq!(
+            insert_before_comments(git, block),
+            "\n# Types: feat, fix\n# Please enter the commit message.\n"
+        );
+        assert_eq!(
+            insert_before_comments(&format!("fix: x{}", git), block),
+            "fix: x\n\n# Types: feat, fix\n# Please enter the commit message.\n"
+        );
+        assert_eq!(insert_before_comments("", block), block);
+    }
+}
@@ -0,0 +1,71 @@
+//! Opt-in recording of check results as `git notes` on the tip commit, so
+//! reviewers and later audits can see the hygiene status at push time.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::check::Report;
+use crate::git;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteRecord {
+    pub sherpa_version: String,
+    pub recorded_at: u64,
+    pub branch: String,
+    pub passed: bool,
+    pub summary: serde_json::Value,
+}
+
+pub fn qualified_ref(notes_ref: &str) -> String {
+    if notes_ref.starts_with("refs/") {
+        notes_ref.to_string()
+    } else {
+        format!("refs/notes/{}", notes_ref)
+    }
+}
+
+pub fn record(report: &Report, notes_ref: &str, passed: bool) -> Result<()> {
+    let record = NoteRecord {
+        sherpa_version: env!("CARGO_PKG_VERSION").to_string(),
+        recorded_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        branch: report.branch.name.clone(),
+        passed,
+        summary: serde_json::to_value(&report.summary).context("serialize summary")?,
+    };
+    let message = serde_json::to_string_pretty(&record).context("serialize note")?;
+    git::notes_add(&qualified_ref(notes_ref), "HEAD", &message)
+}
+
+pub fn show(notes_ref: &str, rev: &str) -> Result<()> {
+    match git::notes_show(&qualified_ref(notes_ref), rev)? {
+        Some(note) => println!("{}", note),
+        None => println!("No git-sherpa note on {}", rev),
+    }
+    Ok(())
+}
+
+pub fn push(notes_ref: &str, remote: &str) -> Result<()> {
+    let full = qualified_ref(notes_ref);
+    git::push_ref(remote, &format!("{0}:{0}", full))?;
+    println!("Pushed {} to {}", full, remote);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_ref_is_namespaced() {
+        assert_eq!(qualified_ref("sherpa"), "refs/notes/sherpa");
+    }
+
+    #[test]
+    fn full_ref_is_kept() {
+        assert_eq!(qualified_ref("refs/notes/audit"), "refs/notes/audit");
+    }
+}
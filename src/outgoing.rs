@@ -0,0 +1,206 @@
+//! Pre-push scanning of the commits being pushed. Files committed a few
+//! commits ago (or with `--no-verify`) never went through the staged checks,
+//! so large files, binaries and secrets are looked for in each outgoing commit.
+//! Secrets are looked for in every blob the push sends, not just in the
+//! commit diffs: a key added and deleted two commits later, or brought in by
+//! a merge's conflict resolution, leaks through history all the same.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::attributes;
+use crate::check::Report;
+use crate::config::Config;
+use crate::git;
+use crate::large_files;
+use crate::secrets;
+
+#[derive(Debug, Default, Serialize)]
+pub struct Outgoing {
+    pub remote: String,
+    /// Commits the push would send, newest first.
+    pub commits: Vec<String>,
+    /// The pushed shas, and the remote ones already here, that delimit
+    /// `commits`.
+    #[serde(skip)]
+    pub tips: Vec<String>,
+    #[serde(skip)]
+    pub exclude: Vec<String>,
+}
+
+/// (local sha, remote sha) of each `<local ref> <local sha> <remote ref>
+/// <remote sha>` line git passes to pre-push on stdin.
+fn parse_pushed_refs(input: &str) -> Vec<(String, String)> {
+    input
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [_, local, _, remote] => Some((local.to_string(), remote.to_string())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// All-zero object id: the ref is being deleted, or does not exist remotely.
+fn is_zero(oid: &str) -> bool {
+    oid.bytes().all(|b| b == b'0')
+}
+
+/// Commits the push described by `input` (pre-push stdin) would send.
+pub fn outgoing(remote: &str, input: &str) -> Result<Outgoing> {
+    let refs = parse_pushed_refs(input);
+    let tips: Vec<String> = refs
+        .iter()
+        .filter(|(local, _)| !is_zero(local))
+        .map(|(local, _)| local.clone())
+        .collect();
+    // Remote tips we already have locally need not be sent again.
+    let mut exclude = Vec::new();
+    for (_, remote_sha) in refs.iter().filter(|(_, r)| !is_zero(r)) {
+        if git::resolve_rev(&format!("{}^{{commit}}", remote_sha))?.is_some() {
+            exclude.push(remote_sha.clone());
+        }
+    }
+    Ok(Outgoing {
+        remote: remote.to_string(),
+        commits: git::outgoing_commits(&tips, &exclude, remote)?,
+        tips,
+        exclude,
+    })
+}
+
+/// Adds what the outgoing commits introduce to `report` and refreshes the
+/// affected summary counts.
+pub fn scan(config: &Config, outgoing: Outgoing, report: &mut Report) -> Result<()> {
+    if let Some(max_kb) = config.checks.max_file_size_kb {
+        for file in large_files::in_commits(max_kb, &outgoing.commits)? {
+            let known = report
+                .large_files
+                .iter()
+                .any(|f| f.path == file.path && f.commit == file.commit);
+            if !known {
+                report.large_files.push(file);
+            }
+        }
+    }
+
+    let attributes_enabled =
+        !config.attributes.required.is_empty() || !config.attributes.binary_patterns.is_empty();
+    if attributes_enabled {
+        for commit in &outgoing.commits {
+            let paths: Vec<String> = git::commit_blobs(commit)?
+                .into_iter()
+                .map(|(_, path)| path)
+                .collect();
+            let binary = git::commit_added_binary_files(commit)?;
+            for mut violation in attributes::binary_violations(&config.attributes, &paths, &binary)?
+            {
+                violation.commit = Some(commit.clone());
+                report.attributes.violations.push(violation);
+            }
+        }
+    }
+
+    if config.sensitive.scan_content {
+        let mut diffed = HashSet::new();
+        let mut added = HashSet::new();
+        for commit in &outgoing.commits {
+            let diff = git::commit_diff(commit)?;
+            for mut finding in secrets::scan_diff(&config.sensitive, &config.files, &diff)? {
+                finding.commit = Some(commit.clone());
+                report.sensitive.content.push(finding);
+            }
+            added.extend(
+                secrets::added_lines(&diff)
+                    .into_iter()
+                    .map(|(path, _, text)| (path, text)),
+            );
+            diffed.extend(git::commit_blobs(commit)?.into_iter().map(|(hash, _)| hash));
+        }
+        scan_undiffed_blobs(config, &outgoing, &diffed, added, report)?;
+    }
+
+    report.summary.large_files = report.large_files.len();
+    report.summary.attribute_issues = report.attributes.issue_count();
+    report.summary.secrets = report
+        .sensitive
+        .content
+        .iter()
+        .filter(|f| f.severity >= config.sensitive.fail_on)
+        .count();
+    report.evaluate_rules(&config.severity);
+    report.outgoing = Some(outgoing);
+    Ok(())
+}
+
+/// Scans the whole text of the outgoing blobs no commit diff showed, which
+/// only merges introduce, and attributes each finding to the oldest commit
+/// that added the blob. `seen` holds the (path, text) of the lines already
+/// scanned, those the commit diffs added to begin with: a line is reported
+/// once, however many merged versions of the file carry it.
+fn scan_undiffed_blobs(
+    config: &Config,
+    outgoing: &Outgoing,
+    diffed: &HashSet<String>,
+    mut seen: HashSet<(String, String)>,
+    report: &mut Report,
+) -> Result<()> {
+    let mut hashes = HashSet::new();
+    let blobs: Vec<(String, String)> =
+        git::outgoing_blobs(&outgoing.tips, &outgoing.exclude, &outgoing.remote)?
+            .into_iter()
+            .filter(|(hash, path)| {
+                !diffed.contains(hash)
+                    && !config.files.is_vendored(path)
+                    && hashes.insert(hash.clone())
+            })
+            .collect();
+    let hashes: Vec<String> = blobs.iter().map(|(hash, _)| hash.clone()).collect();
+    let texts = git::blob_texts(&hashes)?;
+    for ((hash, path), text) in blobs.into_iter().zip(texts) {
+        let Some(text) = text else {
+            continue;
+        };
+        let lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| seen.insert((path.clone(), line.to_string())))
+            .map(|(i, line)| (path.clone(), i + 1, line.to_string()))
+            .collect();
+        let findings = secrets::scan_lines(&config.sensitive, &config.files, lines)?;
+        if findings.is_empty() {
+            continue;
+        }
+        let commit =
+            git::introducing_commit(&hash, &outgoing.tips, &outgoing.exclude, &outgoing.remote)?;
+        for mut finding in findings {
+            finding.commit = commit.clone();
+            report.sensitive.content.push(finding);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pre_push_stdin() {
+        let zero = "0".repeat(40);
+        let input = format!(
+            "refs/heads/feat/x {a} refs/heads/feat/x {z}\n(delete) {z} refs/heads/old {b}\n\n",
+            a = "a".repeat(40),
+            b = "b".repeat(40),
+            z = zero,
+        );
+        let refs = parse_pushed_refs(&input);
+        assert_eq!(refs.len(), 2);
+        assert_eq!(refs[0], ("a".repeat(40), zero.clone()));
+        assert!(is_zero(&refs[1].0));
+        assert!(!is_zero(&refs[1].1));
+    }
+}
@@ -0,0 +1,311 @@
+//! `policy simulate`: evaluates the configured policy against a hypothetical
+//! branch, commit messages and changed files, without reading repository
+//! state, so config changes can be tried out (and tested in the config's own
+//! CI) before anyone's commit is rejected by them.
+//!
+//! `policy sync`: fetches the org-wide policy named by `[policy] source`,
+//! verifies it and caches it for config loading to layer under the repo
+//! config, so a platform team can roll rules out to many repositories.
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use glob_match::glob_match;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::branch_naming::BranchNaming;
+use crate::check::AUTOSQUASH_PREFIXES;
+use crate::cli::{Format, DEFAULT_CONFIG_PATH};
+use crate::config::{
+    load_config, load_policy_config, parse_layer, policy_cache_path, Config, PolicyConfig,
+};
+use crate::error::ExitStatus;
+use crate::git;
+use crate::lint;
+use crate::protected;
+use crate::sensitive;
+use crate::severity::{self, RuleSeverity};
+use crate::signatures::SignatureStatus;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize)]
+pub struct Simulation {
+    /// Whether every check with severity `error` passed.
+    pub passed: bool,
+    pub checks: Vec<SimulatedCheck>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulatedCheck {
+    /// Rule ID as in `[severity]`; `protected-branch` is the pre-push hook's
+    /// guard and always an error.
+    pub rule: &'static str,
+    pub input: String,
+    pub severity: RuleSeverity,
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+}
+
+pub struct PolicyInputs<'a> {
+    pub branch: Option<&'a str>,
+    pub messages: &'a [String],
+    pub files: &'a [String],
+}
+
+pub fn simulate(config_path: &Path, inputs: PolicyInputs, format: Format) -> Result<ExitStatus> {
+    let config = load_config(config_path)?;
+    let simulation = evaluate(&config, &inputs)?;
+    match format {
+        Format::Text => print_text(&simulation),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&simulation)?),
+    }
+    Ok(ExitStatus::from_violations(!simulation.passed))
+}
+
+/// Every check the inputs are subject to; rules set to `off` are left out.
+/// Protected paths are judged on `branch`, or on no allowlisted branch
+/// without one.
+pub fn evaluate(config: &Config, inputs: &PolicyInputs) -> Result<Simulation> {
+    let mut checks = Vec::new();
+    let mut push = |rule: &'static str, input: &str, problems: Vec<String>| {
+        let severity = match rule {
+            "protected-branch" => RuleSeverity::Error,
+            _ => severity::of(rule, &config.severity),
+        };
+        if severity != RuleSeverity::Off {
+            checks.push(SimulatedCheck {
+                rule,
+                input: input.to_string(),
+                severity,
+                valid: problems.is_empty(),
+                problems,
+            });
+        }
+    };
+
+    if let Some(branch) = inputs.branch {
+        let problems = BranchNaming::from_config(&config.branches)?.problems(branch);
+        push("branch-pattern", branch, problems);
+
+        // Configured branches only: forge protection needs the network.
+        let problems = config
+            .hooks
+            .protected_branches
+            .iter()
+            .filter(|protected| glob_match(protected, branch))
+            .map(|protected| {
+                format!(
+                    "direct pushes are blocked ([hooks] protected_branches `{}`)",
+                    protected
+                )
+            })
+            .collect();
+        push("protected-branch", branch, problems);
+    }
+
+    for message in inputs.messages {
+        let mut problems = lint::lint_commit_message(config, message)?.problems;
+        if config.commits.forbid_fixup_commits
+            && AUTOSQUASH_PREFIXES.iter().any(|p| message.starts_with(p))
+        {
+            problems.push("not squashed yet (forbid_fixup_commits)".to_string());
+        }
+        let subject = message.lines().next().unwrap_or_default();
+        push("commit-convention", subject, problems);
+    }
+
+    let sensitive = sensitive::classify_sensitive_files(
+        inputs.files,
+        &config.sensitive.patterns,
+        &config.sensitive.severity,
+    );
+    let protected = protected::protected_changes(
+        &config.checks,
+        inputs.branch.unwrap_or_default(),
+        inputs.files,
+    )?;
+    for file in inputs.files {
+        let problems = sensitive
+            .iter()
+            .filter(|f| &f.path == file && f.severity >= config.sensitive.fail_on)
+            .map(|f| match &f.name {
+                Some(name) => format!("sensitive file ({}, {})", f.severity, name),
+                None => format!("sensitive file ({}, matches `{}`)", f.severity, f.pattern),
+            })
+            .collect();
+        push("sensitive-files", file, problems);
+        if !config.checks.protected_paths.is_empty() {
+            let problems = protected
+                .iter()
+                .filter(|change| &change.path == file)
+                .map(|change| format!("protected path (matches `{}`)", change.pattern))
+                .collect();
+            push("protected-paths", file, problems);
+        }
+    }
+
+    Ok(Simulation {
+        passed: checks
+            .iter()
+            .all(|c| c.valid || c.severity < RuleSeverity::Error),
+        checks,
+    })
+}
+
+pub fn sync(config_path: &Path) -> Result<()> {
+    let policy = &load_policy_config(config_path)?;
+    let Some(source) = policy.source.as_deref() else {
+        bail!(
+            "No [policy] source in {}; nothing to sync",
+            config_path.display()
+        );
+    };
+    let contents = fetch(policy, source)?;
+    let digest = sha256_hex(contents.as_bytes());
+    if let Some(expected) = &policy.sha256 {
+        if !digest.eq_ignore_ascii_case(expected.trim()) {
+            bail!(
+                "Policy from {} has SHA-256 {}, but [policy] sha256 expects {}",
+                source,
+                digest,
+                expected
+            );
+        }
+    }
+    // A broken policy would fail config loading in every repository.
+    parse_layer(Path::new(source), &contents)?;
+
+    let cache = policy_cache_path()?;
+    if let Some(dir) = cache.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    }
+    fs::write(&cache, &contents).with_context(|| format!("write {}", cache.display()))?;
+    println!("{}", format!("Synced policy from {}", source).green());
+    println!("  {}", format!("sha256 {}", digest).dimmed());
+    println!(
+        "  {}",
+        format!(
+            "Layered under the user config and {}",
+            config_path.display()
+        )
+        .dimmed()
+    );
+    Ok(())
+}
+
+/// The policy file: downloaded, read from disk, or read from a shallow clone
+/// when `source` is a git repository.
+fn fetch(policy: &PolicyConfig, source: &str) -> Result<String> {
+    if source.ends_with(".toml") {
+        if policy.verify_signature {
+            bail!("[policy] verify_signature needs a git repository as source");
+        }
+        if source.starts_with("http://") {
+            bail!("Refusing to fetch the policy over plain HTTP; use an https:// source");
+        }
+        if source.starts_with("https://") {
+            return ureq::get(source)
+                .timeout(FETCH_TIMEOUT)
+                .set("User-Agent", "git-sherpa")
+                .call()
+                .with_context(|| format!("download {}", source))?
+                .into_string()
+                .with_context(|| format!("read {}", source));
+        }
+        return fs::read_to_string(source).with_context(|| format!("read {}", source));
+    }
+
+    let checkout = policy_cache_path()?.with_file_name("policy-source");
+    let _ = fs::remove_dir_all(&checkout);
+    let read = || -> Result<String> {
+        git::clone_shallow(source, policy.rev.as_deref(), &checkout)?;
+        if policy.verify_signature {
+            let code = git::head_signature_code(&checkout)?;
+            if let Some(status) = SignatureStatus::from_code(&code) {
+                bail!(
+                    "Policy commit in {} failed signature verification ({})",
+                    source,
+                    status.label()
+                );
+            }
+        }
+        let file = policy.path.as_deref().unwrap_or(DEFAULT_CONFIG_PATH);
+        fs::read_to_string(checkout.join(file))
+            .with_context(|| format!("read {} from {}", file, source))
+    };
+    let contents = read();
+    let _ = fs::remove_dir_all(&checkout);
+    contents
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn print_text(simulation: &Simulation) {
+    println!("{}", "Policy simulation:".bold());
+    for check in &simulation.checks {
+        let status = match (check.valid, check.severity) {
+            (true, _) => "OK".green(),
+            (false, RuleSeverity::Error) => "FAIL".red(),
+            (false, _) => "WARN".yellow(),
+        };
+        println!("  {:<4} {:<18} {}", status, check.rule, check.input);
+        for problem in &check.problems {
+            println!("         - {}", problem);
+        }
+    }
+    if simulation.passed {
+        println!("\n{}", "Policy: inputs would be accepted".green().bold());
+    } else {
+        println!("\n{}", "Policy: inputs would be REJECTED".red().bold());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    #[test]
+    fn simulates_without_a_repository() {
+        let mut config = default_config();
+        config.hooks.protected_branches = vec!["main".to_string()];
+        let messages = vec!["feat: add login".to_string(), "fix stuff".to_string()];
+        let files = vec!["src/login.rs".to_string(), ".env".to_string()];
+        let simulation = evaluate(
+            &config,
+            &PolicyInputs {
+                branch: Some("main"),
+                messages: &messages,
+                files: &files,
+            },
+        )
+        .unwrap();
+
+        let failing: Vec<(&str, &str)> = simulation
+            .checks
+            .iter()
+            .filter(|c| !c.valid)
+            .map(|c| (c.rule, c.input.as_str()))
+            .collect();
+        assert_eq!(
+            failing,
+            vec![
+                ("branch-pattern", "main"),
+                ("protected-branch", "main"),
+                ("commit-convention", "fix stuff"),
+                ("sensitive-files", ".env"),
+            ]
+        );
+        assert!(!simulation.passed);
+    }
+}
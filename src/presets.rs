@@ -0,0 +1,92 @@
+//! Config templates `init --preset` writes instead of the defaults. Each is
+//! an embedded TOML asset under `src/presets/`, written out as-is so its
+//! comments stay in the user's config; the leading comment describes it.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+use crate::config::Config;
+
+pub struct Preset {
+    pub name: &'static str,
+    pub toml: &'static str,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "rust-oss",
+        toml: include_str!("presets/rust-oss.toml"),
+    },
+    Preset {
+        name: "node-app",
+        toml: include_str!("presets/node-app.toml"),
+    },
+    Preset {
+        name: "monorepo",
+        toml: include_str!("presets/monorepo.toml"),
+    },
+    Preset {
+        name: "enterprise-strict",
+        toml: include_str!("presets/enterprise-strict.toml"),
+    },
+];
+
+impl Preset {
+    /// The leading comment block, joined into one line.
+    pub fn description(&self) -> String {
+        self.toml
+            .lines()
+            .map_while(|line| line.strip_prefix('#'))
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn config(&self) -> Result<Config> {
+        Ok(toml::from_str(self.toml)?)
+    }
+}
+
+pub fn find(name: &str) -> Result<&'static Preset> {
+    match PRESETS.iter().find(|preset| preset.name == name) {
+        Some(preset) => Ok(preset),
+        None => bail!(
+            "Unknown preset '{}'; expected one of {} (see `init --list-presets`)",
+            name,
+            names().join(", ")
+        ),
+    }
+}
+
+fn names() -> Vec<&'static str> {
+    PRESETS.iter().map(|preset| preset.name).collect()
+}
+
+/// `init --list-presets`.
+pub fn list() {
+    println!("{}", "Presets (git-sherpa init --preset <name>):".bold());
+    for preset in PRESETS {
+        println!("  {:<18} {}", preset.name.cyan(), preset.description());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::branch_naming::BranchNaming;
+    use crate::convention::Convention;
+
+    #[test]
+    fn every_preset_is_a_valid_config() {
+        for preset in PRESETS {
+            let config = preset
+                .config()
+                .unwrap_or_else(|err| panic!("{}: {:#}", preset.name, err));
+            Convention::from_config(&config.commits).unwrap();
+            BranchNaming::from_config(&config.branches).unwrap();
+            assert!(!preset.description().is_empty(), "{}", preset.name);
+        }
+        assert!(find("monorepo").is_ok());
+        assert!(find("nope").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+//! `[checks] protected_paths`: staged changes to locked-down directories
+//! (e.g. owned by another team) are only allowed on allowlisted branches.
+
+use anyhow::Result;
+use glob_match::glob_match;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::config::CheckConfig;
+use crate::error::SherpaError;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtectedChange {
+    pub path: String,
+    /// The `protected_paths` glob it matched.
+    pub pattern: String,
+}
+
+/// Staged files under a protected glob, unless `branch` matches one of
+/// `protected_paths_allowed_branches`.
+pub fn protected_changes(
+    config: &CheckConfig,
+    branch: &str,
+    staged: &[String],
+) -> Result<Vec<ProtectedChange>> {
+    if config.protected_paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    for pattern in &config.protected_paths_allowed_branches {
+        let regex =
+            Regex::new(pattern).map_err(|err| SherpaError::invalid_pattern(pattern, err))?;
+        if regex.is_match(branch) {
+            return Ok(Vec::new());
+        }
+    }
+    Ok(matches(&config.protected_paths, staged))
+}
+
+fn matches(patterns: &[String], paths: &[String]) -> Vec<ProtectedChange> {
+    paths
+        .iter()
+        .filter_map(|path| {
+            let pattern = patterns.iter().find(|p| glob_match(p, path))?;
+            Some(ProtectedChange {
+                path: path.clone(),
+                pattern: pattern.clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn flags_paths_under_protected_globs() {
+        let found = matches(
+            &strings(&["migrations/**", "infra/prod/**"]),
+            &strings(&["migrations/001.sql", "src/main.rs", "infra/prod/db.tf"]),
+        );
+        let paths: Vec<&str> = found.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, ["migrations/001.sql", "infra/prod/db.tf"]);
+        assert_eq!(found[1].pattern, "infra/prod/**");
+    }
+}
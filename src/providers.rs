@@ -0,0 +1,383 @@
+//! Shared client for forge and tracker APIs (GitHub, GitLab, Jira): token
+//! lookup, on-disk response caching and rate-limit backoff, so integrations
+//! do not each make ad-hoc HTTP calls.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::auth;
+use crate::cache;
+use crate::git;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Retries after a rate-limit or server error before giving up.
+const MAX_RETRIES: u32 = 3;
+/// Longest we are willing to wait for a rate limit to reset.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Longest a "not found" is served from the cache, whatever the caller's
+/// TTL: the resource may be created, or a token added, at any time.
+const NOT_FOUND_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Provider {
+    GitHub,
+    GitLab { host: String },
+    Jira { base_url: String },
+}
+
+impl Provider {
+    /// Forge serving `host`, if it is one we know how to talk to.
+    pub fn forge_for_host(host: &str) -> Option<Provider> {
+        if host == "github.com" {
+            Some(Provider::GitHub)
+        } else if host.contains("gitlab") {
+            Some(Provider::GitLab {
+                host: host.to_string(),
+            })
+        } else {
+            None
+        }
+    }
+
+    fn api_base(&self) -> String {
+        match self {
+            Provider::GitHub => "https://api.github.com".to_string(),
+            Provider::GitLab { host } => format!("https://{}/api/v4", host),
+            Provider::Jira { base_url } => format!("{}/rest/api/2", base_url.trim_end_matches('/')),
+        }
+    }
+
+    /// Host the token belongs to, used as the keychain and credential key.
+    pub fn host(&self) -> String {
+        match self {
+            Provider::GitHub => "github.com".to_string(),
+            Provider::GitLab { host } => host.clone(),
+            Provider::Jira { base_url } => base_url
+                .split("://")
+                .last()
+                .unwrap_or(base_url)
+                .split('/')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    /// Environment variables checked for a token, in order.
+    pub fn token_vars(&self) -> &'static [&'static str] {
+        match self {
+            Provider::GitHub => &["GITHUB_TOKEN", "GH_TOKEN"],
+            Provider::GitLab { .. } => &["GITLAB_TOKEN"],
+            Provider::Jira { .. } => &["JIRA_TOKEN"],
+        }
+    }
+
+    /// Token from the environment, else the keychain (`auth login`), else
+    /// git's credential helpers.
+    pub fn token(&self) -> Option<String> {
+        self.lookup_token().map(|(token, _)| token)
+    }
+
+    pub fn token_source(&self) -> Option<TokenSource> {
+        self.lookup_token().map(|(_, source)| source)
+    }
+
+    fn lookup_token(&self) -> Option<(String, TokenSource)> {
+        let host = self.host();
+        self.token_vars()
+            .iter()
+            .find_map(|var| {
+                let token = std::env::var(var).ok().filter(|t| !t.is_empty())?;
+                Some((token, TokenSource::Env(var)))
+            })
+            .or_else(|| auth::stored_token(&host).map(|t| (t, TokenSource::Keychain)))
+            .or_else(|| {
+                let token = git::credential_fill(&host).ok().flatten()?;
+                Some((token, TokenSource::CredentialHelper))
+            })
+    }
+
+    fn auth_header(&self, token: &str) -> (&'static str, String) {
+        match self {
+            Provider::GitLab { .. } => ("PRIVATE-TOKEN", token.to_string()),
+            _ => ("Authorization", format!("Bearer {}", token)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenSource {
+    Env(&'static str),
+    Keychain,
+    CredentialHelper,
+}
+
+/// Outcome of an API call. Callers decide how to treat `Unavailable`
+/// (offline, no credentials, rate limited for too long).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fetch {
+    Found(Value),
+    /// A 404 or 410 to an authenticated request. Without a token, forges
+    /// answer 404 for private resources too, so that is `Unavailable`.
+    NotFound,
+    Unavailable,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cache {
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    /// `None` records a 404/410.
+    body: Option<Value>,
+    fetched_at: u64,
+}
+
+pub struct Client {
+    provider: Provider,
+    token: OnceCell<Option<String>>,
+    cache_path: Option<PathBuf>,
+}
+
+impl Client {
+    pub fn new(provider: Provider) -> Self {
+        Self {
+            provider,
+            token: OnceCell::new(),
+            cache_path: cache::dir().ok().map(|dir| dir.join(cache::HTTP_FILE)),
+        }
+    }
+
+    pub fn provider(&self) -> &Provider {
+        &self.provider
+    }
+
+    /// GETs `path` (relative to the provider's API base), serving responses
+    /// younger than `ttl` from the cache, or than [`NOT_FOUND_TTL`] if that
+    /// is shorter and the response was a "not found". Only definite answers
+    /// are cached.
+    pub fn get(&self, path: &str, ttl: Duration) -> Fetch {
+        let url = format!("{}{}", self.provider.api_base(), path);
+        let mut cache = self.load_cache();
+        let now = now_secs();
+        if let Some(entry) = cache.entries.get(&url).filter(|e| {
+            let ttl = match e.body {
+                Some(_) => ttl,
+                None => ttl.min(NOT_FOUND_TTL),
+            };
+            now.saturating_sub(e.fetched_at) < ttl.as_secs()
+        }) {
+            return match &entry.body {
+                Some(body) => Fetch::Found(body.clone()),
+                None => Fetch::NotFound,
+            };
+        }
+
+        let fetch = self.request(&url);
+        let body = match &fetch {
+            Fetch::Found(body) => Some(body.clone()),
+            Fetch::NotFound => None,
+            Fetch::Unavailable => return fetch,
+        };
+        cache.entries.insert(
+            url,
+            CacheEntry {
+                body,
+                fetched_at: now,
+            },
+        );
+        // The cache is an optimization; failing to persist it is not an error.
+        let _ = self.save_cache(&cache);
+        fetch
+    }
+
+    fn request(&self, url: &str) -> Fetch {
+        let token = self.token.get_or_init(|| self.provider.token());
+        for attempt in 0..=MAX_RETRIES {
+            let mut request = ureq::get(url)
+                .timeout(REQUEST_TIMEOUT)
+                .set("User-Agent", "git-sherpa")
+                .set("Accept", "application/json");
+            if let Some(token) = token {
+                let (header, value) = self.provider.auth_header(token);
+                request = request.set(header, &value);
+            }
+            match request.call() {
+                Ok(response) => {
+                    return response
+                        .into_json()
+                        .map(Fetch::Found)
+                        .unwrap_or(Fetch::Unavailable)
+                }
+                Err(ureq::Error::Status(404 | 410, _)) if token.is_some() => {
+                    return Fetch::NotFound
+                }
+                // Private resources look missing to anonymous requests.
+                Err(ureq::Error::Status(404 | 410, _)) => return Fetch::Unavailable,
+                Err(ureq::Error::Status(status, response)) => {
+                    let wait = backoff(status, &response, attempt, now_secs());
+                    match wait {
+                        Some(wait) if attempt < MAX_RETRIES => std::thread::sleep(wait),
+                        _ => return Fetch::Unavailable,
+                    }
+                }
+                Err(_) => return Fetch::Unavailable,
+            }
+        }
+        Fetch::Unavailable
+    }
+
+    fn load_cache(&self) -> Cache {
+        self.cache_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_cache(&self, cache: &Cache) -> Result<()> {
+        let Some(path) = &self.cache_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+        }
+        let json = serde_json::to_string(cache)?;
+        fs::write(path, json).with_context(|| format!("write {}", path.display()))
+    }
+}
+
+/// How long to wait before retrying a failed request, or `None` if retrying
+/// is pointless (client error) or the wait would exceed `MAX_BACKOFF`.
+fn backoff(status: u16, response: &ureq::Response, attempt: u32, now: u64) -> Option<Duration> {
+    backoff_from(
+        status,
+        response.header("retry-after"),
+        response.header("x-ratelimit-remaining"),
+        response.header("x-ratelimit-reset"),
+        attempt,
+        now,
+    )
+}
+
+fn backoff_from(
+    status: u16,
+    retry_after: Option<&str>,
+    remaining: Option<&str>,
+    reset: Option<&str>,
+    attempt: u32,
+    now: u64,
+) -> Option<Duration> {
+    let rate_limited = status == 429 || (status == 403 && remaining == Some("0"));
+    if !rate_limited && status < 500 {
+        return None;
+    }
+    let wait = retry_after
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .or_else(|| {
+            reset
+                .and_then(|s| s.trim().parse::<u64>().ok())
+                .map(|reset| reset.saturating_sub(now))
+        })
+        .map(Duration::from_secs)
+        .unwrap_or_else(|| Duration::from_secs(1 << attempt));
+    (wait <= MAX_BACKOFF).then_some(wait)
+}
+
+/// `text` percent-encoded for one path segment or query value of an API
+/// URL: everything but the unreserved characters, so a branch named
+/// `fix/#12?` or `50%-off` still names itself.
+pub fn encode_component(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components_are_percent_encoded() {
+        assert_eq!(encode_component("feat/login-page"), "feat%2Flogin-page");
+        assert_eq!(encode_component("fix/#12?x=1&y"), "fix%2F%2312%3Fx%3D1%26y");
+        assert_eq!(encode_component("50%_off~v1.2"), "50%25_off~v1.2");
+        assert_eq!(encode_component("café"), "caf%C3%A9");
+    }
+
+    #[test]
+    fn forge_detection() {
+        assert_eq!(
+            Provider::forge_for_host("github.com"),
+            Some(Provider::GitHub)
+        );
+        assert!(matches!(
+            Provider::forge_for_host("gitlab.example.com"),
+            Some(Provider::GitLab { .. })
+        ));
+        assert_eq!(Provider::forge_for_host("bitbucket.org"), None);
+    }
+
+    #[test]
+    fn api_bases_and_hosts() {
+        let jira = Provider::Jira {
+            base_url: "https://acme.atlassian.net/".to_string(),
+        };
+        assert_eq!(jira.api_base(), "https://acme.atlassian.net/rest/api/2");
+        assert_eq!(jira.host(), "acme.atlassian.net");
+        let gitlab = Provider::GitLab {
+            host: "gitlab.com".to_string(),
+        };
+        assert_eq!(gitlab.api_base(), "https://gitlab.com/api/v4");
+        assert_eq!(gitlab.auth_header("t").0, "PRIVATE-TOKEN");
+    }
+
+    #[test]
+    fn backoff_honors_rate_limit_headers() {
+        assert_eq!(
+            backoff_from(429, Some("5"), None, None, 0, 0),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            backoff_from(403, None, Some("0"), Some("1010"), 0, 1000),
+            Some(Duration::from_secs(10))
+        );
+        // Resets too far in the future are not worth waiting for.
+        assert_eq!(
+            backoff_from(403, None, Some("0"), Some("5000"), 0, 1000),
+            None
+        );
+    }
+
+    #[test]
+    fn backoff_is_exponential_for_server_errors() {
+        assert_eq!(
+            backoff_from(502, None, None, None, 2, 0),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(backoff_from(401, None, None, None, 0, 0), None);
+        assert_eq!(backoff_from(403, None, Some("12"), None, 0, 0), None);
+    }
+}
@@ -0,0 +1,149 @@
+//! Lookup of the pull/merge request linked to the current branch on GitHub or
+//! GitLab.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::git;
+use crate::providers::{encode_component, Client, Fetch, Provider};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrState {
+    Open,
+    Draft,
+    Merged,
+    Closed,
+    None,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequestStatus {
+    pub forge: String,
+    pub state: PrState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// PR state changes often; cache just long enough to spare repeated checks.
+const CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Queries the forge behind `origin` for a PR/MR whose head is `branch`.
+/// Returns `None` when the remote is not a known forge or the API is unreachable.
+pub fn pull_request_status(branch: &str) -> Option<PullRequestStatus> {
+    let url = git::remote_url("origin").ok().flatten()?;
+    let (host, slug) = git::parse_remote_url(&url)?;
+    let client = Client::new(Provider::forge_for_host(&host)?);
+    match client.provider() {
+        Provider::GitHub => {
+            let owner = slug.split('/').next()?;
+            let path = format!(
+                "/repos/{}/pulls?state=all&head={}:{}",
+                slug,
+                owner,
+                encode_component(branch)
+            );
+            found(client.get(&path, CACHE_TTL)).map(|pulls| parse_github(&pulls))
+        }
+        Provider::GitLab { .. } => {
+            let path = format!(
+                "/projects/{}/merge_requests?state=all&source_branch={}",
+                encode_component(&slug),
+                encode_component(branch)
+            );
+            found(client.get(&path, CACHE_TTL)).map(|mrs| parse_gitlab(&mrs))
+        }
+        Provider::Jira { .. } => None,
+    }
+}
+
+fn found(fetch: Fetch) -> Option<Value> {
+    match fetch {
+        Fetch::Found(body) => Some(body),
+        Fetch::NotFound | Fetch::Unavailable => None,
+    }
+}
+
+fn none_status(forge: &str) -> PullRequestStatus {
+    PullRequestStatus {
+        forge: forge.to_string(),
+        state: PrState::None,
+        number: None,
+        url: None,
+    }
+}
+
+fn parse_github(pulls: &Value) -> PullRequestStatus {
+    let Some(pr) = pulls.as_array().and_then(|a| a.first()) else {
+        return none_status("github");
+    };
+    let state = if !pr["merged_at"].is_null() {
+        PrState::Merged
+    } else if pr["state"] == "closed" {
+        PrState::Closed
+    } else if pr["draft"].as_bool().unwrap_or(false) {
+        PrState::Draft
+    } else {
+        PrState::Open
+    };
+    PullRequestStatus {
+        forge: "github".to_string(),
+        state,
+        number: pr["number"].as_u64(),
+        url: pr["html_url"].as_str().map(str::to_string),
+    }
+}
+
+fn parse_gitlab(mrs: &Value) -> PullRequestStatus {
+    let Some(mr) = mrs.as_array().and_then(|a| a.first()) else {
+        return none_status("gitlab");
+    };
+    let state = match mr["state"].as_str() {
+        Some("merged") => PrState::Merged,
+        Some("closed") | Some("locked") => PrState::Closed,
+        _ if mr["draft"].as_bool().unwrap_or(false) => PrState::Draft,
+        _ => PrState::Open,
+    };
+    PullRequestStatus {
+        forge: "gitlab".to_string(),
+        state,
+        number: mr["iid"].as_u64(),
+        url: mr["web_url"].as_str().map(str::to_string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn github_states() {
+        let merged =
+            json!([{ "number": 4, "state": "closed", "merged_at": "2024-01-01T00:00:00Z" }]);
+        assert_eq!(parse_github(&merged).state, PrState::Merged);
+        let draft = json!([{ "number": 5, "state": "open", "merged_at": null, "draft": true }]);
+        assert_eq!(parse_github(&draft).state, PrState::Draft);
+        let open = json!([{ "number": 6, "state": "open", "merged_at": null, "draft": false }]);
+        let status = parse_github(&open);
+        assert_eq!(status.state, PrState::Open);
+        assert_eq!(status.number, Some(6));
+    }
+
+    #[test]
+    fn no_pull_request() {
+        assert_eq!(parse_github(&json!([])).state, PrState::None);
+        assert_eq!(parse_gitlab(&json!([])).state, PrState::None);
+    }
+
+    #[test]
+    fn gitlab_states() {
+        let mr = json!([{ "iid": 9, "state": "opened", "draft": true }]);
+        assert_eq!(parse_gitlab(&mr).state, PrState::Draft);
+        let mr = json!([{ "iid": 9, "state": "merged" }]);
+        assert_eq!(parse_gitlab(&mr).state, PrState::Merged);
+    }
+}
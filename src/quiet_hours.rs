@@ -0,0 +1,139 @@
+//! `[checks] quiet_hours` / `quiet_weekends`: commits authored late at night
+//! or on weekends, in the author's own time zone, for teams watching for
+//! crunch. The rule is advisory: it warns but never fails the check.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::config::CheckConfig;
+use crate::git;
+
+/// Day names by ISO weekday (`%u`), independent of the locale.
+const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// A time-of-day window in minutes since midnight, end excluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    start: u32,
+    end: u32,
+}
+
+impl Window {
+    /// `22:00-07:00`; a window that ends before it starts wraps past
+    /// midnight.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (start, end) = text.split_once('-')?;
+        Some(Self {
+            start: minutes(start.trim())?,
+            end: minutes(end.trim())?,
+        })
+    }
+
+    fn contains(self, minute: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+/// Minutes since midnight of `HH:MM`.
+fn minutes(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuietReason {
+    /// Inside `quiet_hours`.
+    Night,
+    /// On a Saturday or Sunday, with `quiet_weekends`.
+    Weekend,
+}
+
+impl QuietReason {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Night => "quiet hours",
+            Self::Weekend => "weekend",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuietCommit {
+    pub hash: String,
+    /// When the author wrote it, in their time zone, e.g. `Sat 23:14 +0200`.
+    pub authored: String,
+    pub reason: QuietReason,
+}
+
+/// Commits among `hashes` authored in `quiet_hours` or, with
+/// `quiet_weekends`, on a weekend.
+pub fn quiet_commits(config: &CheckConfig, hashes: &[String]) -> Result<Vec<QuietCommit>> {
+    let window = config.quiet_hours.as_deref().and_then(Window::parse);
+    Ok(git::author_local_times(hashes)?
+        .into_iter()
+        .filter_map(|(hash, time)| {
+            let (authored, reason) = classify(&time, window, config.quiet_weekends)?;
+            Some(QuietCommit {
+                hash,
+                authored,
+                reason,
+            })
+        })
+        .collect())
+}
+
+/// The display form and the reason to flag an author time given as
+/// `<ISO weekday> <HH:MM> <offset>`, e.g. `6 23:14 +0200`, if it is flagged.
+fn classify(time: &str, window: Option<Window>, weekends: bool) -> Option<(String, QuietReason)> {
+    let mut parts = time.split(' ');
+    let day: usize = parts.next()?.parse().ok()?;
+    let clock = parts.next()?;
+    let offset = parts.next().unwrap_or_default();
+    let minute = minutes(clock)?;
+    let reason = if window.is_some_and(|w| w.contains(minute)) {
+        QuietReason::Night
+    } else if weekends && day >= 6 {
+        QuietReason::Weekend
+    } else {
+        return None;
+    };
+    let name = DAYS.get(day.checked_sub(1)?)?;
+    Some((format!("{} {} {}", name, clock, offset), reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_wrap_past_midnight() {
+        let night = Window::parse("22:00-07:00").unwrap();
+        assert!(night.contains(23 * 60) && night.contains(0) && night.contains(6 * 60 + 59));
+        assert!(!night.contains(7 * 60) && !night.contains(12 * 60));
+        let lunch = Window::parse("12:00 - 13:30").unwrap();
+        assert!(lunch.contains(13 * 60) && !lunch.contains(13 * 60 + 30));
+        assert_eq!(Window::parse("24:00-07:00"), None);
+        assert_eq!(Window::parse("evenings"), None);
+    }
+
+    #[test]
+    fn author_times_are_flagged_by_window_then_weekend() {
+        let night = Window::parse("22:00-07:00");
+        assert_eq!(
+            classify("3 23:14 +0200", night, true),
+            Some(("Wed 23:14 +0200".to_string(), QuietReason::Night))
+        );
+        assert_eq!(
+            classify("7 10:00 -0500", night, true),
+            Some(("Sun 10:00 -0500".to_string(), QuietReason::Weekend))
+        );
+        assert_eq!(classify("7 10:00 -0500", night, false), None);
+        assert_eq!(classify("2 09:30 +0000", night, true), None);
+    }
+}
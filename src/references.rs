@@ -0,0 +1,188 @@
+//! Verification of issue references in commit footers (`Fixes #123`,
+//! `Closes JIRA-42`) against GitHub and Jira.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::config::ReferencesConfig;
+use crate::git;
+use crate::providers::{Client, Fetch, Provider};
+
+/// Issues rarely disappear, so lookups are cached for a day.
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const FOOTER_KEYWORDS: &[&str] = &[
+    "fix", "fixes", "fixed", "close", "closes", "closed", "resolve", "resolves", "resolved", "ref",
+    "refs",
+];
+
+#[derive(Debug, Default, Serialize)]
+pub struct ReferencesReport {
+    pub checked: usize,
+    pub unverified: usize,
+    pub dangling: Vec<DanglingReference>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DanglingReference {
+    pub commit: String,
+    pub reference: String,
+}
+
+/// Issue references found on footer lines such as `Fixes #12, #13`. The
+/// footer is the last paragraph of the body: a `Fixes #3` line further up is
+/// prose.
+pub fn extract_references(message: &str) -> Vec<String> {
+    let token = reference_regex();
+    let mut refs = Vec::new();
+    for line in footer(message) {
+        let trimmed = line.trim_start();
+        let keyword: String = trimmed
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+        if !FOOTER_KEYWORDS.contains(&keyword.to_lowercase().as_str()) {
+            continue;
+        }
+        for m in token.find_iter(&trimmed[keyword.len()..]) {
+            let r = m.as_str().to_string();
+            if !refs.contains(&r) {
+                refs.push(r);
+            }
+        }
+    }
+    refs
+}
+
+/// The references `require_issue_reference` accepts: any in the subject,
+/// such as `fix: crash on login (#12)`, then the footer's.
+pub fn subject_and_footer_references(message: &str) -> Vec<String> {
+    let subject = message.lines().next().unwrap_or_default();
+    let mut refs: Vec<String> = reference_regex()
+        .find_iter(subject)
+        .map(|m| m.as_str().to_string())
+        .collect();
+    for r in extract_references(message) {
+        if !refs.contains(&r) {
+            refs.push(r);
+        }
+    }
+    refs
+}
+
+fn reference_regex() -> Regex {
+    Regex::new(r"#\d+|\b[A-Z][A-Z0-9]+-\d+\b").expect("valid reference regex")
+}
+
+/// The lines of the last paragraph of `message`, if it is not the subject's.
+fn footer(message: &str) -> Vec<&str> {
+    let lines: Vec<&str> = message.lines().collect();
+    let end = lines
+        .iter()
+        .rposition(|line| !line.trim().is_empty())
+        .map_or(0, |last| last + 1);
+    let start = lines[..end]
+        .iter()
+        .rposition(|line| line.trim().is_empty())
+        .map_or(end, |blank| blank + 1);
+    lines[start..end].to_vec()
+}
+
+pub fn build_references_report(
+    config: &ReferencesConfig,
+    commits: &[(String, String)],
+) -> Result<ReferencesReport> {
+    let mut report = ReferencesReport::default();
+    if !config.verify {
+        return Ok(report);
+    }
+
+    let github_repo = config.github_repo.clone().or_else(|| {
+        git::remote_url("origin")
+            .ok()
+            .flatten()
+            .and_then(|url| git::parse_remote_url(&url))
+            .filter(|(host, _)| host == "github.com")
+            .map(|(_, slug)| slug)
+    });
+    let github = Client::new(Provider::GitHub);
+    let jira = config.jira_url.as_ref().map(|base_url| {
+        Client::new(Provider::Jira {
+            base_url: base_url.clone(),
+        })
+    });
+
+    for (hash, _) in commits {
+        let message = git::commit_message(hash)?;
+        for reference in extract_references(&message) {
+            report.checked += 1;
+            let path = match reference.strip_prefix('#') {
+                Some(number) => github_repo
+                    .as_ref()
+                    .map(|repo| (&github, format!("/repos/{}/issues/{}", repo, number))),
+                None => jira
+                    .as_ref()
+                    .map(|jira| (jira, format!("/issue/{}?fields=id", reference))),
+            };
+            let fetch = match path {
+                Some((client, path)) => client.get(&path, CACHE_TTL),
+                None => Fetch::Unavailable,
+            };
+            match fetch {
+                Fetch::Found(_) => {}
+                Fetch::NotFound => report.dangling.push(DanglingReference {
+                    commit: hash.clone(),
+                    reference,
+                }),
+                Fetch::Unavailable => report.unverified += 1,
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_footer_references() {
+        let msg = "fix: crash on login\n\nSome details.\n\nFixes #123\nCloses JIRA-42, #7\n";
+        assert_eq!(extract_references(msg), vec!["#123", "JIRA-42", "#7"]);
+    }
+
+    #[test]
+    fn ignores_subject_and_prose() {
+        let msg = "fix: handle #12 in parser\n\nThis mentions #99 in passing.\n";
+        assert!(extract_references(msg).is_empty());
+    }
+
+    #[test]
+    fn only_the_last_paragraph_is_the_footer() {
+        let msg = "fix: crash\n\nFixes #3 was incomplete, so\nthis retries.\n\nRefs #4\n\n";
+        assert_eq!(extract_references(msg), vec!["#4"]);
+        assert!(
+            extract_references("fix: crash\n\nFixes #3 was incomplete.\n\nDetails.").is_empty()
+        );
+        assert!(extract_references("Fixes #3").is_empty());
+    }
+
+    #[test]
+    fn subject_references_count_for_the_requirement() {
+        assert_eq!(
+            subject_and_footer_references("fix: crash on login (#12)\n\nRefs: ABC-1\n"),
+            vec!["#12", "ABC-1"]
+        );
+        assert!(
+            subject_and_footer_references("fix: crash\n\nSee #12 for more.\n\nDone.").is_empty()
+        );
+    }
+
+    #[test]
+    fn footer_keywords_are_case_insensitive() {
+        let msg = "feat: x\n\nRESOLVES: ABC-1\nrefs #5\n";
+        assert_eq!(extract_references(msg), vec!["ABC-1", "#5"]);
+    }
+}
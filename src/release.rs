@@ -0,0 +1,296 @@
+//! `release preview`: the semver bump the conventional commits since the
+//! last release tag call for, and the version it leads to. Breaking changes
+//! (`feat!:` or a `BREAKING CHANGE:` footer) bump the major version, `feat`
+//! the minor and `fix` the patch version; other commits release nothing.
+
+use anyhow::Result;
+use colored::Colorize;
+use regex::Regex;
+use serde::Serialize;
+use std::fmt;
+
+use crate::cli::Format;
+use crate::git;
+
+/// `type(scope)!: description`, more lenient than the `conventional`
+/// convention: any scope, and `!` for a breaking change.
+const SUBJECT_PATTERN: &str = r"^(?P<type>[A-Za-z]+)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?: \S";
+
+/// Footer tokens that mark a breaking change, per the Conventional Commits spec.
+const BREAKING_FOOTERS: &[&str] = &["BREAKING CHANGE:", "BREAKING-CHANGE:"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bump {
+    None,
+    Patch,
+    Minor,
+    Major,
+}
+
+impl fmt::Display for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Bump::None => "none",
+            Bump::Patch => "patch",
+            Bump::Minor => "minor",
+            Bump::Major => "major",
+        })
+    }
+}
+
+/// A `MAJOR.MINOR.PATCH` release version; tags may add a `v` in front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    /// `1.2.3` or `v1.2.3`; pre-releases such as `v1.2.3-rc.1` are not
+    /// releases to bump from.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let mut parts = tag.strip_prefix('v').unwrap_or(tag).split('.');
+        let mut number = || -> Option<u64> {
+            let part = parts.next()?;
+            (!part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+                .then(|| part.parse().ok())?
+        };
+        let version = Self {
+            major: number()?,
+            minor: number()?,
+            patch: number()?,
+        };
+        parts.next().is_none().then_some(version)
+    }
+
+    pub fn bumped(self, bump: Bump) -> Self {
+        match bump {
+            Bump::None => self,
+            Bump::Patch => Self {
+                patch: self.patch + 1,
+                ..self
+            },
+            Bump::Minor => Self {
+                minor: self.minor + 1,
+                patch: 0,
+                ..self
+            },
+            Bump::Major => Self {
+                major: self.major + 1,
+                minor: 0,
+                patch: 0,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseCommit {
+    pub hash: String,
+    pub subject: String,
+    /// `None` when the subject is not a conventional commit.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub bump: Bump,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReleasePreview {
+    /// The release the commits are counted from; `None` before the first.
+    pub last_tag: Option<String>,
+    pub current_version: Option<String>,
+    pub bump: Bump,
+    /// `None` when no commit calls for a release.
+    pub next_version: Option<String>,
+    /// Since `last_tag`, newest first; merge commits are left out.
+    pub commits: Vec<ReleaseCommit>,
+}
+
+/// What a commit message calls for; `pattern` is [`SUBJECT_PATTERN`].
+fn classify(pattern: &Regex, hash: &str, message: &str) -> ReleaseCommit {
+    let subject = message.lines().next().unwrap_or_default().to_string();
+    let captures = pattern.captures(&subject);
+    let group = |name: &str| {
+        let captures = captures.as_ref()?;
+        Some(captures.name(name)?.as_str().to_string())
+    };
+    let kind = group("type").map(|kind| kind.to_lowercase());
+    let scope = group("scope").filter(|scope| !scope.is_empty());
+    let breaking = group("breaking").is_some()
+        || message
+            .lines()
+            .skip(1)
+            .any(|line| BREAKING_FOOTERS.iter().any(|f| line.starts_with(f)));
+    let bump = match kind.as_deref() {
+        _ if breaking => Bump::Major,
+        Some("feat") => Bump::Minor,
+        Some("fix") => Bump::Patch,
+        _ => Bump::None,
+    };
+    ReleaseCommit {
+        hash: hash.to_string(),
+        subject,
+        kind,
+        scope,
+        breaking,
+        bump,
+    }
+}
+
+/// The release the commits since `from` (the highest version tag reachable
+/// from HEAD by default) call for. The first release is bumped from `0.0.0`.
+pub fn preview(from: Option<&str>) -> Result<ReleasePreview> {
+    let last_tag = match from {
+        Some(from) => Some(from.to_string()),
+        None => git::merged_tags()?
+            .into_iter()
+            .find(|tag| Version::parse(tag).is_some()),
+    };
+    let current = last_tag.as_deref().and_then(Version::parse);
+    let range = match &last_tag {
+        Some(tag) => format!("{}..HEAD", tag),
+        None => "HEAD".to_string(),
+    };
+    let pattern = Regex::new(SUBJECT_PATTERN).expect("valid subject pattern");
+    let commits: Vec<ReleaseCommit> = git::commit_messages(&range)?
+        .iter()
+        .map(|(hash, message)| classify(&pattern, hash, message))
+        .collect();
+    let bump = commits
+        .iter()
+        .map(|commit| commit.bump)
+        .max()
+        .unwrap_or(Bump::None);
+    // Follow the last release tag in writing the `v` or not.
+    let prefix = match (&last_tag, current) {
+        (Some(tag), Some(_)) if !tag.starts_with('v') => "",
+        _ => "v",
+    };
+    let next_version = (bump != Bump::None).then(|| {
+        let next = current.unwrap_or(Version {
+            major: 0,
+            minor: 0,
+            patch: 0,
+        });
+        format!("{}{}", prefix, next.bumped(bump))
+    });
+    Ok(ReleasePreview {
+        current_version: current.map(|version| version.to_string()),
+        last_tag,
+        bump,
+        next_version,
+        commits,
+    })
+}
+
+pub fn release_preview(from: Option<&str>, format: Format) -> Result<()> {
+    let preview = preview(from)?;
+    match format {
+        Format::Text => print_text(&preview),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&preview)?),
+    }
+    Ok(())
+}
+
+fn print_text(preview: &ReleasePreview) {
+    match &preview.last_tag {
+        Some(tag) => println!(
+            "Last release: {} ({} commit(s) since)",
+            tag,
+            preview.commits.len()
+        ),
+        None => println!("No release tag yet ({} commit(s))", preview.commits.len()),
+    }
+    let Some(next) = &preview.next_version else {
+        println!(
+            "{}",
+            "No release needed: no feat, fix or breaking change.".yellow()
+        );
+        return;
+    };
+    println!(
+        "Bump: {}  Next version: {}",
+        preview.bump.to_string().bold(),
+        next.green().bold()
+    );
+
+    let sections = [
+        ("Breaking changes:", Bump::Major),
+        ("Features:", Bump::Minor),
+        ("Fixes:", Bump::Patch),
+    ];
+    for (title, bump) in sections {
+        let commits: Vec<&ReleaseCommit> =
+            preview.commits.iter().filter(|c| c.bump == bump).collect();
+        if commits.is_empty() {
+            continue;
+        }
+        println!("\n{}", title.bold());
+        for commit in commits {
+            println!(
+                "  {} {}",
+                git::short_hash(&commit.hash).cyan(),
+                commit.subject
+            );
+        }
+    }
+    let other = preview
+        .commits
+        .iter()
+        .filter(|c| c.bump == Bump::None)
+        .count();
+    if other > 0 {
+        println!("\n{} other commit(s) do not affect the version.", other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_call_for_the_bump_of_their_type() {
+        let pattern = Regex::new(SUBJECT_PATTERN).unwrap();
+        let classify = |message: &str| classify(&pattern, "a", message);
+        let feat = classify("feat(api): add users endpoint");
+        assert_eq!(
+            (feat.kind.as_deref(), feat.scope.as_deref(), feat.bump),
+            (Some("feat"), Some("api"), Bump::Minor)
+        );
+        assert_eq!(classify("fix: off by one").bump, Bump::Patch);
+        assert_eq!(classify("docs: typo").bump, Bump::None);
+        assert_eq!(classify("Update README").kind, None);
+
+        let bang = classify("refactor(core)!: drop the v1 API");
+        assert!(bang.breaking);
+        assert_eq!(bang.bump, Bump::Major);
+        let footer = classify("fix: rename flag\n\nBREAKING CHANGE: --foo is now --bar");
+        assert_eq!(footer.bump, Bump::Major);
+        let mention = classify("fix: x\n\nMentions a BREAKING CHANGE: in prose");
+        assert_eq!(mention.bump, Bump::Patch);
+    }
+
+    #[test]
+    fn versions_parse_from_tags_and_bump() {
+        let version = Version::parse("v1.4.2").unwrap();
+        assert_eq!(version, Version::parse("1.4.2").unwrap());
+        assert_eq!(version.bumped(Bump::Patch).to_string(), "1.4.3");
+        assert_eq!(version.bumped(Bump::Minor).to_string(), "1.5.0");
+        assert_eq!(version.bumped(Bump::Major).to_string(), "2.0.0");
+        assert_eq!(version.bumped(Bump::None), version);
+        for tag in ["v1.2", "v1.2.3-rc.1", "release-1", "v1..2", "v+1.2.3"] {
+            assert_eq!(Version::parse(tag), None, "{}", tag);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! `[checks] require_remote`: the remote the branch pushes to exists,
+//! answers and accepts the credentials at hand. Without that, advice such as
+//! setting an upstream cannot work: the remote is the real problem.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::error::{self, SherpaError};
+use crate::git;
+
+/// How long a remote gets to answer `git ls-remote`.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Lowercased fragments of what git, ssh and the forges print when the
+/// remote answered but refused the credentials (or had none to ask for).
+const AUTH_FAILURES: &[&str] = &[
+    "authentication failed",
+    "permission denied",
+    "could not read username",
+    "could not read password",
+    "terminal prompts disabled",
+    "invalid username or password",
+    "access denied",
+    "host key verification failed",
+    "http 401",
+    "http 403",
+    "error: 403",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteProblem {
+    /// No remote of that name is configured.
+    Missing,
+    /// It did not answer in time, or not at all.
+    Unreachable,
+    /// It answered but refused the credentials.
+    Authentication,
+}
+
+impl RemoteProblem {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Missing => "not configured",
+            Self::Unreachable => "unreachable",
+            Self::Authentication => "authentication failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteStatus {
+    /// The branch's `branch.<name>.remote`, else `origin`.
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub problem: Option<RemoteProblem>,
+    /// What git said when the remote did not work.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl RemoteStatus {
+    pub fn ok(&self) -> bool {
+        self.problem.is_none()
+    }
+}
+
+/// Whether the remote `branch` pushes to exists and `git ls-remote` gets an
+/// answer from it within [`TIMEOUT`], without prompting for credentials.
+pub fn remote_status(branch: &str) -> Result<RemoteStatus> {
+    let name = git::tracking_remote(branch);
+    let mut status = RemoteStatus {
+        url: None,
+        problem: None,
+        detail: None,
+        name,
+    };
+    if !git::remotes()?.contains(&status.name) {
+        status.problem = Some(RemoteProblem::Missing);
+        return Ok(status);
+    }
+    status.url = git::remote_url(&status.name)?;
+    if let Err(err) = git::probe_remote(&status.name, TIMEOUT) {
+        let detail = match error::find(&err) {
+            Some(SherpaError::GitCommandFailed { stderr, .. }) if !stderr.is_empty() => {
+                stderr.clone()
+            }
+            _ => format!("{:#}", err),
+        };
+        status.problem = Some(classify(&detail));
+        status.detail = Some(detail);
+    }
+    Ok(status)
+}
+
+/// Why `git ls-remote` failed, from what it printed.
+fn classify(stderr: &str) -> RemoteProblem {
+    let stderr = stderr.to_lowercase();
+    if AUTH_FAILURES.iter().any(|failure| stderr.contains(failure)) {
+        RemoteProblem::Authentication
+    } else {
+        RemoteProblem::Unreachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_are_told_apart_by_what_git_printed() {
+        let cases = [
+            (
+                "fatal: could not read Username for 'https://github.com': terminal prompts disabled",
+                RemoteProblem::Authentication,
+            ),
+            (
+                "git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository.",
+                RemoteProblem::Authentication,
+            ),
+            (
+                "remote: Invalid username or password.\nfatal: Authentication failed for 'https://github.com/a/b.git/'",
+                RemoteProblem::Authentication,
+            ),
+            (
+                "ssh: Could not resolve hostname example.invalid: Name or service not known",
+                RemoteProblem::Unreachable,
+            ),
+            (
+                "fatal: '/tmp/gone' does not appear to be a git repository",
+                RemoteProblem::Unreachable,
+            ),
+            (
+                "`git ls-remote --quiet origin HEAD` timed out after 10s",
+                RemoteProblem::Unreachable,
+            ),
+        ];
+        for (stderr, problem) in cases {
+            assert_eq!(classify(stderr), problem, "{}", stderr);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use anyhow::Result;
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::cli::Format;
+use crate::git;
+use crate::lfs_audit::lfs_pattern;
+
+/// Git's default `gc.auto` threshold of loose objects.
+const DEFAULT_GC_AUTO: u64 = 6700;
+/// Git's default `gc.autoPackLimit`.
+const DEFAULT_GC_AUTO_PACK_LIMIT: u64 = 50;
+
+#[derive(Debug, Default, Serialize)]
+pub struct HealthReport {
+    pub loose_objects: u64,
+    pub loose_size_kb: u64,
+    pub packed_objects: u64,
+    pub packs: u64,
+    pub pack_size_kb: u64,
+    pub garbage: u64,
+    pub total_size_kb: u64,
+    pub gc_overdue: bool,
+    pub maintenance_enabled: bool,
+    pub largest_blobs: Vec<BlobInfo>,
+    pub lfs_candidates: Vec<BlobInfo>,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlobInfo {
+    pub hash: String,
+    pub size: u64,
+    pub path: String,
+}
+
+pub fn repo_health(format: Format, top: usize, lfs_threshold_kb: u64) -> Result<()> {
+    let report = build_health_report(top, lfs_threshold_kb)?;
+    match format {
+        Format::Text => print_text(&report),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+pub fn build_health_report(top: usize, lfs_threshold_kb: u64) -> Result<HealthReport> {
+    let counts = git::count_objects()?;
+    let value = |key: &str| -> u64 {
+        counts
+            .iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, v)| v.parse().ok())
+            .unwrap_or(0)
+    };
+
+    let config_u64 = |key: &str, default: u64| -> u64 {
+        git::config_get(key)
+            .ok()
+            .flatten()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    };
+    let gc_auto = config_u64("gc.auto", DEFAULT_GC_AUTO);
+    let pack_limit = config_u64("gc.autoPackLimit", DEFAULT_GC_AUTO_PACK_LIMIT);
+
+    let mut report = HealthReport {
+        loose_objects: value("count"),
+        loose_size_kb: value("size"),
+        packed_objects: value("in-pack"),
+        packs: value("packs"),
+        pack_size_kb: value("size-pack"),
+        garbage: value("garbage"),
+        total_size_kb: value("size") + value("size-pack") + value("size-garbage"),
+        maintenance_enabled: maintenance_enabled(),
+        ..Default::default()
+    };
+    report.gc_overdue = gc_overdue(report.loose_objects, report.packs, gc_auto, pack_limit);
+
+    let blobs: Vec<BlobInfo> = git::largest_blobs(top)?
+        .into_iter()
+        .map(|(hash, size, path)| BlobInfo { hash, size, path })
+        .collect();
+    report.lfs_candidates = blobs
+        .iter()
+        .filter(|b| b.size >= lfs_threshold_kb * 1024)
+        .cloned()
+        .collect();
+    report.largest_blobs = blobs;
+    report.suggestions = suggestions(&report);
+    Ok(report)
+}
+
+/// Mirrors git's own auto-gc heuristics; a threshold of 0 disables the check.
+fn gc_overdue(loose: u64, packs: u64, gc_auto: u64, pack_limit: u64) -> bool {
+    (gc_auto > 0 && loose > gc_auto) || (pack_limit > 0 && packs > pack_limit)
+}
+
+fn maintenance_enabled() -> bool {
+    matches!(git::config_get("maintenance.auto"), Ok(Some(v)) if v == "true")
+        || matches!(git::config_get("maintenance.strategy"), Ok(Some(_)))
+}
+
+fn suggestions(report: &HealthReport) -> Vec<String> {
+    let mut out = Vec::new();
+    if report.gc_overdue {
+        out.push("git gc  (loose objects or pack count exceed gc thresholds)".to_string());
+    }
+    if !report.maintenance_enabled {
+        out.push("git maintenance start  (schedule background repository upkeep)".to_string());
+    }
+    if !report.lfs_candidates.is_empty() {
+        let mut exts: Vec<String> = report
+            .lfs_candidates
+            .iter()
+            .map(|b| lfs_pattern(&b.path))
+            .collect();
+        exts.sort();
+        exts.dedup();
+        out.push(format!(
+            "git lfs migrate import --include=\"{}\"  (move large blobs to LFS)",
+            exts.join(",")
+        ));
+    }
+    out
+}
+
+pub(crate) fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn print_text(report: &HealthReport) {
+    println!("{}", "Repository health:".bold());
+    println!(
+        "  Size: {} ({} loose objects, {} packed in {} pack(s))",
+        human_size(report.total_size_kb * 1024),
+        report.loose_objects,
+        report.packed_objects,
+        report.packs
+    );
+    let gc = if report.gc_overdue {
+        "overdue".red().to_string()
+    } else {
+        "OK".green().to_string()
+    };
+    println!("  gc: {}", gc);
+    let maintenance = if report.maintenance_enabled {
+        "enabled".green().to_string()
+    } else {
+        "disabled".yellow().to_string()
+    };
+    println!("  maintenance: {}", maintenance);
+
+    if !report.largest_blobs.is_empty() {
+        println!("\n{}", "Largest blobs in history:".bold());
+        for blob in &report.largest_blobs {
+            println!(
+                "  {:>10}  {}  {}",
+                human_size(blob.size),
+                git::short_hash(&blob.hash),
+                blob.path
+            );
+        }
+    }
+
+    if !report.suggestions.is_empty() {
+        println!("\n{}", "Suggestions:".yellow().bold());
+        for s in &report.suggestions {
+            println!("  {}", s.cyan());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gc_overdue_on_loose_objects() {
+        assert!(gc_overdue(
+            7000,
+            1,
+            DEFAULT_GC_AUTO,
+            DEFAULT_GC_AUTO_PACK_LIMIT
+        ));
+        assert!(!gc_overdue(
+            100,
+            1,
+            DEFAULT_GC_AUTO,
+            DEFAULT_GC_AUTO_PACK_LIMIT
+        ));
+    }
+
+    #[test]
+    fn gc_overdue_on_pack_count() {
+        assert!(gc_overdue(
+            0,
+            51,
+            DEFAULT_GC_AUTO,
+            DEFAULT_GC_AUTO_PACK_LIMIT
+        ));
+    }
+
+    #[test]
+    fn gc_threshold_zero_disables() {
+        assert!(!gc_overdue(100_000, 500, 0, 0));
+    }
+
+    #[test]
+    fn human_sizes() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(2048), "2.0 KiB");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn suggests_lfs_by_extension() {
+        let report = HealthReport {
+            maintenance_enabled: true,
+            lfs_candidates: vec![
+                BlobInfo {
+                    hash: "a".repeat(40),
+                    size: 10,
+                    path: "assets/video.mp4".into(),
+                },
+                BlobInfo {
+                    hash: "b".repeat(40),
+                    size: 10,
+                    path: "data/dump.mp4".into(),
+                },
+            ],
+            ..Default::default()
+        };
+        let out = suggestions(&report);
+        assert_eq!(out.len(), 1);
+        assert!(out[0].contains("--include=\"*.mp4\""));
+    }
+}
@@ -0,0 +1,92 @@
+//! Partial clones and sparse checkouts. In a partial clone, reading a blob
+//! that was filtered out downloads it from the promisor remote, one object at
+//! a time; checks that only need names or sizes must not read contents.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::git;
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RepoMode {
+    /// Remote that lazily serves filtered-out objects, if this is a partial clone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub promisor_remote: Option<String>,
+    /// The clone's `--filter`, e.g. `blob:none`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    pub sparse_checkout: bool,
+    /// Sparse checkout in cone mode (directories only).
+    pub sparse_cone: bool,
+}
+
+impl RepoMode {
+    pub fn is_partial(&self) -> bool {
+        self.promisor_remote.is_some()
+    }
+
+    /// Short description for the text report; `None` for a regular clone.
+    pub fn describe(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(remote) = &self.promisor_remote {
+            parts.push(match &self.filter {
+                Some(filter) => format!("partial clone ({} from {})", filter, remote),
+                None => format!("partial clone (from {})", remote),
+            });
+        }
+        if self.sparse_checkout {
+            parts.push(if self.sparse_cone {
+                "sparse checkout (cone)".to_string()
+            } else {
+                "sparse checkout".to_string()
+            });
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+pub fn detect() -> Result<RepoMode> {
+    let promisor_remote = git::promisor_remote()?;
+    let filter = match &promisor_remote {
+        Some(remote) => git::config_get(&format!("remote.{}.partialCloneFilter", remote))?,
+        None => None,
+    };
+    let sparse_checkout = git::config_get("core.sparseCheckout")?.is_some_and(|v| is_true(&v));
+    let sparse_cone =
+        sparse_checkout && git::config_get("core.sparseCheckoutCone")?.is_some_and(|v| is_true(&v));
+    Ok(RepoMode {
+        promisor_remote,
+        filter,
+        sparse_checkout,
+        sparse_cone,
+    })
+}
+
+/// Git's boolean spellings; an empty value (`[core] sparseCheckout`) is true.
+fn is_true(value: &str) -> bool {
+    matches!(
+        value.to_ascii_lowercase().as_str(),
+        "" | "true" | "yes" | "on" | "1"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_partial_and_sparse_modes() {
+        assert_eq!(RepoMode::default().describe(), None);
+        let mode = RepoMode {
+            promisor_remote: Some("origin".into()),
+            filter: Some("blob:none".into()),
+            sparse_checkout: true,
+            sparse_cone: true,
+        };
+        assert_eq!(
+            mode.describe().unwrap(),
+            "partial clone (blob:none from origin), sparse checkout (cone)"
+        );
+        assert!(is_true("Yes") && !is_true("false"));
+    }
+}
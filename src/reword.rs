@@ -0,0 +1,307 @@
+//! Rewording of invalid commit messages for `fix --apply` and
+//! `fix --interactive`, driven by a scripted `git rebase -i` instead of an
+//! editor session per commit.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use crate::check::CommitReport;
+use crate::config::Config;
+use crate::convention::Convention;
+use crate::fix::ask;
+use crate::git;
+
+/// Rewrites the messages of `commits`, prompting for each new subject when
+/// stdin is a terminal and otherwise using the subject inferred from the
+/// diff. With `confirm`, each inferred subject is offered to take, skip or
+/// edit instead, whatever stdin is. Commits already on a remote or outside
+/// the current branch are left alone.
+pub fn reword(config: &Config, commits: &[&CommitReport], confirm: bool) -> Result<()> {
+    if !git::worktree_clean()? {
+        println!(
+            "  {}",
+            "Skipped: commit or stash your changes before rewording.".yellow()
+        );
+        return Ok(());
+    }
+
+    let convention = Convention::from_config(&config.commits)?;
+    let interactive = io::stdin().is_terminal();
+    // Newest first, as reported; the last one rewritten decides the base.
+    let mut rewords: Vec<(String, String)> = Vec::new();
+    for commit in commits {
+        let short = git::short_hash(&commit.hash);
+        if !git::is_ancestor(&commit.hash, "HEAD")? {
+            println!(
+                "  {} {} is not on the current branch",
+                "skip".yellow(),
+                short
+            );
+            continue;
+        }
+        if git::is_pushed(&commit.hash)? {
+            println!(
+                "  {} {} is already pushed; rewording it would rewrite shared history",
+                "skip".yellow(),
+                short
+            );
+            continue;
+        }
+
+        let suggestion = suggest_subject(&commit.hash, &commit.message)
+            .filter(|s| convention.check_subject(s).is_empty());
+        let subject = if confirm {
+            confirm_subject(&convention, &short, &commit.message, suggestion.as_deref())?
+        } else if interactive {
+            prompt_subject(&convention, &short, &commit.message, suggestion.as_deref())?
+        } else {
+            suggestion
+        };
+        match subject {
+            Some(subject) => {
+                let body = git::commit_message(&commit.hash)?;
+                let rest = body.split_once('\n').map(|(_, rest)| rest).unwrap_or("");
+                println!("  {} {} -> {}", "reword".cyan(), short, subject);
+                rewords.push((commit.hash.clone(), format!("{}\n{}", subject, rest)));
+            }
+            None => println!(
+                "  {} {} (no valid subject could be inferred)",
+                "skip".yellow(),
+                short
+            ),
+        }
+    }
+
+    let Some((oldest, _)) = rewords.last() else {
+        return Ok(());
+    };
+    let base = git::resolve_rev(&format!("{}^", oldest))?;
+    let history = git::commits_since(base.as_deref())?;
+    if history.iter().any(|(_, parents)| *parents > 1) {
+        println!(
+            "  {}",
+            "Skipped: merge commits in the way; use `git rebase -i --reword` instead.".yellow()
+        );
+        return Ok(());
+    }
+
+    let work_dir = git::git_dir()?.join("sherpa-reword");
+    fs::create_dir_all(&work_dir).with_context(|| format!("create {}", work_dir.display()))?;
+    let result = (|| {
+        let mut todo = String::new();
+        for (hash, _) in &history {
+            todo.push_str(&format!("pick {}\n", hash));
+            if let Some((_, message)) = rewords.iter().find(|(h, _)| h == hash) {
+                let path = work_dir.join(format!("{}.msg", hash));
+                fs::write(&path, message).with_context(|| format!("write {}", path.display()))?;
+                todo.push_str(&format!(
+                    "exec git commit --amend --quiet --no-verify --allow-empty -F '{}'\n",
+                    path.to_string_lossy().replace('\'', "'\\''")
+                ));
+            }
+        }
+        let todo_path = work_dir.join("git-rebase-todo");
+        fs::write(&todo_path, todo).with_context(|| format!("write {}", todo_path.display()))?;
+        git::rebase_with_todo(base.as_deref(), &todo_path)
+    })();
+    let _ = fs::remove_dir_all(&work_dir);
+    result?;
+
+    println!(
+        "  {}",
+        format!("Reworded {} commit(s).", rewords.len()).green()
+    );
+    Ok(())
+}
+
+/// Asks for a new subject until it is valid; an empty answer takes the
+/// suggestion (or skips the commit when there is none).
+fn prompt_subject(
+    convention: &Convention,
+    short: &str,
+    current: &str,
+    suggestion: Option<&str>,
+) -> Result<Option<String>> {
+    println!("\n  {} {}", short.yellow(), current);
+    read_subject(convention, suggestion)
+}
+
+/// Offers the suggestion to take (`y`), skip (`n`) or replace with a subject
+/// typed in (`e`); without one, asks for a subject right away.
+fn confirm_subject(
+    convention: &Convention,
+    short: &str,
+    current: &str,
+    suggestion: Option<&str>,
+) -> Result<Option<String>> {
+    let Some(suggestion) = suggestion else {
+        return prompt_subject(convention, short, current, None);
+    };
+    println!("\n  {} {}", short.yellow(), current);
+    println!("  -> {}", suggestion.cyan());
+    match ask("  Reword? [y/n/e] ", "yne")? {
+        Some('y') => Ok(Some(suggestion.to_string())),
+        Some('e') => read_subject(convention, Some(suggestion)),
+        _ => Ok(None),
+    }
+}
+
+fn read_subject(convention: &Convention, suggestion: Option<&str>) -> Result<Option<String>> {
+    let stdin = io::stdin();
+    loop {
+        match suggestion {
+            Some(s) => print!("  New subject [{}]: ", s),
+            None => print!("  New subject (empty to skip): "),
+        }
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let answer = line.trim();
+        if answer.is_empty() {
+            return Ok(suggestion.map(str::to_string));
+        }
+        let problems = convention.check_subject(answer);
+        if problems.is_empty() {
+            return Ok(Some(answer.to_string()));
+        }
+        for problem in problems {
+            println!("    {}", problem.red());
+        }
+    }
+}
+
+/// The current subject prefixed with a Conventional Commits type guessed
+/// from what the commit changed.
+fn suggest_subject(hash: &str, subject: &str) -> Option<String> {
+    let changes = git::commit_changes(hash).ok()?;
+    let description = subject.trim().trim_end_matches('.');
+    let mut chars = description.chars();
+    let first = chars.next()?;
+    Some(format!(
+        "{}: {}{}",
+        infer_type(&changes, subject),
+        first.to_lowercase(),
+        chars.as_str()
+    ))
+}
+
+fn infer_type(changes: &[(String, String)], subject: &str) -> &'static str {
+    let all =
+        |pred: fn(&str) -> bool| !changes.is_empty() && changes.iter().all(|(_, path)| pred(path));
+    if all(is_docs) {
+        return "docs";
+    }
+    if all(is_test) {
+        return "test";
+    }
+    if all(is_ci) {
+        return "ci";
+    }
+    if all(is_build) {
+        return "build";
+    }
+    let lower = subject.to_lowercase();
+    let mentions_fix = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|w| matches!(w, "fix" | "fixed" | "fixes" | "bug" | "bugfix" | "crash"));
+    if mentions_fix {
+        "fix"
+    } else if changes.iter().any(|(status, _)| status == "A") {
+        "feat"
+    } else {
+        "chore"
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn is_docs(path: &str) -> bool {
+    path.starts_with("docs/")
+        || [".md", ".rst", ".adoc"]
+            .iter()
+            .any(|ext| path.ends_with(ext))
+}
+
+fn is_test(path: &str) -> bool {
+    let name = file_name(path);
+    path.starts_with("tests/")
+        || path.contains("/tests/")
+        || name.starts_with("test_")
+        || [
+            "_test.rs", "_test.go", "_test.py", ".test.js", ".test.ts", ".spec.js", ".spec.ts",
+        ]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+}
+
+fn is_ci(path: &str) -> bool {
+    path.starts_with(".github/workflows/")
+        || path.starts_with(".circleci/")
+        || path == ".gitlab-ci.yml"
+}
+
+fn is_build(path: &str) -> bool {
+    matches!(
+        file_name(path),
+        "Cargo.toml"
+            | "Cargo.lock"
+            | "package.json"
+            | "package-lock.json"
+            | "yarn.lock"
+            | "go.mod"
+            | "go.sum"
+            | "Makefile"
+            | "Dockerfile"
+            | "build.rs"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(files: &[(&str, &str)]) -> Vec<(String, String)> {
+        files
+            .iter()
+            .map(|(s, p)| (s.to_string(), p.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn infers_type_from_paths() {
+        assert_eq!(
+            infer_type(&changes(&[("M", "README.md")]), "update"),
+            "docs"
+        );
+        assert_eq!(
+            infer_type(
+                &changes(&[("A", "tests/cli.rs"), ("M", "src/x_test.go")]),
+                "more"
+            ),
+            "test"
+        );
+        assert_eq!(
+            infer_type(&changes(&[("M", ".github/workflows/ci.yml")]), "bump"),
+            "ci"
+        );
+        assert_eq!(
+            infer_type(&changes(&[("M", "Cargo.toml")]), "bump"),
+            "build"
+        );
+    }
+
+    #[test]
+    fn infers_type_from_subject_and_status() {
+        let src = changes(&[("M", "src/main.rs"), ("M", "README.md")]);
+        assert_eq!(infer_type(&src, "Fix crash on empty input"), "fix");
+        assert_eq!(infer_type(&src, "prefix handling"), "chore");
+        let added = changes(&[("A", "src/login.rs")]);
+        assert_eq!(infer_type(&added, "Add login"), "feat");
+    }
+}
@@ -0,0 +1,78 @@
+//! Commits already pushed to the upstream that were amended or rebased
+//! locally since: the branch has diverged from its upstream, and publishing
+//! it takes a force push.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::git;
+
+#[derive(Debug, Serialize)]
+pub struct UpstreamRewrite {
+    pub upstream: String,
+    /// Upstream commits replaced by a local commit with the same patch or
+    /// subject, newest first.
+    pub rewritten: Vec<String>,
+    /// Other upstream commits missing from `HEAD`, e.g. pushed from another
+    /// clone: a force push would drop them.
+    pub dropped: Vec<String>,
+}
+
+/// `None` unless some upstream commit was rewritten locally; commits that
+/// are only new on the upstream just need a pull.
+pub fn detect() -> Result<Option<UpstreamRewrite>> {
+    let upstream = git::upstream_side_commits(true)?;
+    if upstream.is_empty() {
+        return Ok(None);
+    }
+    let local = git::upstream_side_commits(false)?;
+    let (rewritten, dropped) = classify(upstream, &local);
+    if rewritten.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(UpstreamRewrite {
+        upstream: git::upstream_name()?,
+        rewritten,
+        dropped,
+    }))
+}
+
+/// Splits the upstream-only commits into rewritten and dropped ones. A
+/// commit was rewritten when `HEAD` has its patch (a reworded or rebased
+/// commit) or a commit with its subject (an amended change).
+fn classify(
+    upstream: Vec<(String, String, bool)>,
+    local: &[(String, String, bool)],
+) -> (Vec<String>, Vec<String>) {
+    let (rewritten, dropped): (Vec<_>, Vec<_>) =
+        upstream.into_iter().partition(|(_, subject, same_patch)| {
+            *same_patch || local.iter().any(|(_, s, _)| s == subject)
+        });
+    let hashes = |commits: Vec<(String, String, bool)>| commits.into_iter().map(|c| c.0).collect();
+    (hashes(rewritten), hashes(dropped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(hash: &str, subject: &str, same_patch: bool) -> (String, String, bool) {
+        (hash.to_string(), subject.to_string(), same_patch)
+    }
+
+    #[test]
+    fn rewritten_commits_match_by_patch_or_subject() {
+        let upstream = vec![
+            commit("c3", "feat: add login", true),
+            commit("c2", "fix: typo", false),
+            commit("x1", "docs: readme", false),
+        ];
+        let local = vec![
+            commit("c3b", "feat: add login form", true),
+            commit("c2b", "fix: typo", false),
+        ];
+        let (rewritten, dropped) = classify(upstream, &local);
+        assert_eq!(rewritten, vec!["c3", "c2"]);
+        assert_eq!(dropped, vec!["x1"]);
+    }
+}
@@ -0,0 +1,418 @@
+//! `check --recursive`: checks every repository under a directory and rolls
+//! the results up into totals per rule, worst offenders and an overall score.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::baseline;
+use crate::check::{build_report, CommitSelection, Summary};
+use crate::config::load_config;
+use crate::error::ExitStatus;
+
+/// Rules counted in the roll-up, in report order.
+pub const RULES: &[&str] = &[
+    "branch-pattern",
+    "branch-age",
+    "commit-convention",
+    "signed-commits",
+    "author-email",
+    "quiet-hours",
+    "clean-worktree",
+    "remote",
+    "upstream",
+    "sensitive-files",
+    "large-files",
+    "protected-paths",
+    "base-divergence",
+    "linear-history",
+    "attributes",
+    "references",
+    "staged-content",
+];
+
+/// Number of worst offenders listed in the roll-up.
+const WORST_OFFENDERS: usize = 5;
+
+/// Directories never descended into while looking for repositories.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoResult {
+    pub path: String,
+    /// Issue count per failing rule.
+    pub violations: BTreeMap<&'static str, usize>,
+    /// Set when the repository could not be checked at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RepoResult {
+    fn issues(&self) -> usize {
+        self.violations.values().sum()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleTotal {
+    pub rule: &'static str,
+    /// Repositories failing the rule.
+    pub repos: usize,
+    /// Issues across all repositories (e.g. invalid commits).
+    pub issues: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Rollup {
+    pub repos: usize,
+    pub passing: usize,
+    pub failing: usize,
+    pub errored: usize,
+    /// Percentage of rule checks passed across all checked repositories.
+    pub score: u32,
+    pub rules: Vec<RuleTotal>,
+    pub worst_offenders: Vec<RepoResult>,
+    pub results: Vec<RepoResult>,
+}
+
+/// The `check --format` values a roll-up can be rendered in.
+#[derive(Clone, Copy, Debug)]
+pub enum RollupFormat {
+    Text,
+    Json,
+    Markdown,
+}
+
+pub fn check_recursive(
+    root: &Path,
+    config_path: &Path,
+    format: RollupFormat,
+    commits: CommitSelection,
+    baseline: bool,
+) -> Result<ExitStatus> {
+    let repos = discover_repos(root)?;
+    if repos.is_empty() {
+        bail!("No git repositories found under {}", root.display());
+    }
+
+    let cwd = std::env::current_dir()?;
+    let mut results = Vec::new();
+    for repo in &repos {
+        // git runs in the working directory, so check each repo from inside it.
+        std::env::set_current_dir(repo)?;
+        let outcome = load_config(config_path).and_then(|config| {
+            let mut report = build_report(&config, &commits)?;
+            if baseline {
+                baseline::apply_to(&config, &mut report)?;
+            }
+            Ok(report)
+        });
+        std::env::set_current_dir(&cwd)?;
+
+        let path = repo.strip_prefix(root).unwrap_or(repo);
+        let path = if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        };
+        results.push(match outcome {
+            Ok(report) => RepoResult {
+                path: path.display().to_string(),
+                violations: report
+                    .rules
+                    .iter()
+                    .map(|rule| (rule.rule, rule.issues))
+                    .collect(),
+                error: None,
+            },
+            Err(err) => RepoResult {
+                path: path.display().to_string(),
+                violations: BTreeMap::new(),
+                error: Some(format!("{:#}", err)),
+            },
+        });
+    }
+
+    let rollup = summarize(results);
+    match format {
+        RollupFormat::Text => print_text(&rollup),
+        RollupFormat::Json => println!("{}", serde_json::to_string_pretty(&rollup)?),
+        RollupFormat::Markdown => print!("{}", render_markdown(&rollup)),
+    }
+
+    Ok(ExitStatus::from_violations(
+        rollup.failing > 0 || rollup.errored > 0,
+    ))
+}
+
+/// Repositories at or below `root`; nested repositories inside a repository
+/// (submodules, vendored checkouts) are not descended into.
+pub fn discover_repos(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut repos = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if dir.join(".git").exists() {
+            repos.push(dir);
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) {
+                continue;
+            }
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                pending.push(entry.path());
+            }
+        }
+    }
+    repos.sort();
+    Ok(repos)
+}
+
+/// Issue count per failing rule for one repository.
+pub fn violations(summary: &Summary) -> BTreeMap<&'static str, usize> {
+    let counts = [
+        usize::from(!summary.branch_valid),
+        usize::from(summary.branch_too_old),
+        summary.invalid_commits,
+        summary.unsigned_commits,
+        summary.disallowed_author_emails,
+        summary.quiet_hours_commits,
+        usize::from(!summary.worktree_clean),
+        usize::from(summary.remote_broken),
+        usize::from(!summary.upstream_set),
+        summary.sensitive_files + summary.secrets,
+        summary.large_files,
+        summary.protected_paths,
+        usize::from(summary.base_diverged),
+        usize::from(summary.nonlinear_history),
+        summary.attribute_issues,
+        summary.dangling_references,
+        summary.content_issues,
+    ];
+    RULES
+        .iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .map(|(rule, count)| (*rule, count))
+        .collect()
+}
+
+pub fn summarize(results: Vec<RepoResult>) -> Rollup {
+    let checked: Vec<&RepoResult> = results.iter().filter(|r| r.error.is_none()).collect();
+    let errored = results.len() - checked.len();
+    let failing = checked.iter().filter(|r| !r.violations.is_empty()).count();
+
+    let rules = RULES
+        .iter()
+        .map(|rule| RuleTotal {
+            rule,
+            repos: checked
+                .iter()
+                .filter(|r| r.violations.contains_key(rule))
+                .count(),
+            issues: checked.iter().filter_map(|r| r.violations.get(rule)).sum(),
+        })
+        .collect();
+
+    let total_checks = checked.len() * RULES.len();
+    let failed_checks: usize = checked.iter().map(|r| r.violations.len()).sum();
+    let score = ((total_checks - failed_checks) * 100)
+        .checked_div(total_checks)
+        .unwrap_or(0) as u32;
+
+    let mut worst: Vec<RepoResult> = checked
+        .iter()
+        .filter(|r| !r.violations.is_empty())
+        .map(|r| (*r).clone())
+        .collect();
+    worst.sort_by(|a, b| {
+        b.violations
+            .len()
+            .cmp(&a.violations.len())
+            .then_with(|| b.issues().cmp(&a.issues()))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    worst.truncate(WORST_OFFENDERS);
+
+    Rollup {
+        repos: results.len(),
+        passing: checked.len() - failing,
+        failing,
+        errored,
+        score,
+        rules,
+        worst_offenders: worst,
+        results,
+    }
+}
+
+fn describe(violations: &BTreeMap<&'static str, usize>) -> String {
+    violations
+        .iter()
+        .map(|(rule, count)| format!("{} ({})", rule, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Git errors can span several lines; the first one is enough in a summary.
+fn first_line(error: Option<&str>) -> &str {
+    error.and_then(|e| e.lines().next()).unwrap_or_default()
+}
+
+fn print_text(rollup: &Rollup) {
+    println!(
+        "Repositories: {} ({} passing, {} failing, {} errored)",
+        rollup.repos, rollup.passing, rollup.failing, rollup.errored
+    );
+    let score = format!("Score: {}%", rollup.score);
+    if rollup.failing == 0 && rollup.errored == 0 {
+        println!("{}", score.green().bold());
+    } else {
+        println!("{}", score.yellow().bold());
+    }
+
+    println!("\nRules:");
+    for total in &rollup.rules {
+        let line = format!(
+            "  {:<18} {} repo(s), {} issue(s)",
+            total.rule, total.repos, total.issues
+        );
+        if total.repos == 0 {
+            println!("{}", line);
+        } else {
+            println!("{}", line.red());
+        }
+    }
+
+    if !rollup.worst_offenders.is_empty() {
+        println!("\nWorst offenders:");
+        for repo in &rollup.worst_offenders {
+            println!("  - {}: {}", repo.path.red(), describe(&repo.violations));
+        }
+    }
+
+    let errors: Vec<&RepoResult> = rollup
+        .results
+        .iter()
+        .filter(|r| r.error.is_some())
+        .collect();
+    if !errors.is_empty() {
+        println!("\n{}", "Could not check:".red().bold());
+        for repo in errors {
+            println!("  - {}: {}", repo.path, first_line(repo.error.as_deref()));
+        }
+    }
+}
+
+pub fn render_markdown(rollup: &Rollup) -> String {
+    let mut out = String::from("## git-sherpa workspace summary\n\n");
+    out.push_str(&format!(
+        "**Score: {}%** — {} repositories: {} passing, {} failing, {} errored\n\n",
+        rollup.score, rollup.repos, rollup.passing, rollup.failing, rollup.errored
+    ));
+
+    out.push_str("| Rule | Failing repos | Issues |\n|------|---------------|--------|\n");
+    for total in &rollup.rules {
+        out.push_str(&format!(
+            "| `{}` | {} | {} |\n",
+            total.rule, total.repos, total.issues
+        ));
+    }
+
+    if !rollup.worst_offenders.is_empty() {
+        out.push_str(
+            "\n### Worst offenders\n\n| Repository | Violations |\n|------------|------------|\n",
+        );
+        for repo in &rollup.worst_offenders {
+            out.push_str(&format!(
+                "| `{}` | {} |\n",
+                repo.path,
+                describe(&repo.violations)
+            ));
+        }
+    }
+
+    let errors: Vec<&RepoResult> = rollup
+        .results
+        .iter()
+        .filter(|r| r.error.is_some())
+        .collect();
+    if !errors.is_empty() {
+        out.push_str("\n### Could not check\n\n");
+        for repo in errors {
+            out.push_str(&format!(
+                "- `{}`: {}\n",
+                repo.path,
+                first_line(repo.error.as_deref())
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str, violations: &[(&'static str, usize)]) -> RepoResult {
+        RepoResult {
+            path: path.to_string(),
+            violations: violations.iter().copied().collect(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn violations_from_summary() {
+        let summary = Summary {
+            branch_valid: true,
+            worktree_clean: true,
+            upstream_set: false,
+            invalid_commits: 3,
+            ..Default::default()
+        };
+        let v = violations(&summary);
+        assert_eq!(v.len(), 2);
+        assert_eq!(v["commit-convention"], 3);
+        assert_eq!(v["upstream"], 1);
+    }
+
+    #[test]
+    fn rollup_totals_and_score() {
+        let mut broken = result("c", &[]);
+        broken.error = Some("not a git repository".into());
+        let rollup = summarize(vec![
+            result("a", &[]),
+            result("b", &[("commit-convention", 2), ("upstream", 1)]),
+            result("d", &[("commit-convention", 1)]),
+            broken,
+        ]);
+
+        assert_eq!((rollup.passing, rollup.failing, rollup.errored), (1, 2, 1));
+        let commits = rollup
+            .rules
+            .iter()
+            .find(|r| r.rule == "commit-convention")
+            .unwrap();
+        assert_eq!((commits.repos, commits.issues), (2, 3));
+        // 3 checked repos x 17 rules, 3 failed rule checks.
+        assert_eq!(rollup.score, 94);
+        assert_eq!(rollup.worst_offenders[0].path, "b");
+    }
+
+    #[test]
+    fn markdown_has_rule_table_and_offenders() {
+        let rollup = summarize(vec![result("svc/api", &[("upstream", 1)])]);
+        let md = render_markdown(&rollup);
+        assert!(md.contains("| `upstream` | 1 | 1 |"));
+        assert!(md.contains("| `svc/api` | upstream (1) |"));
+    }
+}
@@ -0,0 +1,728 @@
+//! The rule engine behind `check`. Each rule implements [`Rule`]: it fills
+//! its part of the [`Report`] and returns what it found as [`Violation`]s.
+//! A [`Registry`] runs them in order, skips the ones that do not apply or
+//! are set to `off`, and records how long each took. Library users can
+//! [`Registry::register`] rules of their own next to the built-in ones.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::attributes;
+use crate::authors;
+use crate::branch_naming::BranchNaming;
+use crate::check::{self, CommitReport, Report};
+use crate::config::Config;
+use crate::git::{self, StagedChange};
+use crate::large_files;
+use crate::linear;
+use crate::location::Location;
+use crate::protected;
+use crate::quiet_hours;
+use crate::references;
+use crate::remote;
+use crate::repo_mode::RepoMode;
+use crate::rollup;
+use crate::secrets;
+use crate::sensitive;
+use crate::severity::{self, RuleSeverity};
+use crate::signatures;
+use crate::staged_content;
+
+/// One issue found by a rule, e.g. an invalid commit or a staged `.env`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub message: String,
+    pub location: Location,
+}
+
+impl Violation {
+    /// A violation of the repository as a whole; see [`Violation::at`].
+    pub fn new(rule: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            rule,
+            message: message.into(),
+            location: Location::Repo,
+        }
+    }
+
+    pub fn at(mut self, location: Location) -> Self {
+        self.location = location;
+        self
+    }
+}
+
+/// What the rules of one check share: the config, what is being checked,
+/// and the report they fill in.
+pub struct Context<'a> {
+    pub config: &'a Config,
+    /// Only what is being committed: no history, worktree or upstream.
+    pub staged_only: bool,
+    /// The branch has no commits yet.
+    pub unborn: bool,
+    pub mode: RepoMode,
+    /// `git status` found nothing uncommitted; only queried when
+    /// `require_clean_worktree` is set outside `staged_only`.
+    pub worktree_clean: bool,
+    /// The branch has an upstream; always false with `staged_only`.
+    pub has_upstream: bool,
+    /// The staged changes, renames and copies with their source.
+    pub changes: Vec<StagedChange>,
+    /// The paths of `changes`.
+    pub staged: Vec<String>,
+    /// (hash, subject) of the selected commits, newest first.
+    pub commits: Vec<(String, String)>,
+    pub report: Report,
+    /// Handed each commit as soon as it is validated.
+    pub on_commit: &'a mut dyn FnMut(&CommitReport),
+}
+
+impl Context<'_> {
+    pub fn hashes(&self) -> Vec<String> {
+        self.commits.iter().map(|(hash, _)| hash.clone()).collect()
+    }
+}
+
+pub trait Rule {
+    /// Stable ID, as used in `[severity]` and the report.
+    fn id(&self) -> &'static str;
+
+    /// `[severity]` for the rule, `error` by default.
+    fn severity(&self, config: &Config) -> RuleSeverity {
+        severity::of(self.id(), &config.severity)
+    }
+
+    /// Whether the rule has anything to check, e.g. not the history in the
+    /// pre-commit hook's staged-only check.
+    fn applies_to(&self, _ctx: &Context) -> bool {
+        true
+    }
+
+    /// Fills the rule's part of `ctx.report` and returns its violations.
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>>;
+
+    /// Whether `fix --apply` repairs what the rule finds without risking
+    /// work, e.g. unstaging a file or rewording an unpushed commit.
+    fn auto_fix(&self) -> bool {
+        false
+    }
+}
+
+/// How one rule's run went, for `check --timings`.
+#[derive(Debug, Clone)]
+pub struct RuleRun {
+    pub rule: &'static str,
+    pub severity: RuleSeverity,
+    pub violations: Vec<Violation>,
+    pub elapsed: Duration,
+}
+
+pub struct Registry {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Registry {
+    /// The built-in rules, in report order (as in [`rollup::RULES`]).
+    pub fn builtin() -> Self {
+        Self {
+            rules: vec![
+                Box::new(BranchPattern),
+                Box::new(BranchAge),
+                Box::new(CommitConvention),
+                Box::new(SignedCommits),
+                Box::new(AuthorEmail),
+                Box::new(QuietHours),
+                Box::new(CleanWorktree),
+                Box::new(Remote),
+                Box::new(Upstream),
+                Box::new(SensitiveFiles),
+                Box::new(LargeFiles),
+                Box::new(ProtectedPaths),
+                Box::new(BaseDivergence),
+                Box::new(LinearHistory),
+                Box::new(Attributes),
+                Box::new(References),
+                Box::new(StagedContent),
+            ],
+        }
+    }
+
+    /// Adds `rule` after the ones already registered. Its violations are
+    /// counted under `rules` in the report, with its `[severity]`.
+    pub fn register(&mut self, rule: Box<dyn Rule>) {
+        self.rules.push(rule);
+    }
+
+    pub fn ids(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|rule| rule.id()).collect()
+    }
+
+    /// IDs of the rules with a safe auto-fix.
+    pub fn auto_fixable(&self) -> Vec<&'static str> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.auto_fix())
+            .map(|rule| rule.id())
+            .collect()
+    }
+
+    /// Runs every rule that applies and is not `off`, in order.
+    pub fn run(&self, ctx: &mut Context) -> Result<Vec<RuleRun>> {
+        let mut runs = Vec::new();
+        for rule in &self.rules {
+            let severity = rule.severity(ctx.config);
+            if severity == RuleSeverity::Off || !rule.applies_to(ctx) {
+                continue;
+            }
+            let started = Instant::now();
+            let violations = rule.evaluate(ctx)?;
+            runs.push(RuleRun {
+                rule: rule.id(),
+                severity,
+                violations,
+                elapsed: started.elapsed(),
+            });
+        }
+        Ok(runs)
+    }
+}
+
+struct BranchPattern;
+
+impl Rule for BranchPattern {
+    fn id(&self) -> &'static str {
+        "branch-pattern"
+    }
+
+    /// Renames the branch to the suggested name, when there is one.
+    fn auto_fix(&self) -> bool {
+        true
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        let naming = BranchNaming::from_config(&ctx.config.branches)?;
+        let branch = &mut ctx.report.branch;
+        branch.pattern = naming.pattern().to_string();
+        branch.problems = naming.problems(&branch.name);
+        branch.valid = branch.problems.is_empty();
+        Ok(failed(
+            self.id(),
+            !branch.valid,
+            format!("'{}' {}", branch.name, branch.problems.join("; ")),
+        ))
+    }
+}
+
+struct BranchAge;
+
+impl Rule for BranchAge {
+    fn id(&self) -> &'static str {
+        "branch-age"
+    }
+
+    fn applies_to(&self, ctx: &Context) -> bool {
+        !ctx.staged_only && !ctx.unborn && ctx.config.checks.max_branch_age_days.is_some()
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        let branch = &mut ctx.report.branch;
+        branch.age = check::branch_age(ctx.config)?;
+        Ok(match &branch.age {
+            Some(age) if age.too_old() => vec![Violation::new(
+                self.id(),
+                format!(
+                    "'{}' diverged from {} {} day(s) ago (max {})",
+                    branch.name, age.base, age.days, age.max_days
+                ),
+            )],
+            _ => Vec::new(),
+        })
+    }
+}
+
+struct CommitConvention;
+
+impl Rule for CommitConvention {
+    fn id(&self) -> &'static str {
+        "commit-convention"
+    }
+
+    /// Rewords unpushed commits.
+    fn auto_fix(&self) -> bool {
+        true
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        let commits = check::validate_commits_with(
+            ctx.config,
+            ctx.commits.clone(),
+            &ctx.report.short_hashes,
+            ctx.on_commit,
+        )?;
+        let violations = commits
+            .iter()
+            .filter(|c| !c.valid)
+            .map(|c| Violation::new(self.id(), c.problems.join("; ")).at(c.location.clone()))
+            .collect();
+        ctx.report.commits = commits;
+        Ok(violations)
+    }
+}
+
+struct SignedCommits;
+
+impl Rule for SignedCommits {
+    fn id(&self) -> &'static str {
+        "signed-commits"
+    }
+
+    fn applies_to(&self, ctx: &Context) -> bool {
+        ctx.config.checks.require_signed_commits
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        ctx.report.signatures = signatures::signature_issues(&ctx.hashes())?;
+        Ok(ctx
+            .report
+            .signatures
+            .iter()
+            .map(|issue| {
+                let location = ctx.report.commit_location(&issue.hash);
+                Violation::new(self.id(), issue.status.label()).at(location)
+            })
+            .collect())
+    }
+}
+
+struct AuthorEmail;
+
+impl Rule for AuthorEmail {
+    fn id(&self) -> &'static str {
+        "author-email"
+    }
+
+    fn applies_to(&self, ctx: &Context) -> bool {
+        let checks = &ctx.config.checks;
+        !checks.allowed_author_domains.is_empty() || checks.forbid_noreply_emails
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        ctx.report.authors = authors::build_authors_report(&ctx.config.checks, &ctx.hashes())?;
+        Ok(ctx
+            .report
+            .authors
+            .issues
+            .iter()
+            .map(|issue| {
+                let message = format!("{} ({})", issue.email, issue.problem.label());
+                Violation::new(self.id(), message).at(ctx.report.commit_location(&issue.hash))
+            })
+            .collect())
+    }
+}
+
+/// Advisory, capped at `warning` by [`severity::ADVISORY`].
+struct QuietHours;
+
+impl Rule for QuietHours {
+    fn id(&self) -> &'static str {
+        "quiet-hours"
+    }
+
+    fn applies_to(&self, ctx: &Context) -> bool {
+        ctx.config.checks.quiet_hours.is_some() || ctx.config.checks.quiet_weekends
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        ctx.report.quiet_hours = quiet_hours::quiet_commits(&ctx.config.checks, &ctx.hashes())?;
+        Ok(ctx
+            .report
+            .quiet_hours
+            .iter()
+            .map(|commit| {
+                let message = format!("authored {} ({})", commit.authored, commit.reason.label());
+                Violation::new(self.id(), message).at(ctx.report.commit_location(&commit.hash))
+            })
+            .collect())
+    }
+}
+
+struct CleanWorktree;
+
+impl Rule for CleanWorktree {
+    fn id(&self) -> &'static str {
+        "clean-worktree"
+    }
+
+    fn applies_to(&self, ctx: &Context) -> bool {
+        !ctx.staged_only && ctx.config.checks.require_clean_worktree
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        ctx.report.repo.worktree_clean = ctx.worktree_clean;
+        Ok(failed(
+            self.id(),
+            !ctx.report.repo.worktree_clean,
+            "uncommitted changes",
+        ))
+    }
+}
+
+struct Remote;
+
+impl Rule for Remote {
+    fn id(&self) -> &'static str {
+        "remote"
+    }
+
+    fn applies_to(&self, ctx: &Context) -> bool {
+        !ctx.staged_only && ctx.config.checks.require_remote
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        let status = remote::remote_status(&ctx.report.branch.name)?;
+        let violations = match status.problem {
+            Some(problem) => failed(
+                self.id(),
+                true,
+                format!("remote '{}' {}", status.name, problem.label()),
+            ),
+            None => Vec::new(),
+        };
+        ctx.report.repo.remote = Some(status);
+        Ok(violations)
+    }
+}
+
+struct Upstream;
+
+impl Rule for Upstream {
+    fn id(&self) -> &'static str {
+        "upstream"
+    }
+
+    fn auto_fix(&self) -> bool {
+        true
+    }
+
+    fn applies_to(&self, ctx: &Context) -> bool {
+        !ctx.staged_only && ctx.config.checks.require_upstream
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        ctx.report.repo.upstream_set = ctx.has_upstream;
+        Ok(failed(
+            self.id(),
+            !ctx.report.repo.upstream_set,
+            "no upstream branch",
+        ))
+    }
+}
+
+struct SensitiveFiles;
+
+impl Rule for SensitiveFiles {
+    fn id(&self) -> &'static str {
+        "sensitive-files"
+    }
+
+    /// Unstages the files; secrets in content need editing.
+    fn auto_fix(&self) -> bool {
+        true
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        let report = &mut ctx.report.sensitive;
+        report.fail_on = ctx.config.sensitive.fail_on;
+        let mut diff = None;
+        for (scoped, changes) in by_config(ctx.config, ctx.changes.clone(), |c| &c.path) {
+            let config = &scoped.sensitive;
+            report.findings.extend(sensitive::classify_staged_changes(
+                &changes,
+                &config.patterns,
+                &config.severity,
+            ));
+            if config.scan_content {
+                if diff.is_none() {
+                    diff = Some(git::staged_diff()?);
+                }
+                let diff = diff.as_deref().unwrap_or_default();
+                let mut content = secrets::scan_diff(config, &scoped.files, diff)?;
+                content.retain(|f| changes.iter().any(|c| c.path == f.path));
+                report.content.extend(content);
+            }
+        }
+        report.files = report.findings.iter().map(|f| f.path.clone()).collect();
+
+        let fail_on = |path: &str| ctx.config.for_path(path).sensitive.fail_on;
+        let files = report
+            .findings
+            .iter()
+            .filter(|f| f.severity >= fail_on(&f.path))
+            .map(|f| {
+                let message = format!("{} ({})", f.describe(), f.severity);
+                Violation::new(self.id(), message).at(Location::file(&f.path))
+            });
+        let secrets = report
+            .content
+            .iter()
+            .filter(|f| f.severity >= fail_on(&f.path))
+            .map(|f| {
+                let message = format!("{} ({})", f.rule, f.severity);
+                Violation::new(self.id(), message).at(Location::file_line(&f.path, f.line))
+            });
+        Ok(files.chain(secrets).collect())
+    }
+}
+
+struct LargeFiles;
+
+impl Rule for LargeFiles {
+    fn id(&self) -> &'static str {
+        "large-files"
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        ctx.report.large_files = large_files::find_large_files(ctx.config, &ctx.hashes())?;
+        Ok(ctx
+            .report
+            .large_files
+            .iter()
+            .map(|file| {
+                let message = format!("{} KiB", file.size_kb());
+                Violation::new(self.id(), message).at(Location::file(&file.path))
+            })
+            .collect())
+    }
+}
+
+struct ProtectedPaths;
+
+impl Rule for ProtectedPaths {
+    fn id(&self) -> &'static str {
+        "protected-paths"
+    }
+
+    /// A rename touches both paths: moving a file out of a protected
+    /// directory changes it as much as editing it.
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        let mut touched: Vec<String> = Vec::new();
+        for path in ctx.changes.iter().flat_map(StagedChange::touched) {
+            if !touched.iter().any(|p| p == path) {
+                touched.push(path.to_string());
+            }
+        }
+        let mut protected = Vec::new();
+        for (scoped, paths) in by_config(ctx.config, touched, String::as_str) {
+            let branch = &ctx.report.branch.name;
+            protected.extend(protected::protected_changes(
+                &scoped.checks,
+                branch,
+                &paths,
+            )?);
+        }
+        ctx.report.protected_paths = protected;
+        Ok(ctx
+            .report
+            .protected_paths
+            .iter()
+            .map(|change| {
+                let message = format!("matches protected_paths '{}'", change.pattern);
+                Violation::new(self.id(), message).at(Location::file(&change.path))
+            })
+            .collect())
+    }
+}
+
+/// Reads the divergence from the base branch, which the context measures
+/// up front: linear history and the text report need it too.
+struct BaseDivergence;
+
+impl Rule for BaseDivergence {
+    fn id(&self) -> &'static str {
+        "base-divergence"
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        Ok(match &ctx.report.repo.base {
+            Some(base) if base.too_far_behind() || base.too_far_ahead() => vec![Violation::new(
+                self.id(),
+                format!("{} ahead, {} behind {}", base.ahead, base.behind, base.base),
+            )],
+            _ => Vec::new(),
+        })
+    }
+}
+
+struct LinearHistory;
+
+impl Rule for LinearHistory {
+    fn id(&self) -> &'static str {
+        "linear-history"
+    }
+
+    fn applies_to(&self, ctx: &Context) -> bool {
+        ctx.config.checks.require_linear_history
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        let base = match &ctx.report.repo.base {
+            Some(base) => base.base.clone(),
+            None => {
+                if !ctx.staged_only && !ctx.unborn && ctx.config.checks.base_branch.is_none() {
+                    eprintln!("Warning: require_linear_history needs a base_branch; skipping");
+                }
+                return Ok(Vec::new());
+            }
+        };
+        let linearity = linear::linearity(&base)?;
+        let violations = failed(
+            self.id(),
+            !linearity.is_linear(),
+            format!(
+                "{} merge commit(s) on top of {}",
+                linearity.merges.len(),
+                base
+            ),
+        );
+        ctx.report.repo.linearity = Some(linearity);
+        Ok(violations)
+    }
+}
+
+struct Attributes;
+
+impl Rule for Attributes {
+    fn id(&self) -> &'static str {
+        "attributes"
+    }
+
+    /// Appends the missing lines; files violating the policy need a decision.
+    fn auto_fix(&self) -> bool {
+        true
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        ctx.report.attributes =
+            attributes::build_attributes_report(&ctx.config.attributes, &ctx.staged)?;
+        let report = &ctx.report.attributes;
+        let missing = report.missing_lines.iter().map(|line| {
+            Violation::new(self.id(), format!("missing `{}`", line))
+                .at(Location::file(".gitattributes"))
+        });
+        let files = report
+            .violations
+            .iter()
+            .map(|v| Violation::new(self.id(), v.reason.clone()).at(Location::file(&v.path)));
+        Ok(missing.chain(files).collect())
+    }
+}
+
+struct References;
+
+impl Rule for References {
+    fn id(&self) -> &'static str {
+        "references"
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        ctx.report.references =
+            references::build_references_report(&ctx.config.references, &ctx.commits)?;
+        Ok(ctx
+            .report
+            .references
+            .dangling
+            .iter()
+            .map(|r| {
+                Violation::new(self.id(), r.reference.clone())
+                    .at(ctx.report.commit_location(&r.commit))
+            })
+            .collect())
+    }
+}
+
+struct StagedContent;
+
+impl Rule for StagedContent {
+    fn id(&self) -> &'static str {
+        "staged-content"
+    }
+
+    fn applies_to(&self, ctx: &Context) -> bool {
+        ctx.config.with_scopes().any(|config| {
+            config.checks.forbid_conflict_markers || config.checks.forbid_trailing_whitespace
+        })
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Result<Vec<Violation>> {
+        let added = secrets::added_lines(&git::staged_diff()?);
+        let mut issues = Vec::new();
+        for (scoped, changes) in by_config(ctx.config, ctx.changes.clone(), |c| &c.path) {
+            let lines: Vec<_> = added
+                .iter()
+                .filter(|(path, _, _)| changes.iter().any(|c| &c.path == path))
+                .cloned()
+                .collect();
+            issues.extend(staged_content::scan_added(
+                &scoped.checks,
+                &scoped.files,
+                &lines,
+            ));
+        }
+        ctx.report.staged_content = issues;
+        Ok(ctx
+            .report
+            .staged_content
+            .iter()
+            .map(|issue| {
+                Violation::new(self.id(), issue.kind.to_string())
+                    .at(Location::file_line(&issue.path, issue.line))
+            })
+            .collect())
+    }
+}
+
+/// `items` grouped by the config governing each one's path (see
+/// [`Config::for_path`]), in order of first appearance: staged files under a
+/// nested config are checked against its settings.
+fn by_config<T>(
+    config: &Config,
+    items: Vec<T>,
+    path: impl Fn(&T) -> &str,
+) -> Vec<(&Config, Vec<T>)> {
+    let mut groups: Vec<(&Config, Vec<T>)> = Vec::new();
+    for item in items {
+        let governing = config.for_path(path(&item));
+        match groups.iter_mut().find(|(c, _)| std::ptr::eq(*c, governing)) {
+            Some((_, group)) => group.push(item),
+            None => groups.push((governing, vec![item])),
+        }
+    }
+    groups
+}
+
+/// A single violation when `failed`.
+fn failed(rule: &'static str, failed: bool, message: impl Into<String>) -> Vec<Violation> {
+    if failed {
+        vec![Violation::new(rule, message)]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Whether `rule` is one of the built-in rules, whose issues the summary
+/// counts.
+pub(crate) fn is_builtin(rule: &str) -> bool {
+    rollup::RULES.contains(&rule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_rules_are_registered_in_report_order() {
+        assert_eq!(Registry::builtin().ids(), rollup::RULES);
+    }
+}
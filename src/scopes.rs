@@ -0,0 +1,152 @@
+//! `commits.scopes = "auto"`: the allowed commit scopes derived from the
+//! repository layout, so a monorepo's scopes follow its packages. The
+//! members of a Cargo or npm workspace win; without one, each top-level
+//! directory is a scope, or each directory inside a generic one such as
+//! `packages/`.
+
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+
+use crate::git;
+
+/// The entry of `commits.scopes` that stands for the derived scopes.
+pub(crate) const AUTO: &str = "auto";
+
+/// Directories that group code rather than name a part of it: their
+/// subdirectories are the scopes.
+pub(crate) const GENERIC_DIRS: &[&str] = &[
+    "src", "lib", "crates", "packages", "apps", "pkg", "internal",
+];
+
+/// `scopes` with `auto` replaced by the scopes of the repository layout.
+pub(crate) fn resolve(scopes: &[String]) -> Result<Vec<String>> {
+    let mut resolved: BTreeSet<String> = scopes.iter().filter(|s| *s != AUTO).cloned().collect();
+    if scopes.iter().any(|s| s == AUTO) {
+        let root = git::toplevel()?;
+        let manifest = |name: &str| fs::read_to_string(root.join(name)).ok();
+        resolved.extend(from_layout(
+            &git::tracked_files()?,
+            manifest("Cargo.toml").as_deref(),
+            manifest("package.json").as_deref(),
+        ));
+    }
+    Ok(resolved.into_iter().collect())
+}
+
+/// Scopes for the tracked `files`, given the root `Cargo.toml` and
+/// `package.json` when they exist.
+fn from_layout(files: &[String], cargo: Option<&str>, package_json: Option<&str>) -> Vec<String> {
+    let dirs = directories(files);
+    let mut members = Vec::new();
+    if let Some(manifest) = cargo.and_then(|m| toml::from_str::<Value>(m).ok()) {
+        members.extend(strings(manifest.pointer("/workspace/members")));
+    }
+    if let Some(manifest) = package_json.and_then(|m| serde_json::from_str::<Value>(m).ok()) {
+        // `workspaces` is a list of globs, or an object with `packages`.
+        let workspaces = manifest.get("workspaces");
+        members.extend(strings(
+            workspaces.and_then(|w| w.get("packages")).or(workspaces),
+        ));
+    }
+
+    let scopes: BTreeSet<&str> = if members.is_empty() {
+        dirs.iter()
+            .filter_map(|dir| match dir.split_once('/') {
+                None if !GENERIC_DIRS.contains(&dir.as_str()) => Some(dir.as_str()),
+                Some((parent, child)) if GENERIC_DIRS.contains(&parent) && !child.contains('/') => {
+                    Some(child)
+                }
+                _ => None,
+            })
+            .collect()
+    } else {
+        dirs.iter()
+            .filter(|dir| members.iter().any(|glob| matches_glob(glob, dir)))
+            .filter_map(|dir| dir.rsplit('/').next())
+            .collect()
+    };
+    scopes
+        .into_iter()
+        .filter(|scope| !scope.starts_with('.'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Every directory holding a tracked file, e.g. `a` and `a/b` for `a/b/c.rs`.
+fn directories(files: &[String]) -> BTreeSet<String> {
+    files
+        .iter()
+        .flat_map(|file| {
+            file.match_indices('/')
+                .map(move |(i, _)| file[..i].to_string())
+        })
+        .collect()
+}
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|i| i.as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Workspace member globs: `*` matches within one path component.
+fn matches_glob(glob: &str, dir: &str) -> bool {
+    let glob = glob.trim_start_matches("./").trim_end_matches('/');
+    let (globs, parts): (Vec<&str>, Vec<&str>) =
+        (glob.split('/').collect(), dir.split('/').collect());
+    globs.len() == parts.len()
+        && globs
+            .iter()
+            .zip(&parts)
+            .all(|(glob, part)| match glob.split_once('*') {
+                Some((prefix, suffix)) => {
+                    part.len() >= prefix.len() + suffix.len()
+                        && part.starts_with(prefix)
+                        && part.ends_with(suffix)
+                }
+                None => glob == part,
+            })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn scopes_follow_the_workspace_or_the_top_level() {
+        let tracked = files(&[
+            "Cargo.toml",
+            ".github/workflows/ci.yml",
+            "crates/api/src/lib.rs",
+            "crates/cli/src/main.rs",
+            "docs/index.md",
+            "tools/xtask/main.rs",
+        ]);
+        assert_eq!(
+            from_layout(&tracked, None, None),
+            vec!["api", "cli", "docs", "tools"]
+        );
+
+        let cargo = "[workspace]\nmembers = [\"crates/*\", \"tools/xtask\"]\n";
+        assert_eq!(
+            from_layout(&tracked, Some(cargo), None),
+            vec!["api", "cli", "xtask"]
+        );
+
+        let npm = r#"{"workspaces": {"packages": ["crates/a*"]}}"#;
+        assert_eq!(from_layout(&tracked, None, Some(npm)), vec!["api"]);
+    }
+}
@@ -1,4 +1,15 @@
+use anyhow::{Context, Result};
 use glob_match::glob_match;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::git::{ChangeKind, StagedChange};
+
+pub const GITIGNORE: &str = ".gitignore";
 
 const DEFAULT_PATTERNS: &[&str] = &[
     ".env",
@@ -12,18 +23,294 @@ const DEFAULT_PATTERNS: &[&str] = &[
     "**/*.pfx",
 ];
 
-pub fn default_patterns() -> Vec<String> {
-    DEFAULT_PATTERNS.iter().map(|s| s.to_string()).collect()
+/// Suffixes of the templates a project commits in place of a secret file,
+/// such as `.env.example`: they hold placeholders, not secrets.
+const TEMPLATE_SUFFIXES: &[&str] = &[".example", ".sample", ".template", ".dist"];
+
+/// Severity tier of a secret finding. Ordered so thresholds can be compared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    /// Suspicious but unconfirmed, e.g. high-entropy strings.
+    Medium,
+    /// Generic tokens and credential files.
+    #[default]
+    High,
+    /// Private keys and key stores.
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        f.write_str(label)
+    }
+}
+
+/// An entry of `[sensitive] patterns`: a bare file glob, or a named rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum SensitivePattern {
+    Glob(String),
+    Rule(SensitiveRule),
+}
+
+/// A named entry of `[sensitive] patterns`, such as
+/// `{ name = "AWS credentials file", glob = "**/.aws/credentials" }`. A
+/// `glob` matches staged paths; a `regex` matches added lines, like
+/// `[sensitive.rules]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SensitiveRule {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    /// Wins over `[sensitive.severity]` and the built-in tiers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// Why the rule exists, shown with its findings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Globs of paths the rule does not apply to, e.g. test fixtures.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowlist: Vec<String>,
+}
+
+impl SensitiveRule {
+    pub fn allows(&self, path: &str) -> bool {
+        self.allowlist.iter().any(|glob| glob_match(glob, path))
+    }
+}
+
+impl SensitivePattern {
+    /// The file glob, for bare globs and rules with a `glob`.
+    pub fn glob(&self) -> Option<&str> {
+        match self {
+            SensitivePattern::Glob(glob) => Some(glob),
+            SensitivePattern::Rule(rule) => rule.glob.as_deref(),
+        }
+    }
+
+    pub fn rule(&self) -> Option<&SensitiveRule> {
+        match self {
+            SensitivePattern::Glob(_) => None,
+            SensitivePattern::Rule(rule) => Some(rule),
+        }
+    }
+}
+
+/// A string is a bare glob and a table a rule, with the rule's own errors
+/// (an unknown severity, a missing `name`) rather than an untagged mismatch.
+impl<'de> Deserialize<'de> for SensitivePattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PatternVisitor;
+
+        impl<'de> Visitor<'de> for PatternVisitor {
+            type Value = SensitivePattern;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a glob or a table with `name` and `glob` or `regex`")
+            }
+
+            fn visit_str<E: de::Error>(self, glob: &str) -> Result<Self::Value, E> {
+                Ok(SensitivePattern::Glob(glob.to_string()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let rule = SensitiveRule::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                Ok(SensitivePattern::Rule(rule))
+            }
+        }
+
+        deserializer.deserialize_any(PatternVisitor)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SensitiveFinding {
+    pub path: String,
+    /// The glob that matched.
+    pub pattern: String,
+    /// The name of the rule the glob belongs to, if it is a named one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub severity: Severity,
+    /// The source of a staged rename or copy that matched `pattern`: the
+    /// file keeps its content under a name the patterns may not cover.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renamed_from: Option<String>,
+}
+
+impl SensitiveFinding {
+    /// What matched: the rule's name, else the glob.
+    pub fn describe(&self) -> String {
+        match (&self.name, &self.renamed_from) {
+            (Some(name), Some(from)) => format!("{} (renamed from {})", name, from),
+            (Some(name), None) => name.clone(),
+            (None, Some(from)) => format!("{} matches '{}'", from, self.pattern),
+            (None, None) => format!("matches '{}'", self.pattern),
+        }
+    }
+}
+
+pub fn default_patterns() -> Vec<SensitivePattern> {
+    DEFAULT_PATTERNS
+        .iter()
+        .map(|s| SensitivePattern::Glob(s.to_string()))
+        .collect()
+}
+
+/// Built-in severity of a pattern; unknown patterns are treated as high.
+pub fn default_severity(pattern: &str) -> Severity {
+    match pattern {
+        "*.pem" | "*.key" | "**/id_rsa" | "**/*.p12" | "**/*.pfx" => Severity::Critical,
+        "**/id_rsa.pub" => Severity::Low,
+        _ => Severity::default(),
+    }
 }
 
-pub fn check_sensitive_files(staged: &[String], patterns: &[String]) -> Vec<String> {
+/// Matches staged files against patterns, tagging each hit with the severity of
+/// the first matching pattern: a rule's own, else the config override for its
+/// name or glob, else the built-in tier.
+pub fn classify_sensitive_files(
+    staged: &[String],
+    patterns: &[SensitivePattern],
+    overrides: &BTreeMap<String, Severity>,
+) -> Vec<SensitiveFinding> {
     staged
         .iter()
-        .filter(|file| patterns.iter().any(|pat| glob_match(pat, file)))
-        .cloned()
+        .filter_map(|file| classify(file, None, patterns, overrides))
+        .collect()
+}
+
+/// Like [`classify_sensitive_files`], for staged changes: deletions are
+/// skipped, and a rename or copy is flagged under its new path when either
+/// path matches, so moving `.env` to `config/app.conf` is still caught.
+pub fn classify_staged_changes(
+    changes: &[StagedChange],
+    patterns: &[SensitivePattern],
+    overrides: &BTreeMap<String, Severity>,
+) -> Vec<SensitiveFinding> {
+    changes
+        .iter()
+        .filter(|change| change.kind != ChangeKind::Deleted)
+        .filter_map(|change| {
+            classify(&change.path, None, patterns, overrides).or_else(|| {
+                let from = change.from.as_deref()?;
+                classify(&change.path, Some(from), patterns, overrides)
+            })
+        })
+        .collect()
+}
+
+/// The finding for `path`, matching the patterns against `renamed_from`
+/// when given. A rule's allowlist is matched against `path`.
+fn classify(
+    path: &str,
+    renamed_from: Option<&str>,
+    patterns: &[SensitivePattern],
+    overrides: &BTreeMap<String, Severity>,
+) -> Option<SensitiveFinding> {
+    let matched = renamed_from.unwrap_or(path);
+    let (pattern, glob) = patterns.iter().find_map(|pattern| {
+        let glob = pattern.glob()?;
+        let allowed = pattern.rule().is_some_and(|rule| rule.allows(path));
+        let matches = glob_match(glob, matched) && !is_template(glob, matched);
+        (!allowed && matches).then_some((pattern, glob))
+    })?;
+    let rule = pattern.rule();
+    let severity = rule
+        .and_then(|rule| rule.severity.or_else(|| overrides.get(&rule.name).copied()))
+        .or_else(|| overrides.get(glob).copied())
+        .unwrap_or_else(|| default_severity(glob));
+    Some(SensitiveFinding {
+        path: path.to_string(),
+        pattern: glob.to_string(),
+        name: rule.map(|rule| rule.name.clone()),
+        description: rule.and_then(|rule| rule.description.clone()),
+        severity,
+        renamed_from: renamed_from.map(str::to_string),
+    })
+}
+
+/// Whether `path` is a template, like `.env.example`, that `glob` matches
+/// only through a wildcard (`.env.*`) rather than by naming the suffix.
+fn is_template(glob: &str, path: &str) -> bool {
+    TEMPLATE_SUFFIXES
+        .iter()
+        .any(|suffix| path.ends_with(suffix) && !glob.ends_with(suffix))
+}
+
+pub fn check_sensitive_files(staged: &[String], patterns: &[SensitivePattern]) -> Vec<String> {
+    classify_sensitive_files(staged, patterns, &BTreeMap::new())
+        .into_iter()
+        .map(|finding| finding.path)
         .collect()
 }
 
+/// The `.gitignore` lines that keep `finding` out of the index: its
+/// pattern, or the path itself when the pattern uses braces, which
+/// `.gitignore` lacks, or matched the source of a rename rather than the
+/// path. A pattern ending in `.*` is followed by exceptions for the
+/// templates it would hide, e.g. `!.env.example` after `.env.*`.
+pub fn gitignore_entries(finding: &SensitiveFinding) -> Vec<String> {
+    if finding.pattern.contains(['{', '}']) || finding.renamed_from.is_some() {
+        return vec![finding.path.clone()];
+    }
+    let mut entries = vec![finding.pattern.clone()];
+    if let Some(stem) = finding
+        .pattern
+        .strip_suffix('*')
+        .filter(|s| s.ends_with('.'))
+    {
+        entries.extend(
+            TEMPLATE_SUFFIXES
+                .iter()
+                .map(|suffix| format!("!{}{}", stem, &suffix[1..])),
+        );
+    }
+    entries
+}
+
+/// Entries of `wanted` that `existing` does not list yet, without duplicates.
+pub fn missing_gitignore_entries(existing: &str, wanted: &[String]) -> Vec<String> {
+    let mut missing: Vec<String> = Vec::new();
+    for entry in wanted {
+        let listed = existing.lines().any(|line| line.trim() == entry);
+        if !listed && !missing.contains(entry) {
+            missing.push(entry.clone());
+        }
+    }
+    missing
+}
+
+pub fn read_gitignore() -> String {
+    fs::read_to_string(GITIGNORE).unwrap_or_default()
+}
+
+pub fn append_gitignore(entries: &[String]) -> Result<()> {
+    let path = Path::new(GITIGNORE);
+    let mut contents = read_gitignore();
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    for entry in entries {
+        contents.push_str(entry);
+        contents.push('\n');
+    }
+    fs::write(path, contents).with_context(|| format!("write {}", path.display()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -36,6 +323,27 @@ mod tests {
         assert_eq!(found, vec![".env", ".env.local"]);
     }
 
+    #[test]
+    fn env_templates_are_not_sensitive() {
+        let staged = vec![
+            ".env.example".into(),
+            "web/.env.sample".into(),
+            ".env.production".into(),
+        ];
+        let mut patterns = default_patterns();
+        patterns.push(SensitivePattern::Glob("**/.env.*".to_string()));
+        assert_eq!(
+            check_sensitive_files(&staged, &patterns),
+            vec![".env.production"]
+        );
+
+        let explicit = vec![SensitivePattern::Glob(".env.example".to_string())];
+        assert_eq!(
+            check_sensitive_files(&[".env.example".into()], &explicit),
+            vec![".env.example"]
+        );
+    }
+
     #[test]
     fn detects_key_files() {
         let staged = vec!["server.pem".into(), "key.key".into(), "readme.md".into()];
@@ -44,6 +352,96 @@ mod tests {
         assert_eq!(found, vec!["server.pem", "key.key"]);
     }
 
+    #[test]
+    fn classifies_by_severity() {
+        let staged = vec!["server.pem".into(), ".env".into(), "ssh/id_rsa.pub".into()];
+        let found = classify_sensitive_files(&staged, &default_patterns(), &BTreeMap::new());
+        let severities: Vec<Severity> = found.iter().map(|f| f.severity).collect();
+        assert_eq!(
+            severities,
+            vec![Severity::Critical, Severity::High, Severity::Low]
+        );
+    }
+
+    #[test]
+    fn severity_overrides_apply_per_pattern() {
+        let staged = vec![".env".into()];
+        let overrides = BTreeMap::from([(".env".to_string(), Severity::Medium)]);
+        let found = classify_sensitive_files(&staged, &default_patterns(), &overrides);
+        assert_eq!(found[0].severity, Severity::Medium);
+        assert_eq!(found[0].pattern, ".env");
+    }
+
+    #[test]
+    fn renames_and_copies_of_sensitive_files_are_flagged() {
+        let change = |kind, path: &str, from: Option<&str>| StagedChange {
+            kind,
+            path: path.to_string(),
+            from: from.map(str::to_string),
+        };
+        let changes = vec![
+            change(ChangeKind::Renamed, "config/app.conf", Some(".env")),
+            change(ChangeKind::Copied, "backup.txt", Some("server.pem")),
+            change(ChangeKind::Renamed, ".env", Some("env.example")),
+            change(ChangeKind::Renamed, "notes.md", Some("todo.md")),
+            change(ChangeKind::Deleted, "old.key", None),
+        ];
+        let found = classify_staged_changes(&changes, &default_patterns(), &BTreeMap::new());
+        let flagged: Vec<(&str, Option<&str>)> = found
+            .iter()
+            .map(|f| (f.path.as_str(), f.renamed_from.as_deref()))
+            .collect();
+        assert_eq!(
+            flagged,
+            vec![
+                ("config/app.conf", Some(".env")),
+                ("backup.txt", Some("server.pem")),
+                (".env", None),
+            ]
+        );
+        assert_eq!(found[1].severity, Severity::Critical);
+        assert_eq!(gitignore_entries(&found[0]), vec!["config/app.conf"]);
+    }
+
+    #[test]
+    fn named_rules_describe_their_findings() {
+        let rule = SensitiveRule {
+            name: "AWS credentials file".to_string(),
+            glob: Some("**/.aws/credentials".to_string()),
+            regex: None,
+            severity: None,
+            description: Some("Use aws-vault instead".to_string()),
+            allowlist: vec!["docs/**".to_string()],
+        };
+        let patterns = vec![
+            SensitivePattern::Rule(rule),
+            SensitivePattern::Glob(".env".into()),
+        ];
+        let overrides = BTreeMap::from([("AWS credentials file".to_string(), Severity::Critical)]);
+        let staged = vec![
+            "home/.aws/credentials".into(),
+            "docs/.aws/credentials".into(),
+            ".env".into(),
+        ];
+        let found = classify_sensitive_files(&staged, &patterns, &overrides);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].describe(), "AWS credentials file");
+        assert_eq!(found[0].severity, Severity::Critical);
+        assert_eq!(
+            found[0].description.as_deref(),
+            Some("Use aws-vault instead")
+        );
+        assert_eq!(found[1].describe(), "matches '.env'");
+        assert_eq!(found[1].name, None);
+    }
+
+    #[test]
+    fn severities_are_ordered() {
+        assert!(Severity::Critical > Severity::High);
+        assert!(Severity::High > Severity::Medium);
+        assert!(Severity::Medium > Severity::Low);
+    }
+
     #[test]
     fn no_false_positives() {
         let staged = vec!["src/main.rs".into(), "Cargo.toml".into()];
@@ -51,4 +449,43 @@ mod tests {
         let found = check_sensitive_files(&staged, &patterns);
         assert!(found.is_empty());
     }
+
+    #[test]
+    fn gitignore_entries_skip_listed_ones() {
+        let staged = vec![
+            ".env".into(),
+            "certs/server.pem".into(),
+            "server.pem".into(),
+        ];
+        let patterns = vec![
+            SensitivePattern::Glob(".env".to_string()),
+            SensitivePattern::Glob("{certs/,}*.pem".to_string()),
+        ];
+        let entries: Vec<String> = classify_sensitive_files(&staged, &patterns, &BTreeMap::new())
+            .iter()
+            .flat_map(gitignore_entries)
+            .collect();
+        assert_eq!(entries, vec![".env", "certs/server.pem", "server.pem"]);
+        let missing = missing_gitignore_entries("target/\n .env \n", &entries);
+        assert_eq!(missing, vec!["certs/server.pem", "server.pem"]);
+    }
+
+    #[test]
+    fn gitignore_entries_keep_templates_committable() {
+        let found = classify_sensitive_files(
+            &[".env.local".into()],
+            &default_patterns(),
+            &BTreeMap::new(),
+        );
+        assert_eq!(
+            gitignore_entries(&found[0]),
+            vec![
+                ".env.*",
+                "!.env.example",
+                "!.env.sample",
+                "!.env.template",
+                "!.env.dist"
+            ]
+        );
+    }
 }
@@ -0,0 +1,233 @@
+//! `serve --http`: a small HTTP endpoint so bots (e.g. a PR title checker)
+//! validate against exactly the repository's rules and config instead of
+//! reimplementing them. Each connection is handled on its own thread, with
+//! timeouts so a stalled client cannot hold it, and the config is reloaded
+//! for each request, so edits apply without a restart.
+//!
+//! - `GET /health`
+//! - `POST /lint-message` with `{"message": "..."}` or `{"pr_title": "..."}`
+//! - `POST /validate-range` with `{"range": "origin/main..HEAD"}`
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::check::validate_commits;
+use crate::config::load_config;
+use crate::error;
+use crate::git;
+use crate::lint::{lint_commit_message, lint_subject, LintMode};
+
+/// Request bodies larger than this are rejected.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// How long a client may take to send its request or read the response.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connections handled at once; more are answered 503 right away.
+const MAX_WORKERS: usize = 16;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+#[derive(Deserialize)]
+struct LintRequest {
+    message: Option<String>,
+    pr_title: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RangeRequest {
+    range: String,
+}
+
+pub fn serve(config_path: &Path, addr: &str) -> Result<()> {
+    let listener = TcpListener::bind(addr).with_context(|| format!("listen on {}", addr))?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    let busy = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Warning: failed to accept connection: {}", err);
+                continue;
+            }
+        };
+        if let Err(err) = stream
+            .set_read_timeout(Some(IO_TIMEOUT))
+            .and_then(|()| stream.set_write_timeout(Some(IO_TIMEOUT)))
+        {
+            eprintln!("Warning: failed to set connection timeouts: {}", err);
+            continue;
+        }
+        if busy.fetch_add(1, Ordering::SeqCst) >= MAX_WORKERS {
+            busy.fetch_sub(1, Ordering::SeqCst);
+            let body = json!({ "error": { "message": "too many requests in flight" } });
+            if let Err(err) = write_response(&mut stream, 503, &body) {
+                eprintln!("Warning: failed to send response: {}", err);
+            }
+            continue;
+        }
+        let config_path = config_path.to_path_buf();
+        let busy = Arc::clone(&busy);
+        thread::spawn(move || {
+            handle(&config_path, &mut stream);
+            busy.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+/// Reads one request from `stream` and answers it.
+fn handle(config_path: &Path, stream: &mut TcpStream) {
+    let (status, body) = match read_request(stream) {
+        Ok(Some(request)) => route(config_path, &request),
+        Ok(None) => (
+            413,
+            json!({ "error": { "message": "request body too large" } }),
+        ),
+        Err(err) => (400, json!({ "error": { "message": format!("{:#}", err) } })),
+    };
+    if let Err(err) = write_response(stream, status, &body) {
+        eprintln!("Warning: failed to send response: {}", err);
+    }
+}
+
+/// Parses one request; `None` when the body exceeds [`MAX_BODY_BYTES`].
+fn read_request(stream: &mut TcpStream) -> Result<Option<Request>> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        anyhow::bail!("malformed request line");
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().context("invalid Content-Length")?;
+            }
+        }
+    }
+    if length > MAX_BODY_BYTES {
+        return Ok(None);
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(Request {
+        method,
+        path,
+        body: String::from_utf8(body).context("body is not UTF-8")?,
+    }))
+}
+
+fn route(config_path: &Path, request: &Request) -> (u16, Value) {
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/health") => Ok(json!({ "status": "ok" })),
+        ("POST", "/lint-message") => lint(config_path, &request.body),
+        ("POST", "/validate-range") => validate_range(config_path, &request.body),
+        (_, "/health" | "/lint-message" | "/validate-range") => {
+            return (405, json!({ "error": { "message": "method not allowed" } }));
+        }
+        _ => return (404, json!({ "error": { "message": "not found" } })),
+    };
+    match result {
+        Ok(body) => (200, body),
+        Err(err) if err.is::<serde_json::Error>() => (400, error::to_json(&err)),
+        // Mostly a range git cannot resolve.
+        Err(err) if error::exit_code(&err) == 4 => (400, error::to_json(&err)),
+        Err(err) => (500, error::to_json(&err)),
+    }
+}
+
+fn lint(config_path: &Path, body: &str) -> Result<Value> {
+    let request: LintRequest = serde_json::from_str(body)?;
+    let config = load_config(config_path)?;
+    let result = match (request.pr_title, request.message) {
+        (Some(title), _) => lint_subject(&config, &title, LintMode::PrTitle)?,
+        (None, Some(message)) => lint_commit_message(&config, &message)?,
+        (None, None) => anyhow::bail!("pass `message` or `pr_title`"),
+    };
+    Ok(serde_json::to_value(result)?)
+}
+
+fn validate_range(config_path: &Path, body: &str) -> Result<Value> {
+    let request: RangeRequest = serde_json::from_str(body)?;
+    let config = load_config(config_path)?;
+    let mut commits = git::range_commits(&request.range)?;
+    let ignored = config.commits.ignored_commits()?;
+    commits.retain(|(hash, _)| !ignored.contains(hash));
+    let commits = validate_commits(&config, commits)?;
+    Ok(json!({
+        "range": request.range,
+        "valid": commits.iter().all(|c| c.valid),
+        "commits": commits,
+    }))
+}
+
+fn write_response(stream: &mut TcpStream, status: u16, body: &Value) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn routes_by_method_and_path() {
+        let config = Path::new("does-not-exist.toml");
+        assert_eq!(route(config, &request("GET", "/health")).0, 200);
+        assert_eq!(route(config, &request("GET", "/lint-message")).0, 405);
+        assert_eq!(route(config, &request("GET", "/nope")).0, 404);
+        // Malformed JSON is the client's fault, a missing config is not.
+        assert_eq!(route(config, &request("POST", "/lint-message")).0, 400);
+        let mut lint = request("POST", "/lint-message");
+        lint.body = r#"{"pr_title": "feat: x"}"#.to_string();
+        assert_eq!(route(config, &lint).0, 500);
+    }
+}
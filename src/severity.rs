@@ -0,0 +1,139 @@
+//! Per-rule severities from `[severity]`, e.g. `linear-history = "warning"`,
+//! and the `check --fail-on` threshold that decides which of them fail.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::check::Summary;
+use crate::rollup;
+use crate::rules::Violation;
+
+/// Ordered from least to most severe.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSeverity {
+    /// Not reported as failing and never fails the check.
+    Off,
+    Warning,
+    #[default]
+    Error,
+}
+
+impl fmt::Display for RuleSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RuleSeverity::Off => "off",
+            RuleSeverity::Warning => "warning",
+            RuleSeverity::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FailOn {
+    /// Fail only on rules with severity `error`.
+    #[default]
+    Error,
+    /// Fail on `warning` and `error` rules.
+    Warning,
+    /// Report, but always exit 0 on violations.
+    Never,
+}
+
+impl FailOn {
+    pub fn fails(self, severity: RuleSeverity) -> bool {
+        match self {
+            FailOn::Error => severity >= RuleSeverity::Error,
+            FailOn::Warning => severity >= RuleSeverity::Warning,
+            FailOn::Never => false,
+        }
+    }
+}
+
+/// Rules that only advise, such as on when commits were written: `warning`
+/// by default and at most, whatever `[severity]` says.
+pub const ADVISORY: &[&str] = &["quiet-hours"];
+
+/// `[severity]` for `rule`, `error` by default; advisory rules are capped
+/// at `warning`.
+pub fn of(rule: &str, severities: &BTreeMap<String, RuleSeverity>) -> RuleSeverity {
+    let configured = severities.get(rule).copied();
+    if ADVISORY.contains(&rule) {
+        return configured
+            .unwrap_or(RuleSeverity::Warning)
+            .min(RuleSeverity::Warning);
+    }
+    configured.unwrap_or_default()
+}
+
+/// A failing rule, named as in [`rollup::RULES`] or registered.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleResult {
+    pub rule: &'static str,
+    pub severity: RuleSeverity,
+    pub issues: usize,
+    /// What a registered rule found: it has no other part of the report to
+    /// show it in. Empty for built-in rules.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+}
+
+/// Failing rules in report order with their configured severity; rules
+/// set to `off` are left out.
+pub fn evaluate(summary: &Summary, severities: &BTreeMap<String, RuleSeverity>) -> Vec<RuleResult> {
+    rollup::violations(summary)
+        .into_iter()
+        .map(|(rule, issues)| RuleResult {
+            rule,
+            severity: of(rule, severities),
+            issues,
+            violations: Vec::new(),
+        })
+        .filter(|result| result.severity != RuleSeverity::Off)
+        .collect()
+}
+
+/// First key of `[severity]` that is not a rule name.
+pub fn unknown_rule(severities: &BTreeMap<String, RuleSeverity>) -> Option<&str> {
+    severities
+        .keys()
+        .map(String::as_str)
+        .find(|rule| !rollup::RULES.contains(rule))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severities_downgrade_or_drop_failing_rules() {
+        let summary = Summary {
+            branch_valid: false,
+            worktree_clean: true,
+            upstream_set: false,
+            nonlinear_history: true,
+            quiet_hours_commits: 2,
+            ..Summary::default()
+        };
+        let severities = BTreeMap::from([
+            ("upstream".to_string(), RuleSeverity::Off),
+            ("linear-history".to_string(), RuleSeverity::Warning),
+            ("quiet-hours".to_string(), RuleSeverity::Error),
+        ]);
+        let results = evaluate(&summary, &severities);
+        let rules: Vec<_> = results.iter().map(|r| (r.rule, r.severity)).collect();
+        assert_eq!(
+            rules,
+            vec![
+                ("branch-pattern", RuleSeverity::Error),
+                ("linear-history", RuleSeverity::Warning),
+                ("quiet-hours", RuleSeverity::Warning),
+            ]
+        );
+        assert!(FailOn::Warning.fails(RuleSeverity::Warning));
+        assert!(!FailOn::Error.fails(RuleSeverity::Warning));
+        assert!(!FailOn::Never.fails(RuleSeverity::Error));
+        assert_eq!(unknown_rule(&severities), None);
+    }
+}
@@ -0,0 +1,79 @@
+//! `[checks] require_signed_commits`: every inspected commit must carry a
+//! valid GPG/SSH signature, as reported by `git log --pretty=%G?`.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::git;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    Unsigned,
+    /// The signature does not match the commit.
+    Bad,
+    /// Signed with a revoked key.
+    Revoked,
+    /// Signed, but the key is not available to verify it.
+    Unverifiable,
+}
+
+impl SignatureStatus {
+    /// `None` for the `%G?` codes of a good signature (`G`, and `U`/`X`/`Y`,
+    /// which only qualify the key's trust or expiry).
+    pub(crate) fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "G" | "U" | "X" | "Y" => None,
+            "B" => Some(Self::Bad),
+            "R" => Some(Self::Revoked),
+            "E" => Some(Self::Unverifiable),
+            _ => Some(Self::Unsigned),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Unsigned => "unsigned",
+            Self::Bad => "bad signature",
+            Self::Revoked => "revoked key",
+            Self::Unverifiable => "cannot verify",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignatureIssue {
+    pub hash: String,
+    pub status: SignatureStatus,
+}
+
+/// Commits among `hashes` without a good signature, in the same order.
+pub fn signature_issues(hashes: &[String]) -> Result<Vec<SignatureIssue>> {
+    Ok(git::signature_codes(hashes)?
+        .into_iter()
+        .filter_map(|(hash, code)| {
+            let status = SignatureStatus::from_code(&code)?;
+            Some(SignatureIssue { hash, status })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_signature_codes() {
+        assert_eq!(SignatureStatus::from_code("G"), None);
+        assert_eq!(SignatureStatus::from_code("U"), None);
+        assert_eq!(
+            SignatureStatus::from_code("N"),
+            Some(SignatureStatus::Unsigned)
+        );
+        assert_eq!(SignatureStatus::from_code("B"), Some(SignatureStatus::Bad));
+        assert_eq!(
+            SignatureStatus::from_code("E"),
+            Some(SignatureStatus::Unverifiable)
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Leftover conflict markers and trailing whitespace in what is staged.
+//! Both read the index rather than the working tree, so a file staged in
+//! part with `git add -p` is judged by what will be committed.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::config::{CheckConfig, FilesConfig};
+
+/// Marker lines `git merge` leaves in a conflicted file. A lone `=======`
+/// is left out: it also underlines Markdown and reStructuredText headings.
+const CONFLICT_MARKERS: &[&str] = &["<<<<<<<", "|||||||", ">>>>>>>"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    ConflictMarker,
+    TrailingWhitespace,
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IssueKind::ConflictMarker => "conflict marker",
+            IssueKind::TrailingWhitespace => "trailing whitespace",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContentIssue {
+    pub path: String,
+    pub line: usize,
+    pub kind: IssueKind,
+}
+
+/// Conflict markers, with `forbid_conflict_markers`, then trailing
+/// whitespace, with `forbid_trailing_whitespace`, on the lines the staged
+/// changes add. Markers already in a file (say, a merge test fixture) do
+/// not fail an unrelated commit. Binary and `[files] vendored` files are
+/// skipped; `lines` are (path, line number, text), as
+/// [`crate::secrets::added_lines`] reads them from [`crate::git::staged_diff`].
+pub fn scan_added(
+    config: &CheckConfig,
+    files: &FilesConfig,
+    lines: &[(String, usize, String)],
+) -> Vec<ContentIssue> {
+    let lines: Vec<_> = lines
+        .iter()
+        .filter(|(path, _, _)| !files.is_vendored(path))
+        .cloned()
+        .collect();
+    let mut issues = Vec::new();
+    if config.forbid_conflict_markers {
+        issues.extend(conflict_markers(&lines));
+    }
+    if config.forbid_trailing_whitespace {
+        issues.extend(trailing_whitespace(&lines));
+    }
+    issues
+}
+
+/// Added lines, as (path, line number, text), that start with a conflict
+/// marker.
+fn conflict_markers(lines: &[(String, usize, String)]) -> Vec<ContentIssue> {
+    lines
+        .iter()
+        .filter(|(_, _, text)| {
+            CONFLICT_MARKERS.iter().any(|marker| {
+                text.strip_prefix(marker)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+            })
+        })
+        .map(|(path, line, _)| ContentIssue {
+            path: path.clone(),
+            line: *line,
+            kind: IssueKind::ConflictMarker,
+        })
+        .collect()
+}
+
+/// Added lines, as (path, line number, text), ending in a space or tab; the
+/// `\r` of a CRLF line ending does not count.
+fn trailing_whitespace(lines: &[(String, usize, String)]) -> Vec<ContentIssue> {
+    lines
+        .iter()
+        .filter(|(_, _, text)| text.trim_end_matches('\r').ends_with([' ', '\t']))
+        .map(|(path, line, _)| ContentIssue {
+            path: path.clone(),
+            line: *line,
+            kind: IssueKind::TrailingWhitespace,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_conflict_markers_but_not_headings() {
+        let text = "fn a() {}\n<<<<<<< HEAD\nx\n=======\ny\n>>>>>>> feat/login\n\
+                    Title\n=======\n<<<<<<<<< not a marker\n";
+        let added: Vec<(String, usize, String)> = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| ("src/a.rs".to_string(), i + 1, line.to_string()))
+            .collect();
+        let lines: Vec<usize> = conflict_markers(&added)
+            .iter()
+            .map(|issue| issue.line)
+            .collect();
+        assert_eq!(lines, vec![2, 6]);
+    }
+
+    #[test]
+    fn finds_trailing_whitespace_on_added_lines() {
+        let line = |n: usize, text: &str| ("a.txt".to_string(), n, text.to_string());
+        let lines = vec![
+            line(1, "ok"),
+            line(2, "space "),
+            line(3, "tab\t"),
+            line(4, "crlf\r"),
+            line(5, "crlf space \r"),
+        ];
+        let flagged: Vec<usize> = trailing_whitespace(&lines)
+            .iter()
+            .map(|issue| issue.line)
+            .collect();
+        assert_eq!(flagged, vec![2, 3, 5]);
+    }
+}
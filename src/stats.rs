@@ -0,0 +1,286 @@
+//! `stats`: how well the history of the last few weeks follows the
+//! conventions — compliance per author, message length, how much is merged
+//! rather than rebased — and the week-by-week trend, to track adoption.
+
+use anyhow::Result;
+use colored::Colorize;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cli::Format;
+use crate::config::load_config;
+use crate::convention::Convention;
+use crate::git::{self, LogEntry};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+const SECS_PER_WEEK: u64 = 7 * SECS_PER_DAY;
+
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub weeks: u64,
+    pub commits: usize,
+    pub merge_commits: usize,
+    /// Percentage of commits that are merges; 0 for a rebased history.
+    pub merge_pct: u32,
+    /// Percentage of non-merge commits whose subject follows the convention.
+    pub compliance_pct: Option<u32>,
+    /// Mean length of the full message of non-merge commits, in characters.
+    pub avg_message_chars: usize,
+    /// Most commits first.
+    pub authors: Vec<AuthorStats>,
+    /// Oldest week first.
+    pub trend: Vec<WeekStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthorStats {
+    pub name: String,
+    pub commits: usize,
+    pub compliant: usize,
+    pub compliance_pct: Option<u32>,
+    pub avg_message_chars: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeekStats {
+    /// First day of the week, `YYYY-MM-DD` (UTC).
+    pub start: String,
+    pub commits: usize,
+    pub merge_commits: usize,
+    pub compliance_pct: Option<u32>,
+}
+
+/// Running counts behind the percentages and means.
+#[derive(Default)]
+struct Tally {
+    commits: usize,
+    merges: usize,
+    compliant: usize,
+    message_chars: usize,
+}
+
+impl Tally {
+    fn add(&mut self, entry: &LogEntry, compliant: bool) {
+        self.commits += 1;
+        if entry.merge {
+            self.merges += 1;
+            return;
+        }
+        self.compliant += usize::from(compliant);
+        self.message_chars += entry.message.chars().count();
+    }
+
+    fn checked(&self) -> usize {
+        self.commits - self.merges
+    }
+
+    fn compliance_pct(&self) -> Option<u32> {
+        (self.checked() > 0).then(|| pct(self.compliant, self.checked()))
+    }
+
+    fn avg_message_chars(&self) -> usize {
+        self.message_chars.checked_div(self.checked()).unwrap_or(0)
+    }
+}
+
+pub fn stats(config_path: &Path, weeks: u64, format: Format) -> Result<()> {
+    let config = load_config(config_path)?;
+    let convention = Convention::from_config(&config.commits)?;
+    let log = if git::is_unborn()? {
+        Vec::new()
+    } else {
+        git::commit_log(&format!("{} weeks ago", weeks))?
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let stats = summarize(&log, weeks, now, |message| {
+        let subject = message.lines().next().unwrap_or_default();
+        convention.check_subject(subject).is_empty()
+    });
+    match format {
+        Format::Text => print_text(&stats),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+    }
+    Ok(())
+}
+
+/// Tallies `log` over the `weeks` ending at `now`; merge commits count
+/// towards the merge ratio only, not compliance or message length.
+fn summarize(log: &[LogEntry], weeks: u64, now: u64, compliant: impl Fn(&str) -> bool) -> Stats {
+    let weeks = weeks.max(1);
+    let mut total = Tally::default();
+    let mut authors: BTreeMap<&str, Tally> = BTreeMap::new();
+    let mut trend: Vec<Tally> = (0..weeks).map(|_| Tally::default()).collect();
+    for entry in log {
+        let ok = !entry.merge && compliant(&entry.message);
+        total.add(entry, ok);
+        authors.entry(&entry.author).or_default().add(entry, ok);
+        // Author dates can predate the committer date `--since` filters on.
+        let weeks_ago = now.saturating_sub(entry.timestamp) / SECS_PER_WEEK;
+        if let Some(week) = trend.get_mut(weeks_ago as usize) {
+            week.add(entry, ok);
+        }
+    }
+
+    let mut authors: Vec<AuthorStats> = authors
+        .into_iter()
+        .map(|(name, tally)| AuthorStats {
+            name: name.to_string(),
+            commits: tally.commits,
+            compliant: tally.compliant,
+            compliance_pct: tally.compliance_pct(),
+            avg_message_chars: tally.avg_message_chars(),
+        })
+        .collect();
+    authors.sort_by_key(|a| Reverse(a.commits));
+
+    let trend = trend
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(weeks_ago, tally)| WeekStats {
+            start: date(now.saturating_sub((weeks_ago as u64 + 1) * SECS_PER_WEEK)),
+            commits: tally.commits,
+            merge_commits: tally.merges,
+            compliance_pct: tally.compliance_pct(),
+        })
+        .collect();
+
+    Stats {
+        weeks,
+        commits: total.commits,
+        merge_commits: total.merges,
+        merge_pct: if total.commits > 0 {
+            pct(total.merges, total.commits)
+        } else {
+            0
+        },
+        compliance_pct: total.compliance_pct(),
+        avg_message_chars: total.avg_message_chars(),
+        authors,
+        trend,
+    }
+}
+
+fn pct(part: usize, whole: usize) -> u32 {
+    ((part * 100) as f64 / whole as f64).round() as u32
+}
+
+/// `YYYY-MM-DD` (UTC) of a unix timestamp.
+fn date(secs: u64) -> String {
+    // Days to a civil date, after Howard Hinnant's `civil_from_days`.
+    let days = (secs / SECS_PER_DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn print_text(stats: &Stats) {
+    let percent = |pct: Option<u32>| match pct {
+        Some(pct) => format!("{}%", pct),
+        None => "-".to_string(),
+    };
+    println!(
+        "{}",
+        format!("History of the last {} week(s):", stats.weeks).bold()
+    );
+    println!(
+        "  {} commit(s), {} merge(s) ({}% merged, {}% rebased or linear)",
+        stats.commits,
+        stats.merge_commits,
+        stats.merge_pct,
+        100 - stats.merge_pct
+    );
+    println!(
+        "  Convention compliance: {}",
+        percent(stats.compliance_pct).bold()
+    );
+    println!(
+        "  Average message length: {} chars",
+        stats.avg_message_chars
+    );
+
+    if !stats.authors.is_empty() {
+        println!("\n{}", "By author:".bold());
+        for author in &stats.authors {
+            let pct = percent(author.compliance_pct);
+            let pct = match author.compliance_pct {
+                Some(p) if p >= 90 => pct.green(),
+                Some(p) if p >= 50 => pct.yellow(),
+                Some(_) => pct.red(),
+                None => pct.normal(),
+            };
+            println!(
+                "  {:<30} {:>5} commit(s) {:>5} compliant {:>4} chars avg",
+                author.name, author.commits, pct, author.avg_message_chars
+            );
+        }
+    }
+
+    println!("\n{}", "By week:".bold());
+    for week in &stats.trend {
+        println!(
+            "  {}  {:>5} commit(s) {:>5} compliant {:>4} merge(s)",
+            week.start,
+            week.commits,
+            percent(week.compliance_pct),
+            week.merge_commits
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(author: &str, days_ago: u64, merge: bool, message: &str) -> LogEntry {
+        LogEntry {
+            hash: String::new(),
+            author: author.to_string(),
+            timestamp: 100 * SECS_PER_WEEK - days_ago * SECS_PER_DAY,
+            merge,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn tallies_compliance_per_author_and_week() {
+        let log = vec![
+            entry("Ann", 1, false, "feat: add"),
+            entry("Ann", 2, true, "Merge branch 'x'"),
+            entry("Bob", 3, false, "fixed it"),
+            entry("Ann", 9, false, "fix: bug"),
+        ];
+        let stats = summarize(&log, 2, 100 * SECS_PER_WEEK, |m| m.contains(": "));
+
+        assert_eq!(stats.commits, 4);
+        assert_eq!(stats.merge_pct, 25);
+        assert_eq!(stats.compliance_pct, Some(67));
+        assert_eq!(stats.avg_message_chars, 8);
+        assert_eq!(stats.authors[0].name, "Ann");
+        assert_eq!(stats.authors[0].compliance_pct, Some(100));
+        assert_eq!(stats.authors[1].compliance_pct, Some(0));
+        let weeks: Vec<usize> = stats.trend.iter().map(|w| w.commits).collect();
+        assert_eq!(weeks, vec![1, 3]);
+        assert_eq!(stats.trend[1].compliance_pct, Some(50));
+    }
+
+    #[test]
+    fn dates_are_civil_utc() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_790_000_000), "2026-09-21");
+    }
+}
@@ -0,0 +1,124 @@
+//! Scripted throwaway repositories for end-to-end tests.
+//!
+//! ```no_run
+//! use git_sherpa::test_util::TestRepo;
+//!
+//! let repo = TestRepo::new()
+//!     .commit("feat: initial commit")
+//!     .branch("feat/login")
+//!     .commit_file("src/login.rs", "fn login() {}\n", "feat(auth): add login")
+//!     .with_upstream();
+//! repo.stage(".env", "SECRET=1\n");
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tempfile::TempDir;
+
+/// A git repository in a temporary directory, removed on drop.
+///
+/// Builder methods panic on failure: they are meant for test setup, where a
+/// broken fixture should abort the test with the git output.
+pub struct TestRepo {
+    dir: TempDir,
+    remote: Option<TempDir>,
+}
+
+impl TestRepo {
+    /// Empty repository on branch `main` with a local identity configured.
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let repo = Self { dir, remote: None };
+        repo.git(&["init", "-q", "-b", "main"]);
+        repo.git(&["config", "user.name", "Sherpa Test"]);
+        repo.git(&["config", "user.email", "sherpa@example.com"]);
+        repo.git(&["config", "commit.gpgsign", "false"]);
+        repo
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Path of the bare remote created by [`TestRepo::with_upstream`].
+    pub fn remote_path(&self) -> Option<&Path> {
+        self.remote.as_ref().map(TempDir::path)
+    }
+
+    /// Runs git in the repository and returns trimmed stdout.
+    pub fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(self.path())
+            .output()
+            .expect("run git");
+        assert!(
+            output.status.success(),
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// Writes `contents` to `path`, creating parent directories.
+    pub fn write(&self, path: &str, contents: &str) -> PathBuf {
+        let full = self.path().join(path);
+        if let Some(parent) = full.parent() {
+            fs::create_dir_all(parent).expect("create parent dirs");
+        }
+        fs::write(&full, contents).expect("write file");
+        full
+    }
+
+    /// Writes `.gitsherpa.toml` without committing it.
+    pub fn config(self, toml: &str) -> Self {
+        self.write(".gitsherpa.toml", toml);
+        self
+    }
+
+    /// Empty commit with `message`.
+    pub fn commit(self, message: &str) -> Self {
+        self.git(&["commit", "-q", "--allow-empty", "-m", message]);
+        self
+    }
+
+    /// Writes and commits a single file.
+    pub fn commit_file(self, path: &str, contents: &str, message: &str) -> Self {
+        self.write(path, contents);
+        self.git(&["add", "--", path]);
+        self.git(&["commit", "-q", "-m", message]);
+        self
+    }
+
+    /// Creates and checks out `name`.
+    pub fn branch(self, name: &str) -> Self {
+        self.git(&["checkout", "-q", "-b", name]);
+        self
+    }
+
+    /// Adds a bare `origin` remote and pushes the current branch with tracking.
+    pub fn with_upstream(mut self) -> Self {
+        let remote = tempfile::tempdir().expect("create remote dir");
+        let remote_path = remote.path().to_string_lossy().into_owned();
+        self.git(&["init", "-q", "--bare", &remote_path]);
+        self.git(&["remote", "add", "origin", &remote_path]);
+        self.git(&["push", "-q", "-u", "origin", "HEAD"]);
+        self.remote = Some(remote);
+        self
+    }
+
+    /// Writes and stages a file without committing it.
+    pub fn stage(&self, path: &str, contents: &str) {
+        self.write(path, contents);
+        self.git(&["add", "--", path]);
+    }
+}
+
+impl Default for TestRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,97 @@
+//! `--version --json`: what the installed binary supports, so wrapper tools
+//! and editor plugins can adapt instead of parsing `--help`.
+
+use clap::{CommandFactory, ValueEnum};
+use serde::Serialize;
+
+use crate::cli::{CheckFormat, Cli};
+use crate::compat;
+use crate::rollup;
+use crate::rules::Registry;
+
+#[derive(Debug, Serialize)]
+pub struct Capabilities {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// Cargo features the binary was built with.
+    pub features: Vec<&'static str>,
+    pub commands: Vec<String>,
+    pub output_formats: Vec<String>,
+    /// Frozen JSON schema selected by `check --stable`.
+    pub schema_version: u32,
+    /// Versions accepted by `check --compat`.
+    pub compat_versions: Vec<u32>,
+    pub rules: &'static [&'static str],
+    /// Rules `fix --apply` repairs on its own, for scripted `fix --apply --only`.
+    pub auto_fix_rules: Vec<&'static str>,
+    /// Changes whenever a rule is added, removed or renamed.
+    pub rules_hash: String,
+}
+
+pub fn capabilities() -> Capabilities {
+    let features = [
+        ("test-util", cfg!(feature = "test-util")),
+        ("libgit2", cfg!(feature = "libgit2")),
+    ];
+    Capabilities {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        features: features
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect(),
+        commands: Cli::command()
+            .get_subcommands()
+            .map(|c| c.get_name().to_string())
+            .collect(),
+        output_formats: CheckFormat::value_variants()
+            .iter()
+            .filter_map(ValueEnum::to_possible_value)
+            .map(|v| v.get_name().to_string())
+            .collect(),
+        schema_version: compat::STABLE_VERSION,
+        compat_versions: (1..=compat::STABLE_VERSION).collect(),
+        rules: rollup::RULES,
+        auto_fix_rules: Registry::builtin().auto_fixable(),
+        rules_hash: format!("{:016x}", fnv1a(rollup::RULES.join("\n").as_bytes())),
+    }
+}
+
+/// `--version --json` (in either order) and nothing else. clap handles
+/// `--version` on its own before any other flag is looked at.
+pub fn is_version_json(args: &[String]) -> bool {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    args.sort_unstable();
+    matches!(args.as_slice(), ["--json", "--version" | "-V"])
+}
+
+/// FNV-1a: unlike `DefaultHasher`, stable across Rust releases.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn version_json_flag_combinations() {
+        assert!(is_version_json(&args(&["--version", "--json"])));
+        assert!(is_version_json(&args(&["--json", "-V"])));
+        assert!(!is_version_json(&args(&["--version"])));
+        assert!(!is_version_json(&args(&["check", "--json"])));
+    }
+
+    #[test]
+    fn rules_hash_is_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
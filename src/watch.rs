@@ -0,0 +1,326 @@
+//! Long-running `check --watch`: re-runs the check when the worktree, the
+//! index, `HEAD` or a ref changes, hot-reloading `.gitsherpa.toml` without
+//! restarting. Text output is a single live status line.
+
+use anyhow::Result;
+use colored::Colorize;
+use notify::event::{MetadataKind, ModifyKind};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use crate::baseline;
+use crate::check::{build_report, print_json_report, validate_config, CommitSelection, Report};
+use crate::cli::Format;
+use crate::config::{load_config, Config};
+use crate::environment::Environment;
+use crate::git;
+
+/// Quiet period after a change before re-checking, so a checkout or a
+/// rebase step triggers one run rather than hundreds.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Outcome of polling the config file.
+pub enum Reload {
+    Unchanged,
+    Reloaded,
+    /// The new contents failed to load or validate; the previous config stays active.
+    Failed(anyhow::Error),
+}
+
+/// Holds the last valid config and reloads it when the file contents change.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    /// Contents last seen on disk, valid or not, so a broken edit is reported once.
+    seen: Option<String>,
+    config: Config,
+}
+
+impl ConfigWatcher {
+    /// Loads the initial config; unlike reloads, a bad initial config is fatal.
+    pub fn new(path: &Path) -> Result<Self> {
+        let config = load_config(path)?;
+        validate_config(&config)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            seen: fs::read_to_string(path).ok(),
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn poll(&mut self) -> Reload {
+        let contents = fs::read_to_string(&self.path).ok();
+        if contents == self.seen {
+            return Reload::Unchanged;
+        }
+        self.seen = contents;
+        match load_config(&self.path).and_then(|config| {
+            validate_config(&config)?;
+            Ok(config)
+        }) {
+            Ok(config) => {
+                self.config = config;
+                Reload::Reloaded
+            }
+            Err(err) => Reload::Failed(err),
+        }
+    }
+}
+
+/// Changed paths from the filesystem watcher.
+type Changes = Receiver<Vec<PathBuf>>;
+
+/// Watches the worktree and the git dir. `None` (with a warning) when the
+/// platform watcher is unavailable; the caller then polls.
+fn watch_repo() -> Result<Option<(RecommendedWatcher, Changes, PathBuf)>> {
+    let toplevel = fs::canonicalize(git::toplevel()?)?;
+    let git_dir = fs::canonicalize(git::git_dir()?)?;
+    let (tx, rx) = mpsc::channel();
+    let started = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Some(event) = event.ok().filter(|event| is_change(&event.kind)) {
+            let _ = tx.send(event.paths);
+        }
+    })
+    .and_then(|mut watcher| {
+        watcher.watch(&toplevel, RecursiveMode::Recursive)?;
+        // A linked worktree keeps its git dir outside the worktree.
+        if !git_dir.starts_with(&toplevel) {
+            watcher.watch(&git_dir, RecursiveMode::Recursive)?;
+        }
+        Ok(watcher)
+    });
+    match started {
+        Ok(watcher) => Ok(Some((watcher, rx, git_dir))),
+        Err(err) => {
+            eprintln!(
+                "{}",
+                format!(
+                    "Warning: cannot watch the repository ({}); polling instead",
+                    err
+                )
+                .yellow()
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Whether an event changed something: files created, written, removed or
+/// renamed. The check itself opens and reads the index and objects, and
+/// reacting to those reads would re-run it forever.
+fn is_change(kind: &EventKind) -> bool {
+    match kind {
+        EventKind::Create(_) | EventKind::Remove(_) => true,
+        EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime)) => false,
+        EventKind::Modify(_) => true,
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => false,
+    }
+}
+
+/// Whether a change can affect the check. Inside the git dir only the
+/// index, `HEAD` and refs count; objects, logs and lock files are noise.
+fn is_relevant(path: &Path, git_dir: &Path) -> bool {
+    let Ok(inner) = path.strip_prefix(git_dir) else {
+        return true;
+    };
+    if inner.extension().is_some_and(|ext| ext == "lock") {
+        return false;
+    }
+    let first = inner.components().next().map(|c| c.as_os_str());
+    matches!(
+        first.and_then(|c| c.to_str()),
+        Some("index" | "HEAD" | "packed-refs" | "FETCH_HEAD" | "refs")
+    )
+}
+
+/// Blocks until a relevant change has settled, i.e. no event came for
+/// [`DEBOUNCE`], or `interval` passes.
+fn wait_for_change(changes: &Changes, git_dir: &Path, interval: Duration) {
+    loop {
+        match changes.recv_timeout(interval) {
+            Ok(paths) if paths.iter().any(|p| is_relevant(p, git_dir)) => break,
+            Ok(_) => continue,
+            Err(RecvTimeoutError::Timeout) => return,
+            Err(RecvTimeoutError::Disconnected) => {
+                std::thread::sleep(interval);
+                return;
+            }
+        }
+    }
+    while changes.recv_timeout(DEBOUNCE).is_ok() {}
+}
+
+/// One line per result, e.g. `feat/login: FAIL commit-convention=2, clean-worktree=1`.
+fn status_line(report: &Report) -> String {
+    if report.rules.is_empty() {
+        return format!(
+            "{}: {} ({} commits)",
+            report.branch.name,
+            "OK".green(),
+            report.summary.total_commits
+        );
+    }
+    let rules: Vec<String> = report
+        .rules
+        .iter()
+        .map(|rule| format!("{}={}", rule.rule, rule.issues))
+        .collect();
+    let label = if report.has_violations() {
+        "FAIL".red()
+    } else {
+        "WARN".yellow()
+    };
+    format!("{}: {} {}", report.branch.name, label, rules.join(", "))
+}
+
+/// Rewrites the status line in place on a terminal, one line per change
+/// otherwise (e.g. when piped to a log).
+fn print_status(line: &str) {
+    let mut stdout = std::io::stdout();
+    if stdout.is_terminal() {
+        let _ = write!(stdout, "\r\x1b[2K{}", line);
+        let _ = stdout.flush();
+    } else {
+        println!("{}", line);
+    }
+}
+
+pub fn watch_check(
+    config_path: &Path,
+    format: Format,
+    commits: CommitSelection,
+    interval: Duration,
+    baseline: bool,
+) -> Result<()> {
+    let mut watcher = ConfigWatcher::new(config_path)?;
+    let mut last_report: Option<String> = None;
+    let repo = watch_repo()?;
+
+    loop {
+        match watcher.poll() {
+            Reload::Unchanged => {}
+            Reload::Reloaded => {
+                eprintln!("{}", format!("Reloaded {}", config_path.display()).cyan());
+                last_report = None;
+            }
+            Reload::Failed(err) => eprintln!(
+                "{}",
+                format!("Config reload failed, keeping previous config: {:#}", err).yellow()
+            ),
+        }
+
+        // Errors are reported and retried on the next tick: a transient git
+        // failure (e.g. during a rebase) must not stop the watcher.
+        let outcome = build_report(watcher.config(), &commits).and_then(|mut report| {
+            if baseline {
+                baseline::apply_to(watcher.config(), &mut report)?;
+            }
+            Ok(report)
+        });
+        match outcome {
+            Ok(mut report) => {
+                report.environment = Some(Environment::capture(config_path, watcher.config()));
+                let snapshot = serde_json::to_string(&report)?;
+                if last_report.as_ref() != Some(&snapshot) {
+                    match format {
+                        Format::Text => print_status(&status_line(&report)),
+                        Format::Json => print_json_report(&report)?,
+                    }
+                    last_report = Some(snapshot);
+                }
+            }
+            Err(err) => eprintln!("{}", format!("Error: {:#}", err).red()),
+        }
+
+        match &repo {
+            Some((_watcher, changes, git_dir)) => wait_for_change(changes, git_dir, interval),
+            None => std::thread::sleep(interval),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config_toml;
+
+    fn temp_config(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("sherpa-watch-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".gitsherpa.toml");
+        fs::write(&path, default_config_toml().unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn only_index_head_and_refs_matter_inside_git_dir() {
+        let git_dir = Path::new("/repo/.git");
+        assert!(is_relevant(Path::new("/repo/src/main.rs"), git_dir));
+        assert!(is_relevant(Path::new("/repo/.git/index"), git_dir));
+        assert!(is_relevant(
+            Path::new("/repo/.git/refs/heads/main"),
+            git_dir
+        ));
+        assert!(!is_relevant(Path::new("/repo/.git/index.lock"), git_dir));
+        assert!(!is_relevant(
+            Path::new("/repo/.git/objects/ab/cdef"),
+            git_dir
+        ));
+        assert!(!is_relevant(Path::new("/repo/.git/logs/HEAD"), git_dir));
+    }
+
+    #[test]
+    fn reads_are_not_changes() {
+        use notify::event::{AccessKind, CreateKind, DataChange, RenameMode};
+        assert!(is_change(&EventKind::Create(CreateKind::File)));
+        assert!(is_change(&EventKind::Modify(ModifyKind::Data(
+            DataChange::Content
+        ))));
+        assert!(is_change(&EventKind::Modify(ModifyKind::Name(
+            RenameMode::Both
+        ))));
+        assert!(!is_change(&EventKind::Access(AccessKind::Open(
+            notify::event::AccessMode::Read
+        ))));
+        assert!(!is_change(&EventKind::Modify(ModifyKind::Metadata(
+            MetadataKind::AccessTime
+        ))));
+    }
+
+    #[test]
+    fn reloads_changed_config() {
+        let path = temp_config("reload");
+        let mut watcher = ConfigWatcher::new(&path).unwrap();
+        assert!(matches!(watcher.poll(), Reload::Unchanged));
+
+        let edited = default_config_toml()
+            .unwrap()
+            .replace("require_upstream = true", "require_upstream = false");
+        fs::write(&path, edited).unwrap();
+        assert!(matches!(watcher.poll(), Reload::Reloaded));
+        assert!(!watcher.config().checks.require_upstream);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn keeps_previous_config_on_invalid_edit() {
+        let path = temp_config("invalid");
+        let mut watcher = ConfigWatcher::new(&path).unwrap();
+
+        let broken = default_config_toml().unwrap().replace("^(feat", "^((feat");
+        fs::write(&path, broken).unwrap();
+        assert!(matches!(watcher.poll(), Reload::Failed(_)));
+        assert!(watcher.config().branches.pattern.starts_with("^(feat"));
+        // The same broken contents are not reported again.
+        assert!(matches!(watcher.poll(), Reload::Unchanged));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
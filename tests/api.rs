@@ -0,0 +1,67 @@
+//! The library API, as embedding tools use it.
+
+use git_sherpa::check::build_report_with;
+use git_sherpa::location::Location;
+use git_sherpa::rules::{Context, Registry, Rule, Violation};
+use git_sherpa::test_util::TestRepo;
+use git_sherpa::{build_report, load_config, CommitSelection};
+
+const CONFIG: &str = r#"
+[branches]
+pattern = "^feat/.+$"
+
+[commits]
+convention = "conventional"
+
+[checks]
+require_clean_worktree = false
+require_upstream = false
+"#;
+
+#[test]
+fn build_report_from_the_library() {
+    let repo = TestRepo::new()
+        .config(CONFIG)
+        .branch("feat/api")
+        .commit("feat: embed sherpa")
+        .commit("wip");
+
+    // git runs in the working directory; this is the only test in the binary.
+    std::env::set_current_dir(repo.path()).unwrap();
+    let config = load_config(&repo.path().join(".gitsherpa.toml")).unwrap();
+    let report = build_report(&config, &CommitSelection::Recent(2)).unwrap();
+
+    assert!(report.branch.valid);
+    assert_eq!(report.summary.invalid_commits, 1);
+    assert!(report.has_violations());
+
+    // A registered rule's violations are kept in the report.
+    let mut registry = Registry::builtin();
+    registry.register(Box::new(NoTodo));
+    let report =
+        build_report_with(&config, &CommitSelection::Recent(2), &registry, &mut |_| {}).unwrap();
+    let rules = serde_json::to_value(&report.rules).unwrap();
+    let todo = rules
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|rule| rule["rule"] == "no-todo")
+        .unwrap();
+    assert_eq!(todo["issues"], 1);
+    assert_eq!(todo["violations"][0]["message"], "TODO left in");
+    assert_eq!(todo["violations"][0]["location"]["path"], "src/lib.rs");
+}
+
+struct NoTodo;
+
+impl Rule for NoTodo {
+    fn id(&self) -> &'static str {
+        "no-todo"
+    }
+
+    fn evaluate(&self, _ctx: &mut Context) -> anyhow::Result<Vec<Violation>> {
+        Ok(vec![
+            Violation::new("no-todo", "TODO left in").at(Location::file_line("src/lib.rs", 3))
+        ])
+    }
+}